@@ -0,0 +1,372 @@
+//! Fingerprints of the application binary interface of classes.
+//!
+//! The [`fingerprint`] of a class only changes if a part changes which other classes can be compiled against.
+//! This includes the hierarchy of the class, its access flags, generic signatures and annotations,
+//! and the non-private fields and methods with their descriptors, signatures, annotations, declared exceptions
+//! and constant values. Annotations are distinguished by their retention.
+//! Code, private members, synthetic members other than bridge methods and debug information are ignored,
+//! as are the order of the constant pool, of the members, of the attributes and of the implemented interfaces.
+//! Method flags which only affect the body, such as `native` and `synchronized`, are ignored as well.
+//!
+//! Build systems can use the fingerprint to only recompile the dependents of a class if its fingerprint changed.
+//!
+//! ```
+//! use noak::abi;
+//! use noak::tree::{Attribute, Class, Code, Instruction, Method};
+//! use noak::AccessFlags;
+//!
+//! let mut class = Class {
+//!     methods: vec![Method {
+//!         access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC,
+//!         name: "run".into(),
+//!         descriptor: "()V".into(),
+//!         attributes: vec![Attribute::Code(Code {
+//!             max_stack: 0,
+//!             max_locals: 0,
+//!             instructions: vec![Instruction::Return],
+//!             exception_handlers: Vec::new(),
+//!             attributes: Vec::new(),
+//!         })],
+//!     }],
+//!     ..Class::new("Example")
+//! };
+//! let before = abi::fingerprint_tree(&class)?;
+//!
+//! // changing the body of a method doesn't change the fingerprint
+//! let Attribute::Code(code) = &mut class.methods[0].attributes[0] else { unreachable!() };
+//! code.instructions.insert(0, Instruction::Nop);
+//! assert_eq!(abi::fingerprint_tree(&class)?, before);
+//!
+//! // changing its descriptor does
+//! class.methods[0].descriptor = "()I".into();
+//! assert_ne!(abi::fingerprint_tree(&class)?, before);
+//! # Ok::<(), noak::error::EncodeError>(())
+//! ```
+
+use std::{error::Error, fmt};
+
+use crate::error::*;
+use crate::header::{AccessFlags, Version};
+use crate::reader;
+use crate::tree::{Attribute, Class, Field, InnerClass, Method, PoolOrder};
+
+/// A hash of the application binary interface of a class.
+///
+/// The hash is a 128-bit FNV-1a hash, which is not meant to withstand deliberate collisions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fingerprint(pub u128);
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+/// An error which occurred while computing the fingerprint of a class which was read from a class file.
+#[derive(Debug)]
+pub enum FingerprintError {
+    /// The class could not be resolved.
+    Decode(DecodeError),
+    /// The interface of the class could not be written.
+    Encode(EncodeError),
+}
+
+impl From<DecodeError> for FingerprintError {
+    fn from(err: DecodeError) -> FingerprintError {
+        FingerprintError::Decode(err)
+    }
+}
+
+impl From<EncodeError> for FingerprintError {
+    fn from(err: EncodeError) -> FingerprintError {
+        FingerprintError::Encode(err)
+    }
+}
+
+impl Error for FingerprintError {}
+
+impl fmt::Display for FingerprintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FingerprintError::Decode(err) => write!(f, "failed to resolve the class: {err}"),
+            FingerprintError::Encode(err) => write!(f, "failed to write the interface of the class: {err}"),
+        }
+    }
+}
+
+/// Computes the fingerprint of a class which was read from a class file.
+///
+/// Fails if the class can't be resolved or if its interface can't be written.
+pub fn fingerprint(class: &reader::Class<'_>) -> Result<Fingerprint, FingerprintError> {
+    Ok(fingerprint_tree(&Class::from_reader(class)?)?)
+}
+
+/// Computes the fingerprint of a resolved class.
+///
+/// The [constant pool](Class::constant_pool) of the class is ignored.
+/// Fails if the interface of the class can't be written, for example if it has too many constants.
+pub fn fingerprint_tree(class: &Class) -> Result<Fingerprint, EncodeError> {
+    let mut abi = Class {
+        // the fingerprint shouldn't change if only the target version changes
+        version: Version::latest(),
+        access_flags: class.access_flags - AccessFlags::SUPER,
+        name: class.name.clone(),
+        super_class: class.super_class.clone(),
+        interfaces: class.interfaces.clone(),
+        constant_pool: Vec::new(),
+        fields: class
+            .fields
+            .iter()
+            .filter(|field| is_abi(field.access_flags))
+            .map(field)
+            .collect(),
+        methods: class
+            .methods
+            .iter()
+            .filter(|method| is_abi(method.access_flags))
+            .map(method)
+            .collect(),
+        attributes: class
+            .attributes
+            .iter()
+            .filter_map(|attribute| class_attribute(class, attribute))
+            .collect(),
+    };
+    abi.interfaces.sort();
+    abi.attributes.sort_by_cached_key(Attribute::name);
+    abi.fields
+        .sort_by(|a, b| (&a.name, &a.descriptor).cmp(&(&b.name, &b.descriptor)));
+    abi.methods
+        .sort_by(|a, b| (&a.name, &a.descriptor).cmp(&(&b.name, &b.descriptor)));
+    abi.compact_constant_pool(PoolOrder::Sorted)?;
+
+    Ok(Fingerprint(fnv1a(&abi.to_bytes()?)))
+}
+
+/// Whether a member can be referenced by other classes.
+///
+/// Bridge methods are synthetic, but they are invoked by classes compiled against the erased descriptor.
+fn is_abi(access_flags: AccessFlags) -> bool {
+    !access_flags.contains(AccessFlags::PRIVATE)
+        && (!access_flags.contains(AccessFlags::SYNTHETIC) || access_flags.contains(AccessFlags::BRIDGE))
+}
+
+fn field(field: &Field) -> Field {
+    Field {
+        access_flags: field.access_flags,
+        name: field.name.clone(),
+        descriptor: field.descriptor.clone(),
+        attributes: member_attributes(&field.attributes),
+    }
+}
+
+fn method(method: &Method) -> Method {
+    Method {
+        // these only change how the body is run, which callers can't observe
+        access_flags: method.access_flags - (AccessFlags::NATIVE | AccessFlags::SYNCHRONIZED | AccessFlags::STRICT),
+        name: method.name.clone(),
+        descriptor: method.descriptor.clone(),
+        attributes: member_attributes(&method.attributes),
+    }
+}
+
+fn member_attributes(attributes: &[Attribute]) -> Vec<Attribute> {
+    let mut attributes: Vec<Attribute> = attributes
+        .iter()
+        .filter(|attribute| is_member_attribute(attribute))
+        .cloned()
+        .collect();
+    attributes.sort_by_cached_key(Attribute::name);
+    attributes
+}
+
+fn is_member_attribute(attribute: &Attribute) -> bool {
+    matches!(
+        attribute,
+        Attribute::AnnotationDefault { .. }
+            | Attribute::ConstantValue { .. }
+            | Attribute::Deprecated
+            | Attribute::Exceptions(_)
+            | Attribute::RuntimeInvisibleAnnotations(_)
+            | Attribute::RuntimeInvisibleParameterAnnotations(_)
+            | Attribute::RuntimeInvisibleTypeAnnotations(_)
+            | Attribute::RuntimeVisibleAnnotations(_)
+            | Attribute::RuntimeVisibleParameterAnnotations(_)
+            | Attribute::RuntimeVisibleTypeAnnotations(_)
+            | Attribute::Signature(_)
+            | Attribute::Synthetic
+    )
+}
+
+/// Returns the part of a class attribute which belongs to the interface of the class.
+fn class_attribute(class: &Class, attribute: &Attribute) -> Option<Attribute> {
+    match attribute {
+        // only the entries of the class itself and its non-private member classes can be referenced
+        Attribute::InnerClasses(inner_classes) => {
+            let mut entries: Vec<InnerClass> = inner_classes
+                .iter()
+                .filter(|inner| {
+                    inner.inner_class == class.name
+                        || (inner.outer_class.as_ref() == Some(&class.name)
+                            && !inner.inner_access_flags.contains(AccessFlags::PRIVATE))
+                })
+                .cloned()
+                .collect();
+            entries.sort_by(|a, b| a.inner_class.cmp(&b.inner_class));
+            (!entries.is_empty()).then_some(Attribute::InnerClasses(entries))
+        }
+        Attribute::PermittedSubclasses(classes) => {
+            let mut classes = classes.clone();
+            classes.sort();
+            Some(Attribute::PermittedSubclasses(classes))
+        }
+        Attribute::Deprecated
+        | Attribute::Module(_)
+        | Attribute::ModulePackages(_)
+        | Attribute::Record(_)
+        | Attribute::RuntimeInvisibleAnnotations(_)
+        | Attribute::RuntimeInvisibleTypeAnnotations(_)
+        | Attribute::RuntimeVisibleAnnotations(_)
+        | Attribute::RuntimeVisibleTypeAnnotations(_)
+        | Attribute::Signature(_)
+        | Attribute::Synthetic => Some(attribute.clone()),
+        _ => None,
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u128 {
+    const OFFSET_BASIS: u128 = 0x6c62272e_07bb0142_62b82175_6295c58d;
+    const PRIME: u128 = 0x00000000_01000000_00000000_0000013b;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u128::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{Code, Constant, Instruction};
+
+    fn method(name: &str, attributes: Vec<Attribute>) -> Method {
+        Method {
+            access_flags: AccessFlags::PUBLIC,
+            name: name.into(),
+            descriptor: "()V".into(),
+            attributes,
+        }
+    }
+
+    fn code(instructions: Vec<Instruction>) -> Attribute {
+        Attribute::Code(Code {
+            max_stack: 0,
+            max_locals: 1,
+            instructions,
+            exception_handlers: Vec::new(),
+            attributes: Vec::new(),
+        })
+    }
+
+    fn member(name: &str) -> InnerClass {
+        InnerClass {
+            inner_class: format!("Outer${name}").as_str().into(),
+            outer_class: Some("Outer".into()),
+            inner_name: Some(name.into()),
+            inner_access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC,
+        }
+    }
+
+    fn class() -> Class {
+        Class {
+            methods: vec![
+                method(
+                    "first",
+                    vec![
+                        code(vec![Instruction::Return]),
+                        Attribute::Signature("<T:Ljava/lang/Object;>()V".into()),
+                        Attribute::Exceptions(vec!["java/io/IOException".into()]),
+                    ],
+                ),
+                method("second", vec![code(vec![Instruction::Return]), Attribute::Deprecated]),
+            ],
+            attributes: vec![
+                Attribute::InnerClasses(vec![member("A"), member("B")]),
+                Attribute::Signature("Ljava/lang/Object;".into()),
+            ],
+            ..Class::new("Outer")
+        }
+    }
+
+    #[test]
+    fn order_is_ignored() {
+        let mut reordered = class();
+        reordered.methods.reverse();
+        for method in &mut reordered.methods {
+            method.attributes.reverse();
+        }
+        reordered.attributes.reverse();
+        let Attribute::InnerClasses(inner_classes) = &mut reordered.attributes[1] else {
+            unreachable!();
+        };
+        inner_classes.reverse();
+
+        assert_eq!(
+            fingerprint_tree(&reordered).unwrap(),
+            fingerprint_tree(&class()).unwrap()
+        );
+    }
+
+    #[test]
+    fn body_changes_are_ignored() {
+        let mut changed = class();
+        changed.methods[0].access_flags |= AccessFlags::SYNCHRONIZED | AccessFlags::STRICT;
+        changed.methods[0].attributes[0] = code(vec![Instruction::Nop, Instruction::Return]);
+        changed.methods[1].access_flags |= AccessFlags::NATIVE;
+        changed.methods[1].attributes.remove(0);
+        changed.methods.push(Method {
+            access_flags: AccessFlags::PRIVATE,
+            ..method("helper", vec![code(vec![Instruction::Return])])
+        });
+
+        assert_eq!(fingerprint_tree(&changed).unwrap(), fingerprint_tree(&class()).unwrap());
+    }
+
+    #[test]
+    fn signature_changes_are_detected() {
+        let original = fingerprint_tree(&class()).unwrap();
+
+        let mut changed = class();
+        changed.methods[0].attributes[1] = Attribute::Signature("<T:Ljava/lang/Number;>()V".into());
+        assert_ne!(fingerprint_tree(&changed).unwrap(), original);
+
+        let mut changed = class();
+        changed.methods[1].descriptor = "()I".into();
+        assert_ne!(fingerprint_tree(&changed).unwrap(), original);
+    }
+
+    #[test]
+    fn read_classes() {
+        let class = class();
+        let bytes = class.to_bytes().unwrap();
+        assert_eq!(
+            fingerprint(&reader::Class::new(&bytes).unwrap()).unwrap(),
+            fingerprint_tree(&class).unwrap()
+        );
+
+        // the source file refers to the long constant at index 1
+        let invalid = Class {
+            constant_pool: vec![Constant::Long(0)],
+            attributes: vec![Attribute::Unknown {
+                name: "SourceFile".into(),
+                content: vec![0, 1],
+            }],
+            ..Class::new("Invalid")
+        };
+        let bytes = invalid.to_bytes().unwrap();
+        assert!(matches!(
+            fingerprint(&reader::Class::new(&bytes).unwrap()),
+            Err(FingerprintError::Decode(_))
+        ));
+    }
+}
@@ -0,0 +1,922 @@
+//! Text snapshots of the public API of a library.
+//!
+//! An [`Api`] contains the exported classes of a library and their public and protected members,
+//! with their generic signatures, annotations, declared exceptions and constant values.
+//! Its [`Display`](fmt::Display) implementation writes a stable text format, which is sorted by package,
+//! class and member, so it can be kept under version control.
+//! The text can be [parsed](Api::parse) back to compare it with the API of another version.
+//!
+//! ```text
+//! // Signature format: noak 1
+//! package com/example {
+//!
+//!   public class Api extends java/lang/Object {
+//!     public static final field COUNT:I = 3
+//!     @Ljava/lang/Deprecated; public method run(Ljava/lang/String;)V throws java/io/IOException
+//!   }
+//!
+//! }
+//! ```
+//!
+//! Types are written as descriptors and generic signatures as they appear in class files.
+//! Annotations are only recorded by their type.
+//!
+//! ```
+//! use noak::api::Api;
+//! use noak::tree::{Class, Field, Attribute, Constant};
+//! use noak::AccessFlags;
+//!
+//! let class = Class {
+//!     fields: vec![Field {
+//!         access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC | AccessFlags::FINAL,
+//!         name: "NAME".into(),
+//!         descriptor: "Ljava/lang/String;".into(),
+//!         attributes: vec![Attribute::ConstantValue {
+//!             value: Constant::String("api \"v1\"".into()),
+//!         }],
+//!     }],
+//!     ..Class::new("com/example/Api")
+//! };
+//!
+//! let api = Api::from_classes(&[class]);
+//! let text = api.to_string();
+//! assert_eq!(
+//!     text,
+//!     "// Signature format: noak 1\n\
+//!      package com/example {\n\
+//!      \n  public class Api extends java/lang/Object {\n\
+//!      \x20   public static final field NAME:Ljava/lang/String; = \"api \\\"v1\\\"\"\n\
+//!      \x20 }\n\
+//!      \n}\n\n"
+//! );
+//! assert_eq!(Api::parse(&text)?, api);
+//! # Ok::<(), noak::api::ParseError>(())
+//! ```
+
+use std::collections::HashSet;
+use std::{error::Error, fmt};
+
+use crate::header::AccessFlags;
+use crate::mutf8::{MStr, MString};
+use crate::tree::{Annotation, Attribute, Class, Constant, Field, Method};
+
+const HEADER: &str = "// Signature format: noak 1";
+
+/// The access flags which are part of the API, with their names in the text format.
+const MODIFIERS: [(AccessFlags, &str); 7] = [
+    (AccessFlags::PUBLIC, "public"),
+    (AccessFlags::PROTECTED, "protected"),
+    (AccessFlags::STATIC, "static"),
+    (AccessFlags::FINAL, "final"),
+    (AccessFlags::ABSTRACT, "abstract"),
+    (AccessFlags::VOLATILE, "volatile"),
+    (AccessFlags::TRANSIENT, "transient"),
+];
+
+/// The exported classes of a library, sorted by their packages and names.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Api {
+    pub classes: Vec<ApiClass>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiClass {
+    pub name: MString,
+    pub kind: ClassKind,
+    /// The modifiers of the class, nested classes use the access flags of their `InnerClasses` entry.
+    pub access_flags: AccessFlags,
+    pub signature: Option<MString>,
+    /// The superclass, which is omitted for interfaces.
+    pub super_class: Option<MString>,
+    pub interfaces: Vec<MString>,
+    /// The descriptors of the annotation types, sorted.
+    pub annotations: Vec<MString>,
+    /// The fields, sorted by name and descriptor.
+    pub fields: Vec<ApiField>,
+    /// The methods, sorted by name and descriptor.
+    pub methods: Vec<ApiMethod>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClassKind {
+    Class,
+    Interface,
+    Annotation,
+    Enum,
+    Record,
+}
+
+impl ClassKind {
+    fn keyword(self) -> &'static str {
+        match self {
+            ClassKind::Class => "class",
+            ClassKind::Interface => "interface",
+            ClassKind::Annotation => "@interface",
+            ClassKind::Enum => "enum",
+            ClassKind::Record => "record",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiField {
+    pub name: MString,
+    pub descriptor: MString,
+    pub access_flags: AccessFlags,
+    pub signature: Option<MString>,
+    pub annotations: Vec<MString>,
+    /// The value of a constant, which is either an integer, a float, a long, a double or a string.
+    pub value: Option<Constant>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiMethod {
+    pub name: MString,
+    pub descriptor: MString,
+    pub access_flags: AccessFlags,
+    pub signature: Option<MString>,
+    pub annotations: Vec<MString>,
+    /// The annotation types of the parameters, by the index of the parameter.
+    pub parameter_annotations: Vec<(usize, MString)>,
+    pub exceptions: Vec<MString>,
+}
+
+impl Api {
+    /// Collects the API of a library from all of its classes.
+    ///
+    /// If the classes contain a module descriptor, only the packages which it exports to every module are included.
+    /// A class is exported if it is public, or if it is a public or protected member of an exported class.
+    /// Synthetic classes and members are ignored.
+    #[must_use]
+    pub fn from_classes(classes: &[Class]) -> Api {
+        let exported_packages: Option<HashSet<&MString>> = classes.iter().find_map(|class| {
+            class.attributes.iter().find_map(|attribute| match attribute {
+                Attribute::Module(module) if class.access_flags.contains(AccessFlags::MODULE) => Some(
+                    module
+                        .exports
+                        .iter()
+                        .filter(|export| export.exports_to.is_empty())
+                        .map(|export| &export.package)
+                        .collect(),
+                ),
+                _ => None,
+            })
+        });
+
+        let mut api_classes: Vec<_> = classes
+            .iter()
+            .filter(|class| {
+                exported_packages
+                    .as_ref()
+                    .map_or(true, |packages| packages.contains(&MString::from(package(&class.name))))
+            })
+            .filter(|class| is_exported(class, classes, 0))
+            .map(ApiClass::new)
+            .collect();
+        api_classes.sort_by(|a, b| (package(&a.name), &a.name).cmp(&(package(&b.name), &b.name)));
+        Api { classes: api_classes }
+    }
+
+    /// Parses the text format written by the [`Display`](fmt::Display) implementation.
+    pub fn parse(input: &str) -> Result<Api, ParseError> {
+        let mut api = Api::default();
+        let mut package_name: Option<MString> = None;
+        let mut class: Option<ApiClass> = None;
+
+        for (number, line) in input.lines().enumerate() {
+            let error = |kind| ParseError { line: number + 1, kind };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            if let Some(current) = &mut class {
+                if line == "}" {
+                    api.classes.extend(class.take());
+                } else {
+                    current.parse_member(line).ok_or(error(ParseErrorKind::InvalidMember))?;
+                }
+            } else if let Some(package) = &package_name {
+                if line == "}" {
+                    package_name = None;
+                } else {
+                    class = Some(ApiClass::parse(package, line).ok_or(error(ParseErrorKind::InvalidClass))?);
+                }
+            } else {
+                let name = line
+                    .strip_prefix("package")
+                    .and_then(|line| line.strip_suffix('{'))
+                    .ok_or(error(ParseErrorKind::InvalidPackage))?;
+                package_name = Some(MString::from(name.trim()));
+            }
+        }
+
+        if package_name.is_some() || class.is_some() {
+            return Err(ParseError {
+                line: input.lines().count(),
+                kind: ParseErrorKind::UnexpectedEnd,
+            });
+        }
+        api.classes
+            .sort_by(|a, b| (package(&a.name), &a.name).cmp(&(package(&b.name), &b.name)));
+        Ok(api)
+    }
+}
+
+impl fmt::Display for Api {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+        let mut current_package = None;
+        for class in &self.classes {
+            let class_package = package(&class.name);
+            if current_package != Some(class_package) {
+                if current_package.is_some() {
+                    writeln!(f, "}}\n")?;
+                }
+                writeln!(f, "package {} {{", class_package.display())?;
+                current_package = Some(class_package);
+            }
+            writeln!(f)?;
+            write!(f, "{class}")?;
+        }
+        if current_package.is_some() {
+            writeln!(f, "\n}}\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl ApiClass {
+    fn new(class: &Class) -> ApiClass {
+        let kind = if class.access_flags.contains(AccessFlags::ANNOTATION) {
+            ClassKind::Annotation
+        } else if class.access_flags.contains(AccessFlags::INTERFACE) {
+            ClassKind::Interface
+        } else if class.access_flags.contains(AccessFlags::ENUM) {
+            ClassKind::Enum
+        } else if class
+            .attributes
+            .iter()
+            .any(|attribute| matches!(attribute, Attribute::Record(_)))
+        {
+            ClassKind::Record
+        } else {
+            ClassKind::Class
+        };
+        let mut access_flags = inner_access_flags(class).unwrap_or(class.access_flags);
+        if matches!(kind, ClassKind::Interface | ClassKind::Annotation) {
+            access_flags.remove(AccessFlags::ABSTRACT);
+        }
+
+        let mut fields: Vec<_> = class
+            .fields
+            .iter()
+            .filter(|field| is_exported_member(field.access_flags))
+            .map(ApiField::new)
+            .collect();
+        fields.sort_by(|a, b| (&a.name, &a.descriptor).cmp(&(&b.name, &b.descriptor)));
+        let mut methods: Vec<_> = class
+            .methods
+            .iter()
+            .filter(|method| is_exported_member(method.access_flags))
+            .map(ApiMethod::new)
+            .collect();
+        methods.sort_by(|a, b| (&a.name, &a.descriptor).cmp(&(&b.name, &b.descriptor)));
+
+        ApiClass {
+            name: class.name.clone(),
+            kind,
+            access_flags: modifiers(access_flags),
+            signature: signature(&class.attributes),
+            super_class: class
+                .super_class
+                .clone()
+                .filter(|_| !access_flags.contains(AccessFlags::INTERFACE)),
+            interfaces: class.interfaces.clone(),
+            annotations: annotations(&class.attributes),
+            fields,
+            methods,
+        }
+    }
+
+    fn parse(package: &MStr, line: &str) -> Option<ApiClass> {
+        let mut tokens = line.strip_suffix('{')?.split_whitespace().peekable();
+        let annotations = parse_annotations(&mut tokens);
+        let access_flags = parse_modifiers(&mut tokens);
+        let kind = match tokens.next()? {
+            "class" => ClassKind::Class,
+            "interface" => ClassKind::Interface,
+            "@interface" => ClassKind::Annotation,
+            "enum" => ClassKind::Enum,
+            "record" => ClassKind::Record,
+            _ => return None,
+        };
+        let simple_name = tokens.next()?;
+        let name = if package.is_empty() {
+            MString::from(simple_name)
+        } else {
+            MString::from(format!("{}/{simple_name}", package.display()).as_str())
+        };
+
+        let mut class = ApiClass {
+            name,
+            kind,
+            access_flags,
+            signature: None,
+            super_class: None,
+            interfaces: Vec::new(),
+            annotations,
+            fields: Vec::new(),
+            methods: Vec::new(),
+        };
+        while let Some(keyword) = tokens.next() {
+            match keyword {
+                "signature" => class.signature = Some(tokens.next()?.into()),
+                "extends" => class.super_class = Some(tokens.next()?.into()),
+                "implements" => class.interfaces.extend(tokens.by_ref().map(MString::from)),
+                _ => return None,
+            }
+        }
+        Some(class)
+    }
+
+    fn parse_member(&mut self, line: &str) -> Option<()> {
+        // the value of a constant is the rest of the line, as strings may contain spaces
+        let (declaration, value) = match line.split_once(" = ") {
+            Some((declaration, value)) => (declaration, Some(parse_constant(value)?)),
+            None => (line, None),
+        };
+        let mut tokens = declaration.split_whitespace().peekable();
+        let mut annotations = Vec::new();
+        let mut parameter_annotations = Vec::new();
+        while let Some(annotation) = tokens.next_if(|token| token.starts_with('@')) {
+            let annotation = &annotation[1..];
+            match annotation.split_once(':') {
+                Some((index, annotation)) if !annotation.is_empty() && !index.starts_with('L') => {
+                    parameter_annotations.push((index.parse().ok()?, annotation.into()));
+                }
+                _ => annotations.push(annotation.into()),
+            }
+        }
+        let access_flags = parse_modifiers(&mut tokens);
+
+        match tokens.next()? {
+            "field" => {
+                let (name, descriptor) = tokens.next()?.split_once(':')?;
+                let mut field = ApiField {
+                    name: name.into(),
+                    descriptor: descriptor.into(),
+                    access_flags,
+                    signature: None,
+                    annotations,
+                    value,
+                };
+                while let Some(keyword) = tokens.next() {
+                    match keyword {
+                        "signature" => field.signature = Some(tokens.next()?.into()),
+                        _ => return None,
+                    }
+                }
+                self.fields.push(field);
+            }
+            "method" if value.is_none() => {
+                let declaration = tokens.next()?;
+                let start = declaration.find('(')?;
+                let mut method = ApiMethod {
+                    name: declaration[..start].into(),
+                    descriptor: declaration[start..].into(),
+                    access_flags,
+                    signature: None,
+                    annotations,
+                    parameter_annotations,
+                    exceptions: Vec::new(),
+                };
+                while let Some(keyword) = tokens.next() {
+                    match keyword {
+                        "signature" => method.signature = Some(tokens.next()?.into()),
+                        "throws" => method.exceptions.extend(tokens.by_ref().map(MString::from)),
+                        _ => return None,
+                    }
+                }
+                self.methods.push(method);
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+impl fmt::Display for ApiClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let simple_name = &self.name[self.name.len() - simple_name(&self.name).len()..];
+        write!(f, "  ")?;
+        write_annotations(f, &self.annotations)?;
+        write_modifiers(f, self.access_flags)?;
+        write!(f, "{} {}", self.kind.keyword(), simple_name.display())?;
+        if let Some(signature) = &self.signature {
+            write!(f, " signature {}", signature.display())?;
+        }
+        if let Some(super_class) = &self.super_class {
+            write!(f, " extends {}", super_class.display())?;
+        }
+        if !self.interfaces.is_empty() {
+            write!(f, " implements")?;
+            for interface in &self.interfaces {
+                write!(f, " {}", interface.display())?;
+            }
+        }
+        writeln!(f, " {{")?;
+
+        for field in &self.fields {
+            write!(f, "    ")?;
+            write_annotations(f, &field.annotations)?;
+            write_modifiers(f, field.access_flags)?;
+            write!(f, "field {}:{}", field.name.display(), field.descriptor.display())?;
+            if let Some(signature) = &field.signature {
+                write!(f, " signature {}", signature.display())?;
+            }
+            if let Some(value) = &field.value {
+                write!(f, " = ")?;
+                write_constant(f, value)?;
+            }
+            writeln!(f)?;
+        }
+        for method in &self.methods {
+            write!(f, "    ")?;
+            write_annotations(f, &method.annotations)?;
+            for (index, annotation) in &method.parameter_annotations {
+                write!(f, "@{index}:{} ", annotation.display())?;
+            }
+            write_modifiers(f, method.access_flags)?;
+            write!(f, "method {}{}", method.name.display(), method.descriptor.display())?;
+            if let Some(signature) = &method.signature {
+                write!(f, " signature {}", signature.display())?;
+            }
+            if !method.exceptions.is_empty() {
+                write!(f, " throws")?;
+                for exception in &method.exceptions {
+                    write!(f, " {}", exception.display())?;
+                }
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "  }}")
+    }
+}
+
+impl ApiField {
+    fn new(field: &Field) -> ApiField {
+        let value = field.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::ConstantValue { value } if field.access_flags.contains(AccessFlags::STATIC) => {
+                Some(value.clone())
+            }
+            _ => None,
+        });
+        ApiField {
+            name: field.name.clone(),
+            descriptor: field.descriptor.clone(),
+            access_flags: modifiers(field.access_flags),
+            signature: signature(&field.attributes),
+            annotations: annotations(&field.attributes),
+            value,
+        }
+    }
+}
+
+impl ApiMethod {
+    fn new(method: &Method) -> ApiMethod {
+        let mut parameter_annotations = Vec::new();
+        let mut exceptions = Vec::new();
+        for attribute in &method.attributes {
+            match attribute {
+                Attribute::RuntimeVisibleParameterAnnotations(parameters)
+                | Attribute::RuntimeInvisibleParameterAnnotations(parameters) => {
+                    for (index, annotations) in parameters.iter().enumerate() {
+                        parameter_annotations
+                            .extend(annotations.iter().map(|annotation| (index, annotation.type_.clone())));
+                    }
+                }
+                Attribute::Exceptions(classes) => exceptions.extend(classes.iter().cloned()),
+                _ => {}
+            }
+        }
+        parameter_annotations.sort();
+        parameter_annotations.dedup();
+
+        // the flags of methods overlap with the flags of fields
+        let mut access_flags = modifiers(method.access_flags);
+        access_flags.remove(AccessFlags::VOLATILE | AccessFlags::TRANSIENT);
+        ApiMethod {
+            name: method.name.clone(),
+            descriptor: method.descriptor.clone(),
+            access_flags,
+            signature: signature(&method.attributes),
+            annotations: annotations(&method.attributes),
+            parameter_annotations,
+            exceptions,
+        }
+    }
+}
+
+/// An error that occurred while parsing an API file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    line: usize,
+    kind: ParseErrorKind,
+}
+
+impl ParseError {
+    #[must_use]
+    pub fn kind(&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    /// The line in which the error occurred, starting at 1.
+    #[must_use]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in line {}", self.kind, self.line)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    InvalidPackage,
+    InvalidClass,
+    InvalidMember,
+    UnexpectedEnd,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ParseErrorKind::*;
+
+        match *self {
+            InvalidPackage => write!(f, "invalid package"),
+            InvalidClass => write!(f, "invalid class"),
+            InvalidMember => write!(f, "invalid member"),
+            UnexpectedEnd => write!(f, "unexpected end of input"),
+        }
+    }
+}
+
+/// The package of a class, which is empty for the unnamed package.
+fn package(name: &MStr) -> &MStr {
+    let end = name.as_bytes().iter().rposition(|&byte| byte == b'/').unwrap_or(0);
+    &name[..end]
+}
+
+fn simple_name(name: &MStr) -> &MStr {
+    match name.as_bytes().iter().rposition(|&byte| byte == b'/') {
+        Some(position) => &name[position + 1..],
+        None => name,
+    }
+}
+
+/// The access flags of a nested class from its own `InnerClasses` entry.
+fn inner_access_flags(class: &Class) -> Option<AccessFlags> {
+    class.attributes.iter().find_map(|attribute| match attribute {
+        Attribute::InnerClasses(inner_classes) => inner_classes
+            .iter()
+            .find(|inner| inner.inner_class == class.name)
+            .map(|inner| inner.inner_access_flags),
+        _ => None,
+    })
+}
+
+/// Whether a class is visible outside of its library.
+///
+/// Nested classes are only exported if the classes they are nested in are exported as well.
+fn is_exported(class: &Class, classes: &[Class], depth: usize) -> bool {
+    if class
+        .access_flags
+        .intersects(AccessFlags::SYNTHETIC | AccessFlags::MODULE)
+        || depth > classes.len()
+    {
+        return false;
+    }
+    let entry = class.attributes.iter().find_map(|attribute| match attribute {
+        Attribute::InnerClasses(inner_classes) => inner_classes.iter().find(|inner| inner.inner_class == class.name),
+        _ => None,
+    });
+    match entry {
+        Some(entry) => {
+            // local and anonymous classes have no outer class
+            let Some(outer_class) = &entry.outer_class else {
+                return false;
+            };
+            entry
+                .inner_access_flags
+                .intersects(AccessFlags::PUBLIC | AccessFlags::PROTECTED)
+                && classes
+                    .iter()
+                    .find(|class| class.name == *outer_class)
+                    .map_or(true, |outer| is_exported(outer, classes, depth + 1))
+        }
+        None => class.access_flags.contains(AccessFlags::PUBLIC),
+    }
+}
+
+fn is_exported_member(access_flags: AccessFlags) -> bool {
+    access_flags.intersects(AccessFlags::PUBLIC | AccessFlags::PROTECTED)
+        && !access_flags.contains(AccessFlags::SYNTHETIC)
+}
+
+fn modifiers(access_flags: AccessFlags) -> AccessFlags {
+    MODIFIERS
+        .iter()
+        .filter(|(flag, _)| access_flags.contains(*flag))
+        .fold(AccessFlags::empty(), |flags, (flag, _)| flags | *flag)
+}
+
+fn signature(attributes: &[Attribute]) -> Option<MString> {
+    attributes.iter().find_map(|attribute| match attribute {
+        Attribute::Signature(signature) => Some(signature.clone()),
+        _ => None,
+    })
+}
+
+/// The types of the visible and invisible annotations, sorted.
+fn annotations(attributes: &[Attribute]) -> Vec<MString> {
+    let mut types: Vec<_> = attributes
+        .iter()
+        .flat_map(|attribute| match attribute {
+            Attribute::RuntimeVisibleAnnotations(annotations) | Attribute::RuntimeInvisibleAnnotations(annotations) => {
+                &annotations[..]
+            }
+            _ => &[],
+        })
+        .map(|annotation: &Annotation| annotation.type_.clone())
+        .collect();
+    types.sort();
+    types.dedup();
+    types
+}
+
+fn write_annotations(f: &mut fmt::Formatter<'_>, annotations: &[MString]) -> fmt::Result {
+    for annotation in annotations {
+        write!(f, "@{} ", annotation.display())?;
+    }
+    Ok(())
+}
+
+fn write_modifiers(f: &mut fmt::Formatter<'_>, access_flags: AccessFlags) -> fmt::Result {
+    for (flag, name) in MODIFIERS {
+        if access_flags.contains(flag) {
+            write!(f, "{name} ")?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_annotations<'a, I: Iterator<Item = &'a str>>(tokens: &mut std::iter::Peekable<I>) -> Vec<MString> {
+    let mut annotations = Vec::new();
+    while let Some(annotation) = tokens.next_if(|token| token.starts_with('@') && *token != "@interface") {
+        annotations.push(annotation[1..].into());
+    }
+    annotations
+}
+
+fn parse_modifiers<'a, I: Iterator<Item = &'a str>>(tokens: &mut std::iter::Peekable<I>) -> AccessFlags {
+    let mut access_flags = AccessFlags::empty();
+    while let Some(&(flag, _)) = tokens
+        .peek()
+        .and_then(|token| MODIFIERS.iter().find(|(_, name)| name == token))
+    {
+        access_flags |= flag;
+        tokens.next();
+    }
+    access_flags
+}
+
+/// Writes a constant like a Java literal, with a suffix for every type except `int`.
+///
+/// Floating point numbers are written in scientific notation, with the fewest digits that are parsed back exactly.
+fn write_constant(f: &mut fmt::Formatter<'_>, constant: &Constant) -> fmt::Result {
+    match constant {
+        Constant::Integer(value) => write!(f, "{value}"),
+        Constant::Long(value) => write!(f, "{value}L"),
+        Constant::Float(value) if value.is_finite() => write!(f, "{value:e}f"),
+        Constant::Double(value) if value.is_finite() => write!(f, "{value:e}d"),
+        Constant::Float(value) => write!(f, "{}f", non_finite(f64::from(*value))),
+        Constant::Double(value) => write!(f, "{}d", non_finite(*value)),
+        Constant::String(value) => {
+            write!(f, "\"")?;
+            for ch in value.chars() {
+                match ch {
+                    Ok('"') => write!(f, "\\\"")?,
+                    Ok('\\') => write!(f, "\\\\")?,
+                    Ok('\n') => write!(f, "\\n")?,
+                    Ok('\r') => write!(f, "\\r")?,
+                    Ok('\t') => write!(f, "\\t")?,
+                    Ok(ch) if !ch.is_control() => write!(f, "{ch}")?,
+                    Ok(ch) => write!(f, "\\u{:04x}", u32::from(ch))?,
+                    Err(code) => write!(f, "\\u{code:04x}")?,
+                }
+            }
+            write!(f, "\"")
+        }
+        _ => write!(f, "?"),
+    }
+}
+
+fn non_finite(value: f64) -> &'static str {
+    if value.is_nan() {
+        "NaN"
+    } else if value.is_sign_positive() {
+        "Infinity"
+    } else {
+        "-Infinity"
+    }
+}
+
+fn parse_constant(value: &str) -> Option<Constant> {
+    if let Some(string) = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) {
+        return parse_string(string).map(Constant::String);
+    }
+    if let Some(value) = value.strip_suffix('L') {
+        value.parse().ok().map(Constant::Long)
+    } else if let Some(value) = value.strip_suffix('f') {
+        value.parse().ok().map(Constant::Float)
+    } else if let Some(value) = value.strip_suffix('d') {
+        value.parse().ok().map(Constant::Double)
+    } else {
+        value.parse().ok().map(Constant::Integer)
+    }
+}
+
+/// Parses the content of a string literal, where unpaired surrogates may be escaped.
+fn parse_string(string: &str) -> Option<MString> {
+    let mut units: Vec<u16> = Vec::with_capacity(string.len());
+    let mut chars = string.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            units.extend(ch.encode_utf16(&mut [0; 2]).iter());
+            continue;
+        }
+        match chars.next()? {
+            '"' => units.push(u16::from(b'"')),
+            '\\' => units.push(u16::from(b'\\')),
+            'n' => units.push(u16::from(b'\n')),
+            'r' => units.push(u16::from(b'\r')),
+            't' => units.push(u16::from(b'\t')),
+            'u' => {
+                let code: String = chars.by_ref().take(4).collect();
+                units.push(u16::from_str_radix(&code, 16).ok()?);
+            }
+            _ => return None,
+        }
+    }
+
+    // modified UTF-8 encodes every UTF-16 code unit on its own
+    let mut bytes = Vec::with_capacity(units.len());
+    for unit in units {
+        match unit {
+            1..=0x7F => bytes.push(unit as u8),
+            0 | 0x80..=0x7FF => bytes.extend([0xC0 | (unit >> 6) as u8, 0x80 | (unit & 0x3F) as u8]),
+            _ => bytes.extend([
+                0xE0 | (unit >> 12) as u8,
+                0x80 | ((unit >> 6) & 0x3F) as u8,
+                0x80 | (unit & 0x3F) as u8,
+            ]),
+        }
+    }
+    MString::from_mutf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{Export, InnerClass, Module};
+    use crate::Version;
+
+    fn class(name: &str, access_flags: AccessFlags, attributes: Vec<Attribute>) -> Class {
+        Class {
+            version: Version::V11,
+            access_flags,
+            attributes,
+            ..Class::new(name)
+        }
+    }
+
+    fn inner(inner_class: &str, outer_class: &str, inner_access_flags: AccessFlags) -> Attribute {
+        Attribute::InnerClasses(vec![InnerClass {
+            inner_class: inner_class.into(),
+            outer_class: Some(outer_class.into()),
+            inner_name: None,
+            inner_access_flags,
+        }])
+    }
+
+    #[test]
+    fn exported_classes() {
+        let public = AccessFlags::PUBLIC | AccessFlags::SUPER;
+        let module = class(
+            "module-info",
+            AccessFlags::MODULE,
+            vec![Attribute::Module(Box::new(Module {
+                name: "example".into(),
+                flags: AccessFlags::empty(),
+                version: None,
+                requires: Vec::new(),
+                exports: vec![
+                    Export {
+                        package: "com/example".into(),
+                        flags: AccessFlags::empty(),
+                        exports_to: Vec::new(),
+                    },
+                    Export {
+                        package: "com/example/internal".into(),
+                        flags: AccessFlags::empty(),
+                        exports_to: vec!["friend".into()],
+                    },
+                ],
+                opens: Vec::new(),
+                uses: Vec::new(),
+                provides: Vec::new(),
+            }))],
+        );
+        let classes = [
+            module,
+            class("com/example/internal/Util", public, Vec::new()),
+            class("com/example/Api", public, Vec::new()),
+            class("com/example/Hidden", AccessFlags::SUPER, Vec::new()),
+            class(
+                "com/example/Api$Nested",
+                public,
+                vec![inner(
+                    "com/example/Api$Nested",
+                    "com/example/Api",
+                    AccessFlags::PROTECTED | AccessFlags::STATIC,
+                )],
+            ),
+            class(
+                "com/example/Hidden$Nested",
+                public,
+                vec![inner(
+                    "com/example/Hidden$Nested",
+                    "com/example/Hidden",
+                    AccessFlags::PUBLIC,
+                )],
+            ),
+        ];
+
+        let api = Api::from_classes(&classes);
+        let names: Vec<_> = api.classes.iter().map(|class| class.name.clone()).collect();
+        assert_eq!(
+            names,
+            [
+                MString::from("com/example/Api"),
+                MString::from("com/example/Api$Nested")
+            ]
+        );
+        assert_eq!(
+            api.classes[1].access_flags,
+            AccessFlags::PROTECTED | AccessFlags::STATIC
+        );
+        assert_eq!(Api::parse(&api.to_string()), Ok(api));
+    }
+
+    #[test]
+    fn constants() {
+        for constant in [
+            Constant::Integer(-3),
+            Constant::Long(i64::MIN),
+            Constant::Float(f32::MAX),
+            Constant::Float(f32::NEG_INFINITY),
+            Constant::Double(f64::MIN_POSITIVE),
+            Constant::Double(-0.0),
+            Constant::String("tab\t \u{1F600} \\ \"".into()),
+        ] {
+            let api = Api {
+                classes: vec![ApiClass {
+                    name: "Constants".into(),
+                    kind: ClassKind::Interface,
+                    access_flags: AccessFlags::PUBLIC,
+                    signature: None,
+                    super_class: None,
+                    interfaces: Vec::new(),
+                    annotations: Vec::new(),
+                    fields: vec![ApiField {
+                        name: "VALUE".into(),
+                        descriptor: "I".into(),
+                        access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC | AccessFlags::FINAL,
+                        signature: None,
+                        annotations: Vec::new(),
+                        value: Some(constant),
+                    }],
+                    methods: Vec::new(),
+                }],
+            };
+            assert_eq!(Api::parse(&api.to_string()), Ok(api));
+        }
+    }
+}
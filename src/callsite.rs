@@ -0,0 +1,480 @@
+//! Resolution of the call sites of `invokedynamic` instructions.
+//!
+//! The bootstrap methods of the JDK which are used by `javac` are recognized and their static arguments are
+//! decoded. Any other call site, or one whose arguments don't match its bootstrap method, is described generically.
+//!
+//! ```
+//! use noak::callsite::{CallSite, Resolver};
+//! use noak::reader::cpool::MethodKind;
+//! use noak::tree::{Attribute, BootstrapMethod, Class, Constant, DynamicConstant, MemberRef, MethodHandle};
+//!
+//! let method_handle = |kind, class: &str, name: &str, descriptor: &str| MethodHandle {
+//!     kind,
+//!     reference: MemberRef {
+//!         class: class.into(),
+//!         name: name.into(),
+//!         descriptor: descriptor.into(),
+//!     },
+//!     interface: false,
+//! };
+//! // Function<String, Integer> length = String::length;
+//! let class = Class {
+//!     attributes: vec![Attribute::BootstrapMethods(vec![BootstrapMethod {
+//!         method: method_handle(
+//!             MethodKind::InvokeStatic,
+//!             "java/lang/invoke/LambdaMetafactory",
+//!             "metafactory",
+//!             "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;\
+//!              Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)\
+//!              Ljava/lang/invoke/CallSite;",
+//!         ),
+//!         arguments: vec![
+//!             Constant::MethodType("(Ljava/lang/Object;)Ljava/lang/Object;".into()),
+//!             Constant::MethodHandle(method_handle(MethodKind::InvokeVirtual, "java/lang/String", "length", "()I")),
+//!             Constant::MethodType("(Ljava/lang/String;)Ljava/lang/Integer;".into()),
+//!         ],
+//!     }])],
+//!     ..Class::new("Main")
+//! };
+//! let call_site = DynamicConstant {
+//!     bootstrap_method: 0,
+//!     name: "apply".into(),
+//!     descriptor: "()Ljava/util/function/Function;".into(),
+//! };
+//! let CallSite::Lambda(lambda) = Resolver::new(&class).resolve(&call_site)? else {
+//!     panic!("not a lambda");
+//! };
+//! assert_eq!(*lambda.functional_interface, "java/util/function/Function");
+//! assert_eq!(*lambda.method_name, "apply");
+//! assert_eq!(*lambda.implementation.reference.name, "length");
+//! assert!(lambda.captured.is_empty());
+//! # Ok::<(), noak::error::DecodeError>(())
+//! ```
+
+use crate::descriptor::{BaseType, MethodDescriptor};
+use crate::error::*;
+use crate::mutf8::{MStr, MString};
+use crate::reader::cpool::MethodKind;
+use crate::tree::{Attribute, BootstrapMethod, Class, Constant, DynamicConstant, MethodHandle};
+
+/// Resolves the call sites of a class using its `BootstrapMethods` attribute.
+#[derive(Debug, Clone)]
+pub struct Resolver<'a> {
+    bootstrap_methods: &'a [BootstrapMethod],
+}
+
+impl<'a> Resolver<'a> {
+    #[must_use]
+    pub fn new(class: &'a Class) -> Resolver<'a> {
+        let bootstrap_methods = class
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::BootstrapMethods(methods) => Some(&methods[..]),
+                _ => None,
+            })
+            .unwrap_or_default();
+        Resolver { bootstrap_methods }
+    }
+
+    /// Describes the call site of an `invokedynamic` instruction.
+    ///
+    /// Fails if the bootstrap method doesn't exist or the descriptor of the call site is invalid.
+    pub fn resolve(&self, call_site: &DynamicConstant) -> Result<CallSite, DecodeError> {
+        let bootstrap = self
+            .bootstrap_methods
+            .get(usize::from(call_site.bootstrap_method))
+            .ok_or_else(|| DecodeError::new(DecodeErrorKind::InvalidIndex))?;
+        let descriptor = MethodDescriptor::parse(&call_site.descriptor)?;
+
+        let method = &bootstrap.method;
+        let resolved = if method.kind == MethodKind::InvokeStatic {
+            let reference = &method.reference;
+            match (reference.class.as_bytes(), reference.name.as_bytes()) {
+                (b"java/lang/invoke/LambdaMetafactory", b"metafactory") => {
+                    lambda(call_site, &descriptor, &bootstrap.arguments, false).map(CallSite::Lambda)
+                }
+                (b"java/lang/invoke/LambdaMetafactory", b"altMetafactory") => {
+                    lambda(call_site, &descriptor, &bootstrap.arguments, true).map(CallSite::Lambda)
+                }
+                (b"java/lang/invoke/StringConcatFactory", b"makeConcat") => {
+                    let parts = parameters(&descriptor)
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, descriptor)| ConcatPart::Argument { index, descriptor })
+                        .collect();
+                    Some(CallSite::StringConcat(StringConcat { parts }))
+                }
+                (b"java/lang/invoke/StringConcatFactory", b"makeConcatWithConstants") => {
+                    string_concat(&descriptor, &bootstrap.arguments).map(CallSite::StringConcat)
+                }
+                (b"java/lang/runtime/ObjectMethods", b"bootstrap") => {
+                    object_methods(call_site, &bootstrap.arguments).map(CallSite::ObjectMethods)
+                }
+                (b"java/lang/runtime/SwitchBootstraps", b"typeSwitch") => Some(CallSite::Switch(Switch {
+                    kind: SwitchKind::Type,
+                    labels: bootstrap.arguments.clone(),
+                })),
+                (b"java/lang/runtime/SwitchBootstraps", b"enumSwitch") => Some(CallSite::Switch(Switch {
+                    kind: SwitchKind::Enum,
+                    labels: bootstrap.arguments.clone(),
+                })),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        Ok(resolved.unwrap_or_else(|| CallSite::Generic {
+            bootstrap: method.clone(),
+            arguments: bootstrap.arguments.clone(),
+        }))
+    }
+}
+
+/// A call site of an `invokedynamic` instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CallSite {
+    /// A lambda expression or method reference, created by `LambdaMetafactory`.
+    Lambda(Lambda),
+    /// A string concatenation, created by `StringConcatFactory`.
+    StringConcat(StringConcat),
+    /// The `equals`, `hashCode` or `toString` method of a record, created by `ObjectMethods`.
+    ObjectMethods(ObjectMethods),
+    /// A switch on patterns or enum constants, created by `SwitchBootstraps`.
+    Switch(Switch),
+    /// Any other call site.
+    Generic {
+        bootstrap: MethodHandle,
+        arguments: Vec<Constant>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lambda {
+    /// The name of the single abstract method of the functional interface.
+    pub method_name: MString,
+    pub functional_interface: MString,
+    /// The types of the captured values, which are passed to the call site.
+    pub captured: Vec<MString>,
+    /// The erased descriptor of the single abstract method.
+    pub sam_descriptor: MString,
+    /// The method which implements the single abstract method.
+    pub implementation: MethodHandle,
+    /// The descriptor of the single abstract method with the types of the implementation.
+    pub instantiated_descriptor: MString,
+    pub is_serializable: bool,
+    /// Further interfaces implemented by the lambda.
+    pub marker_interfaces: Vec<MString>,
+    /// Further descriptors of the single abstract method which need bridge methods.
+    pub bridges: Vec<MString>,
+}
+
+/// A string concatenation, whose parts are concatenated in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StringConcat {
+    pub parts: Vec<ConcatPart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConcatPart {
+    /// Text which is part of the recipe.
+    Literal(MString),
+    /// An argument of the call site.
+    Argument { index: usize, descriptor: MString },
+    /// A constant, which is passed as a static argument to not confuse it with the tags of the recipe.
+    Constant(Constant),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectMethods {
+    /// The name of the method, `equals`, `hashCode` or `toString`.
+    pub method: MString,
+    pub record: MString,
+    pub components: Vec<RecordComponent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordComponent {
+    pub name: MString,
+    /// The method handle which reads the field of the component.
+    pub getter: MethodHandle,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Switch {
+    pub kind: SwitchKind,
+    /// The labels of the cases in order, e.g. classes, strings, integers or enum constants.
+    pub labels: Vec<Constant>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SwitchKind {
+    /// A switch on the type of a value, `SwitchBootstraps.typeSwitch`.
+    Type,
+    /// A switch on enum constants and types, `SwitchBootstraps.enumSwitch`.
+    Enum,
+}
+
+fn parameters(descriptor: &MethodDescriptor<'_>) -> Vec<MString> {
+    descriptor
+        .parameters()
+        .map(|parameter| MString::from(&*parameter.to_string()))
+        .collect()
+}
+
+fn lambda(
+    call_site: &DynamicConstant,
+    descriptor: &MethodDescriptor<'_>,
+    arguments: &[Constant],
+    alternative: bool,
+) -> Option<Lambda> {
+    const FLAG_SERIALIZABLE: i32 = 1 << 0;
+    const FLAG_MARKERS: i32 = 1 << 1;
+    const FLAG_BRIDGES: i32 = 1 << 2;
+
+    let functional_interface = match descriptor.return_type() {
+        Some(type_) if type_.dimensions == 0 => match type_.base {
+            BaseType::Object(name) => MString::from(name),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let (sam_descriptor, implementation, instantiated_descriptor, rest) = match arguments {
+        [Constant::MethodType(sam), Constant::MethodHandle(implementation), Constant::MethodType(instantiated), rest @ ..] => {
+            (sam, implementation, instantiated, rest)
+        }
+        _ => return None,
+    };
+    let mut lambda = Lambda {
+        method_name: call_site.name.clone(),
+        functional_interface,
+        captured: parameters(descriptor),
+        sam_descriptor: sam_descriptor.clone(),
+        implementation: implementation.clone(),
+        instantiated_descriptor: instantiated_descriptor.clone(),
+        is_serializable: false,
+        marker_interfaces: Vec::new(),
+        bridges: Vec::new(),
+    };
+    if !alternative {
+        return rest.is_empty().then_some(lambda);
+    }
+
+    let (flags, mut rest) = match rest {
+        [Constant::Integer(flags), rest @ ..] => (*flags, rest),
+        _ => return None,
+    };
+    lambda.is_serializable = flags & FLAG_SERIALIZABLE != 0;
+    // both lists are preceded by their length
+    let mut list = |present: bool| -> Option<&[Constant]> {
+        if !present {
+            return Some(&[]);
+        }
+        let (count, tail) = match rest {
+            [Constant::Integer(count), tail @ ..] => (usize::try_from(*count).ok()?, tail),
+            _ => return None,
+        };
+        let list = tail.get(..count)?;
+        rest = &tail[count..];
+        Some(list)
+    };
+    for marker in list(flags & FLAG_MARKERS != 0)? {
+        match marker {
+            Constant::Class(name) => lambda.marker_interfaces.push(name.clone()),
+            _ => return None,
+        }
+    }
+    for bridge in list(flags & FLAG_BRIDGES != 0)? {
+        match bridge {
+            Constant::MethodType(descriptor) => lambda.bridges.push(descriptor.clone()),
+            _ => return None,
+        }
+    }
+    rest.is_empty().then_some(lambda)
+}
+
+fn string_concat(descriptor: &MethodDescriptor<'_>, arguments: &[Constant]) -> Option<StringConcat> {
+    const TAG_ARGUMENT: char = '\u{1}';
+    const TAG_CONSTANT: char = '\u{2}';
+
+    let (recipe, constants) = match arguments {
+        [Constant::String(recipe), constants @ ..] => (recipe, constants),
+        _ => return None,
+    };
+    let mut parameters = parameters(descriptor).into_iter().enumerate();
+    let mut constants = constants.iter();
+    let mut parts = Vec::new();
+    let mut literal = MString::new();
+    let flush = |parts: &mut Vec<ConcatPart>, literal: &mut MString| {
+        if !literal.is_empty() {
+            parts.push(ConcatPart::Literal(std::mem::take(literal)));
+        }
+    };
+    for ch in recipe.chars_lossy() {
+        match ch {
+            TAG_ARGUMENT => {
+                flush(&mut parts, &mut literal);
+                let (index, descriptor) = parameters.next()?;
+                parts.push(ConcatPart::Argument { index, descriptor });
+            }
+            TAG_CONSTANT => {
+                flush(&mut parts, &mut literal);
+                parts.push(ConcatPart::Constant(constants.next()?.clone()));
+            }
+            ch => literal.push(ch),
+        }
+    }
+    flush(&mut parts, &mut literal);
+
+    // every argument and constant is used exactly once
+    if parameters.next().is_some() || constants.next().is_some() {
+        return None;
+    }
+    Some(StringConcat { parts })
+}
+
+fn object_methods(call_site: &DynamicConstant, arguments: &[Constant]) -> Option<ObjectMethods> {
+    let (record, names, getters) = match arguments {
+        [Constant::Class(record), Constant::String(names), getters @ ..] => (record, names, getters),
+        _ => return None,
+    };
+    let names: Vec<&[u8]> = if names.is_empty() {
+        Vec::new()
+    } else {
+        names.as_bytes().split(|&byte| byte == b';').collect()
+    };
+    if names.len() != getters.len() {
+        return None;
+    }
+
+    let mut components = Vec::new();
+    for (name, getter) in names.into_iter().zip(getters) {
+        match getter {
+            Constant::MethodHandle(getter) => components.push(RecordComponent {
+                name: MString::from(MStr::from_mutf8(name).ok()?),
+                getter: getter.clone(),
+            }),
+            _ => return None,
+        }
+    }
+    Some(ObjectMethods {
+        method: call_site.name.clone(),
+        record: record.clone(),
+        components,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::MemberRef;
+
+    fn resolve(bootstrap: (&str, &str), arguments: Vec<Constant>, name: &str, descriptor: &str) -> CallSite {
+        let method = BootstrapMethod {
+            method: MethodHandle {
+                kind: MethodKind::InvokeStatic,
+                reference: MemberRef {
+                    class: bootstrap.0.into(),
+                    name: bootstrap.1.into(),
+                    descriptor: "()V".into(),
+                },
+                interface: false,
+            },
+            arguments,
+        };
+        let resolver = Resolver {
+            bootstrap_methods: &[method],
+        };
+        resolver
+            .resolve(&DynamicConstant {
+                bootstrap_method: 0,
+                name: name.into(),
+                descriptor: descriptor.into(),
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn string_concat() {
+        let factory = ("java/lang/invoke/StringConcatFactory", "makeConcatWithConstants");
+        let call_site = resolve(
+            factory,
+            vec![
+                Constant::String("x = \u{1}, \u{2}\u{1}!".into()),
+                Constant::String("\u{1}".into()),
+            ],
+            "makeConcatWithConstants",
+            "(ILjava/lang/String;)Ljava/lang/String;",
+        );
+        assert_eq!(
+            call_site,
+            CallSite::StringConcat(StringConcat {
+                parts: vec![
+                    ConcatPart::Literal("x = ".into()),
+                    ConcatPart::Argument {
+                        index: 0,
+                        descriptor: "I".into()
+                    },
+                    ConcatPart::Literal(", ".into()),
+                    ConcatPart::Constant(Constant::String("\u{1}".into())),
+                    ConcatPart::Argument {
+                        index: 1,
+                        descriptor: "Ljava/lang/String;".into()
+                    },
+                    ConcatPart::Literal("!".into()),
+                ]
+            })
+        );
+
+        // the recipe uses more arguments than there are
+        let call_site = resolve(
+            factory,
+            vec![Constant::String("\u{1}\u{1}".into())],
+            "makeConcatWithConstants",
+            "(I)Ljava/lang/String;",
+        );
+        assert!(matches!(call_site, CallSite::Generic { .. }));
+    }
+
+    #[test]
+    fn object_methods() {
+        let getter = |name: &str| {
+            Constant::MethodHandle(MethodHandle {
+                kind: MethodKind::GetField,
+                reference: MemberRef {
+                    class: "Point".into(),
+                    name: name.into(),
+                    descriptor: "I".into(),
+                },
+                interface: false,
+            })
+        };
+        let call_site = resolve(
+            ("java/lang/runtime/ObjectMethods", "bootstrap"),
+            vec![
+                Constant::Class("Point".into()),
+                Constant::String("x;y".into()),
+                getter("x"),
+                getter("y"),
+            ],
+            "hashCode",
+            "(LPoint;)I",
+        );
+        let CallSite::ObjectMethods(methods) = call_site else {
+            panic!("not resolved: {call_site:?}");
+        };
+        assert_eq!(*methods.method, "hashCode");
+        assert_eq!(*methods.record, "Point");
+        assert_eq!(methods.components.len(), 2);
+        assert_eq!(*methods.components[1].name, "y");
+    }
+}
@@ -0,0 +1,650 @@
+//! Checking of the binary compatibility between two versions of a library.
+//!
+//! The rules follow chapter 13 of the Java Language Specification.
+//! Only the exported parts of the library are checked: public classes and public or protected nested classes,
+//! and their public and protected members. Synthetic members and static initializers are ignored.
+//!
+//! Changes which break existing binaries, such as the removal of a method, are reported as binary
+//! incompatibilities. Changes which existing binaries still link against, but which break the compilation
+//! of existing sources, such as an additional checked exception, are reported separately.
+//!
+//! ```
+//! use noak::compat::{self, IncompatibilityKind};
+//! use noak::tree::{Class, Method};
+//! use noak::AccessFlags;
+//!
+//! let old = Class {
+//!     methods: vec![Method {
+//!         access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC | AccessFlags::NATIVE,
+//!         name: "run".into(),
+//!         descriptor: "()V".into(),
+//!         attributes: Vec::new(),
+//!     }],
+//!     ..Class::new("com/example/Api")
+//! };
+//! let mut new = old.clone();
+//! new.methods[0].access_flags.remove(AccessFlags::STATIC);
+//!
+//! let report = compat::check(&[old], &[new]);
+//! assert_eq!(report.binary()[0].kind(), &IncompatibilityKind::MemberMadeNonStatic);
+//! assert_eq!(report.binary()[0].to_string(), "com/example/Api.run()V: the member is no longer static");
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::header::AccessFlags;
+use crate::mutf8::MString;
+use crate::tree::{Attribute, Class, Field, Method, NameAndType};
+
+/// Checks which changes between the old and new classes of a library are incompatible.
+///
+/// Both sets should contain every class of the library. Supertypes which are not part of a set are only known
+/// by their name, so a change of their hierarchy isn't detected.
+#[must_use]
+pub fn check(old: &[Class], new: &[Class]) -> Report {
+    let old_classes = Classes::new(old);
+    let new_classes = Classes::new(new);
+    let mut report = Report::default();
+
+    for old_class in old.iter().filter(|class| old_classes.is_exported(class)) {
+        let mut checker = Checker {
+            report: &mut report,
+            class: &old_class.name,
+        };
+        let Some(new_class) = new_classes.get(&old_class.name) else {
+            checker.binary(None, IncompatibilityKind::ClassRemoved);
+            continue;
+        };
+        if !new_classes.is_exported(new_class) {
+            checker.binary(None, IncompatibilityKind::ClassLessAccessible);
+            continue;
+        }
+        checker.class(old_class, new_class, &old_classes, &new_classes);
+    }
+
+    report
+}
+
+/// The incompatibilities between two versions of a library.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    binary: Vec<Incompatibility>,
+    source: Vec<Incompatibility>,
+}
+
+impl Report {
+    /// The changes which break existing binaries.
+    #[must_use]
+    pub fn binary(&self) -> &[Incompatibility] {
+        &self.binary
+    }
+
+    /// The changes which only break the compilation of existing sources.
+    #[must_use]
+    pub fn source(&self) -> &[Incompatibility] {
+        &self.source
+    }
+
+    /// Whether existing binaries still link against the new version.
+    #[must_use]
+    pub fn is_binary_compatible(&self) -> bool {
+        self.binary.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for incompatibility in &self.binary {
+            writeln!(f, "binary: {incompatibility}")?;
+        }
+        for incompatibility in &self.source {
+            writeln!(f, "source: {incompatibility}")?;
+        }
+        Ok(())
+    }
+}
+
+/// An incompatible change of a class or one of its members.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Incompatibility {
+    class: MString,
+    member: Option<NameAndType>,
+    kind: IncompatibilityKind,
+}
+
+impl Incompatibility {
+    /// The name of the class.
+    #[must_use]
+    pub fn class(&self) -> &MString {
+        &self.class
+    }
+
+    /// The field or method of the old class, `None` if the class itself is affected.
+    #[must_use]
+    pub fn member(&self) -> Option<&NameAndType> {
+        self.member.as_ref()
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> &IncompatibilityKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.class.display())?;
+        if let Some(member) = &self.member {
+            if member.descriptor.as_bytes().starts_with(b"(") {
+                write!(f, ".{}{}", member.name.display(), member.descriptor.display())?;
+            } else {
+                write!(f, ".{}:{}", member.name.display(), member.descriptor.display())?;
+            }
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IncompatibilityKind {
+    ClassRemoved,
+    /// The class is no longer public, or protected if it is nested.
+    ClassLessAccessible,
+    /// The class was changed from a class to an interface or the other way around.
+    ClassKindChanged,
+    ClassMadeFinal,
+    ClassMadeAbstract,
+    /// The class was sealed, so existing subclasses may no longer be permitted.
+    ClassMadeSealed,
+    /// A class is no longer a direct or indirect superclass of the class.
+    SuperclassRemoved(MString),
+    /// An interface is no longer a direct or indirect superinterface of the class.
+    InterfaceRemoved(MString),
+    /// The member is neither declared by the class nor inherited.
+    MemberRemoved,
+    /// The member is only declared with a different descriptor.
+    DescriptorChanged(MString),
+    MemberLessAccessible,
+    MemberMadeFinal,
+    MemberMadeStatic,
+    MemberMadeNonStatic,
+    MethodMadeAbstract,
+    /// An abstract method was added to an interface or an abstract class.
+    ///
+    /// While existing subclasses still link, invoking the method on them causes an `AbstractMethodError`.
+    AbstractMethodAdded,
+    /// The method declares an additional exception. Only reported as a source incompatibility.
+    ExceptionAdded(MString),
+    /// The method no longer declares an exception. Only reported as a source incompatibility.
+    ExceptionRemoved(MString),
+    /// The generic signature changed while the descriptor stayed the same.
+    /// Only reported as a source incompatibility.
+    SignatureChanged,
+}
+
+impl fmt::Display for IncompatibilityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use IncompatibilityKind::*;
+
+        match self {
+            ClassRemoved => write!(f, "the class was removed"),
+            ClassLessAccessible => write!(f, "the class is less accessible"),
+            ClassKindChanged => write!(f, "the class was changed between a class and an interface"),
+            ClassMadeFinal => write!(f, "the class is now final"),
+            ClassMadeAbstract => write!(f, "the class is now abstract"),
+            ClassMadeSealed => write!(f, "the class is now sealed"),
+            SuperclassRemoved(class) => write!(f, "{} is no longer a superclass", class.display()),
+            InterfaceRemoved(interface) => write!(f, "{} is no longer a superinterface", interface.display()),
+            MemberRemoved => write!(f, "the member was removed"),
+            DescriptorChanged(descriptor) => write!(f, "the descriptor was changed to {}", descriptor.display()),
+            MemberLessAccessible => write!(f, "the member is less accessible"),
+            MemberMadeFinal => write!(f, "the member is now final"),
+            MemberMadeStatic => write!(f, "the member is now static"),
+            MemberMadeNonStatic => write!(f, "the member is no longer static"),
+            MethodMadeAbstract => write!(f, "the method is now abstract"),
+            AbstractMethodAdded => write!(f, "an abstract method was added"),
+            ExceptionAdded(exception) => write!(f, "the method now throws {}", exception.display()),
+            ExceptionRemoved(exception) => write!(f, "the method no longer throws {}", exception.display()),
+            SignatureChanged => write!(f, "the generic signature was changed"),
+        }
+    }
+}
+
+/// The classes of one version of the library, by their names.
+struct Classes<'a> {
+    by_name: HashMap<&'a MString, &'a Class>,
+}
+
+impl<'a> Classes<'a> {
+    fn new(classes: &'a [Class]) -> Classes<'a> {
+        Classes {
+            by_name: classes.iter().map(|class| (&class.name, class)).collect(),
+        }
+    }
+
+    fn get(&self, name: &MString) -> Option<&'a Class> {
+        self.by_name.get(name).copied()
+    }
+
+    /// Whether the class is visible outside of its package.
+    ///
+    /// The access flags of nested classes are taken from their `InnerClasses` attribute.
+    fn is_exported(&self, class: &Class) -> bool {
+        let inner_flags = class.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::InnerClasses(inner_classes) => inner_classes
+                .iter()
+                .find(|inner| inner.inner_class == class.name)
+                .map(|inner| inner.inner_access_flags),
+            _ => None,
+        });
+        match inner_flags {
+            Some(flags) => flags.intersects(AccessFlags::PUBLIC | AccessFlags::PROTECTED),
+            None => class.access_flags.contains(AccessFlags::PUBLIC),
+        }
+    }
+
+    /// The direct and indirect superclasses of a class.
+    fn superclasses(&self, class: &'a Class) -> Vec<&'a MString> {
+        let mut superclasses = Vec::new();
+        let mut current = class.super_class.as_ref();
+        while let Some(name) = current {
+            if superclasses.contains(&name) {
+                break;
+            }
+            superclasses.push(name);
+            current = self.get(name).and_then(|class| class.super_class.as_ref());
+        }
+        superclasses
+    }
+
+    /// The direct and indirect superinterfaces of a class.
+    fn interfaces(&self, class: &'a Class) -> HashSet<&'a MString> {
+        let mut interfaces = HashSet::new();
+        let mut pending: Vec<&MString> = class.interfaces.iter().collect();
+        for superclass in self.superclasses(class) {
+            pending.extend(self.get(superclass).into_iter().flat_map(|class| &class.interfaces));
+        }
+        while let Some(interface) = pending.pop() {
+            if interfaces.insert(interface) {
+                pending.extend(self.get(interface).into_iter().flat_map(|class| &class.interfaces));
+            }
+        }
+        interfaces
+    }
+
+    /// Whether an exported member is declared by the class or one of its supertypes.
+    fn declares(&self, class: &'a Class, name: &MString, descriptor: &MString) -> bool {
+        let is_method = descriptor.as_bytes().starts_with(b"(");
+        let declared_by = |class: &Class| {
+            if is_method {
+                class.methods.iter().any(|method| {
+                    is_exported(method.access_flags) && method.name == *name && method.descriptor == *descriptor
+                })
+            } else {
+                class.fields.iter().any(|field| {
+                    is_exported(field.access_flags) && field.name == *name && field.descriptor == *descriptor
+                })
+            }
+        };
+
+        declared_by(class)
+            || self
+                .superclasses(class)
+                .into_iter()
+                .chain(self.interfaces(class))
+                .filter_map(|supertype| self.get(supertype))
+                .any(declared_by)
+    }
+}
+
+/// Whether the member is visible outside of its package.
+fn is_exported(access_flags: AccessFlags) -> bool {
+    access_flags.intersects(AccessFlags::PUBLIC | AccessFlags::PROTECTED)
+        && !access_flags.contains(AccessFlags::SYNTHETIC)
+}
+
+/// Orders the accessibility of members, from private to public.
+fn accessibility(access_flags: AccessFlags) -> u8 {
+    if access_flags.contains(AccessFlags::PUBLIC) {
+        3
+    } else if access_flags.contains(AccessFlags::PROTECTED) {
+        2
+    } else if access_flags.contains(AccessFlags::PRIVATE) {
+        0
+    } else {
+        1
+    }
+}
+
+/// The parts of fields and methods which are checked.
+struct Member<'a> {
+    access_flags: AccessFlags,
+    name: &'a MString,
+    descriptor: &'a MString,
+    attributes: &'a [Attribute],
+}
+
+impl<'a> Member<'a> {
+    fn field(field: &'a Field) -> Member<'a> {
+        Member {
+            access_flags: field.access_flags,
+            name: &field.name,
+            descriptor: &field.descriptor,
+            attributes: &field.attributes,
+        }
+    }
+
+    fn method(method: &'a Method) -> Member<'a> {
+        Member {
+            access_flags: method.access_flags,
+            name: &method.name,
+            descriptor: &method.descriptor,
+            attributes: &method.attributes,
+        }
+    }
+
+    fn id(&self) -> NameAndType {
+        NameAndType {
+            name: self.name.clone(),
+            descriptor: self.descriptor.clone(),
+        }
+    }
+
+    fn exceptions(&self) -> &'a [MString] {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Exceptions(exceptions) => Some(&exceptions[..]),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn signature(attributes: &[Attribute]) -> Option<&MString> {
+    attributes.iter().find_map(|attribute| match attribute {
+        Attribute::Signature(signature) => Some(signature),
+        _ => None,
+    })
+}
+
+struct Checker<'r> {
+    report: &'r mut Report,
+    class: &'r MString,
+}
+
+impl<'r> Checker<'r> {
+    fn binary(&mut self, member: Option<NameAndType>, kind: IncompatibilityKind) {
+        self.report.binary.push(Incompatibility {
+            class: self.class.clone(),
+            member,
+            kind,
+        });
+    }
+
+    fn source(&mut self, member: Option<NameAndType>, kind: IncompatibilityKind) {
+        self.report.source.push(Incompatibility {
+            class: self.class.clone(),
+            member,
+            kind,
+        });
+    }
+
+    fn class<'a>(&mut self, old: &'a Class, new: &'a Class, old_classes: &Classes<'a>, new_classes: &Classes<'a>) {
+        use IncompatibilityKind::*;
+
+        let (old_flags, new_flags) = (old.access_flags, new.access_flags);
+        if old_flags.contains(AccessFlags::INTERFACE) != new_flags.contains(AccessFlags::INTERFACE) {
+            self.binary(None, ClassKindChanged);
+            return;
+        }
+        // enums can't be extended outside of their declaration, javac chooses their flags by the constants
+        if !old_flags.contains(AccessFlags::ENUM) {
+            if !old_flags.contains(AccessFlags::FINAL) && new_flags.contains(AccessFlags::FINAL) {
+                self.binary(None, ClassMadeFinal);
+            }
+            if !old_flags.contains(AccessFlags::ABSTRACT) && new_flags.contains(AccessFlags::ABSTRACT) {
+                self.binary(None, ClassMadeAbstract);
+            }
+            let is_sealed = |class: &Class| {
+                class
+                    .attributes
+                    .iter()
+                    .any(|attribute| matches!(attribute, Attribute::PermittedSubclasses(_)))
+            };
+            if !old_flags.contains(AccessFlags::FINAL) && !is_sealed(old) && is_sealed(new) {
+                self.binary(None, ClassMadeSealed);
+            }
+        }
+
+        let new_superclasses = new_classes.superclasses(new);
+        for superclass in old_classes.superclasses(old) {
+            if !new_superclasses.contains(&superclass) {
+                self.binary(None, SuperclassRemoved(superclass.clone()));
+            }
+        }
+        let new_interfaces = new_classes.interfaces(new);
+        let mut old_interfaces: Vec<_> = old_classes.interfaces(old).into_iter().collect();
+        old_interfaces.sort();
+        for interface in old_interfaces {
+            if !new_interfaces.contains(interface) {
+                self.binary(None, InterfaceRemoved(interface.clone()));
+            }
+        }
+        if signature(&old.attributes) != signature(&new.attributes) {
+            self.source(None, SignatureChanged);
+        }
+
+        let old_fields: Vec<_> = old.fields.iter().map(Member::field).collect();
+        let new_fields: Vec<_> = new.fields.iter().map(Member::field).collect();
+        self.members(old, &old_fields, new, &new_fields, new_classes);
+
+        let is_initializer = |method: &&Method| *method.name == *"<clinit>";
+        let old_methods: Vec<_> = old
+            .methods
+            .iter()
+            .filter(|m| !is_initializer(m))
+            .map(Member::method)
+            .collect();
+        let new_methods: Vec<_> = new
+            .methods
+            .iter()
+            .filter(|m| !is_initializer(m))
+            .map(Member::method)
+            .collect();
+        self.members(old, &old_methods, new, &new_methods, new_classes);
+
+        // existing subclasses don't implement abstract methods added to an interface or an abstract class
+        let is_extensible = old_flags.intersects(AccessFlags::INTERFACE | AccessFlags::ABSTRACT)
+            && !old_flags.contains(AccessFlags::FINAL);
+        if is_extensible {
+            for method in &new_methods {
+                if method.access_flags.contains(AccessFlags::ABSTRACT)
+                    && is_exported(method.access_flags)
+                    && !old_classes.declares(old, method.name, method.descriptor)
+                {
+                    self.binary(Some(method.id()), AbstractMethodAdded);
+                }
+            }
+        }
+    }
+
+    fn members<'a>(
+        &mut self,
+        old_class: &'a Class,
+        old: &[Member<'a>],
+        new_class: &'a Class,
+        new: &[Member<'a>],
+        new_classes: &Classes<'a>,
+    ) {
+        use IncompatibilityKind::*;
+
+        for old_member in old.iter().filter(|member| is_exported(member.access_flags)) {
+            let id = old_member.id();
+            let Some(new_member) = new
+                .iter()
+                .find(|member| member.name == old_member.name && member.descriptor == old_member.descriptor)
+            else {
+                if new_classes.declares(new_class, old_member.name, old_member.descriptor) {
+                    continue;
+                }
+                let replacement = new.iter().find(|member| {
+                    member.name == old_member.name
+                        && is_exported(member.access_flags)
+                        && !old
+                            .iter()
+                            .any(|old| old.descriptor == member.descriptor && old.name == member.name)
+                });
+                match replacement {
+                    Some(replacement) => self.binary(Some(id), DescriptorChanged(replacement.descriptor.clone())),
+                    None => self.binary(Some(id), MemberRemoved),
+                }
+                continue;
+            };
+
+            let (old_flags, new_flags) = (old_member.access_flags, new_member.access_flags);
+            if accessibility(new_flags) < accessibility(old_flags) {
+                self.binary(Some(id.clone()), MemberLessAccessible);
+            }
+            let is_method = old_member.descriptor.as_bytes().starts_with(b"(");
+            // only fields can be assigned and only methods of non-final classes can be overridden
+            let final_matters = !is_method || !old_class.access_flags.contains(AccessFlags::FINAL);
+            if final_matters && !old_flags.contains(AccessFlags::FINAL) && new_flags.contains(AccessFlags::FINAL) {
+                self.binary(Some(id.clone()), MemberMadeFinal);
+            }
+            match (
+                old_flags.contains(AccessFlags::STATIC),
+                new_flags.contains(AccessFlags::STATIC),
+            ) {
+                (false, true) => self.binary(Some(id.clone()), MemberMadeStatic),
+                (true, false) => self.binary(Some(id.clone()), MemberMadeNonStatic),
+                _ => {}
+            }
+            if is_method && !old_flags.contains(AccessFlags::ABSTRACT) && new_flags.contains(AccessFlags::ABSTRACT) {
+                self.binary(Some(id.clone()), MethodMadeAbstract);
+            }
+
+            let (old_exceptions, new_exceptions) = (old_member.exceptions(), new_member.exceptions());
+            for exception in new_exceptions
+                .iter()
+                .filter(|exception| !old_exceptions.contains(exception))
+            {
+                self.source(Some(id.clone()), ExceptionAdded(exception.clone()));
+            }
+            for exception in old_exceptions
+                .iter()
+                .filter(|exception| !new_exceptions.contains(exception))
+            {
+                self.source(Some(id.clone()), ExceptionRemoved(exception.clone()));
+            }
+            if signature(old_member.attributes) != signature(new_member.attributes) {
+                self.source(Some(id), SignatureChanged);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(name: &str, access_flags: AccessFlags, super_class: &str, methods: Vec<Method>) -> Class {
+        Class {
+            access_flags,
+            super_class: Some(super_class.into()),
+            methods,
+            ..Class::new(name)
+        }
+    }
+
+    fn method(name: &str, descriptor: &str, access_flags: AccessFlags) -> Method {
+        Method {
+            access_flags,
+            name: name.into(),
+            descriptor: descriptor.into(),
+            attributes: Vec::new(),
+        }
+    }
+
+    fn kinds(incompatibilities: &[Incompatibility]) -> Vec<&IncompatibilityKind> {
+        incompatibilities.iter().map(Incompatibility::kind).collect()
+    }
+
+    #[test]
+    fn members_moved_to_superclass() {
+        let public = AccessFlags::PUBLIC | AccessFlags::SUPER;
+        let old = [
+            class("Base", public, "java/lang/Object", Vec::new()),
+            class("Api", public, "Base", vec![method("run", "()V", AccessFlags::PUBLIC)]),
+        ];
+        let new = [
+            class(
+                "Base",
+                public,
+                "java/lang/Object",
+                vec![method("run", "()V", AccessFlags::PUBLIC)],
+            ),
+            class("Api", public, "Base", vec![method("stop", "(I)V", AccessFlags::PUBLIC)]),
+        ];
+        assert!(check(&old, &new).is_binary_compatible());
+
+        let new = [
+            class("Base", public, "java/lang/Object", Vec::new()),
+            class(
+                "Api",
+                public,
+                "java/lang/Object",
+                vec![method("run", "(I)V", AccessFlags::PUBLIC)],
+            ),
+        ];
+        let report = check(&old, &new);
+        assert_eq!(
+            kinds(report.binary()),
+            [
+                &IncompatibilityKind::SuperclassRemoved("Base".into()),
+                &IncompatibilityKind::DescriptorChanged("(I)V".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn abstract_methods_added_to_interfaces() {
+        let interface = AccessFlags::PUBLIC | AccessFlags::INTERFACE | AccessFlags::ABSTRACT;
+        let old = [class("Api", interface, "java/lang/Object", Vec::new())];
+        let new = [class(
+            "Api",
+            interface,
+            "java/lang/Object",
+            vec![
+                method("run", "()V", AccessFlags::PUBLIC | AccessFlags::ABSTRACT),
+                method("stop", "()V", AccessFlags::PUBLIC),
+            ],
+        )];
+        let report = check(&old, &new);
+        assert_eq!(kinds(report.binary()), [&IncompatibilityKind::AbstractMethodAdded]);
+        assert_eq!(report.binary()[0].member().unwrap().name, MString::from("run"));
+    }
+
+    #[test]
+    fn source_incompatibilities() {
+        let public = AccessFlags::PUBLIC | AccessFlags::SUPER;
+        let old = [class(
+            "Api",
+            public,
+            "java/lang/Object",
+            vec![method("run", "()V", AccessFlags::PUBLIC)],
+        )];
+        let mut new = old.clone();
+        new[0].methods[0]
+            .attributes
+            .push(Attribute::Exceptions(vec!["java/io/IOException".into()]));
+        let report = check(&old, &new);
+        assert!(report.is_binary_compatible());
+        assert_eq!(
+            report.to_string(),
+            "source: Api.run()V: the method now throws java/io/IOException\n"
+        );
+    }
+}
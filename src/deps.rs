@@ -0,0 +1,787 @@
+//! Extraction of the classes, fields and methods a class depends on, similar to jdeps.
+//!
+//! Every reference of a class is collected together with its [kind](DependencyKind) and the [location](Location)
+//! it occurs in. References are found in the hierarchy of the class, in descriptors and generic signatures,
+//! in annotations including their element values, in the instructions of methods,
+//! including the bootstrap methods and arguments of `invokedynamic`, and in attributes such as `InnerClasses`.
+//! Entries of the constant pool which aren't referenced by any of these are reported as well.
+//! References of a class to itself are omitted.
+//!
+//! The references can be aggregated by [class](Dependencies::classes), [package](Dependencies::packages)
+//! and [module](Dependencies::modules).
+//!
+//! ```
+//! use noak::deps::{Dependencies, DependencyKind, Location, Target};
+//! use noak::mutf8::MString;
+//! use noak::reader;
+//! use noak::tree::{Attribute, Class, Code, Instruction, MemberRef, Method, NameAndType};
+//! use noak::AccessFlags;
+//!
+//! let class = Class {
+//!     methods: vec![Method {
+//!         access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC,
+//!         name: "main".into(),
+//!         descriptor: "([Ljava/lang/String;)V".into(),
+//!         attributes: vec![Attribute::Code(Code {
+//!             max_stack: 0,
+//!             max_locals: 1,
+//!             instructions: vec![
+//!                 Instruction::InvokeStatic {
+//!                     method: MemberRef {
+//!                         class: "java/lang/Thread".into(),
+//!                         name: "yield".into(),
+//!                         descriptor: "()V".into(),
+//!                     },
+//!                     interface: false,
+//!                 },
+//!                 Instruction::Return,
+//!             ],
+//!             exception_handlers: Vec::new(),
+//!             attributes: Vec::new(),
+//!         })],
+//!     }],
+//!     ..Class::new("com/example/Main")
+//! };
+//! let bytes = class.to_bytes()?;
+//! let dependencies = Dependencies::of(&reader::Class::new(&bytes)?)?;
+//!
+//! let call = dependencies
+//!     .dependencies()
+//!     .iter()
+//!     .find(|dependency| dependency.kind == DependencyKind::Call)
+//!     .unwrap();
+//! assert_eq!(call.target.class(), "java/lang/Thread");
+//! assert_eq!(
+//!     call.location,
+//!     Location::Method(NameAndType {
+//!         name: "main".into(),
+//!         descriptor: "([Ljava/lang/String;)V".into(),
+//!     })
+//! );
+//!
+//! let packages: Vec<_> = dependencies.packages().into_keys().collect();
+//! assert_eq!(packages, [MString::from("java/lang")]);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::error::DecodeError;
+use crate::mutf8::{MStr, MString};
+use crate::reader::{self, cpool::MethodKind};
+use crate::tree::{
+    Annotation, Attribute, BootstrapMethod, Class, Code, Constant, DynamicConstant, ElementValue, Instruction,
+    MemberRef, MethodHandle, NameAndType, StackMapFrame, VerificationType,
+};
+
+/// A class, field or method which is referenced.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Target {
+    Class(MString),
+    Field(MemberRef),
+    Method(MemberRef),
+}
+
+impl Target {
+    /// The referenced class, or the class the referenced member belongs to.
+    ///
+    /// Array classes are replaced by the class of their elements.
+    #[must_use]
+    pub fn class(&self) -> &MStr {
+        match self {
+            Target::Class(class) => class,
+            Target::Field(member) | Target::Method(member) => element_class(&member.class).unwrap_or(&member.class),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DependencyKind {
+    /// The superclass or an implemented interface.
+    Inheritance,
+    /// A method is invoked, or referenced by a method handle.
+    Call,
+    /// A field is read or written, or referenced by a method handle.
+    FieldAccess,
+    /// A class is used as a type, for example in a descriptor or a cast.
+    TypeUse,
+    /// A class is used as an annotation or in its element values, including enum constants.
+    Annotation,
+}
+
+/// The part of the class a reference occurs in.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Location {
+    /// The class itself, for example its hierarchy or one of its attributes.
+    Class,
+    Field(NameAndType),
+    /// A method, including its code.
+    Method(NameAndType),
+    /// An entry of the constant pool which is not referenced anywhere else.
+    ConstantPool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Dependency {
+    pub target: Target,
+    pub kind: DependencyKind,
+    pub location: Location,
+}
+
+/// The dependencies of a single class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependencies {
+    class: MString,
+    dependencies: Vec<Dependency>,
+}
+
+impl Dependencies {
+    /// Collects the dependencies of a class which was read from a class file.
+    pub fn of(class: &reader::Class<'_>) -> Result<Dependencies, DecodeError> {
+        Ok(Dependencies::of_tree(&Class::from_reader(class)?))
+    }
+
+    /// Collects the dependencies of a resolved class.
+    #[must_use]
+    pub fn of_tree(class: &Class) -> Dependencies {
+        let bootstrap_methods = class
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::BootstrapMethods(methods) => Some(&methods[..]),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let mut collector = Collector {
+            class: &class.name,
+            bootstrap_methods,
+            location: Location::Class,
+            dependencies: Vec::new(),
+        };
+
+        if let Some(super_class) = &class.super_class {
+            collector.class(super_class, DependencyKind::Inheritance);
+        }
+        for interface in &class.interfaces {
+            collector.class(interface, DependencyKind::Inheritance);
+        }
+        collector.attributes(&class.attributes);
+        for field in &class.fields {
+            collector.location = Location::Field(NameAndType {
+                name: field.name.clone(),
+                descriptor: field.descriptor.clone(),
+            });
+            collector.descriptor(&field.descriptor, DependencyKind::TypeUse);
+            collector.attributes(&field.attributes);
+        }
+        for method in &class.methods {
+            collector.location = Location::Method(NameAndType {
+                name: method.name.clone(),
+                descriptor: method.descriptor.clone(),
+            });
+            collector.descriptor(&method.descriptor, DependencyKind::TypeUse);
+            collector.attributes(&method.attributes);
+        }
+
+        // the constant pool may contain entries which are not used by the rest of the class
+        let mut seen: HashSet<MString> = collector
+            .dependencies
+            .iter()
+            .map(|dependency| MString::from(dependency.target.class()))
+            .collect();
+        seen.insert(class.name.clone());
+        let mut dependencies = std::mem::take(&mut collector.dependencies);
+        collector.location = Location::ConstantPool;
+        for constant in &class.constant_pool {
+            if !matches!(constant, Constant::Dynamic(_) | Constant::InvokeDynamic(_)) {
+                collector.constant(constant, DependencyKind::TypeUse);
+            }
+        }
+        for dependency in collector.dependencies {
+            if seen.insert(MString::from(dependency.target.class())) {
+                dependencies.push(dependency);
+            }
+        }
+
+        Dependencies {
+            class: class.name.clone(),
+            dependencies,
+        }
+    }
+
+    /// The name of the class whose dependencies these are.
+    #[must_use]
+    pub fn class(&self) -> &MString {
+        &self.class
+    }
+
+    /// Every reference, in the order they occur in the class.
+    #[must_use]
+    pub fn dependencies(&self) -> &[Dependency] {
+        &self.dependencies
+    }
+
+    /// The referenced classes, with the kinds of their references.
+    ///
+    /// Members are attributed to the class they belong to.
+    #[must_use]
+    pub fn classes(&self) -> BTreeMap<MString, BTreeSet<DependencyKind>> {
+        let mut classes: BTreeMap<MString, BTreeSet<DependencyKind>> = BTreeMap::new();
+        for dependency in &self.dependencies {
+            classes
+                .entry(dependency.target.class().into())
+                .or_default()
+                .insert(dependency.kind);
+        }
+        classes
+    }
+
+    /// The referenced packages, with the kinds of their references.
+    ///
+    /// The unnamed package is the empty string.
+    #[must_use]
+    pub fn packages(&self) -> BTreeMap<MString, BTreeSet<DependencyKind>> {
+        let mut packages: BTreeMap<MString, BTreeSet<DependencyKind>> = BTreeMap::new();
+        for (class, kinds) in self.classes() {
+            packages.entry(package(&class).into()).or_default().extend(kinds);
+        }
+        packages
+    }
+
+    /// The referenced modules, with the kinds of their references.
+    ///
+    /// References to packages which don't belong to any module of the map are omitted,
+    /// they are still included in the [packages](Dependencies::packages).
+    #[must_use]
+    pub fn modules(&self, modules: &ModuleMap) -> BTreeMap<MString, BTreeSet<DependencyKind>> {
+        let mut by_module: BTreeMap<MString, BTreeSet<DependencyKind>> = BTreeMap::new();
+        for (package, kinds) in self.packages() {
+            if let Some(module) = modules.module(&package) {
+                by_module.entry(module.clone()).or_default().extend(kinds);
+            }
+        }
+        by_module
+    }
+}
+
+/// The modules packages belong to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleMap {
+    modules: HashMap<MString, MString>,
+}
+
+impl ModuleMap {
+    #[must_use]
+    pub fn new() -> ModuleMap {
+        ModuleMap::default()
+    }
+
+    /// Adds the packages of a module descriptor, the `module-info` class of a module.
+    ///
+    /// The packages are taken from the `ModulePackages` attribute.
+    /// Without it, only the exported and opened packages are known.
+    pub fn add_module(&mut self, class: &reader::Class<'_>) -> Result<&mut Self, DecodeError> {
+        let class = Class::from_reader(class)?;
+        let Some(module) = class.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::Module(module) => Some(module),
+            _ => None,
+        }) else {
+            return Ok(self);
+        };
+        let packages = class.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::ModulePackages(packages) => Some(packages.clone()),
+            _ => None,
+        });
+        let packages = packages.unwrap_or_else(|| {
+            let exported = module.exports.iter().map(|export| export.package.clone());
+            exported
+                .chain(module.opens.iter().map(|open| open.package.clone()))
+                .collect()
+        });
+        for package in packages {
+            self.insert(package, module.name.clone());
+        }
+        Ok(self)
+    }
+
+    /// Records that a package belongs to a module.
+    pub fn insert(&mut self, package: impl Into<MString>, module: impl Into<MString>) -> &mut Self {
+        self.modules.insert(package.into(), module.into());
+        self
+    }
+
+    /// The module a package belongs to.
+    #[must_use]
+    pub fn module(&self, package: &MStr) -> Option<&MString> {
+        self.modules.get(package)
+    }
+}
+
+struct Collector<'a> {
+    class: &'a MString,
+    bootstrap_methods: &'a [BootstrapMethod],
+    location: Location,
+    dependencies: Vec<Dependency>,
+}
+
+impl<'a> Collector<'a> {
+    fn push(&mut self, target: Target, kind: DependencyKind) {
+        if *target.class() != **self.class {
+            self.dependencies.push(Dependency {
+                target,
+                kind,
+                location: self.location.clone(),
+            });
+        }
+    }
+
+    /// Adds a class by its name, array classes are given by their descriptor.
+    fn class(&mut self, name: &MStr, kind: DependencyKind) {
+        if name.as_bytes().starts_with(b"[") {
+            self.descriptor(name, kind);
+        } else {
+            self.push(Target::Class(name.into()), kind);
+        }
+    }
+
+    /// Adds the classes of a field or method descriptor.
+    fn descriptor(&mut self, descriptor: &MStr, kind: DependencyKind) {
+        let bytes = descriptor.as_bytes();
+        let mut position = 0;
+        while position < bytes.len() {
+            if bytes[position] == b'L' {
+                let Some(length) = bytes[position..].iter().position(|&byte| byte == b';') else {
+                    return;
+                };
+                self.push(Target::Class(descriptor[position + 1..position + length].into()), kind);
+                position += length;
+            }
+            position += 1;
+        }
+    }
+
+    /// Adds the classes of a generic signature, malformed signatures are skipped.
+    fn signature(&mut self, signature: &MStr) {
+        let mut parser = SignatureParser {
+            signature,
+            position: 0,
+            classes: Vec::new(),
+        };
+        if parser.parse().is_some() {
+            for class in parser.classes {
+                self.push(Target::Class(class), DependencyKind::TypeUse);
+            }
+        }
+    }
+
+    fn member(&mut self, member: &MemberRef, kind: DependencyKind) {
+        let target = match kind {
+            DependencyKind::FieldAccess => Target::Field(member.clone()),
+            _ => Target::Method(member.clone()),
+        };
+        self.push(target, kind);
+    }
+
+    fn method_handle(&mut self, handle: &MethodHandle) {
+        match handle.kind {
+            MethodKind::GetField | MethodKind::GetStatic | MethodKind::PutField | MethodKind::PutStatic => {
+                self.member(&handle.reference, DependencyKind::FieldAccess);
+            }
+            _ => self.member(&handle.reference, DependencyKind::Call),
+        }
+    }
+
+    fn dynamic(&mut self, constant: &DynamicConstant) {
+        self.descriptor(&constant.descriptor, DependencyKind::TypeUse);
+        if let Some(bootstrap) = self.bootstrap_methods.get(usize::from(constant.bootstrap_method)) {
+            self.method_handle(&bootstrap.method);
+            for argument in &bootstrap.arguments {
+                self.constant(argument, DependencyKind::TypeUse);
+            }
+        }
+    }
+
+    fn constant(&mut self, constant: &Constant, kind: DependencyKind) {
+        match constant {
+            Constant::Class(class) => self.class(class, kind),
+            Constant::FieldRef(member) => self.member(member, DependencyKind::FieldAccess),
+            Constant::MethodRef(member) | Constant::InterfaceMethodRef(member) => {
+                self.member(member, DependencyKind::Call);
+            }
+            Constant::MethodType(descriptor) => self.descriptor(descriptor, kind),
+            Constant::MethodHandle(handle) => self.method_handle(handle),
+            Constant::Dynamic(constant) | Constant::InvokeDynamic(constant) => self.dynamic(constant),
+            _ => {}
+        }
+    }
+
+    fn annotations(&mut self, annotations: &[Annotation]) {
+        for annotation in annotations {
+            self.annotation(&annotation.type_, annotation.pairs.iter().map(|pair| &pair.value));
+        }
+    }
+
+    fn annotation<'v>(&mut self, type_: &MStr, values: impl Iterator<Item = &'v ElementValue>) {
+        self.descriptor(type_, DependencyKind::Annotation);
+        for value in values {
+            self.element_value(value);
+        }
+    }
+
+    fn element_value(&mut self, value: &ElementValue) {
+        match value {
+            ElementValue::Class(descriptor) => self.descriptor(descriptor, DependencyKind::Annotation),
+            ElementValue::Enum { type_name, const_name } => {
+                let class = type_name
+                    .as_bytes()
+                    .strip_prefix(b"L")
+                    .and_then(|name| name.strip_suffix(b";"))
+                    .map(|name| &type_name[1..=name.len()]);
+                if let Some(class) = class {
+                    let field = MemberRef {
+                        class: class.into(),
+                        name: const_name.clone(),
+                        descriptor: type_name.clone(),
+                    };
+                    self.push(Target::Field(field), DependencyKind::Annotation);
+                }
+            }
+            ElementValue::Annotation(annotation) => self.annotations(std::slice::from_ref(annotation)),
+            ElementValue::Array(values) => {
+                for value in values {
+                    self.element_value(value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            self.attribute(attribute);
+        }
+    }
+
+    fn attribute(&mut self, attribute: &Attribute) {
+        use DependencyKind::TypeUse;
+
+        match attribute {
+            Attribute::AnnotationDefault { value } => self.element_value(value),
+            Attribute::Code(code) => self.code(code),
+            Attribute::ConstantValue { value } => self.constant(value, TypeUse),
+            Attribute::EnclosingMethod { class, method } => {
+                self.class(class, TypeUse);
+                if let Some(method) = method {
+                    self.descriptor(&method.descriptor, TypeUse);
+                }
+            }
+            Attribute::Exceptions(classes)
+            | Attribute::NestMembers(classes)
+            | Attribute::PermittedSubclasses(classes) => {
+                for class in classes {
+                    self.class(class, TypeUse);
+                }
+            }
+            Attribute::NestHost(class) => self.class(class, TypeUse),
+            Attribute::InnerClasses(inner_classes) => {
+                for inner in inner_classes {
+                    self.class(&inner.inner_class, TypeUse);
+                    if let Some(outer) = &inner.outer_class {
+                        self.class(outer, TypeUse);
+                    }
+                }
+            }
+            Attribute::LocalVariableTable(variables) => {
+                for variable in variables {
+                    self.descriptor(&variable.descriptor, TypeUse);
+                }
+            }
+            Attribute::LocalVariableTypeTable(variables) => {
+                for variable in variables {
+                    self.signature(&variable.signature);
+                }
+            }
+            Attribute::Module(module) => {
+                for class in module.uses.iter().chain(
+                    module
+                        .provides
+                        .iter()
+                        .flat_map(|provide| std::iter::once(&provide.service).chain(&provide.provides_with)),
+                ) {
+                    self.class(class, TypeUse);
+                }
+            }
+            Attribute::Record(components) => {
+                for component in components {
+                    self.descriptor(&component.descriptor, TypeUse);
+                    self.attributes(&component.attributes);
+                }
+            }
+            Attribute::RuntimeInvisibleAnnotations(annotations) | Attribute::RuntimeVisibleAnnotations(annotations) => {
+                self.annotations(annotations);
+            }
+            Attribute::RuntimeInvisibleParameterAnnotations(parameters)
+            | Attribute::RuntimeVisibleParameterAnnotations(parameters) => {
+                for annotations in parameters {
+                    self.annotations(annotations);
+                }
+            }
+            Attribute::RuntimeInvisibleTypeAnnotations(annotations)
+            | Attribute::RuntimeVisibleTypeAnnotations(annotations) => {
+                for annotation in annotations {
+                    self.annotation(&annotation.type_, annotation.pairs.iter().map(|pair| &pair.value));
+                }
+            }
+            Attribute::Signature(signature) => self.signature(signature),
+            Attribute::StackMapTable(frames) => {
+                for frame in frames {
+                    let types: Vec<&VerificationType> = match frame {
+                        StackMapFrame::Same1 { stack, .. } | StackMapFrame::Same1Extended { stack, .. } => vec![stack],
+                        StackMapFrame::Append { locals, .. } => locals.iter().collect(),
+                        StackMapFrame::Full { locals, stack, .. } => locals.iter().chain(stack).collect(),
+                        _ => Vec::new(),
+                    };
+                    for verification_type in types {
+                        if let VerificationType::Object(class) = verification_type {
+                            self.class(class, TypeUse);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn code(&mut self, code: &Code) {
+        use DependencyKind::*;
+
+        for instruction in &code.instructions {
+            match instruction {
+                Instruction::ANewArray { class }
+                | Instruction::CheckCast { class }
+                | Instruction::InstanceOf { class }
+                | Instruction::MultiANewArray { class, .. }
+                | Instruction::New { class } => self.class(class, TypeUse),
+                Instruction::GetField { field }
+                | Instruction::GetStatic { field }
+                | Instruction::PutField { field }
+                | Instruction::PutStatic { field } => self.member(field, FieldAccess),
+                Instruction::InvokeInterface { method, .. }
+                | Instruction::InvokeSpecial { method, .. }
+                | Instruction::InvokeStatic { method, .. }
+                | Instruction::InvokeVirtual { method } => self.member(method, Call),
+                Instruction::InvokeDynamic { call_site } => self.dynamic(call_site),
+                Instruction::LdC { constant } | Instruction::LdCW { constant } | Instruction::LdC2W { constant } => {
+                    self.constant(constant, TypeUse);
+                }
+                _ => {}
+            }
+        }
+        for handler in &code.exception_handlers {
+            if let Some(catch_type) = &handler.catch_type {
+                self.class(catch_type, TypeUse);
+            }
+        }
+        self.attributes(&code.attributes);
+    }
+}
+
+/// Collects the classes of a generic signature of a class, field or method.
+struct SignatureParser<'a> {
+    signature: &'a MStr,
+    position: usize,
+    classes: Vec<MString>,
+}
+
+impl<'a> SignatureParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.signature.as_bytes().get(self.position).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.peek()? == byte).then(|| self.position += 1)
+    }
+
+    fn parse(&mut self) -> Option<()> {
+        if self.peek() == Some(b'<') {
+            self.type_parameters()?;
+        }
+        if self.peek() == Some(b'(') {
+            self.position += 1;
+            while self.peek()? != b')' {
+                self.type_signature()?;
+            }
+            self.position += 1;
+            self.type_signature()?;
+            while self.peek() == Some(b'^') {
+                self.position += 1;
+                self.type_signature()?;
+            }
+        } else {
+            // a class signature consists of its superclass and interfaces, a field signature of its type
+            while self.peek().is_some() {
+                self.type_signature()?;
+            }
+        }
+        Some(())
+    }
+
+    fn type_parameters(&mut self) -> Option<()> {
+        self.expect(b'<')?;
+        while self.peek()? != b'>' {
+            self.identifier(b":")?;
+            // the class bound may be empty, the interface bounds follow it
+            while self.peek() == Some(b':') {
+                self.position += 1;
+                if matches!(self.peek()?, b'L' | b'T' | b'[') {
+                    self.type_signature()?;
+                }
+            }
+        }
+        self.expect(b'>')
+    }
+
+    fn type_signature(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b'V' => {
+                self.position += 1;
+                Some(())
+            }
+            b'[' => {
+                self.position += 1;
+                self.type_signature()
+            }
+            b'T' => {
+                self.position += 1;
+                self.identifier(b";")?;
+                self.expect(b';')
+            }
+            b'L' => self.class_type(),
+            _ => None,
+        }
+    }
+
+    fn class_type(&mut self) -> Option<()> {
+        self.expect(b'L')?;
+        let mut name = MString::from(self.identifier(b"<.;")?);
+        loop {
+            if self.peek()? == b'<' {
+                self.type_arguments()?;
+            }
+            match self.peek()? {
+                b'.' => {
+                    self.position += 1;
+                    self.classes.push(name.clone());
+                    let inner = self.identifier(b"<.;")?;
+                    name = MString::from(format!("{}${}", name.display(), inner.display()).as_str());
+                }
+                b';' => {
+                    self.position += 1;
+                    self.classes.push(name);
+                    return Some(());
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn type_arguments(&mut self) -> Option<()> {
+        self.expect(b'<')?;
+        while self.peek()? != b'>' {
+            match self.peek()? {
+                b'*' => self.position += 1,
+                b'+' | b'-' => {
+                    self.position += 1;
+                    self.type_signature()?;
+                }
+                _ => self.type_signature()?,
+            }
+        }
+        self.expect(b'>')
+    }
+
+    /// Reads an identifier up to one of the terminators, which is not consumed.
+    fn identifier(&mut self, terminators: &[u8]) -> Option<&'a MStr> {
+        let start = self.position;
+        let length = self.signature.as_bytes()[start..]
+            .iter()
+            .position(|byte| terminators.contains(byte))?;
+        self.position += length;
+        (length > 0).then(|| &self.signature[start..start + length])
+    }
+}
+
+/// The class of the elements of an array class, or `None` if the elements are primitive.
+fn element_class(name: &MStr) -> Option<&MStr> {
+    let dimensions = name.as_bytes().iter().take_while(|&&byte| byte == b'[').count();
+    if dimensions == 0 {
+        return None;
+    }
+    let element = &name[dimensions..];
+    let bytes = element.as_bytes();
+    (bytes.len() > 2 && bytes[0] == b'L' && bytes[bytes.len() - 1] == b';').then(|| &element[1..element.len() - 1])
+}
+
+fn package(class: &MStr) -> &MStr {
+    let end = class.as_bytes().iter().rposition(|&byte| byte == b'/').unwrap_or(0);
+    &class[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::AccessFlags;
+    use crate::tree::{ElementValuePair, Field};
+
+    #[test]
+    fn signatures_and_annotations() {
+        let class = Class {
+            fields: vec![Field {
+                access_flags: AccessFlags::PRIVATE,
+                name: "entries".into(),
+                descriptor: "Ljava/util/Map;".into(),
+                attributes: vec![
+                    Attribute::Signature("Ljava/util/Map<TK;+Lcom/a/Outer<[Lcom/b/Value;>.Inner;>;".into()),
+                    Attribute::RuntimeVisibleAnnotations(vec![Annotation {
+                        type_: "Lcom/c/Retention;".into(),
+                        pairs: vec![ElementValuePair {
+                            name: "value".into(),
+                            value: ElementValue::Array(vec![ElementValue::Enum {
+                                type_name: "Lcom/d/Policy;".into(),
+                                const_name: "RUNTIME".into(),
+                            }]),
+                        }],
+                    }]),
+                ],
+            }],
+            ..Class::new("Example")
+        };
+        let dependencies = Dependencies::of_tree(&class);
+
+        let classes: Vec<_> = dependencies.classes().into_iter().collect();
+        let type_use = BTreeSet::from([DependencyKind::TypeUse]);
+        let annotation = BTreeSet::from([DependencyKind::Annotation]);
+        assert_eq!(
+            classes,
+            [
+                (MString::from("com/a/Outer"), type_use.clone()),
+                (MString::from("com/a/Outer$Inner"), type_use.clone()),
+                (MString::from("com/b/Value"), type_use.clone()),
+                (MString::from("com/c/Retention"), annotation.clone()),
+                (MString::from("com/d/Policy"), annotation),
+                (
+                    MString::from("java/lang/Object"),
+                    BTreeSet::from([DependencyKind::Inheritance])
+                ),
+                (MString::from("java/util/Map"), type_use),
+            ]
+        );
+
+        let mut modules = ModuleMap::new();
+        modules
+            .insert("java/lang", "java.base")
+            .insert("java/util", "java.base");
+        let modules: Vec<_> = dependencies.modules(&modules).into_keys().collect();
+        assert_eq!(modules, [MString::from("java.base")]);
+    }
+}
@@ -0,0 +1,797 @@
+//! Semantic comparison of two classes.
+//!
+//! Both classes are resolved before they are compared, so differences in the order of their constant pools
+//! or in the encoding of their instructions, such as the offsets of jumps, are not reported.
+//! Fields and methods are matched by their name and descriptor, and attributes by their name.
+//! The instructions of methods with the same signature are compared by a longest common subsequence,
+//! where jumps only match if their targets match as well.
+//!
+//! The [`ClassDiff`] can be inspected, or rendered in a human-readable form by its [`Display`](fmt::Display)
+//! implementation.
+//!
+//! ```
+//! use noak::diff::{self, Edit, MemberChange};
+//! use noak::tree::{Class, Field};
+//! use noak::AccessFlags;
+//!
+//! let old = Class::new("Example");
+//! let mut new = old.clone();
+//! new.fields.push(Field {
+//!     access_flags: AccessFlags::PRIVATE,
+//!     name: "count".into(),
+//!     descriptor: "I".into(),
+//!     attributes: Vec::new(),
+//! });
+//!
+//! let diff = diff::diff_trees(&old, &new);
+//! assert_eq!(diff.fields[0].change, MemberChange::Added);
+//! assert_eq!(diff.to_string(), "class Example\n  + field count I\n");
+//! ```
+
+mod display;
+
+use std::collections::HashMap;
+
+use crate::error::DecodeError;
+use crate::header::{AccessFlags, Version};
+use crate::mutf8::MString;
+use crate::reader;
+use crate::tree::{
+    Attribute, Class, Code, ExceptionHandler, Field, Instruction, Label, Method, StackMapFrame, VerificationType,
+};
+
+/// The most entries of the table used to compare the instructions of a method.
+///
+/// Larger differences are reported as the removal of the old instructions and the addition of the new ones.
+const MAX_TABLE_SIZE: usize = 1 << 22;
+
+/// Compares two classes which were read from class files.
+///
+/// Fails if either class cannot be resolved.
+pub fn diff(old: &reader::Class<'_>, new: &reader::Class<'_>) -> Result<ClassDiff, DecodeError> {
+    Ok(diff_trees(&Class::from_reader(old)?, &Class::from_reader(new)?))
+}
+
+/// Compares two resolved classes.
+///
+/// The [constant pools](Class::constant_pool) of the classes are ignored.
+#[must_use]
+pub fn diff_trees(old: &Class, new: &Class) -> ClassDiff {
+    let mut interfaces: Vec<_> = old
+        .interfaces
+        .iter()
+        .filter(|interface| !new.interfaces.contains(interface))
+        .map(|interface| Edit::Removed(interface.clone()))
+        .collect();
+    interfaces.extend(
+        new.interfaces
+            .iter()
+            .filter(|interface| !old.interfaces.contains(interface))
+            .map(|interface| Edit::Added(interface.clone())),
+    );
+
+    ClassDiff {
+        name: new.name.clone(),
+        version: Change::of(&old.version, &new.version),
+        access_flags: Change::of(&old.access_flags, &new.access_flags),
+        this_class: Change::of(&old.name, &new.name),
+        super_class: Change::of(&old.super_class, &new.super_class),
+        interfaces,
+        fields: members(&old.fields, &new.fields, Member::field),
+        methods: members(&old.methods, &new.methods, Member::method),
+        attributes: attributes(&old.attributes, &new.attributes, |attribute| attribute.clone()),
+    }
+}
+
+/// A value which differs between the classes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+impl<T: Clone + PartialEq> Change<T> {
+    fn of(old: &T, new: &T) -> Option<Change<T>> {
+        (old != new).then(|| Change {
+            old: old.clone(),
+            new: new.clone(),
+        })
+    }
+}
+
+/// An entry which was added to, removed from or changed in a list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit<T> {
+    Added(T),
+    Removed(T),
+    Changed(Change<T>),
+}
+
+/// The differences between two classes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDiff {
+    /// The name of the new class.
+    pub name: MString,
+    pub version: Option<Change<Version>>,
+    pub access_flags: Option<Change<AccessFlags>>,
+    /// The change of the name of the class.
+    pub this_class: Option<Change<MString>>,
+    pub super_class: Option<Change<Option<MString>>>,
+    /// The interfaces which were added or removed, a change of their order is not reported.
+    pub interfaces: Vec<Edit<MString>>,
+    pub fields: Vec<MemberDiff>,
+    pub methods: Vec<MemberDiff>,
+    pub attributes: Vec<Edit<Attribute>>,
+}
+
+impl ClassDiff {
+    /// Whether the classes are equivalent.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.version.is_none()
+            && self.access_flags.is_none()
+            && self.this_class.is_none()
+            && self.super_class.is_none()
+            && self.interfaces.is_empty()
+            && self.fields.is_empty()
+            && self.methods.is_empty()
+            && self.attributes.is_empty()
+    }
+}
+
+/// A field or method which differs between the classes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberDiff {
+    pub name: MString,
+    pub descriptor: MString,
+    pub change: MemberChange,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemberChange {
+    Added,
+    Removed,
+    Changed {
+        access_flags: Option<Change<AccessFlags>>,
+        /// The changes of every attribute except for the `Code` attribute.
+        attributes: Vec<Edit<Attribute>>,
+        /// The changes of the code, a method without code is treated like a method with empty code.
+        code: Option<CodeDiff>,
+    },
+}
+
+/// The differences between the code of two methods.
+///
+/// The code is compared without its [labels](Instruction::Label).
+/// Instead, every label is replaced by the index of the instruction it is placed before,
+/// both in the operands of jumps and in the exception handlers and attributes.
+/// As their choice depends on the layout of the class, `ldc_w`, `goto_w` and `jsr_w` and the extended stack map frames
+/// are replaced by their short forms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeDiff {
+    pub max_stack: Option<Change<u16>>,
+    pub max_locals: Option<Change<u16>>,
+    /// The instructions which were removed from the old code or added to the new code, in the order of the code.
+    pub instructions: Vec<InstructionEdit>,
+    pub exception_handlers: Vec<Edit<ExceptionHandler>>,
+    /// The changes of the attributes of the code.
+    ///
+    /// The old side of a changed attribute refers to the positions of the matching instructions in the new code,
+    /// positions of removed instructions are replaced by `Label(u32::MAX)`.
+    pub attributes: Vec<Edit<Attribute>>,
+}
+
+impl CodeDiff {
+    fn is_empty(&self) -> bool {
+        self.max_stack.is_none()
+            && self.max_locals.is_none()
+            && self.instructions.is_empty()
+            && self.exception_handlers.is_empty()
+            && self.attributes.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstructionEdit {
+    /// An instruction of the old code, at the index in the old code.
+    Removed { index: usize, instruction: Instruction },
+    /// An instruction of the new code, at the index in the new code.
+    Added { index: usize, instruction: Instruction },
+}
+
+/// The parts of fields and methods which are compared.
+struct Member<'a> {
+    access_flags: AccessFlags,
+    name: &'a MString,
+    descriptor: &'a MString,
+    attributes: &'a [Attribute],
+    code: Option<&'a Code>,
+}
+
+impl<'a> Member<'a> {
+    fn field(field: &'a Field) -> Member<'a> {
+        Member {
+            access_flags: field.access_flags,
+            name: &field.name,
+            descriptor: &field.descriptor,
+            attributes: &field.attributes,
+            code: None,
+        }
+    }
+
+    fn method(method: &'a Method) -> Member<'a> {
+        Member {
+            access_flags: method.access_flags,
+            name: &method.name,
+            descriptor: &method.descriptor,
+            attributes: &method.attributes,
+            code: method.attributes.iter().find_map(|attribute| match attribute {
+                Attribute::Code(code) => Some(code),
+                _ => None,
+            }),
+        }
+    }
+}
+
+fn members<'a, T>(old: &'a [T], new: &'a [T], member: fn(&'a T) -> Member<'a>) -> Vec<MemberDiff> {
+    let old: Vec<_> = old.iter().map(member).collect();
+    let new: Vec<_> = new.iter().map(member).collect();
+    let by_signature: HashMap<_, _> = new
+        .iter()
+        .map(|member| ((member.name, member.descriptor), member))
+        .collect();
+
+    let mut diffs = Vec::new();
+    for old in &old {
+        let change = match by_signature.get(&(old.name, old.descriptor)) {
+            Some(new) => {
+                let is_code = |attribute: &&Attribute| !matches!(attribute, Attribute::Code(_));
+                let old_attributes: Vec<_> = old.attributes.iter().filter(is_code).cloned().collect();
+                let new_attributes: Vec<_> = new.attributes.iter().filter(is_code).cloned().collect();
+                let access_flags = Change::of(&old.access_flags, &new.access_flags);
+                let attributes = attributes(&old_attributes, &new_attributes, |attribute| attribute.clone());
+                let code = match (old.code, new.code) {
+                    (None, None) => None,
+                    (old, new) => Some(code(old, new)).filter(|diff| !diff.is_empty()),
+                };
+                if access_flags.is_none() && attributes.is_empty() && code.is_none() {
+                    continue;
+                }
+                MemberChange::Changed {
+                    access_flags,
+                    attributes,
+                    code,
+                }
+            }
+            None => MemberChange::Removed,
+        };
+        diffs.push(MemberDiff {
+            name: old.name.clone(),
+            descriptor: old.descriptor.clone(),
+            change,
+        });
+    }
+
+    let old_signatures: Vec<_> = old.iter().map(|member| (member.name, member.descriptor)).collect();
+    diffs.extend(
+        new.iter()
+            .filter(|new| !old_signatures.contains(&(new.name, new.descriptor)))
+            .map(|new| MemberDiff {
+                name: new.name.clone(),
+                descriptor: new.descriptor.clone(),
+                change: MemberChange::Added,
+            }),
+    );
+    diffs
+}
+
+/// Compares attributes by their name, and by their order among the attributes of the same name.
+///
+/// The old attributes are compared after passing them through `translate`,
+/// and changed attributes are reported in their translated form.
+fn attributes(
+    old: &[Attribute],
+    new: &[Attribute],
+    translate: impl Fn(&Attribute) -> Attribute,
+) -> Vec<Edit<Attribute>> {
+    let old = keyed(old);
+    let new = keyed(new);
+    let new_by_key: HashMap<_, _> = new.iter().cloned().collect();
+
+    let mut edits = Vec::new();
+    for (key, old) in &old {
+        match new_by_key.get(key) {
+            Some(&new) => {
+                let old = translate(old);
+                if old != *new {
+                    edits.push(Edit::Changed(Change { old, new: new.clone() }));
+                }
+            }
+            None => edits.push(Edit::Removed((*old).clone())),
+        }
+    }
+    for (key, new) in &new {
+        if !old.iter().any(|(old_key, _)| old_key == key) {
+            edits.push(Edit::Added((*new).clone()));
+        }
+    }
+    edits
+}
+
+/// Keys the attributes by their name and their occurrence among the attributes of the same name.
+fn keyed(attributes: &[Attribute]) -> Vec<((MString, usize), &Attribute)> {
+    let mut occurrences: HashMap<MString, usize> = HashMap::new();
+    attributes
+        .iter()
+        .map(|attribute| {
+            let name = attribute.name();
+            let occurrence = occurrences.entry(name.clone()).or_default();
+            *occurrence += 1;
+            ((name, *occurrence), attribute)
+        })
+        .collect()
+}
+
+/// Code whose labels are replaced by the index of the instruction they are placed before.
+struct Normalized {
+    instructions: Vec<Instruction>,
+    exception_handlers: Vec<ExceptionHandler>,
+    attributes: Vec<Attribute>,
+}
+
+impl Normalized {
+    fn new(code: &Code) -> Normalized {
+        let mut positions = HashMap::new();
+        let mut instructions = Vec::new();
+        for instruction in &code.instructions {
+            match instruction {
+                Instruction::Label(label) => {
+                    positions.insert(*label, Label(instructions.len() as u32));
+                }
+                // the wide forms only depend on the layout of the constant pool and the code
+                Instruction::LdCW { constant } => instructions.push(Instruction::LdC {
+                    constant: constant.clone(),
+                }),
+                Instruction::GotoW { target } => instructions.push(Instruction::Goto { target: *target }),
+                Instruction::JSrW { target } => instructions.push(Instruction::JSr { target: *target }),
+                instruction => instructions.push(instruction.clone()),
+            }
+        }
+        // labels which are not placed are kept as they are
+        let position = |label: Label| positions.get(&label).copied().unwrap_or(label);
+
+        for instruction in &mut instructions {
+            for target in instruction.targets_mut() {
+                *target = position(*target);
+            }
+        }
+        let exception_handlers = code
+            .exception_handlers
+            .iter()
+            .map(|handler| relabel_handler(handler, position))
+            .collect();
+        let attributes = code
+            .attributes
+            .iter()
+            .map(|attribute| {
+                let mut attribute = relabel_attribute(attribute, position);
+                if let Attribute::StackMapTable(frames) = &mut attribute {
+                    for frame in frames {
+                        *frame = match frame.clone() {
+                            StackMapFrame::SameExtended { label } => StackMapFrame::Same { label },
+                            StackMapFrame::Same1Extended { label, stack } => StackMapFrame::Same1 { label, stack },
+                            frame => frame,
+                        };
+                    }
+                }
+                attribute
+            })
+            .collect();
+        Normalized {
+            instructions,
+            exception_handlers,
+            attributes,
+        }
+    }
+}
+
+fn code(old: Option<&Code>, new: Option<&Code>) -> CodeDiff {
+    let empty = Code {
+        max_stack: 0,
+        max_locals: 0,
+        instructions: Vec::new(),
+        exception_handlers: Vec::new(),
+        attributes: Vec::new(),
+    };
+    let (old, new) = (old.unwrap_or(&empty), new.unwrap_or(&empty));
+    let old_code = Normalized::new(old);
+    let new_code = Normalized::new(new);
+
+    let pairs = match_instructions(&old_code.instructions, &new_code.instructions);
+
+    let mut instructions = Vec::new();
+    let (mut old_index, mut new_index) = (0, 0);
+    let end = (old_code.instructions.len(), new_code.instructions.len());
+    for &(old_match, new_match) in pairs.iter().chain(std::iter::once(&end)) {
+        instructions.extend((old_index..old_match).map(|index| InstructionEdit::Removed {
+            index,
+            instruction: old_code.instructions[index].clone(),
+        }));
+        instructions.extend((new_index..new_match).map(|index| InstructionEdit::Added {
+            index,
+            instruction: new_code.instructions[index].clone(),
+        }));
+        (old_index, new_index) = (old_match + 1, new_match + 1);
+    }
+
+    // the old positions are translated to the new ones, so only the handlers of changed instructions differ
+    let mut translation: HashMap<Label, Label> = pairs
+        .iter()
+        .map(|&(old, new)| (Label(old as u32), Label(new as u32)))
+        .collect();
+    translation.insert(Label(end.0 as u32), Label(end.1 as u32));
+    let translate = |label: Label| translation.get(&label).copied().unwrap_or(Label(u32::MAX));
+
+    let old_handlers: Vec<_> = old_code
+        .exception_handlers
+        .iter()
+        .map(|handler| relabel_handler(handler, translate))
+        .collect();
+    let handler_pairs = match_sequences(&old_handlers, &new_code.exception_handlers, |old, new| old == new);
+    let mut exception_handlers = Vec::new();
+    let (mut old_index, mut new_index) = (0, 0);
+    let end = (old_handlers.len(), new_code.exception_handlers.len());
+    for &(old_match, new_match) in handler_pairs.iter().chain(std::iter::once(&end)) {
+        exception_handlers.extend(
+            old_code.exception_handlers[old_index..old_match]
+                .iter()
+                .cloned()
+                .map(Edit::Removed),
+        );
+        exception_handlers.extend(
+            new_code.exception_handlers[new_index..new_match]
+                .iter()
+                .cloned()
+                .map(Edit::Added),
+        );
+        (old_index, new_index) = (old_match + 1, new_match + 1);
+    }
+
+    CodeDiff {
+        max_stack: Change::of(&old.max_stack, &new.max_stack),
+        max_locals: Change::of(&old.max_locals, &new.max_locals),
+        instructions,
+        exception_handlers,
+        attributes: attributes(&old_code.attributes, &new_code.attributes, |attribute| {
+            relabel_attribute(attribute, translate)
+        }),
+    }
+}
+
+/// Matches the instructions of the old and new code.
+///
+/// Jumps are first matched regardless of their targets. Afterwards, every pair of jumps whose targets
+/// are not matched with each other is split up again.
+fn match_instructions(old: &[Instruction], new: &[Instruction]) -> Vec<(usize, usize)> {
+    let without_targets = |instructions: &[Instruction]| {
+        instructions
+            .iter()
+            .map(|instruction| {
+                let mut instruction = instruction.clone();
+                for target in instruction.targets_mut() {
+                    *target = Label(0);
+                }
+                instruction
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut pairs = match_sequences(&without_targets(old), &without_targets(new), |old, new| old == new);
+
+    let mut matched: HashMap<usize, usize> = pairs.iter().copied().collect();
+    matched.insert(old.len(), new.len());
+    pairs.retain(|&(old_index, new_index)| {
+        let (mut old_instruction, mut new_instruction) = (old[old_index].clone(), new[new_index].clone());
+        old_instruction
+            .targets_mut()
+            .into_iter()
+            .zip(new_instruction.targets_mut())
+            .all(|(old_target, new_target)| matched.get(&(old_target.0 as usize)) == Some(&(new_target.0 as usize)))
+    });
+    pairs
+}
+
+/// Returns the indices of the entries of a longest common subsequence of both sequences.
+///
+/// If the sequences differ too much, only their common prefix and suffix is matched.
+fn match_sequences<T>(old: &[T], new: &[T], eq: impl Fn(&T, &T) -> bool) -> Vec<(usize, usize)> {
+    let prefix = old.iter().zip(new).take_while(|(old, new)| eq(old, new)).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| eq(old, new))
+        .count();
+    let (old_middle, new_middle) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut pairs: Vec<_> = (0..prefix).map(|index| (index, index)).collect();
+    let (rows, columns) = (old_middle.len(), new_middle.len());
+    if rows > 0 && columns > 0 && (rows + 1).saturating_mul(columns + 1) <= MAX_TABLE_SIZE {
+        // lengths[i][j] is the length of a longest common subsequence of old_middle[i..] and new_middle[j..]
+        let mut lengths = vec![0u32; (rows + 1) * (columns + 1)];
+        let at = |i: usize, j: usize| i * (columns + 1) + j;
+        for i in (0..rows).rev() {
+            for j in (0..columns).rev() {
+                lengths[at(i, j)] = if eq(&old_middle[i], &new_middle[j]) {
+                    lengths[at(i + 1, j + 1)] + 1
+                } else {
+                    lengths[at(i + 1, j)].max(lengths[at(i, j + 1)])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < rows && j < columns {
+            if eq(&old_middle[i], &new_middle[j]) {
+                pairs.push((prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if lengths[at(i + 1, j)] >= lengths[at(i, j + 1)] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+    pairs.extend((0..suffix).map(|index| (old.len() - suffix + index, new.len() - suffix + index)));
+    pairs
+}
+
+fn relabel_handler(handler: &ExceptionHandler, relabel: impl Fn(Label) -> Label) -> ExceptionHandler {
+    ExceptionHandler {
+        start: relabel(handler.start),
+        end: relabel(handler.end),
+        handler: relabel(handler.handler),
+        catch_type: handler.catch_type.clone(),
+    }
+}
+
+/// Replaces the labels of the attributes of code which refer to positions in the code.
+fn relabel_attribute(attribute: &Attribute, relabel: impl Fn(Label) -> Label) -> Attribute {
+    let mut attribute = attribute.clone();
+    match &mut attribute {
+        Attribute::LineNumberTable(lines) => {
+            for line in lines {
+                line.start = relabel(line.start);
+            }
+        }
+        Attribute::LocalVariableTable(variables) => {
+            for variable in variables {
+                (variable.start, variable.end) = (relabel(variable.start), relabel(variable.end));
+            }
+        }
+        Attribute::LocalVariableTypeTable(variables) => {
+            for variable in variables {
+                (variable.start, variable.end) = (relabel(variable.start), relabel(variable.end));
+            }
+        }
+        Attribute::StackMapTable(frames) => {
+            for frame in frames {
+                let (label, types) = match frame {
+                    StackMapFrame::Same { label } | StackMapFrame::SameExtended { label } => (label, Vec::new()),
+                    StackMapFrame::Same1 { label, stack } | StackMapFrame::Same1Extended { label, stack } => {
+                        (label, vec![stack])
+                    }
+                    StackMapFrame::Chop { label, .. } => (label, Vec::new()),
+                    StackMapFrame::Append { label, locals } => (label, locals.iter_mut().collect()),
+                    StackMapFrame::Full { label, locals, stack } => {
+                        (label, locals.iter_mut().chain(stack.iter_mut()).collect())
+                    }
+                };
+                *label = relabel(*label);
+                for verification_type in types {
+                    if let VerificationType::Uninitialized(label) = verification_type {
+                        *label = relabel(*label);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    attribute
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::LineNumber;
+
+    fn method(instructions: Vec<Instruction>) -> Method {
+        Method {
+            access_flags: AccessFlags::STATIC,
+            name: "run".into(),
+            descriptor: "(I)V".into(),
+            attributes: vec![Attribute::Code(Code {
+                max_stack: 1,
+                max_locals: 1,
+                instructions,
+                exception_handlers: Vec::new(),
+                attributes: Vec::new(),
+            })],
+        }
+    }
+
+    fn method_with_lines(instructions: Vec<Instruction>, lines: &[(u32, u16)]) -> Method {
+        let mut method = method(instructions);
+        if let Attribute::Code(code) = &mut method.attributes[0] {
+            code.attributes.push(line_numbers(lines));
+        }
+        method
+    }
+
+    fn line_numbers(lines: &[(u32, u16)]) -> Attribute {
+        Attribute::LineNumberTable(
+            lines
+                .iter()
+                .map(|&(start, line_number)| LineNumber {
+                    start: Label(start),
+                    line_number,
+                })
+                .collect(),
+        )
+    }
+
+    fn class(method: Method) -> Class {
+        Class {
+            methods: vec![method],
+            ..Class::new("Example")
+        }
+    }
+
+    fn instruction_edits(diff: &ClassDiff) -> &[InstructionEdit] {
+        match &diff.methods[..] {
+            [MemberDiff {
+                change: MemberChange::Changed { code: Some(code), .. },
+                ..
+            }] => &code.instructions,
+            methods => panic!("unexpected method changes {methods:?}"),
+        }
+    }
+
+    #[test]
+    fn moved_jump_targets() {
+        let old = class(method(vec![
+            Instruction::ILoad0,
+            Instruction::IfEq { target: Label(5) },
+            Instruction::Nop,
+            Instruction::Label(Label(5)),
+            Instruction::Return,
+        ]));
+        let new = class(method(vec![
+            Instruction::ILoad0,
+            Instruction::IfEq { target: Label(6) },
+            Instruction::Nop,
+            Instruction::Nop,
+            Instruction::Label(Label(6)),
+            Instruction::Return,
+        ]));
+        let diff = diff_trees(&old, &new);
+        assert_eq!(
+            instruction_edits(&diff),
+            [InstructionEdit::Added {
+                index: 3,
+                instruction: Instruction::Nop
+            }]
+        );
+    }
+
+    #[test]
+    fn changed_jump_targets() {
+        let old = class(method(vec![
+            Instruction::ILoad0,
+            Instruction::IfEq { target: Label(5) },
+            Instruction::Nop,
+            Instruction::Label(Label(5)),
+            Instruction::Return,
+        ]));
+        let new = class(method(vec![
+            Instruction::ILoad0,
+            Instruction::IfEq { target: Label(4) },
+            Instruction::Label(Label(4)),
+            Instruction::Nop,
+            Instruction::Return,
+        ]));
+        let diff = diff_trees(&old, &new);
+        assert_eq!(
+            instruction_edits(&diff),
+            [
+                InstructionEdit::Removed {
+                    index: 1,
+                    instruction: Instruction::IfEq { target: Label(3) }
+                },
+                InstructionEdit::Added {
+                    index: 1,
+                    instruction: Instruction::IfEq { target: Label(2) }
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn shifted_line_table() {
+        let old = class(method_with_lines(
+            vec![
+                Instruction::Label(Label(0)),
+                Instruction::ILoad0,
+                Instruction::Label(Label(1)),
+                Instruction::IReturn,
+            ],
+            &[(0, 10), (1, 11)],
+        ));
+        let new_instructions = || {
+            vec![
+                Instruction::Nop,
+                Instruction::Label(Label(0)),
+                Instruction::ILoad0,
+                Instruction::Label(Label(1)),
+                Instruction::IReturn,
+            ]
+        };
+
+        let shifted = class(method_with_lines(new_instructions(), &[(0, 10), (1, 11)]));
+        match &diff_trees(&old, &shifted).methods[..] {
+            [MemberDiff {
+                change: MemberChange::Changed { code: Some(code), .. },
+                ..
+            }] => assert!(code.attributes.is_empty()),
+            methods => panic!("unexpected method changes {methods:?}"),
+        }
+
+        let changed = class(method_with_lines(new_instructions(), &[(0, 10), (1, 12)]));
+        match &diff_trees(&old, &changed).methods[..] {
+            [MemberDiff {
+                change: MemberChange::Changed { code: Some(code), .. },
+                ..
+            }] => assert_eq!(
+                code.attributes,
+                [Edit::Changed(Change {
+                    old: line_numbers(&[(1, 10), (2, 11)]),
+                    new: line_numbers(&[(1, 10), (2, 12)]),
+                })]
+            ),
+            methods => panic!("unexpected method changes {methods:?}"),
+        }
+    }
+
+    #[test]
+    fn display() {
+        let old = class(method(vec![
+            Instruction::ILoad0,
+            Instruction::IfEq { target: Label(5) },
+            Instruction::Nop,
+            Instruction::Label(Label(5)),
+            Instruction::Return,
+        ]));
+        let mut new = class(Method {
+            access_flags: AccessFlags::STATIC | AccessFlags::SYNCHRONIZED,
+            ..method(vec![
+                Instruction::ILoad0,
+                Instruction::IfEq { target: Label(4) },
+                Instruction::Label(Label(4)),
+                Instruction::Nop,
+                Instruction::Return,
+            ])
+        });
+        new.access_flags |= AccessFlags::FINAL;
+        assert_eq!(
+            diff_trees(&old, &new).to_string(),
+            concat!(
+                "class Example\n",
+                "  access flags: (0x0021) ACC_PUBLIC, ACC_SUPER -> (0x0031) ACC_PUBLIC, ACC_FINAL, ACC_SUPER\n",
+                "  ~ method run (I)V\n",
+                "      access flags: (0x0008) ACC_STATIC -> (0x0028) ACC_STATIC, ACC_SYNCHRONIZED\n",
+                "      -     1: ifeq 3\n",
+                "      +     1: ifeq 2\n",
+            )
+        );
+    }
+}
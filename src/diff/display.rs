@@ -0,0 +1,1000 @@
+use std::fmt;
+
+use crate::header::AccessFlags;
+use crate::mutf8::MString;
+use crate::reader::attributes::annotations::{SuperTypeIndex, TargetType, TypePathSegmentKind};
+use crate::reader::attributes::ArrayType;
+use crate::reader::cpool::MethodKind;
+use crate::tree::{
+    Annotation, Attribute, Constant, ElementValue, ElementValuePair, ExceptionHandler, Instruction, Label, MemberRef,
+    StackMapFrame, TargetInfo, TypeAnnotation, VerificationType,
+};
+
+use super::{ClassDiff, Edit, InstructionEdit, MemberChange, MemberDiff};
+
+const CLASS_FLAGS: [(AccessFlags, &str); 9] = [
+    (AccessFlags::PUBLIC, "ACC_PUBLIC"),
+    (AccessFlags::FINAL, "ACC_FINAL"),
+    (AccessFlags::SUPER, "ACC_SUPER"),
+    (AccessFlags::INTERFACE, "ACC_INTERFACE"),
+    (AccessFlags::ABSTRACT, "ACC_ABSTRACT"),
+    (AccessFlags::SYNTHETIC, "ACC_SYNTHETIC"),
+    (AccessFlags::ANNOTATION, "ACC_ANNOTATION"),
+    (AccessFlags::ENUM, "ACC_ENUM"),
+    (AccessFlags::MODULE, "ACC_MODULE"),
+];
+
+const INNER_CLASS_FLAGS: [(AccessFlags, &str); 10] = [
+    (AccessFlags::PUBLIC, "ACC_PUBLIC"),
+    (AccessFlags::PRIVATE, "ACC_PRIVATE"),
+    (AccessFlags::PROTECTED, "ACC_PROTECTED"),
+    (AccessFlags::STATIC, "ACC_STATIC"),
+    (AccessFlags::FINAL, "ACC_FINAL"),
+    (AccessFlags::INTERFACE, "ACC_INTERFACE"),
+    (AccessFlags::ABSTRACT, "ACC_ABSTRACT"),
+    (AccessFlags::SYNTHETIC, "ACC_SYNTHETIC"),
+    (AccessFlags::ANNOTATION, "ACC_ANNOTATION"),
+    (AccessFlags::ENUM, "ACC_ENUM"),
+];
+
+const FIELD_FLAGS: [(AccessFlags, &str); 9] = [
+    (AccessFlags::PUBLIC, "ACC_PUBLIC"),
+    (AccessFlags::PRIVATE, "ACC_PRIVATE"),
+    (AccessFlags::PROTECTED, "ACC_PROTECTED"),
+    (AccessFlags::STATIC, "ACC_STATIC"),
+    (AccessFlags::FINAL, "ACC_FINAL"),
+    (AccessFlags::VOLATILE, "ACC_VOLATILE"),
+    (AccessFlags::TRANSIENT, "ACC_TRANSIENT"),
+    (AccessFlags::SYNTHETIC, "ACC_SYNTHETIC"),
+    (AccessFlags::ENUM, "ACC_ENUM"),
+];
+
+const METHOD_FLAGS: [(AccessFlags, &str); 12] = [
+    (AccessFlags::PUBLIC, "ACC_PUBLIC"),
+    (AccessFlags::PRIVATE, "ACC_PRIVATE"),
+    (AccessFlags::PROTECTED, "ACC_PROTECTED"),
+    (AccessFlags::STATIC, "ACC_STATIC"),
+    (AccessFlags::FINAL, "ACC_FINAL"),
+    (AccessFlags::SYNCHRONIZED, "ACC_SYNCHRONIZED"),
+    (AccessFlags::BRIDGE, "ACC_BRIDGE"),
+    (AccessFlags::VARARGS, "ACC_VARARGS"),
+    (AccessFlags::NATIVE, "ACC_NATIVE"),
+    (AccessFlags::ABSTRACT, "ACC_ABSTRACT"),
+    (AccessFlags::STRICT, "ACC_STRICT"),
+    (AccessFlags::SYNTHETIC, "ACC_SYNTHETIC"),
+];
+
+const PARAMETER_FLAGS: [(AccessFlags, &str); 3] = [
+    (AccessFlags::FINAL, "ACC_FINAL"),
+    (AccessFlags::SYNTHETIC, "ACC_SYNTHETIC"),
+    (AccessFlags::MANDATED, "ACC_MANDATED"),
+];
+
+// the flags of modules share their bits with flags of classes and methods
+const MODULE_FLAGS: [(AccessFlags, &str); 3] = [
+    (AccessFlags::SUPER, "ACC_OPEN"),
+    (AccessFlags::SYNTHETIC, "ACC_SYNTHETIC"),
+    (AccessFlags::MANDATED, "ACC_MANDATED"),
+];
+
+const REQUIRES_FLAGS: [(AccessFlags, &str); 4] = [
+    (AccessFlags::SUPER, "ACC_TRANSITIVE"),
+    (AccessFlags::BRIDGE, "ACC_STATIC_PHASE"),
+    (AccessFlags::SYNTHETIC, "ACC_SYNTHETIC"),
+    (AccessFlags::MANDATED, "ACC_MANDATED"),
+];
+
+const EXPORTS_FLAGS: [(AccessFlags, &str); 2] = [
+    (AccessFlags::SYNTHETIC, "ACC_SYNTHETIC"),
+    (AccessFlags::MANDATED, "ACC_MANDATED"),
+];
+
+/// Access flags named like by `javap`, as the meaning of their bits depends on what they belong to.
+struct Flags<'a>(AccessFlags, &'a [(AccessFlags, &'a str)]);
+
+impl fmt::Display for Flags<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Flags(flags, names) = *self;
+        write!(f, "(0x{:04x})", flags.bits())?;
+        let mut separator = " ";
+        for (flag, name) in names {
+            if flags.contains(*flag) {
+                write!(f, "{separator}{name}")?;
+                separator = ", ";
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ClassDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "class {}", self.name.display())?;
+        if let Some(version) = &self.version {
+            writeln!(
+                f,
+                "  version: {}.{} -> {}.{}",
+                version.old.major, version.old.minor, version.new.major, version.new.minor
+            )?;
+        }
+        if let Some(access_flags) = &self.access_flags {
+            writeln!(
+                f,
+                "  access flags: {} -> {}",
+                Flags(access_flags.old, &CLASS_FLAGS),
+                Flags(access_flags.new, &CLASS_FLAGS)
+            )?;
+        }
+        if let Some(this_class) = &self.this_class {
+            writeln!(
+                f,
+                "  name: {} -> {}",
+                this_class.old.display(),
+                this_class.new.display()
+            )?;
+        }
+        if let Some(super_class) = &self.super_class {
+            let name = |class: &Option<MString>| {
+                class
+                    .as_ref()
+                    .map_or_else(String::new, |class| class.display().to_string())
+            };
+            writeln!(
+                f,
+                "  super class: {} -> {}",
+                name(&super_class.old),
+                name(&super_class.new)
+            )?;
+        }
+        for interface in &self.interfaces {
+            match interface {
+                Edit::Added(interface) => writeln!(f, "  + interface {}", interface.display())?,
+                Edit::Removed(interface) => writeln!(f, "  - interface {}", interface.display())?,
+                Edit::Changed(change) => {
+                    writeln!(f, "  - interface {}", change.old.display())?;
+                    writeln!(f, "  + interface {}", change.new.display())?;
+                }
+            }
+        }
+        for member in &self.fields {
+            write_member(f, "field", &FIELD_FLAGS, member)?;
+        }
+        for member in &self.methods {
+            write_member(f, "method", &METHOD_FLAGS, member)?;
+        }
+        write_attributes(f, "  ", &self.attributes)
+    }
+}
+
+fn write_member(
+    f: &mut fmt::Formatter<'_>,
+    kind: &str,
+    flag_names: &[(AccessFlags, &str)],
+    member: &MemberDiff,
+) -> fmt::Result {
+    let (name, descriptor) = (member.name.display(), member.descriptor.display());
+    match &member.change {
+        MemberChange::Added => writeln!(f, "  + {kind} {name} {descriptor}"),
+        MemberChange::Removed => writeln!(f, "  - {kind} {name} {descriptor}"),
+        MemberChange::Changed {
+            access_flags,
+            attributes,
+            code,
+        } => {
+            writeln!(f, "  ~ {kind} {name} {descriptor}")?;
+            if let Some(access_flags) = access_flags {
+                writeln!(
+                    f,
+                    "      access flags: {} -> {}",
+                    Flags(access_flags.old, flag_names),
+                    Flags(access_flags.new, flag_names)
+                )?;
+            }
+            write_attributes(f, "      ", attributes)?;
+            let Some(code) = code else {
+                return Ok(());
+            };
+            if let Some(max_stack) = &code.max_stack {
+                writeln!(f, "      max stack: {} -> {}", max_stack.old, max_stack.new)?;
+            }
+            if let Some(max_locals) = &code.max_locals {
+                writeln!(f, "      max locals: {} -> {}", max_locals.old, max_locals.new)?;
+            }
+            for edit in &code.instructions {
+                let (sign, index, instruction) = match edit {
+                    InstructionEdit::Removed { index, instruction } => ('-', index, instruction),
+                    InstructionEdit::Added { index, instruction } => ('+', index, instruction),
+                };
+                write!(f, "      {sign} {index:>5}: ")?;
+                write_instruction(f, instruction)?;
+                writeln!(f)?;
+            }
+            for handler in &code.exception_handlers {
+                match handler {
+                    Edit::Added(handler) => write_handler(f, '+', handler)?,
+                    Edit::Removed(handler) => write_handler(f, '-', handler)?,
+                    Edit::Changed(change) => {
+                        write_handler(f, '-', &change.old)?;
+                        write_handler(f, '+', &change.new)?;
+                    }
+                }
+            }
+            write_attributes(f, "      ", &code.attributes)
+        }
+    }
+}
+
+fn write_handler(f: &mut fmt::Formatter<'_>, sign: char, handler: &ExceptionHandler) -> fmt::Result {
+    let catch_type = handler
+        .catch_type
+        .as_ref()
+        .map_or_else(|| "any".to_owned(), |class| class.display().to_string());
+    writeln!(
+        f,
+        "      {sign} handler {}..{} -> {} catching {catch_type}",
+        handler.start.0, handler.end.0, handler.handler.0
+    )
+}
+
+fn write_attributes(f: &mut fmt::Formatter<'_>, indent: &str, attributes: &[Edit<Attribute>]) -> fmt::Result {
+    for edit in attributes {
+        let (old, new) = match edit {
+            Edit::Added(attribute) => (None, Some(attribute)),
+            Edit::Removed(attribute) => (Some(attribute), None),
+            Edit::Changed(change) => (Some(&change.old), Some(&change.new)),
+        };
+        for (sign, attribute) in [('-', old), ('+', new)] {
+            if let Some(attribute) = attribute {
+                write!(f, "{indent}{sign} attribute ")?;
+                write_attribute(f, attribute)?;
+                writeln!(f)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the items separated by commas.
+fn write_list<T>(
+    f: &mut fmt::Formatter<'_>,
+    items: &[T],
+    mut write: impl FnMut(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            f.write_str(", ")?;
+        }
+        write(f, item)?;
+    }
+    Ok(())
+}
+
+fn write_names(f: &mut fmt::Formatter<'_>, names: &[MString]) -> fmt::Result {
+    write_list(f, names, |f, name| write!(f, "{}", name.display()))
+}
+
+/// Writes an instruction like `javap`, with the constant pool entries written like its comments.
+fn write_instruction(f: &mut fmt::Formatter<'_>, instruction: &Instruction) -> fmt::Result {
+    f.write_str(mnemonic(instruction))?;
+    match instruction {
+        Instruction::Label(label) => write!(f, " {}", label.0),
+        Instruction::ALoad { index }
+        | Instruction::AStore { index }
+        | Instruction::DLoad { index }
+        | Instruction::DStore { index }
+        | Instruction::FLoad { index }
+        | Instruction::FStore { index }
+        | Instruction::ILoad { index }
+        | Instruction::IStore { index }
+        | Instruction::LLoad { index }
+        | Instruction::LStore { index }
+        | Instruction::Ret { index } => write!(f, " {index}"),
+        Instruction::ALoadW { index }
+        | Instruction::AStoreW { index }
+        | Instruction::DLoadW { index }
+        | Instruction::DStoreW { index }
+        | Instruction::FLoadW { index }
+        | Instruction::FStoreW { index }
+        | Instruction::ILoadW { index }
+        | Instruction::IStoreW { index }
+        | Instruction::LLoadW { index }
+        | Instruction::LStoreW { index }
+        | Instruction::RetW { index } => write!(f, " {index}"),
+        Instruction::BIPush { value } => write!(f, " {value}"),
+        Instruction::SIPush { value } => write!(f, " {value}"),
+        Instruction::IInc { index, value } => write!(f, " {index}, {value}"),
+        Instruction::IIncW { index, value } => write!(f, " {index}, {value}"),
+        Instruction::ANewArray { class }
+        | Instruction::CheckCast { class }
+        | Instruction::InstanceOf { class }
+        | Instruction::New { class } => write!(f, " class {}", class.display()),
+        Instruction::MultiANewArray { class, dimensions } => write!(f, " class {}, {dimensions}", class.display()),
+        Instruction::NewArray { atype } => {
+            let name = match atype {
+                ArrayType::Boolean => "boolean",
+                ArrayType::Char => "char",
+                ArrayType::Float => "float",
+                ArrayType::Double => "double",
+                ArrayType::Byte => "byte",
+                ArrayType::Short => "short",
+                ArrayType::Int => "int",
+                ArrayType::Long => "long",
+            };
+            write!(f, " {name}")
+        }
+        Instruction::GetField { field }
+        | Instruction::GetStatic { field }
+        | Instruction::PutField { field }
+        | Instruction::PutStatic { field } => write!(f, " Field {}", Member(field)),
+        Instruction::InvokeVirtual { method }
+        | Instruction::InvokeSpecial {
+            method,
+            interface: false,
+        }
+        | Instruction::InvokeStatic {
+            method,
+            interface: false,
+        } => write!(f, " Method {}", Member(method)),
+        Instruction::InvokeSpecial {
+            method,
+            interface: true,
+        }
+        | Instruction::InvokeStatic {
+            method,
+            interface: true,
+        } => write!(f, " InterfaceMethod {}", Member(method)),
+        Instruction::InvokeInterface { method, count } => write!(f, " InterfaceMethod {}, {count}", Member(method)),
+        Instruction::InvokeDynamic { call_site } => write!(
+            f,
+            " InvokeDynamic #{}:{}:{}",
+            call_site.bootstrap_method,
+            call_site.name.display(),
+            call_site.descriptor.display()
+        ),
+        Instruction::LdC { constant } | Instruction::LdCW { constant } | Instruction::LdC2W { constant } => {
+            f.write_str(" ")?;
+            write_constant(f, constant)
+        }
+        Instruction::Goto { target }
+        | Instruction::GotoW { target }
+        | Instruction::JSr { target }
+        | Instruction::JSrW { target }
+        | Instruction::IfACmpEq { target }
+        | Instruction::IfACmpNe { target }
+        | Instruction::IfICmpEq { target }
+        | Instruction::IfICmpNe { target }
+        | Instruction::IfICmpLt { target }
+        | Instruction::IfICmpGe { target }
+        | Instruction::IfICmpGt { target }
+        | Instruction::IfICmpLe { target }
+        | Instruction::IfEq { target }
+        | Instruction::IfNe { target }
+        | Instruction::IfLt { target }
+        | Instruction::IfGe { target }
+        | Instruction::IfGt { target }
+        | Instruction::IfLe { target }
+        | Instruction::IfNonNull { target }
+        | Instruction::IfNull { target } => write!(f, " {}", target.0),
+        Instruction::TableSwitch { default, low, targets } => {
+            f.write_str(" { ")?;
+            for (key, target) in (*low..).zip(targets) {
+                write!(f, "{key}: {}, ", target.0)?;
+            }
+            write!(f, "default: {} }}", default.0)
+        }
+        Instruction::LookupSwitch { default, pairs } => {
+            f.write_str(" { ")?;
+            for (key, target) in pairs {
+                write!(f, "{key}: {}, ", target.0)?;
+            }
+            write!(f, "default: {} }}", default.0)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A field or method written as `class.name:descriptor`.
+struct Member<'a>(&'a MemberRef);
+
+impl fmt::Display for Member<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}:{}",
+            self.0.class.display(),
+            self.0.name.display(),
+            self.0.descriptor.display()
+        )
+    }
+}
+
+fn write_constant(f: &mut fmt::Formatter<'_>, constant: &Constant) -> fmt::Result {
+    match constant {
+        Constant::Utf8(value) => write!(f, "Utf8 {}", value.display()),
+        Constant::Integer(value) => write!(f, "int {value}"),
+        Constant::Float(value) => write!(f, "float {value}f"),
+        Constant::Long(value) => write!(f, "long {value}l"),
+        Constant::Double(value) => write!(f, "double {value}d"),
+        Constant::Class(name) => write!(f, "class {}", name.display()),
+        Constant::String(value) => write!(f, "String {}", value.display()),
+        Constant::FieldRef(field) => write!(f, "Field {}", Member(field)),
+        Constant::MethodRef(method) => write!(f, "Method {}", Member(method)),
+        Constant::InterfaceMethodRef(method) => write!(f, "InterfaceMethod {}", Member(method)),
+        Constant::NameAndType(name_and_type) => write!(
+            f,
+            "NameAndType {}:{}",
+            name_and_type.name.display(),
+            name_and_type.descriptor.display()
+        ),
+        Constant::MethodHandle(handle) => {
+            let kind = match handle.kind {
+                MethodKind::GetField => "REF_getField",
+                MethodKind::GetStatic => "REF_getStatic",
+                MethodKind::PutField => "REF_putField",
+                MethodKind::PutStatic => "REF_putStatic",
+                MethodKind::InvokeVirtual => "REF_invokeVirtual",
+                MethodKind::InvokeStatic => "REF_invokeStatic",
+                MethodKind::InvokeSpecial => "REF_invokeSpecial",
+                MethodKind::NewInvokeSpecial => "REF_newInvokeSpecial",
+                MethodKind::InvokeInterface => "REF_invokeInterface",
+            };
+            let interface = if handle.interface { "InterfaceMethod " } else { "" };
+            write!(f, "MethodHandle {kind} {interface}{}", Member(&handle.reference))
+        }
+        Constant::MethodType(descriptor) => write!(f, "MethodType {}", descriptor.display()),
+        Constant::Dynamic(dynamic) => write!(
+            f,
+            "Dynamic #{}:{}:{}",
+            dynamic.bootstrap_method,
+            dynamic.name.display(),
+            dynamic.descriptor.display()
+        ),
+        Constant::InvokeDynamic(call_site) => write!(
+            f,
+            "InvokeDynamic #{}:{}:{}",
+            call_site.bootstrap_method,
+            call_site.name.display(),
+            call_site.descriptor.display()
+        ),
+        Constant::Module(name) => write!(f, "Module {}", name.display()),
+        Constant::Package(name) => write!(f, "Package {}", name.display()),
+    }
+}
+
+/// Writes the name of an attribute, followed by its content.
+///
+/// Positions in code are written as they are, which are instruction indices in a [`CodeDiff`](super::CodeDiff).
+fn write_attribute(f: &mut fmt::Formatter<'_>, attribute: &Attribute) -> fmt::Result {
+    write!(f, "{}", attribute.name().display())?;
+    if matches!(
+        attribute,
+        Attribute::Code(_) | Attribute::Deprecated | Attribute::Synthetic
+    ) {
+        return Ok(());
+    }
+    f.write_str(": ")?;
+    match attribute {
+        Attribute::AnnotationDefault { value } => write_element_value(f, value),
+        Attribute::BootstrapMethods(methods) => write_list(f, methods, |f, method| {
+            write_constant(f, &Constant::MethodHandle(method.method.clone()))?;
+            f.write_str(" [")?;
+            write_list(f, &method.arguments, write_constant)?;
+            f.write_str("]")
+        }),
+        Attribute::ConstantValue { value } => write_constant(f, value),
+        Attribute::EnclosingMethod { class, method } => {
+            write!(f, "{}", class.display())?;
+            match method {
+                Some(method) => write!(f, ".{}:{}", method.name.display(), method.descriptor.display()),
+                None => Ok(()),
+            }
+        }
+        Attribute::Exceptions(classes)
+        | Attribute::ModulePackages(classes)
+        | Attribute::NestMembers(classes)
+        | Attribute::PermittedSubclasses(classes) => write_names(f, classes),
+        Attribute::InnerClasses(classes) => write_list(f, classes, |f, class| {
+            if let Some(name) = &class.inner_name {
+                write!(f, "{}=", name.display())?;
+            }
+            write!(f, "{}", class.inner_class.display())?;
+            if let Some(outer_class) = &class.outer_class {
+                write!(f, " of {}", outer_class.display())?;
+            }
+            write!(f, " {}", Flags(class.inner_access_flags, &INNER_CLASS_FLAGS))
+        }),
+        Attribute::LineNumberTable(lines) => write_list(f, lines, |f, line| {
+            write!(f, "line {}: {}", line.line_number, line.start.0)
+        }),
+        Attribute::LocalVariableTable(variables) => write_list(f, variables, |f, variable| {
+            write!(
+                f,
+                "{} {} in slot {} from {} to {}",
+                variable.name.display(),
+                variable.descriptor.display(),
+                variable.index,
+                variable.start.0,
+                variable.end.0
+            )
+        }),
+        Attribute::LocalVariableTypeTable(variables) => write_list(f, variables, |f, variable| {
+            write!(
+                f,
+                "{} {} in slot {} from {} to {}",
+                variable.name.display(),
+                variable.signature.display(),
+                variable.index,
+                variable.start.0,
+                variable.end.0
+            )
+        }),
+        Attribute::MethodParameters(parameters) => write_list(f, parameters, |f, parameter| {
+            match &parameter.name {
+                Some(name) => write!(f, "{}", name.display())?,
+                None => f.write_str("<no name>")?,
+            }
+            write!(f, " {}", Flags(parameter.access_flags, &PARAMETER_FLAGS))
+        }),
+        Attribute::Module(module) => {
+            write!(f, "{}", module.name.display())?;
+            if let Some(version) = &module.version {
+                write!(f, "@{}", version.display())?;
+            }
+            write!(f, " {}", Flags(module.flags, &MODULE_FLAGS))?;
+            for require in &module.requires {
+                write!(
+                    f,
+                    ", requires {} {}",
+                    require.module.display(),
+                    Flags(require.flags, &REQUIRES_FLAGS)
+                )?;
+                if let Some(version) = &require.version {
+                    write!(f, " version {}", version.display())?;
+                }
+            }
+            for export in &module.exports {
+                write!(
+                    f,
+                    ", exports {} {}",
+                    export.package.display(),
+                    Flags(export.flags, &EXPORTS_FLAGS)
+                )?;
+                if !export.exports_to.is_empty() {
+                    f.write_str(" to [")?;
+                    write_names(f, &export.exports_to)?;
+                    f.write_str("]")?;
+                }
+            }
+            for open in &module.opens {
+                write!(
+                    f,
+                    ", opens {} {}",
+                    open.package.display(),
+                    Flags(open.flags, &EXPORTS_FLAGS)
+                )?;
+                if !open.opens_to.is_empty() {
+                    f.write_str(" to [")?;
+                    write_names(f, &open.opens_to)?;
+                    f.write_str("]")?;
+                }
+            }
+            for service in &module.uses {
+                write!(f, ", uses {}", service.display())?;
+            }
+            for provide in &module.provides {
+                write!(f, ", provides {} with [", provide.service.display())?;
+                write_names(f, &provide.provides_with)?;
+                f.write_str("]")?;
+            }
+            Ok(())
+        }
+        Attribute::ModuleMainClass(name)
+        | Attribute::NestHost(name)
+        | Attribute::Signature(name)
+        | Attribute::SourceDebugExtension(name)
+        | Attribute::SourceFile(name) => write!(f, "{}", name.display()),
+        Attribute::Record(components) => write_list(f, components, |f, component| {
+            write!(f, "{} {}", component.name.display(), component.descriptor.display())?;
+            if !component.attributes.is_empty() {
+                f.write_str(" [")?;
+                write_list(f, &component.attributes, write_attribute)?;
+                f.write_str("]")?;
+            }
+            Ok(())
+        }),
+        Attribute::RuntimeInvisibleAnnotations(annotations) | Attribute::RuntimeVisibleAnnotations(annotations) => {
+            write_list(f, annotations, write_annotation)
+        }
+        Attribute::RuntimeInvisibleParameterAnnotations(parameters)
+        | Attribute::RuntimeVisibleParameterAnnotations(parameters) => write_list(f, parameters, |f, annotations| {
+            f.write_str("[")?;
+            write_list(f, annotations, write_annotation)?;
+            f.write_str("]")
+        }),
+        Attribute::RuntimeInvisibleTypeAnnotations(annotations)
+        | Attribute::RuntimeVisibleTypeAnnotations(annotations) => write_list(f, annotations, write_type_annotation),
+        Attribute::StackMapTable(frames) => write_list(f, frames, write_frame),
+        Attribute::Unknown { content, .. } => write!(f, "{} bytes", content.len()),
+        Attribute::Code(_) | Attribute::Deprecated | Attribute::Synthetic => Ok(()),
+    }
+}
+
+fn write_annotation(f: &mut fmt::Formatter<'_>, annotation: &Annotation) -> fmt::Result {
+    write_annotation_of(f, &annotation.type_, &annotation.pairs)
+}
+
+fn write_annotation_of(f: &mut fmt::Formatter<'_>, type_: &MString, pairs: &[ElementValuePair]) -> fmt::Result {
+    write!(f, "@{}(", type_.display())?;
+    write_list(f, pairs, |f, pair| {
+        write!(f, "{}=", pair.name.display())?;
+        write_element_value(f, &pair.value)
+    })?;
+    f.write_str(")")
+}
+
+/// Writes an element value like a Java literal.
+fn write_element_value(f: &mut fmt::Formatter<'_>, value: &ElementValue) -> fmt::Result {
+    match value {
+        ElementValue::Boolean(value) => write!(f, "{}", *value != 0),
+        ElementValue::Byte(value) | ElementValue::Short(value) | ElementValue::Int(value) => write!(f, "{value}"),
+        ElementValue::Long(value) => write!(f, "{value}L"),
+        ElementValue::Float(value) => write!(f, "{value}f"),
+        ElementValue::Double(value) => write!(f, "{value}d"),
+        ElementValue::Char(value) => match u32::try_from(*value).ok().and_then(char::from_u32) {
+            Some(value) => write!(f, "'{value}'"),
+            None => write!(f, "{value}"),
+        },
+        ElementValue::String(value) => write!(f, "\"{}\"", value.display()),
+        ElementValue::Class(descriptor) => write!(f, "class {}", descriptor.display()),
+        ElementValue::Enum { type_name, const_name } => {
+            write!(f, "{}.{}", type_name.display(), const_name.display())
+        }
+        ElementValue::Annotation(annotation) => write_annotation(f, annotation),
+        ElementValue::Array(values) => {
+            f.write_str("{")?;
+            write_list(f, values, write_element_value)?;
+            f.write_str("}")
+        }
+    }
+}
+
+/// Writes a type annotation followed by its target like `javap`.
+fn write_type_annotation(f: &mut fmt::Formatter<'_>, annotation: &TypeAnnotation) -> fmt::Result {
+    write_annotation_of(f, &annotation.type_, &annotation.pairs)?;
+    let target_type = match annotation.target_type {
+        TargetType::ClassTypeParameter => "CLASS_TYPE_PARAMETER",
+        TargetType::MethodTypeParameter => "METHOD_TYPE_PARAMETER",
+        TargetType::ClassExtends => "CLASS_EXTENDS",
+        TargetType::ClassTypeParameterBound => "CLASS_TYPE_PARAMETER_BOUND",
+        TargetType::MethodTypeParameterBound => "METHOD_TYPE_PARAMETER_BOUND",
+        TargetType::Field => "FIELD",
+        TargetType::MethodReturn => "METHOD_RETURN",
+        TargetType::MethodReceiver => "METHOD_RECEIVER",
+        TargetType::MethodFormalParameter => "METHOD_FORMAL_PARAMETER",
+        TargetType::Throws => "THROWS",
+        TargetType::LocalVariable => "LOCAL_VARIABLE",
+        TargetType::ResourceVariable => "RESOURCE_VARIABLE",
+        TargetType::ExceptionParameter => "EXCEPTION_PARAMETER",
+        TargetType::InstanceOf => "INSTANCEOF",
+        TargetType::New => "NEW",
+        TargetType::ConstructorReference => "CONSTRUCTOR_REFERENCE",
+        TargetType::MethodReference => "METHOD_REFERENCE",
+        TargetType::Cast => "CAST",
+        TargetType::ConstructorInvocationTypeArgument => "CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT",
+        TargetType::MethodInvocationTypeArgument => "METHOD_INVOCATION_TYPE_ARGUMENT",
+        TargetType::ConstructorReferenceTypeArgument => "CONSTRUCTOR_REFERENCE_TYPE_ARGUMENT",
+        TargetType::MethodReferenceTypeArgument => "METHOD_REFERENCE_TYPE_ARGUMENT",
+    };
+    write!(f, ": {target_type}")?;
+    match &annotation.target_info {
+        TargetInfo::TypeParameter { parameter_index }
+        | TargetInfo::FormalParameter {
+            formal_parameter_index: parameter_index,
+        } => write!(f, ", param_index={parameter_index}")?,
+        TargetInfo::SuperType {
+            supertype_index: SuperTypeIndex::Class,
+        }
+        | TargetInfo::Empty => {}
+        TargetInfo::SuperType {
+            supertype_index: SuperTypeIndex::Interface { index },
+        } => write!(f, ", type_index={index}")?,
+        TargetInfo::TypeParameterBound {
+            type_parameter_index,
+            bound_index,
+        } => write!(f, ", param_index={type_parameter_index}, bound_index={bound_index}")?,
+        TargetInfo::Throws { throws_type_index } => write!(f, ", throws_index={throws_type_index}")?,
+        TargetInfo::LocalVariable { table } => {
+            f.write_str(", ")?;
+            write_list(f, table, |f, variable| {
+                write!(
+                    f,
+                    "{{start={}, end={}, index={}}}",
+                    variable.start.0, variable.end.0, variable.index
+                )
+            })?;
+        }
+        TargetInfo::Catch { exception_table_index } => write!(f, ", exception_index={exception_table_index}")?,
+        TargetInfo::Offset { offset } => write!(f, ", offset={}", offset.0)?,
+        TargetInfo::TypeArgument {
+            offset,
+            type_argument_index,
+        } => write!(f, ", offset={}, type_index={type_argument_index}", offset.0)?,
+    }
+    if !annotation.target_path.is_empty() {
+        f.write_str(", location=[")?;
+        write_list(f, &annotation.target_path, |f, segment| match segment.kind {
+            TypePathSegmentKind::ArrayElement => f.write_str("ARRAY"),
+            TypePathSegmentKind::InnerType => f.write_str("INNER_TYPE"),
+            TypePathSegmentKind::WildcardBound => f.write_str("WILDCARD"),
+            TypePathSegmentKind::TypeArgument => write!(f, "TYPE_ARGUMENT({})", segment.type_argument_index),
+        })?;
+        f.write_str("]")?;
+    }
+    Ok(())
+}
+
+fn write_frame(f: &mut fmt::Formatter<'_>, frame: &StackMapFrame) -> fmt::Result {
+    write!(f, "{}: ", frame.label().0)?;
+    let write_types = |f: &mut fmt::Formatter<'_>, types: &[VerificationType]| {
+        f.write_str("[")?;
+        write_list(f, types, write_verification_type)?;
+        f.write_str("]")
+    };
+    match frame {
+        StackMapFrame::Same { .. } => f.write_str("same"),
+        StackMapFrame::SameExtended { .. } => f.write_str("same_frame_extended"),
+        StackMapFrame::Same1 { stack, .. } => {
+            f.write_str("same_locals_1_stack_item, stack ")?;
+            write_types(f, std::slice::from_ref(stack))
+        }
+        StackMapFrame::Same1Extended { stack, .. } => {
+            f.write_str("same_locals_1_stack_item_extended, stack ")?;
+            write_types(f, std::slice::from_ref(stack))
+        }
+        StackMapFrame::Chop { to_chop, .. } => write!(f, "chop {to_chop}"),
+        StackMapFrame::Append { locals, .. } => {
+            f.write_str("append, locals ")?;
+            write_types(f, locals)
+        }
+        StackMapFrame::Full { locals, stack, .. } => {
+            f.write_str("full_frame, locals ")?;
+            write_types(f, locals)?;
+            f.write_str(", stack ")?;
+            write_types(f, stack)
+        }
+    }
+}
+
+fn write_verification_type(f: &mut fmt::Formatter<'_>, verification_type: &VerificationType) -> fmt::Result {
+    match verification_type {
+        VerificationType::Top => f.write_str("top"),
+        VerificationType::Integer => f.write_str("int"),
+        VerificationType::Float => f.write_str("float"),
+        VerificationType::Double => f.write_str("double"),
+        VerificationType::Long => f.write_str("long"),
+        VerificationType::Null => f.write_str("null"),
+        VerificationType::UninitializedThis => f.write_str("uninitialized this"),
+        VerificationType::Object(class) => write!(f, "class {}", class.display()),
+        VerificationType::Uninitialized(Label(label)) => write!(f, "uninitialized {label}"),
+    }
+}
+
+/// The mnemonic of an instruction, the wide forms of local variable instructions are prefixed with `wide`.
+fn mnemonic(instruction: &Instruction) -> &'static str {
+    match instruction {
+        Instruction::Label(_) => "label",
+        Instruction::AALoad => "aaload",
+        Instruction::AAStore => "aastore",
+        Instruction::AConstNull => "aconst_null",
+        Instruction::ALoad { .. } => "aload",
+        Instruction::ALoadW { .. } => "wide aload",
+        Instruction::ALoad0 => "aload_0",
+        Instruction::ALoad1 => "aload_1",
+        Instruction::ALoad2 => "aload_2",
+        Instruction::ALoad3 => "aload_3",
+        Instruction::ANewArray { .. } => "anewarray",
+        Instruction::AReturn => "areturn",
+        Instruction::ArrayLength => "arraylength",
+        Instruction::AStore { .. } => "astore",
+        Instruction::AStoreW { .. } => "wide astore",
+        Instruction::AStore0 => "astore_0",
+        Instruction::AStore1 => "astore_1",
+        Instruction::AStore2 => "astore_2",
+        Instruction::AStore3 => "astore_3",
+        Instruction::AThrow => "athrow",
+        Instruction::BALoad => "baload",
+        Instruction::BAStore => "bastore",
+        Instruction::BIPush { .. } => "bipush",
+        Instruction::CALoad => "caload",
+        Instruction::CAStore => "castore",
+        Instruction::CheckCast { .. } => "checkcast",
+        Instruction::D2F => "d2f",
+        Instruction::D2I => "d2i",
+        Instruction::D2L => "d2l",
+        Instruction::DAdd => "dadd",
+        Instruction::DALoad => "daload",
+        Instruction::DAStore => "dastore",
+        Instruction::DCmpG => "dcmpg",
+        Instruction::DCmpL => "dcmpl",
+        Instruction::DConst0 => "dconst_0",
+        Instruction::DConst1 => "dconst_1",
+        Instruction::DDiv => "ddiv",
+        Instruction::DLoad { .. } => "dload",
+        Instruction::DLoadW { .. } => "wide dload",
+        Instruction::DLoad0 => "dload_0",
+        Instruction::DLoad1 => "dload_1",
+        Instruction::DLoad2 => "dload_2",
+        Instruction::DLoad3 => "dload_3",
+        Instruction::DMul => "dmul",
+        Instruction::DNeg => "dneg",
+        Instruction::DRem => "drem",
+        Instruction::DReturn => "dreturn",
+        Instruction::DStore { .. } => "dstore",
+        Instruction::DStoreW { .. } => "wide dstore",
+        Instruction::DStore0 => "dstore_0",
+        Instruction::DStore1 => "dstore_1",
+        Instruction::DStore2 => "dstore_2",
+        Instruction::DStore3 => "dstore_3",
+        Instruction::DSub => "dsub",
+        Instruction::Dup => "dup",
+        Instruction::DupX1 => "dup_x1",
+        Instruction::DupX2 => "dup_x2",
+        Instruction::Dup2 => "dup2",
+        Instruction::Dup2X1 => "dup2_x1",
+        Instruction::Dup2X2 => "dup2_x2",
+        Instruction::F2D => "f2d",
+        Instruction::F2I => "f2i",
+        Instruction::F2L => "f2l",
+        Instruction::FAdd => "fadd",
+        Instruction::FALoad => "faload",
+        Instruction::FAStore => "fastore",
+        Instruction::FCmpG => "fcmpg",
+        Instruction::FCmpL => "fcmpl",
+        Instruction::FConst0 => "fconst_0",
+        Instruction::FConst1 => "fconst_1",
+        Instruction::FConst2 => "fconst_2",
+        Instruction::FDiv => "fdiv",
+        Instruction::FLoad { .. } => "fload",
+        Instruction::FLoadW { .. } => "wide fload",
+        Instruction::FLoad0 => "fload_0",
+        Instruction::FLoad1 => "fload_1",
+        Instruction::FLoad2 => "fload_2",
+        Instruction::FLoad3 => "fload_3",
+        Instruction::FMul => "fmul",
+        Instruction::FNeg => "fneg",
+        Instruction::FRem => "frem",
+        Instruction::FReturn => "freturn",
+        Instruction::FStore { .. } => "fstore",
+        Instruction::FStoreW { .. } => "wide fstore",
+        Instruction::FStore0 => "fstore_0",
+        Instruction::FStore1 => "fstore_1",
+        Instruction::FStore2 => "fstore_2",
+        Instruction::FStore3 => "fstore_3",
+        Instruction::FSub => "fsub",
+        Instruction::GetField { .. } => "getfield",
+        Instruction::GetStatic { .. } => "getstatic",
+        Instruction::Goto { .. } => "goto",
+        Instruction::GotoW { .. } => "goto_w",
+        Instruction::I2B => "i2b",
+        Instruction::I2C => "i2c",
+        Instruction::I2D => "i2d",
+        Instruction::I2F => "i2f",
+        Instruction::I2L => "i2l",
+        Instruction::I2S => "i2s",
+        Instruction::IAdd => "iadd",
+        Instruction::IALoad => "iaload",
+        Instruction::IAnd => "iand",
+        Instruction::IAStore => "iastore",
+        Instruction::IConstM1 => "iconst_m1",
+        Instruction::IConst0 => "iconst_0",
+        Instruction::IConst1 => "iconst_1",
+        Instruction::IConst2 => "iconst_2",
+        Instruction::IConst3 => "iconst_3",
+        Instruction::IConst4 => "iconst_4",
+        Instruction::IConst5 => "iconst_5",
+        Instruction::IDiv => "idiv",
+        Instruction::IfACmpEq { .. } => "if_acmpeq",
+        Instruction::IfACmpNe { .. } => "if_acmpne",
+        Instruction::IfICmpEq { .. } => "if_icmpeq",
+        Instruction::IfICmpNe { .. } => "if_icmpne",
+        Instruction::IfICmpLt { .. } => "if_icmplt",
+        Instruction::IfICmpGe { .. } => "if_icmpge",
+        Instruction::IfICmpGt { .. } => "if_icmpgt",
+        Instruction::IfICmpLe { .. } => "if_icmple",
+        Instruction::IfEq { .. } => "ifeq",
+        Instruction::IfNe { .. } => "ifne",
+        Instruction::IfLt { .. } => "iflt",
+        Instruction::IfGe { .. } => "ifge",
+        Instruction::IfGt { .. } => "ifgt",
+        Instruction::IfLe { .. } => "ifle",
+        Instruction::IfNonNull { .. } => "ifnonnull",
+        Instruction::IfNull { .. } => "ifnull",
+        Instruction::IInc { .. } => "iinc",
+        Instruction::IIncW { .. } => "wide iinc",
+        Instruction::ILoad { .. } => "iload",
+        Instruction::ILoadW { .. } => "wide iload",
+        Instruction::ILoad0 => "iload_0",
+        Instruction::ILoad1 => "iload_1",
+        Instruction::ILoad2 => "iload_2",
+        Instruction::ILoad3 => "iload_3",
+        Instruction::IMul => "imul",
+        Instruction::INeg => "ineg",
+        Instruction::InstanceOf { .. } => "instanceof",
+        Instruction::InvokeDynamic { .. } => "invokedynamic",
+        Instruction::InvokeInterface { .. } => "invokeinterface",
+        Instruction::InvokeSpecial { .. } => "invokespecial",
+        Instruction::InvokeStatic { .. } => "invokestatic",
+        Instruction::InvokeVirtual { .. } => "invokevirtual",
+        Instruction::IOr => "ior",
+        Instruction::IRem => "irem",
+        Instruction::IReturn => "ireturn",
+        Instruction::IShL => "ishl",
+        Instruction::IShR => "ishr",
+        Instruction::IStore { .. } => "istore",
+        Instruction::IStoreW { .. } => "wide istore",
+        Instruction::IStore0 => "istore_0",
+        Instruction::IStore1 => "istore_1",
+        Instruction::IStore2 => "istore_2",
+        Instruction::IStore3 => "istore_3",
+        Instruction::ISub => "isub",
+        Instruction::IUShR => "iushr",
+        Instruction::IXor => "ixor",
+        Instruction::JSr { .. } => "jsr",
+        Instruction::JSrW { .. } => "jsr_w",
+        Instruction::L2D => "l2d",
+        Instruction::L2F => "l2f",
+        Instruction::L2I => "l2i",
+        Instruction::LAdd => "ladd",
+        Instruction::LALoad => "laload",
+        Instruction::LAnd => "land",
+        Instruction::LAStore => "lastore",
+        Instruction::LCmp => "lcmp",
+        Instruction::LConst0 => "lconst_0",
+        Instruction::LConst1 => "lconst_1",
+        Instruction::LdC { .. } => "ldc",
+        Instruction::LdCW { .. } => "ldc_w",
+        Instruction::LdC2W { .. } => "ldc2_w",
+        Instruction::LDiv => "ldiv",
+        Instruction::LLoad { .. } => "lload",
+        Instruction::LLoadW { .. } => "wide lload",
+        Instruction::LLoad0 => "lload_0",
+        Instruction::LLoad1 => "lload_1",
+        Instruction::LLoad2 => "lload_2",
+        Instruction::LLoad3 => "lload_3",
+        Instruction::LMul => "lmul",
+        Instruction::LNeg => "lneg",
+        Instruction::LookupSwitch { .. } => "lookupswitch",
+        Instruction::LOr => "lor",
+        Instruction::LRem => "lrem",
+        Instruction::LReturn => "lreturn",
+        Instruction::LShL => "lshl",
+        Instruction::LShR => "lshr",
+        Instruction::LStore { .. } => "lstore",
+        Instruction::LStoreW { .. } => "wide lstore",
+        Instruction::LStore0 => "lstore_0",
+        Instruction::LStore1 => "lstore_1",
+        Instruction::LStore2 => "lstore_2",
+        Instruction::LStore3 => "lstore_3",
+        Instruction::LSub => "lsub",
+        Instruction::LUShR => "lushr",
+        Instruction::LXor => "lxor",
+        Instruction::MonitorEnter => "monitorenter",
+        Instruction::MonitorExit => "monitorexit",
+        Instruction::MultiANewArray { .. } => "multianewarray",
+        Instruction::New { .. } => "new",
+        Instruction::NewArray { .. } => "newarray",
+        Instruction::Nop => "nop",
+        Instruction::Pop => "pop",
+        Instruction::Pop2 => "pop2",
+        Instruction::PutField { .. } => "putfield",
+        Instruction::PutStatic { .. } => "putstatic",
+        Instruction::Ret { .. } => "ret",
+        Instruction::RetW { .. } => "wide ret",
+        Instruction::Return => "return",
+        Instruction::SALoad => "saload",
+        Instruction::SAStore => "sastore",
+        Instruction::SIPush { .. } => "sipush",
+        Instruction::Swap => "swap",
+        Instruction::TableSwitch { .. } => "tableswitch",
+    }
+}
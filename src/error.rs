@@ -1,3 +1,5 @@
+use crate::header::{Feature, Version};
+use crate::mutf8::MString;
 use crate::reader::decoding::Decoder;
 use std::{error::Error, fmt};
 
@@ -43,7 +45,9 @@ impl fmt::Display for DecodeErrorKind {
 pub struct DecodeError {
     kind: DecodeErrorKind,
     position: Option<usize>,
+    excerpt: Excerpt,
     context: Context,
+    path: Path,
 }
 
 impl DecodeError {
@@ -52,7 +56,9 @@ impl DecodeError {
         DecodeError {
             kind,
             position: None,
+            excerpt: Excerpt::EMPTY,
             context: Context::None,
+            path: Path::new(),
         }
     }
 
@@ -61,7 +67,9 @@ impl DecodeError {
         DecodeError {
             kind,
             position: None,
+            excerpt: Excerpt::EMPTY,
             context,
+            path: Path::new(),
         }
     }
 
@@ -70,7 +78,9 @@ impl DecodeError {
         DecodeError {
             kind,
             position: Some(position),
+            excerpt: Excerpt::EMPTY,
             context,
+            path: Path::new(),
         }
     }
 
@@ -79,7 +89,9 @@ impl DecodeError {
         DecodeError {
             kind,
             position: Some(decoder.file_position()),
+            excerpt: Excerpt::new(decoder.buf()),
             context: decoder.context(),
+            path: Path::new(),
         }
     }
 
@@ -94,21 +106,84 @@ impl DecodeError {
         self.position
     }
 
+    /// The first few bytes starting at the position of the error.
+    ///
+    /// This is empty if the position is unknown.
+    #[must_use]
+    pub fn excerpt(&self) -> &[u8] {
+        self.excerpt.as_slice()
+    }
+
     #[must_use]
     pub fn context(&self) -> Context {
         self.context
     }
+
+    /// The path to the part of the class in which the error occurred.
+    ///
+    /// The path is complete if the error occurred while the class was read as a whole,
+    /// such as in [`tree::Class::validate`](crate::tree::Class::validate) or [`tree::Class::read`](crate::tree::Class::read).
+    /// Errors of the lazy [`reader`](crate::reader) only contain the innermost member, attribute or instruction
+    /// that was being read.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Adds a segment to the front of the path.
+    #[must_use]
+    pub(crate) fn within(mut self, segment: PathSegment) -> DecodeError {
+        self.path.segments.insert(0, segment);
+        self
+    }
 }
 
 impl Error for DecodeError {}
 
 impl fmt::Display for DecodeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind())?;
         if let Some(pos) = self.position() {
-            write!(f, "{} at {} in {}", self.kind(), pos, self.context())
-        } else {
-            write!(f, "{}", self.kind())
+            write!(f, " at {} in {}", pos, self.context())?;
+            if !self.excerpt().is_empty() {
+                write!(f, " (bytes:")?;
+                for byte in self.excerpt() {
+                    write!(f, " {:02x}", byte)?;
+                }
+                write!(f, ")")?;
+            }
+        }
+        if !self.path.is_empty() {
+            write!(f, ", in {}", self.path)?;
         }
+        Ok(())
+    }
+}
+
+/// A few bytes of the input, stored inline as errors should stay cheap to create.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Excerpt {
+    bytes: [u8; Excerpt::MAX_LEN],
+    len: u8,
+}
+
+impl Excerpt {
+    const MAX_LEN: usize = 8;
+    const EMPTY: Excerpt = Excerpt {
+        bytes: [0; Excerpt::MAX_LEN],
+        len: 0,
+    };
+
+    fn new(input: &[u8]) -> Excerpt {
+        let mut excerpt = Excerpt::EMPTY;
+        let len = input.len().min(Excerpt::MAX_LEN);
+        excerpt.bytes[..len].copy_from_slice(&input[..len]);
+        excerpt.len = len as u8;
+        excerpt
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
     }
 }
 
@@ -127,6 +202,19 @@ pub enum EncodeErrorKind {
     NegativeOffset,
     IncorrectBounds,
     InvalidKeyOrder,
+    /// A key occurs in multiple cases of a switch.
+    DuplicateKey,
+    /// A case of an enum switch is not a constant of the enum.
+    UnknownEnumConstant,
+    /// A scope was ended without having begun one.
+    NotInScope,
+    /// A loop was left or continued outside of a loop.
+    NotInLoop,
+    /// A feature was used which is not legal in the version of the class.
+    UnsupportedFeature {
+        feature: Feature,
+        version: Version,
+    },
     Other(Box<dyn Error + 'static>),
 }
 
@@ -150,6 +238,15 @@ impl fmt::Display for EncodeErrorKind {
                 f,
                 "the keys in the lookupswitch instruction must be in an increasing numerical order"
             ),
+            DuplicateKey => write!(f, "a key occurs in multiple cases of a switch"),
+            UnknownEnumConstant => write!(f, "the case is not a constant of the enum"),
+            NotInScope => write!(f, "no scope was begun"),
+            NotInLoop => write!(f, "not inside of a loop"),
+            UnsupportedFeature { feature, version } => write!(
+                f,
+                "{} is not supported by class file version {}.{}",
+                feature, version.major, version.minor
+            ),
             Other(err) => write!(f, "other: {}", err),
         }
     }
@@ -159,6 +256,7 @@ impl fmt::Display for EncodeErrorKind {
 pub struct EncodeError {
     kind: EncodeErrorKind,
     context: Context,
+    path: Path,
 }
 
 impl EncodeError {
@@ -166,11 +264,16 @@ impl EncodeError {
         EncodeError {
             kind: EncodeErrorKind::Other(Box::new(err)),
             context,
+            path: Path::new(),
         }
     }
 
     pub(crate) fn with_context(kind: EncodeErrorKind, context: Context) -> EncodeError {
-        EncodeError { kind, context }
+        EncodeError {
+            kind,
+            context,
+            path: Path::new(),
+        }
     }
 
     #[must_use]
@@ -182,6 +285,33 @@ impl EncodeError {
     pub fn context(&self) -> Context {
         self.context
     }
+
+    /// The path of builder steps and items that were written when the error occurred.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The innermost builder step that failed, such as `CodeWriter::instructions`.
+    #[must_use]
+    pub fn step(&self) -> Option<&'static str> {
+        self.path.segments.iter().rev().find_map(|segment| match segment {
+            PathSegment::Step { name } => Some(*name),
+            _ => None,
+        })
+    }
+
+    /// Adds a segment to the front of the path.
+    #[must_use]
+    pub(crate) fn within(mut self, segment: PathSegment) -> EncodeError {
+        self.path.segments.insert(0, segment);
+        self
+    }
+}
+
+/// Returns a function adding a builder step to the path of an error, to be used with [`Result::map_err`].
+pub(crate) fn step(name: &'static str) -> impl FnOnce(EncodeError) -> EncodeError {
+    move |err| err.within(PathSegment::Step { name })
 }
 
 impl Error for EncodeError {
@@ -196,7 +326,123 @@ impl Error for EncodeError {
 
 impl fmt::Display for EncodeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} in {}", self.kind(), self.context())
+        write!(f, "{} in {}", self.kind(), self.context())?;
+        if !self.path.is_empty() {
+            write!(f, ", in {}", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// The location of an error inside of a class, from the outermost to the innermost part.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Path {
+    segments: Vec<PathSegment>,
+}
+
+impl Path {
+    #[must_use]
+    pub(crate) const fn new() -> Path {
+        Path { segments: Vec::new() }
+    }
+
+    #[must_use]
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.segments.is_empty() {
+            return write!(f, "class");
+        }
+
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i != 0 {
+                write!(f, " -> ")?;
+            }
+            write!(f, "{}", segment)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single part of a [`Path`].
+///
+/// Names are `None` if they could not be resolved themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PathSegment {
+    ConstantPool {
+        index: u16,
+    },
+    /// The name of the class or its super class.
+    ClassInfo,
+    Interface {
+        index: u16,
+    },
+    Field {
+        index: u16,
+        name: Option<MString>,
+    },
+    Method {
+        index: u16,
+        name: Option<MString>,
+        descriptor: Option<MString>,
+    },
+    Attribute {
+        index: u16,
+        name: Option<MString>,
+    },
+    Instruction {
+        pc: u32,
+    },
+    /// An operand of an instruction, named after the field of [`tree::Instruction`](crate::tree::Instruction).
+    Operand {
+        name: &'static str,
+    },
+    ExceptionHandler {
+        index: u16,
+    },
+    /// An entry of a table, such as a record component or a single method or attribute that is written.
+    Entry {
+        index: u32,
+    },
+    /// A method of a writer, such as `ClassWriter::methods`.
+    Step {
+        name: &'static str,
+    },
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::ConstantPool { index } => write!(f, "constant pool entry #{}", index),
+            PathSegment::ClassInfo => write!(f, "class information"),
+            PathSegment::Interface { index } => write!(f, "interface {}", index),
+            PathSegment::Field { name: Some(name), .. } => write!(f, "field `{}`", name.display()),
+            PathSegment::Field { index, name: None } => write!(f, "field {}", index),
+            PathSegment::Method {
+                name: Some(name),
+                descriptor: Some(descriptor),
+                ..
+            } => write!(f, "method `{}{}`", name.display(), descriptor.display()),
+            PathSegment::Method { name: Some(name), .. } => write!(f, "method `{}`", name.display()),
+            PathSegment::Method { index, .. } => write!(f, "method {}", index),
+            PathSegment::Attribute { name: Some(name), .. } => write!(f, "attribute `{}`", name.display()),
+            PathSegment::Attribute { index, name: None } => write!(f, "attribute {}", index),
+            PathSegment::Instruction { pc } => write!(f, "instruction at pc {}", pc),
+            PathSegment::Operand { name } => write!(f, "operand `{}`", name),
+            PathSegment::ExceptionHandler { index } => write!(f, "exception handler {}", index),
+            PathSegment::Entry { index } => write!(f, "entry {}", index),
+            PathSegment::Step { name } => write!(f, "{}", name),
+        }
     }
 }
 
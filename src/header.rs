@@ -2,9 +2,11 @@ use crate::error::*;
 use crate::reader::decoding::{Decode, Decoder};
 use crate::writer::encoding::{Encode, Encoder};
 use bitflags::bitflags;
+use std::fmt;
 
 /// The version numbers of a class file.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Version {
     pub major: u16,
     pub minor: u16,
@@ -30,18 +32,169 @@ impl Version {
     pub const V16: Version = Version { major: 60, minor: 0 };
     pub const V17: Version = Version { major: 61, minor: 0 };
     pub const V18: Version = Version { major: 62, minor: 0 };
+    pub const V19: Version = Version { major: 63, minor: 0 };
+    pub const V20: Version = Version { major: 64, minor: 0 };
+    pub const V21: Version = Version { major: 65, minor: 0 };
+    pub const V22: Version = Version { major: 66, minor: 0 };
+    pub const V23: Version = Version { major: 67, minor: 0 };
+    pub const V24: Version = Version { major: 68, minor: 0 };
+    pub const V25: Version = Version { major: 69, minor: 0 };
+    pub const V26: Version = Version { major: 70, minor: 0 };
+    pub const V27: Version = Version { major: 71, minor: 0 };
 
     /// The latest version which is guaranteed to work with this library.
     /// Changes of this value are not considered breaking changes.
     #[must_use]
     pub const fn latest() -> Version {
-        Version::V18
+        Version::V27
     }
 
     #[must_use]
     pub fn is_preview(self) -> bool {
         self.major >= Version::V12.major && self.minor == 65535
     }
+
+    /// Whether classes of this version may use a feature.
+    ///
+    /// ```
+    /// use noak::{Feature, Version};
+    ///
+    /// assert!(Version::V17.supports(Feature::Attribute("Record")));
+    /// assert!(!Version::V8.supports(Feature::Attribute("Record")));
+    /// assert!(!Version::V8.supports(Feature::Subroutines));
+    /// ```
+    #[must_use]
+    pub fn supports(self, feature: Feature) -> bool {
+        // preview versions have the features of their major version
+        let version = Version {
+            major: self.major,
+            minor: if self.is_preview() { 0 } else { self.minor },
+        };
+        version >= feature.since() && feature.until().map_or(true, |until| version < until)
+    }
+
+    /// Whether every method with branches needs a `StackMapTable` attribute to pass verification.
+    ///
+    /// Classes of version 50 may omit it, in which case the JVM falls back to verification by type inference.
+    #[must_use]
+    pub fn requires_stack_maps(self) -> bool {
+        self >= Version::V7
+    }
+}
+
+/// A part of the class file format which is only legal in some versions.
+///
+/// See [`Version::supports`] for whether a version supports a feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Feature {
+    /// An attribute, by its name.
+    ///
+    /// Attributes which are not defined by the JVM specification are supported by every version.
+    Attribute(&'static str),
+    /// The `MethodHandle` and `MethodType` constants.
+    MethodHandleConstants,
+    /// The `InvokeDynamic` constant and the `invokedynamic` instruction.
+    InvokeDynamic,
+    /// The `Dynamic` constant.
+    DynamicConstants,
+    /// The `Module` and `Package` constants and the `ACC_MODULE` flag.
+    Modules,
+    /// The `jsr`, `jsr_w` and `ret` instructions, which are illegal since version 51.
+    Subroutines,
+    /// The `ACC_SYNTHETIC`, `ACC_ANNOTATION` and `ACC_ENUM` flags of classes.
+    ExtendedAccessFlags,
+    /// Methods with code in interfaces, and invoking methods of interfaces with `invokestatic` and `invokespecial`.
+    DefaultMethods,
+}
+
+/// The lowest version of the class file format.
+const INITIAL: Version = Version { major: 45, minor: 0 };
+
+/// The attributes defined by the JVM specification and the versions which introduced them.
+const ATTRIBUTES: &[(&str, Version)] = &[
+    ("ConstantValue", INITIAL),
+    ("Code", INITIAL),
+    ("Exceptions", INITIAL),
+    ("SourceFile", INITIAL),
+    ("LineNumberTable", INITIAL),
+    ("LocalVariableTable", INITIAL),
+    ("InnerClasses", INITIAL),
+    ("Synthetic", INITIAL),
+    ("Deprecated", INITIAL),
+    ("EnclosingMethod", Version::V5_0),
+    ("Signature", Version::V5_0),
+    ("SourceDebugExtension", Version::V5_0),
+    ("LocalVariableTypeTable", Version::V5_0),
+    ("RuntimeVisibleAnnotations", Version::V5_0),
+    ("RuntimeInvisibleAnnotations", Version::V5_0),
+    ("RuntimeVisibleParameterAnnotations", Version::V5_0),
+    ("RuntimeInvisibleParameterAnnotations", Version::V5_0),
+    ("AnnotationDefault", Version::V5_0),
+    ("StackMapTable", Version::V6),
+    ("BootstrapMethods", Version::V7),
+    ("RuntimeVisibleTypeAnnotations", Version::V8),
+    ("RuntimeInvisibleTypeAnnotations", Version::V8),
+    ("MethodParameters", Version::V8),
+    ("Module", Version::V9),
+    ("ModulePackages", Version::V9),
+    ("ModuleMainClass", Version::V9),
+    ("NestHost", Version::V11),
+    ("NestMembers", Version::V11),
+    ("Record", Version::V16),
+    ("PermittedSubclasses", Version::V17),
+];
+
+impl Feature {
+    /// Returns the feature of an attribute defined by the JVM specification.
+    #[must_use]
+    pub fn attribute(name: &[u8]) -> Option<Feature> {
+        ATTRIBUTES
+            .iter()
+            .find(|(attribute, _)| attribute.as_bytes() == name)
+            .map(|(attribute, _)| Feature::Attribute(attribute))
+    }
+
+    /// The first version which supports this feature.
+    #[must_use]
+    pub fn since(self) -> Version {
+        match self {
+            Feature::Attribute(name) => ATTRIBUTES
+                .iter()
+                .find(|(attribute, _)| *attribute == name)
+                .map_or(INITIAL, |(_, version)| *version),
+            Feature::MethodHandleConstants | Feature::InvokeDynamic => Version::V7,
+            Feature::DynamicConstants => Version::V11,
+            Feature::Modules => Version::V9,
+            Feature::Subroutines => INITIAL,
+            Feature::ExtendedAccessFlags => Version::V5_0,
+            Feature::DefaultMethods => Version::V8,
+        }
+    }
+
+    /// The first version which does not support this feature anymore.
+    #[must_use]
+    pub fn until(self) -> Option<Version> {
+        match self {
+            Feature::Subroutines => Some(Version::V7),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Feature::Attribute(name) => write!(f, "the `{}` attribute", name),
+            Feature::MethodHandleConstants => write!(f, "method handle constants"),
+            Feature::InvokeDynamic => write!(f, "invokedynamic"),
+            Feature::DynamicConstants => write!(f, "dynamic constants"),
+            Feature::Modules => write!(f, "modules"),
+            Feature::Subroutines => write!(f, "subroutines"),
+            Feature::ExtendedAccessFlags => write!(f, "the synthetic, annotation and enum flags"),
+            Feature::DefaultMethods => write!(f, "default methods"),
+        }
+    }
 }
 
 bitflags! {
@@ -83,3 +236,19 @@ impl Encode for AccessFlags {
         Ok(())
     }
 }
+
+/// Access flags are serialized as their raw bit representation, as several flags share the same bit.
+#[cfg(feature = "serde")]
+impl serde::Serialize for AccessFlags {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.bits())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for AccessFlags {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<AccessFlags, D::Error> {
+        let bits = u16::deserialize(deserializer)?;
+        Ok(AccessFlags::from_bits_truncate(bits))
+    }
+}
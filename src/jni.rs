@@ -0,0 +1,503 @@
+//! Generation of JNI headers for native methods, as written by `javac -h`, and of Rust [bindings](Bindings)
+//! to call Java code.
+//!
+//! The header of a class declares a function for each native method, named as required by the JNI specification,
+//! and a `#define` for each static final field of a primitive type with a constant value.
+//! The constants of superclasses are included as well, if the superclasses were [added](Headers::add_class).
+//! Floating-point constants are formatted like Java 19 and later do, older versions of `javac` sometimes write
+//! more digits than needed.
+//!
+//! ```
+//! use noak::jni::Headers;
+//! use noak::reader;
+//! use noak::tree::{Attribute, Class, Constant, Field, Method};
+//! use noak::AccessFlags;
+//!
+//! let class = Class {
+//!     fields: vec![Field {
+//!         access_flags: AccessFlags::STATIC | AccessFlags::FINAL,
+//!         name: "MAX".into(),
+//!         descriptor: "I".into(),
+//!         attributes: vec![Attribute::ConstantValue {
+//!             value: Constant::Integer(10),
+//!         }],
+//!     }],
+//!     methods: vec![Method {
+//!         access_flags: AccessFlags::PUBLIC | AccessFlags::NATIVE,
+//!         name: "add".into(),
+//!         descriptor: "(II)I".into(),
+//!         attributes: Vec::new(),
+//!     }],
+//!     ..Class::new("com/example/Native")
+//! };
+//! let bytes = class.to_bytes()?;
+//! let header = Headers::new().header(&reader::Class::new(&bytes)?)?;
+//!
+//! assert_eq!(header.file_name(), "com_example_Native.h");
+//! assert_eq!(
+//!     header.to_string(),
+//!     r#"/* DO NOT EDIT THIS FILE - it is machine generated */
+//! #include <jni.h>
+//! /* Header for class com_example_Native */
+//!
+//! #ifndef _Included_com_example_Native
+//! #define _Included_com_example_Native
+//! #ifdef __cplusplus
+//! extern "C" {
+//! #endif
+//! #undef com_example_Native_MAX
+//! #define com_example_Native_MAX 10L
+//! /*
+//!  * Class:     com_example_Native
+//!  * Method:    add
+//!  * Signature: (II)I
+//!  */
+//! JNIEXPORT jint JNICALL Java_com_example_Native_add
+//!   (JNIEnv *, jobject, jint, jint);
+//!
+//! #ifdef __cplusplus
+//! }
+//! #endif
+//! #endif
+//! "#
+//! );
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+mod bindings;
+
+pub use bindings::Bindings;
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use crate::descriptor::{BaseType, MethodDescriptor, TypeDescriptor};
+use crate::error::*;
+use crate::header::AccessFlags;
+use crate::mutf8::{MStr, MString};
+use crate::reader;
+use crate::tree::{Attribute, Class, Constant};
+
+/// The classes known when generating headers.
+///
+/// Classes are needed to include the constants of superclasses and to recognize subclasses of
+/// `java.lang.Throwable`, which are passed as `jthrowable`.
+#[derive(Debug, Clone, Default)]
+pub struct Headers {
+    classes: HashMap<MString, Declarations>,
+}
+
+#[derive(Debug, Clone)]
+struct Declarations {
+    super_class: Option<MString>,
+    /// The names of the constants and their values as C literals.
+    constants: Vec<(MString, String)>,
+}
+
+impl Headers {
+    #[must_use]
+    pub fn new() -> Headers {
+        Headers::default()
+    }
+
+    /// Adds a class, whose constants are inherited by the headers of its subclasses.
+    pub fn add_class(&mut self, class: &reader::Class<'_>) -> Result<&mut Self, DecodeError> {
+        let class = Class::from_reader(class)?;
+        self.classes.insert(class.name.clone(), declarations(&class));
+        Ok(self)
+    }
+
+    /// Generates the header of a class.
+    ///
+    /// `javac` only writes headers for classes with native methods or constants annotated with
+    /// `@java.lang.annotation.Native`, which is not retained in class files.
+    pub fn header(&self, class: &reader::Class<'_>) -> Result<Header, DecodeError> {
+        let class = Class::from_reader(class)?;
+        let own = declarations(&class);
+
+        // the constants of the topmost superclass come first
+        let mut superclasses = Vec::new();
+        let mut super_class = own.super_class.as_deref();
+        while let Some(declarations) = super_class.and_then(|name| self.classes.get(name)) {
+            if superclasses.len() > self.classes.len() {
+                // the hierarchy is cyclic
+                break;
+            }
+            superclasses.push(declarations);
+            super_class = declarations.super_class.as_deref();
+        }
+        let constants = superclasses
+            .iter()
+            .rev()
+            .chain(std::iter::once(&&own))
+            .flat_map(|declarations| declarations.constants.iter().cloned())
+            .collect();
+
+        let natives: Vec<_> = class
+            .methods
+            .iter()
+            .filter(|method| method.access_flags.contains(AccessFlags::NATIVE))
+            .collect();
+        let mut methods = Vec::with_capacity(natives.len());
+        for method in &natives {
+            let descriptor = MethodDescriptor::parse(&method.descriptor)?;
+            methods.push(NativeMethod {
+                name: method.name.clone(),
+                descriptor: method.descriptor.clone(),
+                is_static: method.access_flags.contains(AccessFlags::STATIC),
+                overloaded: natives.iter().filter(|other| other.name == method.name).count() > 1,
+                return_type: self.jni_type(descriptor.return_type().as_ref()),
+                parameters: descriptor
+                    .parameters()
+                    .map(|parameter| self.jni_type(Some(&parameter)))
+                    .collect(),
+            });
+        }
+
+        Ok(Header {
+            qualified_name: qualified_name(&class),
+            class: class.name,
+            constants,
+            methods,
+        })
+    }
+
+    fn is_throwable<'a>(&'a self, mut class: &'a MStr) -> bool {
+        for _ in 0..=self.classes.len() {
+            if class == "java/lang/Throwable" {
+                return true;
+            }
+            match self
+                .classes
+                .get(class)
+                .and_then(|declarations| declarations.super_class.as_deref())
+            {
+                Some(super_class) => class = super_class,
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// The C type of a Java type, `None` is `void`.
+    fn jni_type(&self, descriptor: Option<&TypeDescriptor<'_>>) -> &'static str {
+        let Some(descriptor) = descriptor else {
+            return "void";
+        };
+        match (descriptor.dimensions, &descriptor.base) {
+            (0, BaseType::Boolean) => "jboolean",
+            (0, BaseType::Byte) => "jbyte",
+            (0, BaseType::Char) => "jchar",
+            (0, BaseType::Short) => "jshort",
+            (0, BaseType::Integer) => "jint",
+            (0, BaseType::Long) => "jlong",
+            (0, BaseType::Float) => "jfloat",
+            (0, BaseType::Double) => "jdouble",
+            (0, BaseType::Object(class)) => {
+                if *class == "java/lang/String" {
+                    "jstring"
+                } else if self.is_throwable(class) {
+                    "jthrowable"
+                } else if *class == "java/lang/Class" {
+                    "jclass"
+                } else {
+                    "jobject"
+                }
+            }
+            (1, BaseType::Boolean) => "jbooleanArray",
+            (1, BaseType::Byte) => "jbyteArray",
+            (1, BaseType::Char) => "jcharArray",
+            (1, BaseType::Short) => "jshortArray",
+            (1, BaseType::Integer) => "jintArray",
+            (1, BaseType::Long) => "jlongArray",
+            (1, BaseType::Float) => "jfloatArray",
+            (1, BaseType::Double) => "jdoubleArray",
+            _ => "jobjectArray",
+        }
+    }
+}
+
+fn declarations(class: &Class) -> Declarations {
+    let constants = class
+        .fields
+        .iter()
+        .filter(|field| field.access_flags.contains(AccessFlags::STATIC | AccessFlags::FINAL))
+        .filter_map(|field| {
+            let value = field.attributes.iter().find_map(|attribute| match attribute {
+                Attribute::ConstantValue { value } => c_literal(value),
+                _ => None,
+            })?;
+            Some((field.name.clone(), value))
+        })
+        .collect();
+
+    Declarations {
+        super_class: class.super_class.clone(),
+        constants,
+    }
+}
+
+/// The name of a class as written in Java, in which nested classes are separated by dots.
+fn qualified_name(class: &Class) -> MString {
+    let nesting: HashMap<&MStr, (&MStr, &MStr)> = class
+        .attributes
+        .iter()
+        .filter_map(|attribute| match attribute {
+            Attribute::InnerClasses(inner_classes) => Some(inner_classes),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|inner| {
+            Some((
+                &*inner.inner_class,
+                (inner.outer_class.as_deref()?, inner.inner_name.as_deref()?),
+            ))
+        })
+        .collect();
+
+    let mut names = Vec::new();
+    let mut name: &MStr = &class.name;
+    while let Some(&(outer, simple_name)) = nesting.get(name) {
+        if names.len() > nesting.len() {
+            // the nesting is cyclic
+            break;
+        }
+        names.push(simple_name);
+        name = outer;
+    }
+
+    let mut bytes = name.as_bytes().to_vec();
+    for simple_name in names.into_iter().rev() {
+        bytes.push(b'.');
+        bytes.extend_from_slice(simple_name.as_bytes());
+    }
+    MString::from_mutf8(bytes).unwrap_or_else(|_| class.name.clone())
+}
+
+/// Formats a constant like `javac`, including its quirks. Strings have no C literal.
+fn c_literal(value: &Constant) -> Option<String> {
+    Some(match *value {
+        // booleans, bytes, chars and shorts are stored as integers
+        Constant::Integer(value) => format!("{value}L"),
+        Constant::Long(value) => format!("{value}LL"),
+        Constant::Float(value) if value.is_infinite() => format!("{}Inff", if value < 0.0 { "-" } else { "" }),
+        Constant::Float(value) if value.is_nan() => "NaNf".to_owned(),
+        Constant::Float(value) => format!("{}f", java_decimal(&format!("{value:e}"), &format!("{value:.1e}"))),
+        Constant::Double(value) if value.is_infinite() => format!("{}InfD", if value < 0.0 { "-" } else { "" }),
+        Constant::Double(value) if value.is_nan() => "NaN".to_owned(),
+        Constant::Double(value) => java_decimal(&format!("{value:e}"), &format!("{value:.1e}")),
+        _ => return None,
+    })
+}
+
+/// Converts the shortest scientific representation of a number (e.g. `1.5e-7`)
+/// to the representation of `Double.toString` in Java (e.g. `1.5E-7`).
+///
+/// Java uses at least two digits, so the number rounded to two digits is used instead of a single digit.
+/// Versions of Java before 19 don't always use the shortest representation.
+fn java_decimal(shortest: &str, two_digits: &str) -> String {
+    let scientific = if shortest.contains('.') { shortest } else { two_digits };
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((scientific, "0"));
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let mut digits: String = mantissa.chars().filter(|&ch| ch != '.').collect();
+    while digits.len() > 1 && digits.ends_with('0') {
+        digits.pop();
+    }
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+
+    if digits.bytes().all(|digit| digit == b'0') {
+        return format!("{sign}0.0");
+    }
+    if (-3..7).contains(&exponent) {
+        let point = exponent + 1;
+        if point <= 0 {
+            let zeros = "0".repeat(point.unsigned_abs() as usize);
+            format!("{sign}0.{zeros}{digits}")
+        } else {
+            let point = point as usize;
+            if digits.len() > point {
+                format!("{sign}{}.{}", &digits[..point], &digits[point..])
+            } else {
+                format!("{sign}{digits}{}.0", "0".repeat(point - digits.len()))
+            }
+        }
+    } else {
+        let fraction = if digits.len() > 1 { &digits[1..] } else { "0" };
+        format!("{sign}{}.{fraction}E{exponent}", &digits[..1])
+    }
+}
+
+/// The JNI header of a class.
+///
+/// The header is written by its [`Display`](fmt::Display) implementation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    class: MString,
+    qualified_name: MString,
+    constants: Vec<(MString, String)>,
+    methods: Vec<NativeMethod>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NativeMethod {
+    name: MString,
+    descriptor: MString,
+    is_static: bool,
+    /// Whether there are other native methods with the same name, so the parameters are part of the symbol.
+    overloaded: bool,
+    return_type: &'static str,
+    parameters: Vec<&'static str>,
+}
+
+impl Header {
+    /// The name of the class of the header.
+    #[must_use]
+    pub fn class(&self) -> &MString {
+        &self.class
+    }
+
+    /// The name of the header file, e.g. `com_example_Outer_Inner.h`.
+    #[must_use]
+    pub fn file_name(&self) -> String {
+        let mut name: String = self
+            .class
+            .display()
+            .to_string()
+            .chars()
+            .map(|ch| if ch == '/' || ch == '$' { '_' } else { ch })
+            .collect();
+        name.push_str(".h");
+        name
+    }
+
+    /// The names of the functions which implement the native methods, in the order of the methods.
+    #[must_use]
+    pub fn symbols(&self) -> Vec<String> {
+        self.methods.iter().map(|method| self.symbol(method)).collect()
+    }
+
+    fn symbol(&self, method: &NativeMethod) -> String {
+        let mut symbol = format!(
+            "Java_{}_{}",
+            encode(&self.class, Encoding::Jni),
+            encode(&method.name, Encoding::Jni)
+        );
+        if method.overloaded {
+            let parameters = method.descriptor.as_bytes();
+            let end = parameters.iter().position(|&byte| byte == b')').unwrap_or(1);
+            symbol.push_str("__");
+            symbol.push_str(&encode(&method.descriptor[1..end], Encoding::Jni));
+        }
+        symbol
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let class = encode(&self.qualified_name, Encoding::Class);
+        writeln!(f, "/* DO NOT EDIT THIS FILE - it is machine generated */")?;
+        writeln!(f, "#include <jni.h>")?;
+        writeln!(f, "/* Header for class {class} */")?;
+        writeln!(f)?;
+        writeln!(f, "#ifndef _Included_{class}")?;
+        writeln!(f, "#define _Included_{class}")?;
+        writeln!(f, "#ifdef __cplusplus")?;
+        writeln!(f, "extern \"C\" {{")?;
+        writeln!(f, "#endif")?;
+        for (name, value) in &self.constants {
+            let name = encode(name, Encoding::FieldStub);
+            writeln!(f, "#undef {class}_{name}")?;
+            writeln!(f, "#define {class}_{name} {value}")?;
+        }
+        for method in &self.methods {
+            writeln!(f, "/*")?;
+            writeln!(f, " * Class:     {class}")?;
+            writeln!(f, " * Method:    {}", encode(&method.name, Encoding::FieldStub))?;
+            writeln!(f, " * Signature: {}", method.descriptor.display())?;
+            writeln!(f, " */")?;
+            writeln!(f, "JNIEXPORT {} JNICALL {}", method.return_type, self.symbol(method))?;
+            let this = if method.is_static { "jclass" } else { "jobject" };
+            write!(f, "  (JNIEnv *, {this}")?;
+            for parameter in &method.parameters {
+                write!(f, ", {parameter}")?;
+            }
+            writeln!(f, ");")?;
+            writeln!(f)?;
+        }
+        writeln!(f, "#ifdef __cplusplus")?;
+        writeln!(f, "}}")?;
+        writeln!(f, "#endif")?;
+        writeln!(f, "#endif")
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Encoding {
+    /// The name of a class in the names of macros.
+    Class,
+    /// The name of a field or method in the names of macros and comments.
+    FieldStub,
+    /// A part of the symbol of a native method, as specified by JNI.
+    Jni,
+}
+
+/// Escapes a name so it only consists of characters valid in C identifiers.
+fn encode(name: &MStr, encoding: Encoding) -> String {
+    let mut result = String::with_capacity(name.len());
+    for ch in name.chars() {
+        let replacement = match (encoding, ch) {
+            (_, Ok(ch)) if ch.is_ascii_alphanumeric() => {
+                result.push(ch);
+                continue;
+            }
+            (Encoding::Class, Ok('/' | '.' | '_')) | (Encoding::FieldStub, Ok('_')) => "_",
+            (Encoding::Class, Ok('$')) => "__",
+            (Encoding::Jni, Ok('/' | '.')) => "_",
+            (Encoding::Jni, Ok('_')) => "_1",
+            (Encoding::Jni, Ok(';')) => "_2",
+            (Encoding::Jni, Ok('[')) => "_3",
+            (_, Ok(ch)) => {
+                for unit in ch.encode_utf16(&mut [0; 2]) {
+                    let _ = write!(result, "_{unit:05x}");
+                }
+                continue;
+            }
+            (_, Err(surrogate)) => {
+                let _ = write!(result, "_{surrogate:05x}");
+                continue;
+            }
+        };
+        result.push_str(replacement);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constants_like_javac() {
+        assert_eq!(c_literal(&Constant::Integer(-5)).unwrap(), "-5L");
+        assert_eq!(c_literal(&Constant::Long(1 << 40)).unwrap(), "1099511627776LL");
+        assert_eq!(c_literal(&Constant::Float(0.1)).unwrap(), "0.1f");
+        assert_eq!(c_literal(&Constant::Float(3e38)).unwrap(), "3.0E38f");
+        assert_eq!(c_literal(&Constant::Float(1.4e-45)).unwrap(), "1.4E-45f");
+        assert_eq!(c_literal(&Constant::Float(f32::NEG_INFINITY)).unwrap(), "-Inff");
+        assert_eq!(c_literal(&Constant::Double(100.0)).unwrap(), "100.0");
+        assert_eq!(c_literal(&Constant::Double(1e7)).unwrap(), "1.0E7");
+        assert_eq!(c_literal(&Constant::Double(-0.001)).unwrap(), "-0.001");
+        assert_eq!(c_literal(&Constant::Double(f64::NAN)).unwrap(), "NaN");
+        assert_eq!(c_literal(&Constant::String("text".into())), None);
+    }
+
+    #[test]
+    fn mangled_names() {
+        assert_eq!(encode(&MString::from("p_q/Ä$B"), Encoding::Jni), "p_1q__000c4_00024B");
+        assert_eq!(encode(&MString::from("p_q/Ä$B"), Encoding::Class), "p_q__000c4__B");
+        assert_eq!(encode(&MString::from("[I;"), Encoding::Jni), "_3I_2");
+    }
+}
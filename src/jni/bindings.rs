@@ -0,0 +1,806 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+
+use crate::descriptor::{BaseType, MethodDescriptor, TypeDescriptor};
+use crate::error::*;
+use crate::header::AccessFlags;
+use crate::mutf8::{MStr, MString};
+use crate::reader;
+use crate::tree::{Attribute, Class, Method};
+
+/// Rust bindings of Java classes for the [`jni`](https://docs.rs/jni/0.21) crate.
+///
+/// Each class becomes a wrapper type around a `JObject` with functions for the public constructors, methods and
+/// fields of the class. Parameters and return values of the types of other added classes use their wrapper types.
+/// Overloaded methods are distinguished by appending their parameter types to their names, e.g. `value_of_int`.
+///
+/// The source code is written by the [`Display`](fmt::Display) implementation.
+///
+/// ```
+/// use noak::jni::Bindings;
+/// use noak::reader;
+/// use noak::tree::{Class, Method};
+/// use noak::AccessFlags;
+///
+/// let method = |name: &str, descriptor: &str| Method {
+///     access_flags: AccessFlags::PUBLIC | AccessFlags::ABSTRACT,
+///     name: name.into(),
+///     descriptor: descriptor.into(),
+///     attributes: Vec::new(),
+/// };
+/// let class = Class {
+///     access_flags: AccessFlags::PUBLIC | AccessFlags::INTERFACE | AccessFlags::ABSTRACT,
+///     methods: vec![method("addAll", "(I)V"), method("addAll", "([I)V"), method("next", "()Lcom/example/Counter;")],
+///     ..Class::new("com/example/Counter")
+/// };
+/// let bytes = class.to_bytes()?;
+/// let mut bindings = Bindings::new();
+/// bindings.add_class(&reader::Class::new(&bytes)?)?;
+/// let source = bindings.to_string();
+///
+/// assert!(source.contains("pub struct Counter<'local>(pub jni::objects::JObject<'local>);"));
+/// assert!(source.contains(
+///     "pub fn add_all_int(&self, env: &mut jni::JNIEnv<'local>, arg0: jni::sys::jint) -> jni::errors::Result<()> {"
+/// ));
+/// assert!(source.contains("pub fn add_all_int_array("));
+/// assert!(source.contains(
+///     r#"env.call_method(&self.0, "next", "()Lcom/example/Counter;", &[])?.l().map(Counter)"#
+/// ));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Bindings {
+    classes: Vec<BoundClass>,
+}
+
+#[derive(Debug, Clone)]
+struct BoundClass {
+    name: MString,
+    members: Vec<Member>,
+}
+
+#[derive(Debug, Clone)]
+struct Member {
+    kind: MemberKind,
+    name: MString,
+    descriptor: MString,
+    is_static: bool,
+    /// The names of the parameters of methods, if they are known.
+    parameter_names: Vec<Option<MString>>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum MemberKind {
+    Constructor,
+    Method,
+    Field { is_final: bool },
+}
+
+impl Bindings {
+    #[must_use]
+    pub fn new() -> Bindings {
+        Bindings::default()
+    }
+
+    /// Adds a class, for which a wrapper type is generated. A class added before with the same name is replaced.
+    pub fn add_class(&mut self, class: &reader::Class<'_>) -> Result<&mut Self, DecodeError> {
+        let class = Class::from_reader(class)?;
+        // abstract classes can't be instantiated
+        let instantiable = !class
+            .access_flags
+            .intersects(AccessFlags::ABSTRACT | AccessFlags::INTERFACE);
+
+        let mut members = Vec::new();
+        for method in &class.methods {
+            if !method.access_flags.contains(AccessFlags::PUBLIC)
+                || method
+                    .access_flags
+                    .intersects(AccessFlags::SYNTHETIC | AccessFlags::BRIDGE)
+                || *method.name == *"<clinit>"
+                || (*method.name == *"<init>" && !instantiable)
+            {
+                continue;
+            }
+            let descriptor = MethodDescriptor::parse(&method.descriptor)?;
+            members.push(Member {
+                kind: if *method.name == *"<init>" {
+                    MemberKind::Constructor
+                } else {
+                    MemberKind::Method
+                },
+                name: method.name.clone(),
+                descriptor: method.descriptor.clone(),
+                is_static: method.access_flags.contains(AccessFlags::STATIC),
+                parameter_names: parameter_names(method, &descriptor),
+            });
+        }
+        for field in &class.fields {
+            if !field.access_flags.contains(AccessFlags::PUBLIC) || field.access_flags.contains(AccessFlags::SYNTHETIC)
+            {
+                continue;
+            }
+            TypeDescriptor::parse(&field.descriptor)?;
+            members.push(Member {
+                kind: MemberKind::Field {
+                    is_final: field.access_flags.contains(AccessFlags::FINAL),
+                },
+                name: field.name.clone(),
+                descriptor: field.descriptor.clone(),
+                is_static: field.access_flags.contains(AccessFlags::STATIC),
+                parameter_names: Vec::new(),
+            });
+        }
+
+        let bound = BoundClass {
+            name: class.name,
+            members,
+        };
+        match self.classes.iter_mut().find(|existing| existing.name == bound.name) {
+            Some(existing) => *existing = bound,
+            None => self.classes.push(bound),
+        }
+        Ok(self)
+    }
+}
+
+/// The names of the parameters from the `MethodParameters` attribute or the local variables of the code.
+fn parameter_names(method: &Method, descriptor: &MethodDescriptor<'_>) -> Vec<Option<MString>> {
+    let count = descriptor.parameters().count();
+    for attribute in &method.attributes {
+        if let Attribute::MethodParameters(parameters) = attribute {
+            if parameters.len() == count {
+                return parameters.iter().map(|parameter| parameter.name.clone()).collect();
+            }
+        }
+    }
+
+    let locals: Vec<_> = method
+        .attributes
+        .iter()
+        .filter_map(|attribute| match attribute {
+            Attribute::Code(code) => Some(&code.attributes),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|attribute| match attribute {
+            Attribute::LocalVariableTable(locals) => Some(locals),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    let mut index = u16::from(!method.access_flags.contains(AccessFlags::STATIC));
+    let mut names = Vec::with_capacity(count);
+    for parameter in descriptor.parameters() {
+        let parameter_descriptor = parameter.to_string();
+        names.push(
+            locals
+                .iter()
+                .find(|local| local.index == index && *local.descriptor == *parameter_descriptor)
+                .map(|local| local.name.clone()),
+        );
+        let wide = parameter.dimensions == 0 && matches!(parameter.base, BaseType::Long | BaseType::Double);
+        index = index.saturating_add(if wide { 2 } else { 1 });
+    }
+    names
+}
+
+impl fmt::Display for Bindings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut used = HashSet::new();
+        let mut type_names = HashMap::new();
+        for class in &self.classes {
+            let type_name = unique(&mut used, type_name(&class.name));
+            type_names.entry(&*class.name).or_insert(type_name);
+        }
+
+        for (index, class) in self.classes.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            Generator {
+                type_names: &type_names,
+                class,
+            }
+            .write(f)?;
+        }
+        Ok(())
+    }
+}
+
+struct Generator<'a> {
+    /// The wrapper types of the classes by their names.
+    type_names: &'a HashMap<&'a MStr, String>,
+    class: &'a BoundClass,
+}
+
+impl Generator<'_> {
+    fn write(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = &self.type_names[&*self.class.name];
+        writeln!(f, "/// `{}`", java_name(&self.class.name))?;
+        writeln!(f, "#[derive(Debug)]")?;
+        writeln!(f, "#[repr(transparent)]")?;
+        writeln!(f, "pub struct {name}<'local>(pub jni::objects::JObject<'local>);")?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "impl<'local> ::core::convert::From<jni::objects::JObject<'local>> for {name}<'local> {{"
+        )?;
+        writeln!(f, "    fn from(object: jni::objects::JObject<'local>) -> Self {{")?;
+        writeln!(f, "        {name}(object)")?;
+        writeln!(f, "    }}")?;
+        writeln!(f, "}}")?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "impl<'local> ::core::convert::AsRef<jni::objects::JObject<'local>> for {name}<'local> {{"
+        )?;
+        writeln!(f, "    fn as_ref(&self) -> &jni::objects::JObject<'local> {{")?;
+        writeln!(f, "        &self.0")?;
+        writeln!(f, "    }}")?;
+        writeln!(f, "}}")?;
+        writeln!(f)?;
+        writeln!(f, "impl<'local> {name}<'local> {{")?;
+        writeln!(f, "    /// The internal name of the class.")?;
+        writeln!(
+            f,
+            "    pub const CLASS: &'static str = \"{}\";",
+            escape(&self.class.name.display().to_string())
+        )?;
+
+        let mut used = HashSet::new();
+        let methods: Vec<_> = self
+            .class
+            .members
+            .iter()
+            .filter(|member| !matches!(member.kind, MemberKind::Field { .. }))
+            .filter_map(|member| Some((member, MethodDescriptor::parse(&member.descriptor).ok()?)))
+            .collect();
+        let base_names: Vec<String> = methods
+            .iter()
+            .map(|(member, _)| match member.kind {
+                MemberKind::Constructor => "new".to_owned(),
+                _ => snake_case(&member.name),
+            })
+            .collect();
+        for ((member, descriptor), base_name) in methods.iter().zip(&base_names) {
+            let overloaded = base_names.iter().filter(|name| *name == base_name).count() > 1;
+            let mut name = base_name.clone();
+            if overloaded {
+                for parameter in descriptor.parameters() {
+                    name.push('_');
+                    name.push_str(&type_word(&parameter));
+                }
+            }
+            let name = unique(&mut used, name);
+            writeln!(f)?;
+            self.write_method(f, member, descriptor, &name)?;
+        }
+
+        for member in &self.class.members {
+            let MemberKind::Field { is_final } = member.kind else {
+                continue;
+            };
+            let Ok(descriptor) = TypeDescriptor::parse(&member.descriptor) else {
+                continue;
+            };
+            let base_name = snake_case(&member.name);
+            let getter = unique(&mut used, format!("get_{base_name}"));
+            writeln!(f)?;
+            self.write_getter(f, member, &descriptor, &getter)?;
+            if !is_final {
+                let setter = unique(&mut used, format!("set_{base_name}"));
+                writeln!(f)?;
+                self.write_setter(f, member, &descriptor, &setter)?;
+            }
+        }
+
+        writeln!(f, "}}")
+    }
+
+    fn write_method(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        member: &Member,
+        descriptor: &MethodDescriptor<'_>,
+        name: &str,
+    ) -> fmt::Result {
+        let parameters: Vec<_> = descriptor.parameters().collect();
+        let java_parameters: Vec<_> = parameters.iter().map(java_type).collect();
+        let return_type = descriptor.return_type();
+        match member.kind {
+            MemberKind::Constructor => writeln!(
+                f,
+                "    /// `{}({})`",
+                simple_name(&self.class.name),
+                java_parameters.join(", ")
+            )?,
+            _ => writeln!(
+                f,
+                "    /// `{}{} {}({})`",
+                if member.is_static { "static " } else { "" },
+                return_type.as_ref().map_or_else(|| "void".to_owned(), java_type),
+                member.name.display(),
+                java_parameters.join(", ")
+            )?,
+        }
+
+        // the names of the parameters must be distinct from each other and from `env`
+        let mut used = HashSet::from(["env".to_owned()]);
+        let mut names = Vec::with_capacity(parameters.len());
+        for (index, parameter_name) in member.parameter_names.iter().enumerate() {
+            let name = parameter_name.as_deref().map(snake_case);
+            names.push(match name {
+                Some(name) if used.insert(name.clone()) => name,
+                _ => unique(&mut used, format!("arg{index}")),
+            });
+        }
+        for index in names.len()..parameters.len() {
+            names.push(unique(&mut used, format!("arg{index}")));
+        }
+
+        write!(f, "    pub fn {}(", identifier(name))?;
+        if member.kind == MemberKind::Method && !member.is_static {
+            write!(f, "&self, ")?;
+        }
+        write!(f, "env: &mut jni::JNIEnv<'local>")?;
+        for (name, parameter) in names.iter().zip(&parameters) {
+            write!(f, ", {}: {}", identifier(name), self.parameter_type(parameter))?;
+        }
+        let result = match member.kind {
+            MemberKind::Constructor => "Self".to_owned(),
+            _ => self.return_type(return_type.as_ref()),
+        };
+        writeln!(f, ") -> jni::errors::Result<{result}> {{")?;
+
+        let arguments: Vec<_> = names
+            .iter()
+            .zip(&parameters)
+            .map(|(name, parameter)| self.value(&identifier(name), parameter))
+            .collect();
+        let arguments = arguments.join(", ");
+        let signature = escape(&member.descriptor.display().to_string());
+        let method_name = escape(&member.name.display().to_string());
+        match member.kind {
+            MemberKind::Constructor => writeln!(
+                f,
+                "        env.new_object(Self::CLASS, \"{signature}\", &[{arguments}]).map(Self)"
+            )?,
+            _ if member.is_static => writeln!(
+                f,
+                "        env.call_static_method(Self::CLASS, \"{method_name}\", \"{signature}\", &[{arguments}])?{}",
+                self.conversion(return_type.as_ref())
+            )?,
+            _ => writeln!(
+                f,
+                "        env.call_method(&self.0, \"{method_name}\", \"{signature}\", &[{arguments}])?{}",
+                self.conversion(return_type.as_ref())
+            )?,
+        }
+        writeln!(f, "    }}")
+    }
+
+    fn write_getter(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        member: &Member,
+        descriptor: &TypeDescriptor<'_>,
+        name: &str,
+    ) -> fmt::Result {
+        let field_name = escape(&member.name.display().to_string());
+        let signature = escape(&member.descriptor.display().to_string());
+        writeln!(f, "    /// Gets the value of `{}`.", member.name.display())?;
+        if member.is_static {
+            writeln!(
+                f,
+                "    pub fn {}(env: &mut jni::JNIEnv<'local>) -> jni::errors::Result<{}> {{",
+                identifier(name),
+                self.return_type(Some(descriptor))
+            )?;
+            writeln!(
+                f,
+                "        env.get_static_field(Self::CLASS, \"{field_name}\", \"{signature}\")?{}",
+                self.conversion(Some(descriptor))
+            )?;
+        } else {
+            writeln!(
+                f,
+                "    pub fn {}(&self, env: &mut jni::JNIEnv<'local>) -> jni::errors::Result<{}> {{",
+                identifier(name),
+                self.return_type(Some(descriptor))
+            )?;
+            writeln!(
+                f,
+                "        env.get_field(&self.0, \"{field_name}\", \"{signature}\")?{}",
+                self.conversion(Some(descriptor))
+            )?;
+        }
+        writeln!(f, "    }}")
+    }
+
+    fn write_setter(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        member: &Member,
+        descriptor: &TypeDescriptor<'_>,
+        name: &str,
+    ) -> fmt::Result {
+        let field_name = escape(&member.name.display().to_string());
+        let signature = escape(&member.descriptor.display().to_string());
+        writeln!(f, "    /// Sets the value of `{}`.", member.name.display())?;
+        if member.is_static {
+            writeln!(
+                f,
+                "    pub fn {}(env: &mut jni::JNIEnv<'local>, value: {}) -> jni::errors::Result<()> {{",
+                identifier(name),
+                self.parameter_type(descriptor)
+            )?;
+            writeln!(
+                f,
+                "        env.set_static_field(Self::CLASS, (Self::CLASS, \"{field_name}\", \"{signature}\"), {})",
+                self.value("value", descriptor)
+            )?;
+        } else {
+            writeln!(
+                f,
+                "    pub fn {}(&self, env: &mut jni::JNIEnv<'local>, value: {}) -> jni::errors::Result<()> {{",
+                identifier(name),
+                self.parameter_type(descriptor)
+            )?;
+            writeln!(
+                f,
+                "        env.set_field(&self.0, \"{field_name}\", \"{signature}\", {})",
+                self.value("value", descriptor)
+            )?;
+        }
+        writeln!(f, "    }}")
+    }
+
+    /// The wrapper type of a class, if it was added.
+    fn wrapper(&self, descriptor: &TypeDescriptor<'_>) -> Option<&str> {
+        match descriptor.base {
+            BaseType::Object(class) if descriptor.dimensions == 0 => self.type_names.get(class).map(String::as_str),
+            _ => None,
+        }
+    }
+
+    fn parameter_type(&self, descriptor: &TypeDescriptor<'_>) -> String {
+        if let Some(wrapper) = self.wrapper(descriptor) {
+            return format!("&{wrapper}<'_>");
+        }
+        match (descriptor.dimensions, &descriptor.base) {
+            (0, BaseType::Boolean) => "bool".to_owned(),
+            (0, BaseType::Byte) => "jni::sys::jbyte".to_owned(),
+            (0, BaseType::Char) => "jni::sys::jchar".to_owned(),
+            (0, BaseType::Short) => "jni::sys::jshort".to_owned(),
+            (0, BaseType::Integer) => "jni::sys::jint".to_owned(),
+            (0, BaseType::Long) => "jni::sys::jlong".to_owned(),
+            (0, BaseType::Float) => "jni::sys::jfloat".to_owned(),
+            (0, BaseType::Double) => "jni::sys::jdouble".to_owned(),
+            _ => "&jni::objects::JObject<'_>".to_owned(),
+        }
+    }
+
+    /// The `JValue` of a parameter.
+    fn value(&self, name: &str, descriptor: &TypeDescriptor<'_>) -> String {
+        if self.wrapper(descriptor).is_some() {
+            format!("jni::objects::JValue::Object(&{name}.0)")
+        } else if descriptor.dimensions > 0 || matches!(descriptor.base, BaseType::Object(_)) {
+            format!("jni::objects::JValue::Object({name})")
+        } else {
+            format!("jni::objects::JValue::from({name})")
+        }
+    }
+
+    fn return_type(&self, descriptor: Option<&TypeDescriptor<'_>>) -> String {
+        let Some(descriptor) = descriptor else {
+            return "()".to_owned();
+        };
+        if let Some(wrapper) = self.wrapper(descriptor) {
+            return format!("{wrapper}<'local>");
+        }
+        match (descriptor.dimensions, &descriptor.base) {
+            (0, BaseType::Object(_)) | (1.., _) => "jni::objects::JObject<'local>".to_owned(),
+            _ => self.parameter_type(descriptor),
+        }
+    }
+
+    /// Converts a `JValueOwned` to the return type.
+    fn conversion(&self, descriptor: Option<&TypeDescriptor<'_>>) -> String {
+        let Some(descriptor) = descriptor else {
+            return ".v()".to_owned();
+        };
+        if let Some(wrapper) = self.wrapper(descriptor) {
+            return format!(".l().map({wrapper})");
+        }
+        let accessor = match (descriptor.dimensions, &descriptor.base) {
+            (0, BaseType::Boolean) => "z",
+            (0, BaseType::Byte) => "b",
+            (0, BaseType::Char) => "c",
+            (0, BaseType::Short) => "s",
+            (0, BaseType::Integer) => "i",
+            (0, BaseType::Long) => "j",
+            (0, BaseType::Float) => "f",
+            (0, BaseType::Double) => "d",
+            _ => "l",
+        };
+        format!(".{accessor}()")
+    }
+}
+
+/// Appends a number to a name if it is already used.
+fn unique(used: &mut HashSet<String>, name: String) -> String {
+    if used.insert(name.clone()) {
+        return name;
+    }
+    (2..)
+        .map(|number| format!("{name}{number}"))
+        .find(|name| used.insert(name.clone()))
+        .unwrap_or(name)
+}
+
+/// The name of the wrapper type of a class, e.g. `MapEntry` for `java/util/Map$Entry`.
+fn type_name(class: &MStr) -> String {
+    let mut name = String::new();
+    for part in simple_name(class).split('$').filter(|part| !part.is_empty()) {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            push_identifier_char(&mut name, first.to_ascii_uppercase());
+        }
+        for ch in chars {
+            push_identifier_char(&mut name, ch);
+        }
+    }
+    if name.is_empty() || name.starts_with(|ch: char| ch.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    identifier(&name)
+}
+
+/// Converts a name from camel case to snake case, e.g. `getURLPath` to `get_url_path`.
+fn snake_case(name: &MStr) -> String {
+    let chars: Vec<char> = name.chars_lossy().collect();
+    let mut result = String::with_capacity(chars.len());
+    for (index, &ch) in chars.iter().enumerate() {
+        if ch.is_ascii_uppercase() {
+            let previous = index.checked_sub(1).map(|index| chars[index]);
+            let next = chars.get(index + 1);
+            let boundary = previous.is_some_and(|previous| {
+                previous.is_ascii_lowercase()
+                    || previous.is_ascii_digit()
+                    || (previous.is_ascii_uppercase() && next.is_some_and(char::is_ascii_lowercase))
+            });
+            if boundary {
+                result.push('_');
+            }
+            result.push(ch.to_ascii_lowercase());
+        } else {
+            push_identifier_char(&mut result, ch);
+        }
+    }
+    if result.is_empty() || result.starts_with(|ch: char| ch.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    result
+}
+
+fn push_identifier_char(identifier: &mut String, ch: char) {
+    if ch.is_ascii_alphanumeric() || ch == '_' {
+        identifier.push(ch);
+    } else if ch == '$' {
+        identifier.push('_');
+    } else {
+        let _ = write!(identifier, "_u{:04x}", u32::from(ch));
+    }
+}
+
+/// Escapes names which are keywords in Rust.
+fn identifier(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn", "else", "enum",
+        "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+        "move", "mut", "override", "priv", "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type",
+        "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+    ];
+
+    match name {
+        // these can't be raw identifiers
+        "_" | "crate" | "self" | "Self" | "super" => format!("{name}_"),
+        _ if KEYWORDS.contains(&name) => format!("r#{name}"),
+        _ => name.to_owned(),
+    }
+}
+
+/// Escapes a string for a Rust string literal.
+fn escape(s: &str) -> String {
+    s.escape_default().to_string()
+}
+
+/// The name of a class without its package.
+fn simple_name(class: &MStr) -> String {
+    let name = class.display().to_string();
+    match name.rsplit_once('/') {
+        Some((_, simple_name)) => simple_name.to_owned(),
+        None => name,
+    }
+}
+
+/// The name of a class as written in Java, e.g. `java.util.Map$Entry`.
+fn java_name(class: &MStr) -> String {
+    class.display().to_string().replace('/', ".")
+}
+
+fn java_type(descriptor: &TypeDescriptor<'_>) -> String {
+    let mut name = match descriptor.base {
+        BaseType::Boolean => "boolean".to_owned(),
+        BaseType::Byte => "byte".to_owned(),
+        BaseType::Char => "char".to_owned(),
+        BaseType::Short => "short".to_owned(),
+        BaseType::Integer => "int".to_owned(),
+        BaseType::Long => "long".to_owned(),
+        BaseType::Float => "float".to_owned(),
+        BaseType::Double => "double".to_owned(),
+        BaseType::Object(class) => java_name(class),
+    };
+    for _ in 0..descriptor.dimensions {
+        name.push_str("[]");
+    }
+    name
+}
+
+/// The part of the names of overloaded methods for a parameter, e.g. `string_array` for `String[]`.
+fn type_word(descriptor: &TypeDescriptor<'_>) -> String {
+    let mut word = match descriptor.base {
+        BaseType::Object(class) => {
+            let simple_name = simple_name(class);
+            let simple_name = simple_name.rsplit('$').next().unwrap_or_default();
+            snake_case(&MString::from(simple_name))
+        }
+        _ => java_type(&TypeDescriptor {
+            dimensions: 0,
+            base: descriptor.base.clone(),
+        }),
+    };
+    for _ in 0..descriptor.dimensions {
+        word.push_str("_array");
+    }
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{Field, MethodParameter};
+
+    fn class(name: &str, access_flags: AccessFlags, fields: Vec<Field>, methods: Vec<Method>) -> Vec<u8> {
+        Class {
+            access_flags,
+            fields,
+            methods,
+            ..Class::new(name)
+        }
+        .to_bytes()
+        .unwrap()
+    }
+
+    fn method(access_flags: AccessFlags, name: &str, descriptor: &str, parameters: &[&str]) -> Method {
+        let parameters = parameters
+            .iter()
+            .map(|name| MethodParameter {
+                name: Some((*name).into()),
+                access_flags: AccessFlags::empty(),
+            })
+            .collect();
+        Method {
+            access_flags: access_flags | AccessFlags::NATIVE,
+            name: name.into(),
+            descriptor: descriptor.into(),
+            attributes: vec![Attribute::MethodParameters(parameters)],
+        }
+    }
+
+    fn field(access_flags: AccessFlags, name: &str, descriptor: &str) -> Field {
+        Field {
+            access_flags,
+            name: name.into(),
+            descriptor: descriptor.into(),
+            attributes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn generates_valid_rust() {
+        let public = AccessFlags::PUBLIC;
+        let static_ = AccessFlags::PUBLIC | AccessFlags::STATIC;
+        let node = class(
+            "com/example/Tree$Node",
+            public | AccessFlags::SUPER,
+            vec![
+                field(public | AccessFlags::FINAL, "type", "I"),
+                field(static_, "count", "J"),
+                field(static_ | AccessFlags::FINAL, "ROOT", "Lcom/example/Tree$Node;"),
+                field(public, "children", "[[Lcom/example/Tree$Node;"),
+                field(AccessFlags::PRIVATE, "hidden", "Z"),
+            ],
+            vec![
+                method(public, "<init>", "(ILjava/lang/String;)V", &["type", "self"]),
+                method(public, "<init>", "()V", &[]),
+                method(public, "get", "(I)Lcom/example/Tree$Node;", &["index"]),
+                method(public, "get", "(Ljava/lang/String;)Lcom/example/Tree$Node;", &["name"]),
+                method(public, "get", "([J)[Lcom/example/Tree$Node;", &["path"]),
+                method(static_, "sum", "(BSCZFD)D", &["b", "s", "c", "z", "f", "d"]),
+                method(public, "parent", "()Lcom/example/Tree;", &[]),
+                method(public, "match", "(Ljava/lang/Object;)V", &["fn"]),
+            ],
+        );
+        let tree = class(
+            "com/example/Tree",
+            public | AccessFlags::INTERFACE | AccessFlags::ABSTRACT,
+            Vec::new(),
+            vec![method(
+                public | AccessFlags::ABSTRACT,
+                "root",
+                "()Lcom/example/Tree$Node;",
+                &[],
+            )],
+        );
+
+        let mut bindings = Bindings::new();
+        bindings.add_class(&reader::Class::new(&node).unwrap()).unwrap();
+        bindings.add_class(&reader::Class::new(&tree).unwrap()).unwrap();
+        let source = bindings.to_string();
+
+        let file = syn::parse_file(&source).unwrap_or_else(|err| panic!("{err} in:\n{source}"));
+        let structs: Vec<_> = file
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Struct(item) => Some(item.ident.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(structs, ["TreeNode", "Tree"]);
+
+        let functions: HashSet<_> = file
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Impl(item) if item.trait_.is_none() => Some(&item.items),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|item| match item {
+                syn::ImplItem::Fn(function) => Some(function.sig.ident.to_string()),
+                _ => None,
+            })
+            .collect();
+        for name in [
+            "get_int",
+            "get_string",
+            "get_long_array",
+            "sum",
+            "parent",
+            "r#match",
+            "root",
+        ] {
+            assert!(functions.contains(name), "`{name}` is missing in:\n{source}");
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(snake_case(&MString::from("getURLPath")), "get_url_path");
+        assert_eq!(snake_case(&MString::from("MAX_VALUE")), "max_value");
+        assert_eq!(snake_case(&MString::from("utf8Bytes")), "utf8_bytes");
+        assert_eq!(snake_case(&MString::from("access$000")), "access_000");
+        assert_eq!(type_name(&MString::from("java/util/Map$Entry")), "MapEntry");
+        assert_eq!(type_name(&MString::from("Outer$1")), "Outer1");
+        assert_eq!(identifier("type"), "r#type");
+        assert_eq!(identifier("self"), "self_");
+        assert_eq!(identifier("value"), "value");
+
+        let mut used = HashSet::new();
+        assert_eq!(unique(&mut used, "get".to_owned()), "get");
+        assert_eq!(unique(&mut used, "get".to_owned()), "get2");
+        assert_eq!(unique(&mut used, "get".to_owned()), "get3");
+    }
+}
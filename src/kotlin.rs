@@ -0,0 +1,1573 @@
+//! Decoding of the metadata of classes compiled by Kotlin.
+//!
+//! The Kotlin compiler stores the declarations of a class as they were written in Kotlin, including nullability,
+//! properties, extension receivers and modifiers like `suspend` and `inline`, in the `kotlin.Metadata` annotation.
+//! The declarations are encoded as protocol buffers in [`data1`](Metadata::data1),
+//! whose strings are stored in [`data2`](Metadata::data2).
+//!
+//! Class names are written like in Kotlin metadata: packages are separated by slashes and nested classes by dots,
+//! e.g. `kotlin/collections/Map.Entry`. The names of local classes start with a dot.
+//!
+//! ```
+//! use noak::kotlin::{Classifier, Declarations, Metadata};
+//!
+//! // the declaration `fun greet(name: String?): String` of the file facade `GreetKt`
+//! let metadata = Metadata {
+//!     kind: Metadata::FILE_FACADE,
+//!     metadata_version: vec![1, 8, 0],
+//!     data1: vec!["\0\0\x1a\x10\x10\0\x1a\x020\x012\x08\x10\x02\x1a\x04\x18\x010\x01".to_owned()],
+//!     data2: vec!["greet".to_owned(), "kotlin/String".to_owned(), "name".to_owned()],
+//!     extra_string: None,
+//!     package_name: None,
+//!     extra_int: 0,
+//! };
+//! let Declarations::FileFacade(package) = metadata.decode()? else {
+//!     panic!("not a file facade");
+//! };
+//! let function = &package.functions[0];
+//! assert_eq!(function.name, "greet");
+//! assert_eq!(function.return_type.classifier, Classifier::Class("kotlin/String".to_owned()));
+//! assert!(!function.return_type.nullable);
+//! assert_eq!(function.value_parameters[0].name, "name");
+//! assert!(function.value_parameters[0].type_.nullable);
+//! assert_eq!(function.signature.as_ref().unwrap().descriptor, "(Ljava/lang/String;)Ljava/lang/String;");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::HashSet;
+
+use crate::error::*;
+use crate::mutf8::MStr;
+use crate::reader;
+use crate::tree::{Class as ClassFile, ElementValue};
+
+/// Returns the metadata of a class, if it was compiled by Kotlin.
+pub fn metadata(class: &reader::Class<'_>) -> Result<Option<Metadata>, DecodeError> {
+    Ok(metadata_tree(&ClassFile::from_reader(class)?))
+}
+
+/// Returns the metadata of a resolved class, if it was compiled by Kotlin.
+#[must_use]
+pub fn metadata_tree(class: &ClassFile) -> Option<Metadata> {
+    let annotation = class.annotations().get("Lkotlin/Metadata;")?;
+
+    let mut metadata = Metadata {
+        kind: Metadata::CLASS,
+        metadata_version: Vec::new(),
+        data1: Vec::new(),
+        data2: Vec::new(),
+        extra_string: None,
+        package_name: None,
+        extra_int: 0,
+    };
+    for pair in &annotation.pairs {
+        match (&*pair.name, &pair.value) {
+            (name, ElementValue::Int(kind)) if name == "k" => metadata.kind = *kind,
+            (name, ElementValue::Array(version)) if name == "mv" => {
+                metadata.metadata_version = version
+                    .iter()
+                    .filter_map(|value| match value {
+                        ElementValue::Int(value) => Some(*value),
+                        _ => None,
+                    })
+                    .collect();
+            }
+            (name, ElementValue::Array(strings)) if name == "d1" => metadata.data1 = strings_of(strings),
+            (name, ElementValue::Array(strings)) if name == "d2" => metadata.data2 = strings_of(strings),
+            (name, ElementValue::String(string)) if name == "xs" => metadata.extra_string = Some(lossy(string)),
+            (name, ElementValue::String(string)) if name == "pn" => metadata.package_name = Some(lossy(string)),
+            (name, ElementValue::Int(flags)) if name == "xi" => metadata.extra_int = *flags,
+            _ => {}
+        }
+    }
+    Some(metadata)
+}
+
+fn strings_of(values: &[ElementValue]) -> Vec<String> {
+    values
+        .iter()
+        .filter_map(|value| match value {
+            ElementValue::String(string) => Some(lossy(string)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn lossy(string: &MStr) -> String {
+    string.chars_lossy().collect()
+}
+
+/// The values of the `kotlin.Metadata` annotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    /// The kind of the class, `k`.
+    pub kind: i32,
+    /// The version of the metadata format, `mv`.
+    pub metadata_version: Vec<i32>,
+    /// The encoded declarations, `d1`.
+    pub data1: Vec<String>,
+    /// The strings used by the declarations, `d2`.
+    pub data2: Vec<String>,
+    /// The name of the facade of a multi-file class part, `xs`.
+    pub extra_string: Option<String>,
+    /// The Kotlin package if it differs from the package of the class, `pn`.
+    pub package_name: Option<String>,
+    /// Flags, `xi`.
+    pub extra_int: i32,
+}
+
+impl Metadata {
+    pub const CLASS: i32 = 1;
+    pub const FILE_FACADE: i32 = 2;
+    pub const SYNTHETIC_CLASS: i32 = 3;
+    pub const MULTI_FILE_CLASS_FACADE: i32 = 4;
+    pub const MULTI_FILE_CLASS_PART: i32 = 5;
+
+    /// Decodes the declarations.
+    pub fn decode(&self) -> Result<Declarations, DecodeError> {
+        match self.kind {
+            Metadata::CLASS => {
+                let bytes = decode_bytes(&self.data1)?;
+                let (strings, message) = Strings::read(&bytes, &self.data2)?;
+                Ok(Declarations::Class(Context::new(&strings).class(message)?))
+            }
+            Metadata::FILE_FACADE => {
+                let bytes = decode_bytes(&self.data1)?;
+                let (strings, message) = Strings::read(&bytes, &self.data2)?;
+                Ok(Declarations::FileFacade(Context::new(&strings).package(message)?))
+            }
+            Metadata::SYNTHETIC_CLASS if self.data1.is_empty() => Ok(Declarations::SyntheticClass(None)),
+            Metadata::SYNTHETIC_CLASS => {
+                let bytes = decode_bytes(&self.data1)?;
+                let (strings, message) = Strings::read(&bytes, &self.data2)?;
+                let function = Context::new(&strings).function(message)?;
+                Ok(Declarations::SyntheticClass(Some(function)))
+            }
+            // the names of the parts are stored directly
+            Metadata::MULTI_FILE_CLASS_FACADE => Ok(Declarations::MultiFileClassFacade(self.data1.clone())),
+            Metadata::MULTI_FILE_CLASS_PART => {
+                let bytes = decode_bytes(&self.data1)?;
+                let (strings, message) = Strings::read(&bytes, &self.data2)?;
+                Ok(Declarations::MultiFileClassPart {
+                    package: Context::new(&strings).package(message)?,
+                    facade: self.extra_string.clone().unwrap_or_default(),
+                })
+            }
+            _ => Ok(Declarations::Unknown),
+        }
+    }
+}
+
+/// The declarations of a class compiled by Kotlin, depending on the kind of the class.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Declarations {
+    /// A class, interface, object or annotation.
+    Class(Class),
+    /// The class of a file, which contains the top-level declarations of the file.
+    FileFacade(Package),
+    /// A class generated by the compiler, e.g. for a lambda, which is described by the function if it is a lambda.
+    SyntheticClass(Option<Function>),
+    /// The class of files annotated with `@JvmMultifileClass`, which only stores the names of its parts.
+    MultiFileClassFacade(Vec<String>),
+    /// The part of a multi-file class of a single file.
+    MultiFileClassPart {
+        package: Package,
+        /// The name of the facade class.
+        facade: String,
+    },
+    /// A kind not known by this version of noak.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Class {
+    pub name: String,
+    pub has_annotations: bool,
+    pub visibility: Visibility,
+    pub modality: Modality,
+    pub kind: ClassKind,
+    pub is_inner: bool,
+    pub is_data: bool,
+    pub is_external: bool,
+    pub is_expect: bool,
+    /// Whether it is a value class, formerly called inline class.
+    pub is_value: bool,
+    pub is_fun_interface: bool,
+    pub type_parameters: Vec<TypeParameter>,
+    pub supertypes: Vec<Type>,
+    pub context_receiver_types: Vec<Type>,
+    pub constructors: Vec<Constructor>,
+    pub functions: Vec<Function>,
+    pub properties: Vec<Property>,
+    pub type_aliases: Vec<TypeAlias>,
+    /// The simple name of the companion object.
+    pub companion_object: Option<String>,
+    /// The simple names of the nested classes.
+    pub nested_classes: Vec<String>,
+    pub enum_entries: Vec<String>,
+    pub sealed_subclasses: Vec<String>,
+    pub inline_class_underlying_property: Option<String>,
+    pub inline_class_underlying_type: Option<Type>,
+    /// The name of the module which contains the class.
+    pub module_name: Option<String>,
+}
+
+/// The top-level declarations of a file.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Package {
+    pub functions: Vec<Function>,
+    pub properties: Vec<Property>,
+    pub type_aliases: Vec<TypeAlias>,
+    /// The name of the module which contains the file.
+    pub module_name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Constructor {
+    pub has_annotations: bool,
+    pub visibility: Visibility,
+    pub is_secondary: bool,
+    pub value_parameters: Vec<ValueParameter>,
+    /// The signature of the constructor in the class file, if it can be determined.
+    pub signature: Option<JvmSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Function {
+    pub name: String,
+    pub has_annotations: bool,
+    pub visibility: Visibility,
+    pub modality: Modality,
+    pub member_kind: MemberKind,
+    pub is_operator: bool,
+    pub is_infix: bool,
+    pub is_inline: bool,
+    pub is_tailrec: bool,
+    pub is_external: bool,
+    pub is_suspend: bool,
+    pub is_expect: bool,
+    pub type_parameters: Vec<TypeParameter>,
+    /// The type of the receiver of an extension function.
+    pub receiver_type: Option<Type>,
+    pub context_receiver_types: Vec<Type>,
+    pub value_parameters: Vec<ValueParameter>,
+    pub return_type: Type,
+    /// The signature of the method in the class file, if it can be determined.
+    pub signature: Option<JvmSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Property {
+    pub name: String,
+    pub has_annotations: bool,
+    pub visibility: Visibility,
+    pub modality: Modality,
+    pub member_kind: MemberKind,
+    pub is_var: bool,
+    pub is_const: bool,
+    pub is_lateinit: bool,
+    pub has_constant: bool,
+    pub is_external: bool,
+    pub is_delegated: bool,
+    pub is_expect: bool,
+    pub getter: Option<Accessor>,
+    pub setter: Option<Accessor>,
+    pub setter_parameter: Option<ValueParameter>,
+    pub type_parameters: Vec<TypeParameter>,
+    /// The type of the receiver of an extension property.
+    pub receiver_type: Option<Type>,
+    pub context_receiver_types: Vec<Type>,
+    pub return_type: Type,
+    /// The signature of the backing field in the class file, if there is one.
+    pub field_signature: Option<JvmSignature>,
+    /// The signature of the getter in the class file, if there is one.
+    pub getter_signature: Option<JvmSignature>,
+    /// The signature of the setter in the class file, if there is one.
+    pub setter_signature: Option<JvmSignature>,
+}
+
+/// The getter or setter of a property.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Accessor {
+    pub has_annotations: bool,
+    pub visibility: Visibility,
+    pub modality: Modality,
+    /// Whether the accessor has a body or annotations.
+    pub is_not_default: bool,
+    pub is_external: bool,
+    pub is_inline: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValueParameter {
+    pub name: String,
+    pub has_annotations: bool,
+    pub declares_default_value: bool,
+    pub is_crossinline: bool,
+    pub is_noinline: bool,
+    /// The type of the parameter, which is an array for variable arguments.
+    pub type_: Type,
+    /// The type of the elements of variable arguments.
+    pub vararg_element_type: Option<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeAlias {
+    pub name: String,
+    pub has_annotations: bool,
+    pub visibility: Visibility,
+    pub type_parameters: Vec<TypeParameter>,
+    pub underlying_type: Type,
+    /// The underlying type with all type aliases expanded.
+    pub expanded_type: Type,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeParameter {
+    /// The identifier used by [`Classifier::TypeParameter`].
+    pub id: i32,
+    pub name: String,
+    pub is_reified: bool,
+    pub variance: Variance,
+    pub upper_bounds: Vec<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Type {
+    pub classifier: Classifier,
+    pub arguments: Vec<TypeArgument>,
+    pub nullable: bool,
+    /// Whether it is the type of a suspend function, e.g. `suspend () -> Unit`.
+    pub is_suspend: bool,
+    /// Whether it is a definitely non-nullable type, e.g. `T & Any`.
+    pub is_definitely_non_null: bool,
+    /// The type of the outer class of an inner class.
+    pub outer_type: Option<Box<Type>>,
+    /// The type alias used instead of this type.
+    pub abbreviated_type: Option<Box<Type>>,
+    /// The upper bound of a flexible type, whose lower bound is this type. Platform types are flexible.
+    pub flexible_upper_bound: Option<Box<Type>>,
+    /// Whether it is a raw type of Java.
+    pub is_raw: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Classifier {
+    Class(String),
+    /// The identifier of a [`TypeParameter`].
+    TypeParameter(i32),
+    TypeAlias(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TypeArgument {
+    /// The star projection `*`.
+    Star,
+    Projection {
+        variance: Variance,
+        type_: Type,
+    },
+}
+
+/// The signature of a member in the class file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JvmSignature {
+    pub name: String,
+    pub descriptor: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Visibility {
+    Internal,
+    Private,
+    Protected,
+    Public,
+    PrivateToThis,
+    Local,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Modality {
+    Final,
+    Open,
+    Abstract,
+    Sealed,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClassKind {
+    Class,
+    Interface,
+    EnumClass,
+    EnumEntry,
+    AnnotationClass,
+    Object,
+    CompanionObject,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MemberKind {
+    Declaration,
+    FakeOverride,
+    Delegation,
+    Synthesized,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Variance {
+    In,
+    Out,
+    Invariant,
+}
+
+/// Decodes the bytes stored in strings, which either contain one byte per character or seven bits per character.
+fn decode_bytes(data: &[String]) -> Result<Vec<u8>, DecodeError> {
+    let mut chars = data.iter().flat_map(|string| string.chars()).peekable();
+    match chars.peek() {
+        Some('\0') => {
+            chars.next();
+            chars
+                .map(|ch| u8::try_from(ch).map_err(|_| DecodeError::new(DecodeErrorKind::InvalidMutf8)))
+                .collect()
+        }
+        marker => {
+            if marker == Some(&'\u{1}') {
+                chars.next();
+            }
+            let bytes: Vec<u8> = chars
+                .map(|ch| {
+                    u8::try_from(ch)
+                        .map(|byte| byte.wrapping_add(0x7f) & 0x7f)
+                        .map_err(|_| DecodeError::new(DecodeErrorKind::InvalidMutf8))
+                })
+                .collect::<Result<_, _>>()?;
+
+            // every byte stores seven bits
+            let mut result = Vec::with_capacity(bytes.len() * 7 / 8);
+            let mut index = 0;
+            let mut bit = 0;
+            while result.len() < bytes.len() * 7 / 8 {
+                let first = bytes[index] >> bit;
+                index += 1;
+                let second = (bytes[index] & ((1 << (bit + 1)) - 1)) << (7 - bit);
+                result.push(first | second);
+                if bit == 6 {
+                    index += 1;
+                    bit = 0;
+                } else {
+                    bit += 1;
+                }
+            }
+            Ok(result)
+        }
+    }
+}
+
+/// The strings which are predefined by the JVM backend of Kotlin.
+const PREDEFINED_STRINGS: [&str; 44] = [
+    "kotlin/Any",
+    "kotlin/Nothing",
+    "kotlin/Unit",
+    "kotlin/Throwable",
+    "kotlin/Number",
+    "kotlin/Byte",
+    "kotlin/Double",
+    "kotlin/Float",
+    "kotlin/Int",
+    "kotlin/Long",
+    "kotlin/Short",
+    "kotlin/Boolean",
+    "kotlin/Char",
+    "kotlin/CharSequence",
+    "kotlin/String",
+    "kotlin/Comparable",
+    "kotlin/Enum",
+    "kotlin/Array",
+    "kotlin/ByteArray",
+    "kotlin/DoubleArray",
+    "kotlin/FloatArray",
+    "kotlin/IntArray",
+    "kotlin/LongArray",
+    "kotlin/ShortArray",
+    "kotlin/BooleanArray",
+    "kotlin/CharArray",
+    "kotlin/Cloneable",
+    "kotlin/Annotation",
+    "kotlin/collections/Iterable",
+    "kotlin/collections/MutableIterable",
+    "kotlin/collections/Collection",
+    "kotlin/collections/MutableCollection",
+    "kotlin/collections/List",
+    "kotlin/collections/MutableList",
+    "kotlin/collections/Set",
+    "kotlin/collections/MutableSet",
+    "kotlin/collections/Map",
+    "kotlin/collections/MutableMap",
+    "kotlin/collections/Map.Entry",
+    "kotlin/collections/MutableMap.MutableEntry",
+    "kotlin/collections/Iterator",
+    "kotlin/collections/MutableIterator",
+    "kotlin/collections/ListIterator",
+    "kotlin/collections/MutableListIterator",
+];
+
+/// Resolves the indices of strings in the declarations, as described by a `StringTableTypes` message.
+#[derive(Debug)]
+struct Strings<'a> {
+    strings: &'a [String],
+    records: Vec<StringRecord>,
+    /// The index of the record of each string.
+    string_records: Vec<usize>,
+    local_names: HashSet<i32>,
+}
+
+#[derive(Debug, Default)]
+struct StringRecord {
+    predefined_index: Option<i32>,
+    string: Option<String>,
+    operation: i32,
+    substring_index: Vec<i32>,
+    replace_char: Vec<i32>,
+}
+
+impl<'a> Strings<'a> {
+    /// Reads the length-delimited string table, which is followed by the message of the declarations.
+    fn read<'b>(bytes: &'b [u8], strings: &'a [String]) -> Result<(Strings<'a>, &'b [u8]), DecodeError> {
+        let mut input = bytes;
+        let length = read_varint(&mut input)?;
+        let length = usize::try_from(length).map_err(|_| DecodeError::new(DecodeErrorKind::InvalidLength))?;
+        if input.len() < length {
+            return Err(DecodeError::new(DecodeErrorKind::UnexpectedEoi));
+        }
+        let (table, message) = input.split_at(length);
+
+        let mut result = Strings {
+            strings,
+            records: Vec::new(),
+            string_records: Vec::new(),
+            local_names: HashSet::new(),
+        };
+        for field in fields(table) {
+            match field? {
+                (1, value) => {
+                    let mut record = StringRecord::default();
+                    let mut range = 1;
+                    for field in fields(value.bytes()?) {
+                        match field? {
+                            (1, value) => range = value.int()?,
+                            (2, value) => record.predefined_index = Some(value.int()?),
+                            (3, value) => record.operation = value.int()?,
+                            (4, value) => value.push_ints(&mut record.substring_index)?,
+                            (5, value) => value.push_ints(&mut record.replace_char)?,
+                            (6, value) => record.string = Some(value.string()?),
+                            _ => {}
+                        }
+                    }
+                    let index = result.records.len();
+                    result.records.push(record);
+                    // a record may describe more strings than there are
+                    let range = usize::try_from(range).unwrap_or(0).min(strings.len());
+                    result.string_records.extend(std::iter::repeat(index).take(range));
+                }
+                (5, value) => {
+                    let mut local_names = Vec::new();
+                    value.push_ints(&mut local_names)?;
+                    result.local_names.extend(local_names);
+                }
+                _ => {}
+            }
+        }
+        Ok((result, message))
+    }
+
+    fn get(&self, index: i32) -> Result<String, DecodeError> {
+        let index = usize::try_from(index).map_err(|_| DecodeError::new(DecodeErrorKind::InvalidIndex))?;
+        let raw = self.strings.get(index);
+        let Some(record) = self.string_records.get(index).map(|&record| &self.records[record]) else {
+            return raw
+                .cloned()
+                .ok_or_else(|| DecodeError::new(DecodeErrorKind::InvalidIndex));
+        };
+
+        let predefined = record
+            .predefined_index
+            .and_then(|index| usize::try_from(index).ok())
+            .and_then(|index| PREDEFINED_STRINGS.get(index));
+        let mut string = match (&record.string, predefined, raw) {
+            (Some(string), _, _) => string.clone(),
+            (None, Some(predefined), _) => (*predefined).to_owned(),
+            (None, None, Some(raw)) => raw.clone(),
+            (None, None, None) => return Err(DecodeError::new(DecodeErrorKind::InvalidIndex)),
+        };
+
+        if let [begin, end, ..] = record.substring_index[..] {
+            // the indices count UTF-16 code units, like in Java
+            let units: Vec<u16> = string.encode_utf16().collect();
+            if let (Ok(begin), Ok(end)) = (usize::try_from(begin), usize::try_from(end)) {
+                if begin <= end && end <= units.len() {
+                    string = String::from_utf16_lossy(&units[begin..end]);
+                }
+            }
+        }
+        if let [from, to, ..] = record.replace_char[..] {
+            let from = u32::try_from(from).ok().and_then(char::from_u32);
+            let to = u32::try_from(to).ok().and_then(char::from_u32);
+            if let (Some(from), Some(to)) = (from, to) {
+                string = string.replace(from, &to.to_string());
+            }
+        }
+        match record.operation {
+            // an internal name
+            1 => string = string.replace('$', "."),
+            // a descriptor
+            2 => {
+                let mut chars = string.chars();
+                chars.next();
+                chars.next_back();
+                string = chars.as_str().replace('$', ".");
+            }
+            _ => {}
+        }
+        Ok(string)
+    }
+
+    fn class_name(&self, index: i32) -> Result<String, DecodeError> {
+        let name = self.get(index)?;
+        if self.local_names.contains(&index) {
+            Ok(format!(".{name}"))
+        } else {
+            Ok(name)
+        }
+    }
+}
+
+/// The types referenced by their index in the `TypeTable` of a declaration.
+#[derive(Debug, Clone, Default)]
+struct TypeTable<'a> {
+    types: Vec<&'a [u8]>,
+    first_nullable: Option<usize>,
+}
+
+impl<'a> TypeTable<'a> {
+    /// Reads the type table of a declaration, if it has one.
+    fn of(message: &'a [u8]) -> Result<Option<TypeTable<'a>>, DecodeError> {
+        for field in fields(message) {
+            if let (30, value) = field? {
+                let mut table = TypeTable::default();
+                for field in fields(value.bytes()?) {
+                    match field? {
+                        (1, value) => table.types.push(value.bytes()?),
+                        (2, value) => table.first_nullable = usize::try_from(value.int()?).ok(),
+                        _ => {}
+                    }
+                }
+                return Ok(Some(table));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The limit of nested types, which prevents cyclic type tables from overflowing the stack.
+const MAX_TYPE_DEPTH: u32 = 128;
+
+#[derive(Debug, Clone)]
+struct Context<'a> {
+    strings: &'a Strings<'a>,
+    types: TypeTable<'a>,
+    /// The names and identifiers of the type parameters in scope.
+    type_parameters: Vec<(String, i32)>,
+}
+
+impl<'a> Context<'a> {
+    fn new(strings: &'a Strings<'a>) -> Context<'a> {
+        Context {
+            strings,
+            types: TypeTable::default(),
+            type_parameters: Vec::new(),
+        }
+    }
+
+    /// The context of a declaration, which may have its own type table and type parameters.
+    fn child(&self, message: &'a [u8], type_parameter_field: u32) -> Result<Context<'a>, DecodeError> {
+        let mut context = self.clone();
+        if let Some(types) = TypeTable::of(message)? {
+            context.types = types;
+        }
+        for field in fields(message) {
+            match field? {
+                (number, value) if number == type_parameter_field => {
+                    let (mut id, mut name) = (0, None);
+                    for field in fields(value.bytes()?) {
+                        match field? {
+                            (1, value) => id = value.int()?,
+                            (2, value) => name = Some(self.strings.get(value.int()?)?),
+                            _ => {}
+                        }
+                    }
+                    if let Some(name) = name {
+                        context.type_parameters.push((name, id));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(context)
+    }
+
+    fn class(&self, message: &'a [u8]) -> Result<Class, DecodeError> {
+        let context = self.child(message, 5)?;
+        let mut flags = 6;
+        let mut name = None;
+        let mut class = Class {
+            name: String::new(),
+            has_annotations: false,
+            visibility: Visibility::Public,
+            modality: Modality::Final,
+            kind: ClassKind::Class,
+            is_inner: false,
+            is_data: false,
+            is_external: false,
+            is_expect: false,
+            is_value: false,
+            is_fun_interface: false,
+            type_parameters: Vec::new(),
+            supertypes: Vec::new(),
+            context_receiver_types: Vec::new(),
+            constructors: Vec::new(),
+            functions: Vec::new(),
+            properties: Vec::new(),
+            type_aliases: Vec::new(),
+            companion_object: None,
+            nested_classes: Vec::new(),
+            enum_entries: Vec::new(),
+            sealed_subclasses: Vec::new(),
+            inline_class_underlying_property: None,
+            inline_class_underlying_type: None,
+            module_name: None,
+        };
+        let mut supertype_ids = Vec::new();
+        let mut context_receiver_type_ids = Vec::new();
+        let mut nested_classes = Vec::new();
+        let mut sealed_subclasses = Vec::new();
+        for field in fields(message) {
+            match field? {
+                (1, value) => flags = value.int()?,
+                (2, value) => value.push_ints(&mut supertype_ids)?,
+                (3, value) => name = Some(self.strings.class_name(value.int()?)?),
+                (4, value) => class.companion_object = Some(self.strings.get(value.int()?)?),
+                (5, value) => class.type_parameters.push(context.type_parameter(value.bytes()?)?),
+                (6, value) => class.supertypes.push(context.type_(value.bytes()?, 0)?),
+                (7, value) => value.push_ints(&mut nested_classes)?,
+                (8, value) => class.constructors.push(context.constructor(value.bytes()?)?),
+                (9, value) => class.functions.push(context.function(value.bytes()?)?),
+                (10, value) => class.properties.push(context.property(value.bytes()?)?),
+                (11, value) => class.type_aliases.push(context.type_alias(value.bytes()?)?),
+                (13, value) => {
+                    for field in fields(value.bytes()?) {
+                        if let (1, value) = field? {
+                            class.enum_entries.push(self.strings.get(value.int()?)?);
+                        }
+                    }
+                }
+                (16, value) => value.push_ints(&mut sealed_subclasses)?,
+                (17, value) => class.inline_class_underlying_property = Some(self.strings.get(value.int()?)?),
+                (18, value) => class.inline_class_underlying_type = Some(context.type_(value.bytes()?, 0)?),
+                (19, value) => class.inline_class_underlying_type = Some(context.type_by_id(value.int()?, 0)?),
+                (20, value) => class.context_receiver_types.push(context.type_(value.bytes()?, 0)?),
+                (21, value) => value.push_ints(&mut context_receiver_type_ids)?,
+                (101, value) => class.module_name = Some(self.strings.get(value.int()?)?),
+                _ => {}
+            }
+        }
+
+        class.name = name.ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?;
+        class.has_annotations = flags & 1 != 0;
+        class.visibility = visibility(flags)?;
+        class.modality = modality(flags);
+        class.kind = match (flags >> 6) & 0b111 {
+            0 => ClassKind::Class,
+            1 => ClassKind::Interface,
+            2 => ClassKind::EnumClass,
+            3 => ClassKind::EnumEntry,
+            4 => ClassKind::AnnotationClass,
+            5 => ClassKind::Object,
+            6 => ClassKind::CompanionObject,
+            _ => return Err(DecodeError::new(DecodeErrorKind::InvalidTag)),
+        };
+        class.is_inner = flags & (1 << 9) != 0;
+        class.is_data = flags & (1 << 10) != 0;
+        class.is_external = flags & (1 << 11) != 0;
+        class.is_expect = flags & (1 << 12) != 0;
+        class.is_value = flags & (1 << 13) != 0;
+        class.is_fun_interface = flags & (1 << 14) != 0;
+        // types are either stored directly or in the type table
+        if class.supertypes.is_empty() {
+            for id in supertype_ids {
+                class.supertypes.push(context.type_by_id(id, 0)?);
+            }
+        }
+        if class.context_receiver_types.is_empty() {
+            for id in context_receiver_type_ids {
+                class.context_receiver_types.push(context.type_by_id(id, 0)?);
+            }
+        }
+        for name in nested_classes {
+            class.nested_classes.push(self.strings.get(name)?);
+        }
+        for name in sealed_subclasses {
+            class.sealed_subclasses.push(self.strings.class_name(name)?);
+        }
+        Ok(class)
+    }
+
+    fn package(&self, message: &'a [u8]) -> Result<Package, DecodeError> {
+        let context = self.child(message, 0)?;
+        let mut package = Package {
+            functions: Vec::new(),
+            properties: Vec::new(),
+            type_aliases: Vec::new(),
+            module_name: None,
+        };
+        for field in fields(message) {
+            match field? {
+                (3, value) => package.functions.push(context.function(value.bytes()?)?),
+                (4, value) => package.properties.push(context.property(value.bytes()?)?),
+                (5, value) => package.type_aliases.push(context.type_alias(value.bytes()?)?),
+                (101, value) => package.module_name = Some(self.strings.get(value.int()?)?),
+                _ => {}
+            }
+        }
+        Ok(package)
+    }
+
+    fn constructor(&self, message: &'a [u8]) -> Result<Constructor, DecodeError> {
+        let mut flags = 6;
+        let mut value_parameters = Vec::new();
+        let mut signature = None;
+        for field in fields(message) {
+            match field? {
+                (1, value) => flags = value.int()?,
+                (2, value) => value_parameters.push(self.value_parameter(value.bytes()?)?),
+                (100, value) => signature = Some(self.signature(value.bytes()?)?),
+                _ => {}
+            }
+        }
+
+        let signature = match signature {
+            Some((name, Some(descriptor))) => Some(JvmSignature {
+                name: name.unwrap_or_else(|| "<init>".to_owned()),
+                descriptor,
+            }),
+            // the descriptor is only stored if it differs from the default mapping of the types
+            signature => {
+                let name = signature.and_then(|(name, _)| name);
+                descriptor(value_parameters.iter().map(|parameter| &parameter.type_), None).map(|descriptor| {
+                    JvmSignature {
+                        name: name.unwrap_or_else(|| "<init>".to_owned()),
+                        descriptor,
+                    }
+                })
+            }
+        };
+        Ok(Constructor {
+            has_annotations: flags & 1 != 0,
+            visibility: visibility(flags)?,
+            is_secondary: flags & (1 << 4) != 0,
+            value_parameters,
+            signature,
+        })
+    }
+
+    fn function(&self, message: &'a [u8]) -> Result<Function, DecodeError> {
+        let context = self.child(message, 4)?;
+        let (mut flags, mut old_flags) = (None, None);
+        let mut name = None;
+        let (mut return_type, mut receiver_type) = (None, None);
+        let mut type_parameters = Vec::new();
+        let mut context_receiver_types = Vec::new();
+        let mut context_receiver_type_ids = Vec::new();
+        let mut value_parameters = Vec::new();
+        let mut signature = None;
+        for field in fields(message) {
+            match field? {
+                (1, value) => old_flags = Some(value.int()?),
+                (2, value) => name = Some(self.strings.get(value.int()?)?),
+                (3, value) => return_type = Some(context.type_(value.bytes()?, 0)?),
+                (4, value) => type_parameters.push(context.type_parameter(value.bytes()?)?),
+                (5, value) => receiver_type = Some(context.type_(value.bytes()?, 0)?),
+                (6, value) => value_parameters.push(context.value_parameter(value.bytes()?)?),
+                (7, value) => return_type = Some(context.type_by_id(value.int()?, 0)?),
+                (8, value) => receiver_type = Some(context.type_by_id(value.int()?, 0)?),
+                (9, value) => flags = Some(value.int()?),
+                (10, value) => context_receiver_types.push(context.type_(value.bytes()?, 0)?),
+                (11, value) => value.push_ints(&mut context_receiver_type_ids)?,
+                (100, value) => signature = Some(self.signature(value.bytes()?)?),
+                _ => {}
+            }
+        }
+        if context_receiver_types.is_empty() {
+            for id in context_receiver_type_ids {
+                context_receiver_types.push(context.type_by_id(id, 0)?);
+            }
+        }
+
+        let flags = flags.or(old_flags.map(convert_old_flags)).unwrap_or(6);
+        let name = name.ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?;
+        let return_type = return_type.ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?;
+        let signature = match signature {
+            Some((jvm_name, Some(descriptor))) => Some(JvmSignature {
+                name: jvm_name.unwrap_or_else(|| name.clone()),
+                descriptor,
+            }),
+            signature => {
+                let parameter_types = context_receiver_types
+                    .iter()
+                    .chain(&receiver_type)
+                    .chain(value_parameters.iter().map(|parameter| &parameter.type_));
+                descriptor(parameter_types, Some(&return_type)).map(|descriptor| JvmSignature {
+                    name: signature.and_then(|(name, _)| name).unwrap_or_else(|| name.clone()),
+                    descriptor,
+                })
+            }
+        };
+        Ok(Function {
+            name,
+            has_annotations: flags & 1 != 0,
+            visibility: visibility(flags)?,
+            modality: modality(flags),
+            member_kind: member_kind(flags),
+            is_operator: flags & (1 << 8) != 0,
+            is_infix: flags & (1 << 9) != 0,
+            is_inline: flags & (1 << 10) != 0,
+            is_tailrec: flags & (1 << 11) != 0,
+            is_external: flags & (1 << 12) != 0,
+            is_suspend: flags & (1 << 13) != 0,
+            is_expect: flags & (1 << 14) != 0,
+            type_parameters,
+            receiver_type,
+            context_receiver_types,
+            value_parameters,
+            return_type,
+            signature,
+        })
+    }
+
+    fn property(&self, message: &'a [u8]) -> Result<Property, DecodeError> {
+        let context = self.child(message, 4)?;
+        let (mut flags, mut old_flags) = (None, None);
+        let (mut getter_flags, mut setter_flags) = (None, None);
+        let mut name = None;
+        let (mut return_type, mut receiver_type) = (None, None);
+        let mut type_parameters = Vec::new();
+        let mut context_receiver_types = Vec::new();
+        let mut context_receiver_type_ids = Vec::new();
+        let mut setter_parameter = None;
+        let mut signature = None;
+        for field in fields(message) {
+            match field? {
+                (1, value) => old_flags = Some(value.int()?),
+                (2, value) => name = Some(self.strings.get(value.int()?)?),
+                (3, value) => return_type = Some(context.type_(value.bytes()?, 0)?),
+                (4, value) => type_parameters.push(context.type_parameter(value.bytes()?)?),
+                (5, value) => receiver_type = Some(context.type_(value.bytes()?, 0)?),
+                (6, value) => setter_parameter = Some(context.value_parameter(value.bytes()?)?),
+                (7, value) => getter_flags = Some(value.int()?),
+                (8, value) => setter_flags = Some(value.int()?),
+                (9, value) => return_type = Some(context.type_by_id(value.int()?, 0)?),
+                (10, value) => receiver_type = Some(context.type_by_id(value.int()?, 0)?),
+                (11, value) => flags = Some(value.int()?),
+                (12, value) => context_receiver_types.push(context.type_(value.bytes()?, 0)?),
+                (13, value) => value.push_ints(&mut context_receiver_type_ids)?,
+                (100, value) => signature = Some(value.bytes()?),
+                _ => {}
+            }
+        }
+        if context_receiver_types.is_empty() {
+            for id in context_receiver_type_ids {
+                context_receiver_types.push(context.type_by_id(id, 0)?);
+            }
+        }
+
+        let flags = flags.or(old_flags.map(convert_old_flags)).unwrap_or(518);
+        let name = name.ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?;
+        let return_type = return_type.ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?;
+        // accessors have the visibility and modality of the property by default
+        let default_accessor_flags = flags & 0b11_1111;
+        let accessor = |flags: i32| -> Result<Accessor, DecodeError> {
+            Ok(Accessor {
+                has_annotations: flags & 1 != 0,
+                visibility: visibility(flags)?,
+                modality: modality(flags),
+                is_not_default: flags & (1 << 6) != 0,
+                is_external: flags & (1 << 7) != 0,
+                is_inline: flags & (1 << 8) != 0,
+            })
+        };
+
+        let (mut field_signature, mut getter_signature, mut setter_signature) = (None, None, None);
+        if let Some(signature) = signature {
+            for field in fields(signature) {
+                match field? {
+                    (1, value) => {
+                        let (jvm_name, descriptor) = self.signature(value.bytes()?)?;
+                        let descriptor = descriptor.or_else(|| type_descriptor(&return_type));
+                        field_signature = descriptor.map(|descriptor| JvmSignature {
+                            name: jvm_name.unwrap_or_else(|| name.clone()),
+                            descriptor,
+                        });
+                    }
+                    (3, value) => getter_signature = self.accessor_signature(value.bytes()?)?,
+                    (4, value) => setter_signature = self.accessor_signature(value.bytes()?)?,
+                    _ => {}
+                }
+            }
+        }
+        Ok(Property {
+            name,
+            has_annotations: flags & 1 != 0,
+            visibility: visibility(flags)?,
+            modality: modality(flags),
+            member_kind: member_kind(flags),
+            is_var: flags & (1 << 8) != 0,
+            is_const: flags & (1 << 11) != 0,
+            is_lateinit: flags & (1 << 12) != 0,
+            has_constant: flags & (1 << 13) != 0,
+            is_external: flags & (1 << 14) != 0,
+            is_delegated: flags & (1 << 15) != 0,
+            is_expect: flags & (1 << 16) != 0,
+            getter: if flags & (1 << 9) != 0 {
+                Some(accessor(getter_flags.unwrap_or(default_accessor_flags))?)
+            } else {
+                None
+            },
+            setter: if flags & (1 << 10) != 0 {
+                Some(accessor(setter_flags.unwrap_or(default_accessor_flags))?)
+            } else {
+                None
+            },
+            setter_parameter,
+            type_parameters,
+            receiver_type,
+            context_receiver_types,
+            return_type,
+            field_signature,
+            getter_signature,
+            setter_signature,
+        })
+    }
+
+    fn value_parameter(&self, message: &'a [u8]) -> Result<ValueParameter, DecodeError> {
+        let mut flags = 0;
+        let mut name = None;
+        let (mut type_, mut vararg_element_type) = (None, None);
+        for field in fields(message) {
+            match field? {
+                (1, value) => flags = value.int()?,
+                (2, value) => name = Some(self.strings.get(value.int()?)?),
+                (3, value) => type_ = Some(self.type_(value.bytes()?, 0)?),
+                (4, value) => vararg_element_type = Some(self.type_(value.bytes()?, 0)?),
+                (5, value) => type_ = Some(self.type_by_id(value.int()?, 0)?),
+                (6, value) => vararg_element_type = Some(self.type_by_id(value.int()?, 0)?),
+                _ => {}
+            }
+        }
+        Ok(ValueParameter {
+            name: name.ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?,
+            has_annotations: flags & 1 != 0,
+            declares_default_value: flags & (1 << 1) != 0,
+            is_crossinline: flags & (1 << 2) != 0,
+            is_noinline: flags & (1 << 3) != 0,
+            type_: type_.ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?,
+            vararg_element_type,
+        })
+    }
+
+    fn type_alias(&self, message: &'a [u8]) -> Result<TypeAlias, DecodeError> {
+        let context = self.child(message, 3)?;
+        let mut flags = 6;
+        let mut name = None;
+        let mut type_parameters = Vec::new();
+        let (mut underlying_type, mut expanded_type) = (None, None);
+        for field in fields(message) {
+            match field? {
+                (1, value) => flags = value.int()?,
+                (2, value) => name = Some(self.strings.get(value.int()?)?),
+                (3, value) => type_parameters.push(context.type_parameter(value.bytes()?)?),
+                (4, value) => underlying_type = Some(context.type_(value.bytes()?, 0)?),
+                (5, value) => underlying_type = Some(context.type_by_id(value.int()?, 0)?),
+                (6, value) => expanded_type = Some(context.type_(value.bytes()?, 0)?),
+                (7, value) => expanded_type = Some(context.type_by_id(value.int()?, 0)?),
+                _ => {}
+            }
+        }
+        Ok(TypeAlias {
+            name: name.ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?,
+            has_annotations: flags & 1 != 0,
+            visibility: visibility(flags)?,
+            type_parameters,
+            underlying_type: underlying_type.ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?,
+            expanded_type: expanded_type.ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?,
+        })
+    }
+
+    fn type_parameter(&self, message: &'a [u8]) -> Result<TypeParameter, DecodeError> {
+        let mut type_parameter = TypeParameter {
+            id: 0,
+            name: String::new(),
+            is_reified: false,
+            variance: Variance::Invariant,
+            upper_bounds: Vec::new(),
+        };
+        let mut upper_bound_ids = Vec::new();
+        for field in fields(message) {
+            match field? {
+                (1, value) => type_parameter.id = value.int()?,
+                (2, value) => type_parameter.name = self.strings.get(value.int()?)?,
+                (3, value) => type_parameter.is_reified = value.int()? != 0,
+                (4, value) => type_parameter.variance = variance(value.int()?)?,
+                (5, value) => type_parameter.upper_bounds.push(self.type_(value.bytes()?, 0)?),
+                (6, value) => value.push_ints(&mut upper_bound_ids)?,
+                _ => {}
+            }
+        }
+        if type_parameter.upper_bounds.is_empty() {
+            for id in upper_bound_ids {
+                type_parameter.upper_bounds.push(self.type_by_id(id, 0)?);
+            }
+        }
+        Ok(type_parameter)
+    }
+
+    fn type_by_id(&self, id: i32, depth: u32) -> Result<Type, DecodeError> {
+        let index = usize::try_from(id).map_err(|_| DecodeError::new(DecodeErrorKind::InvalidIndex))?;
+        let message = self
+            .types
+            .types
+            .get(index)
+            .ok_or_else(|| DecodeError::new(DecodeErrorKind::InvalidIndex))?;
+        let mut type_ = self.type_(message, depth + 1)?;
+        if self.types.first_nullable.is_some_and(|first| index >= first) {
+            type_.nullable = true;
+        }
+        Ok(type_)
+    }
+
+    fn type_(&self, message: &'a [u8], depth: u32) -> Result<Type, DecodeError> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(DecodeError::new(DecodeErrorKind::InvalidIndex));
+        }
+        let nested = |type_: Type| Some(Box::new(type_));
+        let mut classifier = None;
+        let mut type_ = Type {
+            classifier: Classifier::TypeParameter(0),
+            arguments: Vec::new(),
+            nullable: false,
+            is_suspend: false,
+            is_definitely_non_null: false,
+            outer_type: None,
+            abbreviated_type: None,
+            flexible_upper_bound: None,
+            is_raw: false,
+        };
+        for field in fields(message) {
+            match field? {
+                (1, value) => {
+                    let flags = value.int()?;
+                    type_.is_suspend = flags & 1 != 0;
+                    type_.is_definitely_non_null = flags & (1 << 1) != 0;
+                }
+                (2, value) => type_.arguments.push(self.type_argument(value.bytes()?, depth)?),
+                (3, value) => type_.nullable = value.int()? != 0,
+                (5, value) => type_.flexible_upper_bound = nested(self.type_(value.bytes()?, depth + 1)?),
+                (6, value) => classifier = Some(Classifier::Class(self.strings.class_name(value.int()?)?)),
+                (7, value) => classifier = Some(Classifier::TypeParameter(value.int()?)),
+                (8, value) => type_.flexible_upper_bound = nested(self.type_by_id(value.int()?, depth + 1)?),
+                (9, value) => {
+                    let name = self.strings.get(value.int()?)?;
+                    let id = self
+                        .type_parameters
+                        .iter()
+                        .rev()
+                        .find(|(other, _)| *other == name)
+                        .map(|&(_, id)| id)
+                        .ok_or_else(|| DecodeError::new(DecodeErrorKind::InvalidIndex))?;
+                    classifier = Some(Classifier::TypeParameter(id));
+                }
+                (10, value) => type_.outer_type = nested(self.type_(value.bytes()?, depth + 1)?),
+                (11, value) => type_.outer_type = nested(self.type_by_id(value.int()?, depth + 1)?),
+                (12, value) => classifier = Some(Classifier::TypeAlias(self.strings.class_name(value.int()?)?)),
+                (13, value) => type_.abbreviated_type = nested(self.type_(value.bytes()?, depth + 1)?),
+                (14, value) => type_.abbreviated_type = nested(self.type_by_id(value.int()?, depth + 1)?),
+                (101, value) => type_.is_raw = value.int()? != 0,
+                _ => {}
+            }
+        }
+        type_.classifier = classifier.ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?;
+        Ok(type_)
+    }
+
+    fn type_argument(&self, message: &'a [u8], depth: u32) -> Result<TypeArgument, DecodeError> {
+        let mut projection = 2;
+        let mut type_ = None;
+        for field in fields(message) {
+            match field? {
+                (1, value) => projection = value.int()?,
+                (2, value) => type_ = Some(self.type_(value.bytes()?, depth + 1)?),
+                (3, value) => type_ = Some(self.type_by_id(value.int()?, depth + 1)?),
+                _ => {}
+            }
+        }
+        if projection == 3 {
+            return Ok(TypeArgument::Star);
+        }
+        Ok(TypeArgument::Projection {
+            variance: variance(projection)?,
+            type_: type_.ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?,
+        })
+    }
+
+    /// Reads the optional name and descriptor of a `JvmMethodSignature` or `JvmFieldSignature`.
+    fn signature(&self, message: &[u8]) -> Result<(Option<String>, Option<String>), DecodeError> {
+        let (mut name, mut descriptor) = (None, None);
+        for field in fields(message) {
+            match field? {
+                (1, value) => name = Some(self.strings.get(value.int()?)?),
+                (2, value) => descriptor = Some(self.strings.get(value.int()?)?),
+                _ => {}
+            }
+        }
+        Ok((name, descriptor))
+    }
+
+    fn accessor_signature(&self, message: &[u8]) -> Result<Option<JvmSignature>, DecodeError> {
+        Ok(match self.signature(message)? {
+            (Some(name), Some(descriptor)) => Some(JvmSignature { name, descriptor }),
+            _ => None,
+        })
+    }
+}
+
+/// Converts the flags of functions and properties written by old versions of Kotlin.
+fn convert_old_flags(flags: i32) -> i32 {
+    (flags & 0x3f) | ((flags >> 8) << 6)
+}
+
+fn visibility(flags: i32) -> Result<Visibility, DecodeError> {
+    Ok(match (flags >> 1) & 0b111 {
+        0 => Visibility::Internal,
+        1 => Visibility::Private,
+        2 => Visibility::Protected,
+        3 => Visibility::Public,
+        4 => Visibility::PrivateToThis,
+        5 => Visibility::Local,
+        _ => return Err(DecodeError::new(DecodeErrorKind::InvalidTag)),
+    })
+}
+
+fn modality(flags: i32) -> Modality {
+    match (flags >> 4) & 0b11 {
+        0 => Modality::Final,
+        1 => Modality::Open,
+        2 => Modality::Abstract,
+        _ => Modality::Sealed,
+    }
+}
+
+fn member_kind(flags: i32) -> MemberKind {
+    match (flags >> 6) & 0b11 {
+        0 => MemberKind::Declaration,
+        1 => MemberKind::FakeOverride,
+        2 => MemberKind::Delegation,
+        _ => MemberKind::Synthesized,
+    }
+}
+
+fn variance(value: i32) -> Result<Variance, DecodeError> {
+    Ok(match value {
+        0 => Variance::In,
+        1 => Variance::Out,
+        2 => Variance::Invariant,
+        _ => return Err(DecodeError::new(DecodeErrorKind::InvalidTag)),
+    })
+}
+
+/// The method descriptor derived from the Kotlin types, which is used if no descriptor is stored.
+fn descriptor<'a>(parameter_types: impl IntoIterator<Item = &'a Type>, return_type: Option<&Type>) -> Option<String> {
+    let mut descriptor = String::from("(");
+    for type_ in parameter_types {
+        descriptor.push_str(&type_descriptor(type_)?);
+    }
+    descriptor.push(')');
+    match return_type {
+        Some(return_type) => descriptor.push_str(&type_descriptor(return_type)?),
+        None => descriptor.push('V'),
+    }
+    Some(descriptor)
+}
+
+/// The descriptor of a Kotlin class in the class file, if the type refers to a class.
+fn type_descriptor(type_: &Type) -> Option<String> {
+    let Classifier::Class(name) = &type_.classifier else {
+        return None;
+    };
+    let name = name.strip_prefix('.').unwrap_or(name);
+
+    let Some(simple_name) = name.strip_prefix("kotlin/") else {
+        return Some(format!("L{};", name.replace('.', "$")));
+    };
+    let primitive = |name: &str| match name {
+        "Boolean" => Some("Z"),
+        "Char" => Some("C"),
+        "Byte" => Some("B"),
+        "Short" => Some("S"),
+        "Int" => Some("I"),
+        "Float" => Some("F"),
+        "Long" => Some("J"),
+        "Double" => Some("D"),
+        _ => None,
+    };
+    if let Some(primitive) = primitive(simple_name) {
+        return Some(primitive.to_owned());
+    }
+    if let Some(primitive) = simple_name.strip_suffix("Array").and_then(primitive) {
+        return Some(format!("[{primitive}"));
+    }
+    let class = match simple_name {
+        "Unit" => return Some("V".to_owned()),
+        "Any" => "java/lang/Object".to_owned(),
+        "Nothing" => "java/lang/Void".to_owned(),
+        "Annotation" => "java/lang/annotation/Annotation".to_owned(),
+        "String" | "CharSequence" | "Throwable" | "Cloneable" | "Number" | "Comparable" | "Enum" => {
+            format!("java/lang/{simple_name}")
+        }
+        "collections/Iterable" | "collections/MutableIterable" => "java/lang/Iterable".to_owned(),
+        "collections/Map.Entry" | "collections/MutableMap.MutableEntry" => "java/util/Map$Entry".to_owned(),
+        _ => {
+            if let Some(collection) = simple_name.strip_prefix("collections/") {
+                let collection = collection.strip_prefix("Mutable").unwrap_or(collection);
+                if matches!(
+                    collection,
+                    "Iterator" | "Collection" | "List" | "Set" | "Map" | "ListIterator"
+                ) {
+                    return Some(format!("Ljava/util/{collection};"));
+                }
+            }
+            // functions with up to 22 parameters
+            let has_arity = |prefix: &str| {
+                simple_name
+                    .strip_prefix(prefix)
+                    .is_some_and(|arity| (0..=22).any(|n: u8| arity == n.to_string()))
+            };
+            if has_arity("Function") {
+                format!("kotlin/jvm/functions/{simple_name}")
+            } else if has_arity("reflect/KFunction") {
+                "kotlin/reflect/KFunction".to_owned()
+            } else if let Some(class) = simple_name.strip_suffix(".Companion").filter(|class| {
+                matches!(
+                    *class,
+                    "Char" | "Byte" | "Short" | "Int" | "Float" | "Long" | "Double" | "String" | "Enum"
+                )
+            }) {
+                format!("kotlin/jvm/internal/{class}CompanionObject")
+            } else {
+                name.replace('.', "$")
+            }
+        }
+    };
+    Some(format!("L{class};"))
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Value<'a> {
+    Varint(u64),
+    Fixed64,
+    Bytes(&'a [u8]),
+    Fixed32,
+}
+
+impl<'a> Value<'a> {
+    fn int(self) -> Result<i32, DecodeError> {
+        match self {
+            // negative numbers are sign-extended to 64 bits
+            Value::Varint(value) => Ok(value as i32),
+            _ => Err(DecodeError::new(DecodeErrorKind::TagMismatch)),
+        }
+    }
+
+    fn bytes(self) -> Result<&'a [u8], DecodeError> {
+        match self {
+            Value::Bytes(bytes) => Ok(bytes),
+            _ => Err(DecodeError::new(DecodeErrorKind::TagMismatch)),
+        }
+    }
+
+    fn string(self) -> Result<String, DecodeError> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| DecodeError::new(DecodeErrorKind::InvalidMutf8))
+    }
+
+    /// Appends the numbers of a repeated field, which are either packed or stored one per field.
+    fn push_ints(self, ints: &mut Vec<i32>) -> Result<(), DecodeError> {
+        match self {
+            Value::Bytes(mut bytes) => {
+                while !bytes.is_empty() {
+                    ints.push(Value::Varint(read_varint(&mut bytes)?).int()?);
+                }
+                Ok(())
+            }
+            value => {
+                ints.push(value.int()?);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Iterates over the fields of a protocol buffer message.
+fn fields(message: &[u8]) -> impl Iterator<Item = Result<(u32, Value<'_>), DecodeError>> + '_ {
+    let mut input = message;
+    std::iter::from_fn(move || {
+        if input.is_empty() {
+            return None;
+        }
+        let field = read_field(&mut input);
+        if field.is_err() {
+            input = &[];
+        }
+        Some(field)
+    })
+}
+
+fn read_field<'a>(input: &mut &'a [u8]) -> Result<(u32, Value<'a>), DecodeError> {
+    let key = read_varint(input)?;
+    let number = u32::try_from(key >> 3).map_err(|_| DecodeError::new(DecodeErrorKind::InvalidTag))?;
+    let value = match key & 0b111 {
+        0 => Value::Varint(read_varint(input)?),
+        1 => {
+            skip(input, 8)?;
+            Value::Fixed64
+        }
+        2 => {
+            let length = read_varint(input)?;
+            let length = usize::try_from(length).map_err(|_| DecodeError::new(DecodeErrorKind::InvalidLength))?;
+            let bytes = input
+                .get(..length)
+                .ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?;
+            *input = &input[length..];
+            Value::Bytes(bytes)
+        }
+        5 => {
+            skip(input, 4)?;
+            Value::Fixed32
+        }
+        // groups aren't used
+        _ => return Err(DecodeError::new(DecodeErrorKind::InvalidTag)),
+    };
+    Ok((number, value))
+}
+
+fn skip(input: &mut &[u8], count: usize) -> Result<(), DecodeError> {
+    if input.len() < count {
+        return Err(DecodeError::new(DecodeErrorKind::UnexpectedEoi));
+    }
+    *input = &input[count..];
+    Ok(())
+}
+
+fn read_varint(input: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = input
+            .split_first()
+            .ok_or_else(|| DecodeError::new(DecodeErrorKind::UnexpectedEoi))?;
+        *input = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(DecodeError::new(DecodeErrorKind::InvalidLength))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seven_bit_encoding() {
+        // "ab" in the encoding of old versions of Kotlin, which stores seven bits per character
+        let encoded: String = [0x61u8, 0x44, 0x01]
+            .iter()
+            .map(|&byte| char::from(byte.wrapping_sub(0x7f) & 0x7f))
+            .collect();
+        assert_eq!(decode_bytes(&[encoded]).unwrap(), b"ab");
+        assert_eq!(decode_bytes(&["\0\u{ff}".to_owned()]).unwrap(), [0xff]);
+    }
+
+    #[test]
+    fn strings_and_descriptors() {
+        let data = ["Ljava/util/Map$Entry;".to_owned(), "unused".to_owned()];
+        // records: the descriptor of a class, then a predefined string
+        let table = [
+            0x00, // placeholder for the length
+            0x0a, 0x02, 0x18, 0x02, // operation = DESC_TO_CLASS_ID
+            0x0a, 0x02, 0x10, 0x08, // predefined_index = 8
+        ];
+        let mut bytes = table.to_vec();
+        bytes[0] = (table.len() - 1) as u8;
+        let (strings, message) = Strings::read(&bytes, &data).unwrap();
+        assert!(message.is_empty());
+        assert_eq!(strings.get(0).unwrap(), "java/util/Map.Entry");
+        assert_eq!(strings.get(1).unwrap(), "kotlin/Int");
+        assert!(strings.get(2).is_err());
+
+        let type_ = |name: &str, nullable| Type {
+            classifier: Classifier::Class(name.to_owned()),
+            arguments: Vec::new(),
+            nullable,
+            is_suspend: false,
+            is_definitely_non_null: false,
+            outer_type: None,
+            abbreviated_type: None,
+            flexible_upper_bound: None,
+            is_raw: false,
+        };
+        assert_eq!(type_descriptor(&type_("kotlin/IntArray", false)).unwrap(), "[I");
+        assert_eq!(
+            type_descriptor(&type_("kotlin/collections/MutableList", false)).unwrap(),
+            "Ljava/util/List;"
+        );
+        assert_eq!(
+            type_descriptor(&type_("kotlin/Function2", false)).unwrap(),
+            "Lkotlin/jvm/functions/Function2;"
+        );
+        assert_eq!(
+            type_descriptor(&type_("kotlin/Int.Companion", false)).unwrap(),
+            "Lkotlin/jvm/internal/IntCompanionObject;"
+        );
+        assert_eq!(
+            type_descriptor(&type_("a/Outer.Inner", true)).unwrap(),
+            "La/Outer$Inner;"
+        );
+    }
+}
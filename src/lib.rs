@@ -30,7 +30,6 @@
     clippy::print_stderr,
     clippy::print_stdout,
     clippy::separated_literal_suffix,
-    clippy::string_to_string,
     clippy::todo,
     clippy::try_err,
     clippy::undocumented_unsafe_blocks,
@@ -39,12 +38,29 @@
     clippy::use_debug
 )]
 
+pub mod abi;
+pub mod api;
+pub mod callsite;
+pub mod compat;
+pub mod deps;
 pub mod descriptor;
+pub mod diff;
 pub mod error;
 mod header;
+pub mod jni;
+pub mod kotlin;
 pub mod mutf8;
 pub mod reader;
+pub mod reflect;
+pub mod retrace;
+pub mod serial;
+pub mod smap;
+pub mod tree;
 pub mod writer;
 
-pub use header::{AccessFlags, Version};
+pub use header::{AccessFlags, Feature, Version};
 pub use mutf8::{MStr, MString};
+
+// serde_json is only used by the tests of the serde feature
+#[cfg(all(test, not(feature = "serde")))]
+use serde_json as _;
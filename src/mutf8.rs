@@ -535,6 +535,51 @@ impl fmt::Debug for MString {
     }
 }
 
+/// Strings are serialized as regular strings where possible.
+/// Strings containing unpaired surrogates are serialized as their modified UTF-8 bytes instead.
+#[cfg(feature = "serde")]
+impl serde::Serialize for MString {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.chars().collect::<Result<String, u32>>() {
+            Ok(s) => serializer.serialize_str(&s),
+            Err(_) => serializer.serialize_bytes(self.as_bytes()),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MString {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<MString, D::Error> {
+        struct MStringVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for MStringVisitor {
+            type Value = MString;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a string or modified UTF-8 bytes")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<MString, E> {
+                Ok(MString::from(v))
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<MString, E> {
+                MString::from_mutf8(v.to_vec()).map_err(E::custom)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<MString, A::Error> {
+                let mut buf = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element::<u8>()? {
+                    buf.push(byte);
+                }
+                MString::from_mutf8(buf).map_err(serde::de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_any(MStringVisitor)
+    }
+}
+
 pub struct Display<'a> {
     inner: &'a [u8],
 }
@@ -9,7 +9,7 @@ mod module;
 use std::fmt;
 
 pub use annotations::{
-    AnnotationDefault, RuntimeInvisibleAnnotations, RuntimeInvisibleParameterAnnotations,
+    AnnotationDefault, ParameterAnnotations, RuntimeInvisibleAnnotations, RuntimeInvisibleParameterAnnotations,
     RuntimeInvisibleTypeAnnotations, RuntimeVisibleAnnotations, RuntimeVisibleParameterAnnotations,
     RuntimeVisibleTypeAnnotations,
 };
@@ -29,6 +29,8 @@ use crate::MStr;
 pub struct Attribute<'input> {
     name: cpool::Index<cpool::Utf8<'input>>,
     content: Decoder<'input>,
+    /// The index of the attribute in its table, used for the path of errors.
+    index: u16,
 }
 
 impl<'input> Decode<'input> for Attribute<'input> {
@@ -40,8 +42,15 @@ impl<'input> Decode<'input> for Attribute<'input> {
         Ok(Attribute {
             name,
             content: content_decoder,
+            index: 0,
         })
     }
+
+    fn decode_item(decoder: &mut Decoder<'input>, index: u16) -> Result<Self, DecodeError> {
+        let attribute =
+            Attribute::decode(decoder).map_err(|err| err.within(PathSegment::Attribute { index, name: None }))?;
+        Ok(Attribute { index, ..attribute })
+    }
 }
 
 impl<'input> Attribute<'input> {
@@ -55,7 +64,25 @@ impl<'input> Attribute<'input> {
         self.content.buf()
     }
 
+    /// Reads the content of the attribute, adding the attribute to the path of errors.
     pub fn read_content(&self, pool: &cpool::ConstantPool<'input>) -> Result<AttributeContent<'input>, DecodeError> {
+        self.decode_content(pool).map_err(|err| self.within(pool, err))
+    }
+
+    /// Adds this attribute to the path of an error.
+    fn within(&self, pool: &cpool::ConstantPool<'input>, err: DecodeError) -> DecodeError {
+        err.within(PathSegment::Attribute {
+            index: self.index,
+            name: pool.retrieve(self.name).ok().map(MStr::to_owned),
+        })
+    }
+
+    /// Reads the content of the attribute without changing the path of errors,
+    /// for callers which track the path themselves.
+    pub(crate) fn decode_content(
+        &self,
+        pool: &cpool::ConstantPool<'input>,
+    ) -> Result<AttributeContent<'input>, DecodeError> {
         let name = pool.get(self.name)?.content;
         let decoder = self.content.with_context(Context::AttributeContent);
         match name.as_bytes() {
@@ -257,9 +284,82 @@ impl<'input> DecodeMany<'input, Attribute<'input>, u16> {
             let attribute = attribute?;
             if pool.retrieve(attribute.name())? == A::NAME {
                 let decoder = attribute.content.with_context(Context::AttributeContent);
-                return Ok(Some(decoder.read_into()?));
+                return decoder.read_into().map(Some).map_err(|err| attribute.within(pool, err));
             }
         }
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode<'input, T: DecodeInto<'input>>(bytes: &'input [u8]) -> T {
+        Decoder::new(bytes, Context::AttributeContent).read_into().unwrap()
+    }
+
+    #[test]
+    fn line_number_table() {
+        #[rustfmt::skip]
+        let table: LineNumberTable<'_> = decode(&[
+            0x00, 0x02,
+            // start pc and line number
+            0x00, 0x00, 0x00, 0x0a,
+            0x00, 0x04, 0x00, 0x0b,
+        ]);
+        let lines: Vec<_> = table
+            .lines()
+            .into_iter()
+            .map(|line| {
+                let line = line.unwrap();
+                (line.start().as_u32(), line.line_number())
+            })
+            .collect();
+        assert_eq!(lines, [(0, 10), (4, 11)]);
+    }
+
+    #[test]
+    fn parameter_annotations() {
+        #[rustfmt::skip]
+        let attribute: RuntimeVisibleParameterAnnotations<'_> = decode(&[
+            0x02,
+            // the first parameter has no annotations
+            0x00, 0x00,
+            // the second one has a single annotation without elements
+            0x00, 0x01, 0x00, 0x07, 0x00, 0x00,
+        ]);
+        let counts: Vec<_> = attribute
+            .parameters()
+            .into_iter()
+            .map(|parameter| parameter.unwrap().annotations().into_iter().count())
+            .collect();
+        assert_eq!(counts, [0, 1]);
+    }
+
+    #[test]
+    fn bootstrap_methods() {
+        #[rustfmt::skip]
+        let attribute: BootstrapMethods<'_> = decode(&[
+            0x00, 0x01,
+            // the method handle and two static arguments of any type
+            0x00, 0x05, 0x00, 0x02, 0x00, 0x06, 0x00, 0x07,
+        ]);
+        let method = attribute.methods().into_iter().next().unwrap().unwrap();
+        assert_eq!(method.method_ref().as_u16(), 5);
+        let arguments: Vec<_> = method
+            .arguments()
+            .into_iter()
+            .map(|argument| argument.unwrap().as_u16())
+            .collect();
+        assert_eq!(arguments, [6, 7]);
+    }
+
+    #[test]
+    fn unnamed_method_parameter() {
+        let attribute: MethodParameters<'_> = decode(&[0x01, 0x00, 0x00, 0x00, 0x10]);
+        let parameter = attribute.parameters().into_iter().next().unwrap().unwrap();
+        assert!(parameter.name().is_none());
+        assert_eq!(parameter.access_flags(), crate::AccessFlags::FINAL);
+    }
+}
@@ -108,6 +108,7 @@ impl<'input> fmt::Debug for TypeAnnotation<'input> {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TargetType {
     ClassTypeParameter,
     MethodTypeParameter,
@@ -201,6 +202,7 @@ pub enum TargetInfo<'input> {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SuperTypeIndex {
     Class,
     Interface { index: u16 },
@@ -262,6 +264,7 @@ dec_structure! {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TypePathSegmentKind {
     ArrayElement,
     InnerType,
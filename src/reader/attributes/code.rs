@@ -185,7 +185,8 @@ impl Index {
 
 impl<'input> Decode<'input> for Index {
     fn decode(decoder: &mut Decoder<'input>) -> Result<Index, DecodeError> {
-        Ok(Index::new(decoder.read()?))
+        let index: u16 = decoder.read()?;
+        Ok(Index::new(index.into()))
     }
 }
 
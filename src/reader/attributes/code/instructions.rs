@@ -22,7 +22,7 @@ impl<'input> Iterator for RawInstructions<'input> {
         let position = self.decoder.file_position() - self.start_position;
         match RawInstruction::decode(&mut self.decoder, self.start_position) {
             Ok(insn) => Some(Ok((code::Index::new(position as u32), insn))),
-            Err(err) => Some(Err(err)),
+            Err(err) => Some(Err(err.within(PathSegment::Instruction { pc: position as u32 }))),
         }
     }
 }
@@ -382,16 +382,19 @@ pub enum RawInstruction<'input> {
     TableSwitch(TableSwitch<'input>),
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// The element type of a primitive array, represented by its `atype` operand of `newarray`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
 pub enum ArrayType {
-    Boolean,
-    Char,
-    Float,
-    Double,
-    Byte,
-    Short,
-    Int,
-    Long,
+    Boolean = 4,
+    Char = 5,
+    Float = 6,
+    Double = 7,
+    Byte = 8,
+    Short = 9,
+    Int = 10,
+    Long = 11,
 }
 
 #[derive(Clone)]
@@ -484,7 +487,8 @@ impl<'input> TableSwitch<'input> {
 
     #[must_use]
     pub fn low(&self) -> i32 {
-        self.pairs.key
+        // the pairs of the instruction itself are never advanced
+        self.pairs.key as i32
     }
 
     #[must_use]
@@ -507,7 +511,8 @@ impl<'input> fmt::Debug for TableSwitch<'input> {
 #[derive(Clone)]
 pub struct TablePairs<'input> {
     decoder: Decoder<'input>,
-    key: i32,
+    /// The key of the next pair, which is wider than the keys so it can be incremented past `i32::MAX`.
+    key: i64,
     high: i32,
 }
 
@@ -515,14 +520,14 @@ impl<'input> Iterator for TablePairs<'input> {
     type Item = TablePair;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.key > self.high {
+        if self.key > i64::from(self.high) {
             return None;
         }
         let offset = self
             .decoder
             .read()
             .expect("the integer is guaranteed to be valid by now");
-        let key = self.key;
+        let key = self.key as i32;
         self.key += 1;
         Some(TablePair { key, offset })
     }
@@ -903,7 +908,7 @@ impl<'input> Decode<'input> for TablePairs<'input> {
 
         Ok(TablePairs {
             decoder: pair_decoder,
-            key: low,
+            key: i64::from(low),
             high,
         })
     }
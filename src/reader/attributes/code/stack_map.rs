@@ -27,7 +27,7 @@ impl<'input> DecodeInto<'input> for StackMapTable<'input> {
             iter: StackMapIter {
                 decoder,
                 remaining: count,
-                current_offset: 0,
+                previous_offset: None,
             },
         })
     }
@@ -47,7 +47,8 @@ impl<'input> fmt::Debug for StackMapTable<'input> {
 pub struct StackMapIter<'input> {
     decoder: Decoder<'input>,
     remaining: u16,
-    current_offset: u32,
+    /// The code index of the previous frame, `None` if no frame was read yet.
+    previous_offset: Option<u32>,
 }
 
 impl<'input> Iterator for StackMapIter<'input> {
@@ -58,9 +59,10 @@ impl<'input> Iterator for StackMapIter<'input> {
             None
         } else {
             self.remaining -= 1;
-            let bytes_remaining = self.decoder.bytes_remaining() as u32;
-            let stack_map_frame = decode_stack_map_frame(&mut self.decoder, self.current_offset);
-            self.current_offset += bytes_remaining - self.decoder.bytes_remaining() as u32;
+            let stack_map_frame = decode_stack_map_frame(&mut self.decoder, self.previous_offset);
+            if let Ok((index, _)) = &stack_map_frame {
+                self.previous_offset = Some(index.as_u32());
+            }
             Some(stack_map_frame)
         }
     }
@@ -98,46 +100,45 @@ pub enum StackMapFrame<'input> {
 
 fn decode_stack_map_frame<'input>(
     decoder: &mut Decoder<'input>,
-    current_offset: u32,
+    previous_offset: Option<u32>,
 ) -> Result<(code::Index, StackMapFrame<'input>), DecodeError> {
+    // The first frame is located at its offset delta, while every subsequent frame is located
+    // at `previous + delta + 1`.
+    let index = |delta: u16| match previous_offset {
+        Some(previous) => code::Index::new(previous + u32::from(delta) + 1),
+        None => code::Index::new(delta.into()),
+    };
+
     let frame_type: u8 = decoder.read()?;
     match frame_type {
-        0..=63 => {
-            let index = code::Index::new(frame_type.into());
-            Ok((index, StackMapFrame::Same))
-        }
+        0..=63 => Ok((index(frame_type.into()), StackMapFrame::Same)),
         64..=127 => {
-            let index = code::Index::new(u32::from(frame_type - 64) + current_offset);
-            let stack = decode_verification_type(decoder, current_offset)?;
-            Ok((index, StackMapFrame::Same1 { stack }))
+            let stack = decode_verification_type(decoder)?;
+            Ok((index((frame_type - 64).into()), StackMapFrame::Same1 { stack }))
         }
         247 => {
-            let index = code::Index::new(u32::from(decoder.read::<u16>()?) + current_offset);
-            let stack = decode_verification_type(decoder, current_offset)?;
-            Ok((index, StackMapFrame::Same1 { stack }))
+            let index = index(decoder.read()?);
+            let stack = decode_verification_type(decoder)?;
+            Ok((index, StackMapFrame::Same1Extended { stack }))
         }
         248..=250 => {
             let to_chop = 251 - frame_type;
-            let index = code::Index::new(u32::from(decoder.read::<u16>()?) + current_offset);
-            Ok((index, StackMapFrame::Chop { to_chop }))
-        }
-        251 => {
-            let index = code::Index::new(u32::from(decoder.read::<u16>()?) + current_offset);
-            Ok((index, StackMapFrame::SameExtended))
+            Ok((index(decoder.read()?), StackMapFrame::Chop { to_chop }))
         }
+        251 => Ok((index(decoder.read()?), StackMapFrame::SameExtended)),
         252..=254 => {
-            let index = code::Index::new(u32::from(decoder.read::<u16>()?) + current_offset);
-            let locals = VerificationTypeIter::new(decoder, (frame_type - 251).into(), current_offset)?;
+            let index = index(decoder.read()?);
+            let locals = VerificationTypeIter::new(decoder, (frame_type - 251).into())?;
             Ok((index, StackMapFrame::Append { locals }))
         }
         255 => {
-            let index = code::Index::new(u32::from(decoder.read::<u16>()?) + current_offset);
+            let index = index(decoder.read()?);
 
             let local_count = decoder.read()?;
-            let locals = VerificationTypeIter::new(decoder, local_count, current_offset)?;
+            let locals = VerificationTypeIter::new(decoder, local_count)?;
 
             let stack_count = decoder.read()?;
-            let stack = VerificationTypeIter::new(decoder, stack_count, current_offset)?;
+            let stack = VerificationTypeIter::new(decoder, stack_count)?;
 
             Ok((index, StackMapFrame::Full { locals, stack }))
         }
@@ -158,10 +159,7 @@ pub enum VerificationType<'input> {
     Double,
 }
 
-fn decode_verification_type<'input>(
-    decoder: &mut Decoder<'input>,
-    current_offset: u32,
-) -> Result<VerificationType<'input>, DecodeError> {
+fn decode_verification_type<'input>(decoder: &mut Decoder<'input>) -> Result<VerificationType<'input>, DecodeError> {
     let tag: u8 = decoder.read()?;
     match tag {
         0x00 => Ok(VerificationType::Top),
@@ -173,8 +171,8 @@ fn decode_verification_type<'input>(
         0x06 => Ok(VerificationType::UninitializedThis),
         0x07 => Ok(VerificationType::Object(decoder.read()?)),
         0x08 => {
-            let index = code::Index::new(current_offset + u32::from(decoder.read::<u16>()?));
-            Ok(VerificationType::UninitializedVariable(index))
+            let offset: u16 = decoder.read()?;
+            Ok(VerificationType::UninitializedVariable(code::Index::new(offset.into())))
         }
         _ => Err(DecodeError::from_decoder(DecodeErrorKind::InvalidTag, decoder)),
     }
@@ -200,15 +198,10 @@ fn skip_verification_type<'input>(decoder: &mut Decoder<'input>) -> Result<(), D
 pub struct VerificationTypeIter<'input> {
     decoder: Decoder<'input>,
     remaining: u16,
-    current_offset: u32,
 }
 
 impl<'input> VerificationTypeIter<'input> {
-    fn new(
-        decoder: &mut Decoder<'input>,
-        count: u16,
-        current_offset: u32,
-    ) -> Result<VerificationTypeIter<'input>, DecodeError> {
+    fn new(decoder: &mut Decoder<'input>, count: u16) -> Result<VerificationTypeIter<'input>, DecodeError> {
         let old_decoder = decoder.clone();
         for _ in 0..count {
             skip_verification_type(decoder)?;
@@ -216,7 +209,6 @@ impl<'input> VerificationTypeIter<'input> {
         Ok(VerificationTypeIter {
             decoder: old_decoder,
             remaining: count,
-            current_offset,
         })
     }
 }
@@ -229,10 +221,7 @@ impl<'input> Iterator for VerificationTypeIter<'input> {
             None
         } else {
             self.remaining -= 1;
-            let bytes_remaining = self.decoder.bytes_remaining() as u32;
-            let verification_type = decode_verification_type(&mut self.decoder, self.current_offset);
-            self.current_offset += bytes_remaining - self.decoder.bytes_remaining() as u32;
-            Some(verification_type)
+            Some(decode_verification_type(&mut self.decoder))
         }
     }
 }
@@ -68,3 +68,7 @@ impl<'input> fmt::Debug for SourceDebugExtension<'input> {
 dec_structure! {
     pub struct Synthetic<'input> into {}
 }
+
+impl<'input> FromAttribute<'input> for Synthetic<'input> {
+    const NAME: &'static MStr = mutf8!("Synthetic");
+}
@@ -47,7 +47,7 @@ dec_structure! {
     pub struct Require<'input> {
         index: cpool::Index<cpool::Module<'input>>,
         flags: AccessFlags,
-        version: cpool::Index<cpool::Utf8<'input>>,
+        version: Option<cpool::Index<cpool::Utf8<'input>>>,
     }
 }
 
@@ -34,10 +34,38 @@ impl<'input> Class<'input> {
     /// # Ok::<(), noak::error::DecodeError>(())
     /// ```
     pub fn new(v: &'input [u8]) -> Result<Class<'input>, DecodeError> {
+        Class::read(v, None)
+    }
+
+    /// Initializes a class reader which tolerates malformed parts of the class that the JVM ignores.
+    ///
+    /// Constant pool entries which aren't valid modified UTF-8 are skipped instead of rejected,
+    /// as the JVM only reports them once they are used.
+    /// Retrieving a skipped entry results in an error.
+    /// Every part that was skipped is returned as a warning,
+    /// and so is every name or descriptor which refers to a skipped entry.
+    ///
+    /// ```no_run
+    /// use noak::reader::Class;
+    ///
+    /// # let data = &[];
+    /// let (class, warnings) = Class::new_lenient(data)?;
+    /// for warning in warnings {
+    ///     println!("skipped: {}", warning);
+    /// }
+    /// # Ok::<(), noak::error::DecodeError>(())
+    /// ```
+    pub fn new_lenient(v: &'input [u8]) -> Result<(Class<'input>, Vec<DecodeError>), DecodeError> {
+        let mut warnings = Vec::new();
+        let class = Class::read(v, Some(&mut warnings))?;
+        Ok((class, warnings))
+    }
+
+    fn read(v: &'input [u8], mut warnings: Option<&mut Vec<DecodeError>>) -> Result<Class<'input>, DecodeError> {
         let mut decoder = Decoder::new(v, Context::Start);
         let version = read_header(&mut decoder)?;
         decoder.set_context(Context::ConstantPool);
-        let pool = decoder.read()?;
+        let pool = ConstantPool::decode_lenient(&mut decoder, warnings.as_deref_mut())?;
         decoder.set_context(Context::ClassInfo);
         let access_flags = decoder.read()?;
         let this_class = decoder.read()?;
@@ -52,7 +80,7 @@ impl<'input> Class<'input> {
         let attributes = decoder.read()?;
         let buffer_size = decoder.file_position();
 
-        Ok(Class {
+        let class = Class {
             version,
             pool,
             access_flags,
@@ -63,7 +91,56 @@ impl<'input> Class<'input> {
             methods,
             attributes,
             buffer_size,
-        })
+        };
+        if let Some(warnings) = warnings {
+            class.report_skipped_references(warnings);
+        }
+        Ok(class)
+    }
+
+    /// Records a warning for every reference to a constant pool entry that was skipped,
+    /// as the entry can't be read even though it is used.
+    ///
+    /// Only the names and descriptors in the constant pool and of members, and the names of attributes
+    /// are checked, other references fail once they are read.
+    fn report_skipped_references(&self, warnings: &mut Vec<DecodeError>) {
+        let mut references = SkippedReferences {
+            pool: &self.pool,
+            warnings,
+        };
+
+        for (index, item) in self.pool.iter_indices() {
+            let path = [PathSegment::ConstantPool { index: index.as_u16() }];
+            match item {
+                cpool::Item::Class(item) => references.check(&[item.name], &path),
+                cpool::Item::String(item) => references.check(&[item.string], &path),
+                cpool::Item::NameAndType(item) => references.check(&[item.name, item.descriptor], &path),
+                cpool::Item::MethodType(item) => references.check(&[item.descriptor], &path),
+                cpool::Item::Module(item) => references.check(&[item.name], &path),
+                cpool::Item::Package(item) => references.check(&[item.name], &path),
+                _ => {}
+            }
+        }
+
+        for (index, field) in (0..).zip(self.fields()) {
+            let Ok(field) = field else { break };
+            let path = [PathSegment::Field { index, name: None }];
+            references.check(&[field.name(), field.descriptor()], &path);
+            references.check_attributes(field.attributes(), &path);
+        }
+
+        for (index, method) in (0..).zip(self.methods()) {
+            let Ok(method) = method else { break };
+            let path = [PathSegment::Method {
+                index,
+                name: None,
+                descriptor: None,
+            }];
+            references.check(&[method.name(), method.descriptor()], &path);
+            references.check_attributes(method.attributes(), &path);
+        }
+
+        references.check_attributes(self.attributes(), &[]);
     }
 
     /// Returns the class version.
@@ -214,6 +291,32 @@ impl<'input> Class<'input> {
     }
 }
 
+struct SkippedReferences<'a, 'input> {
+    pool: &'a ConstantPool<'input>,
+    warnings: &'a mut Vec<DecodeError>,
+}
+
+impl<'a, 'input> SkippedReferences<'a, 'input> {
+    fn check(&mut self, references: &[cpool::Index<cpool::Utf8<'input>>], path: &[PathSegment]) {
+        if references.iter().any(|index| self.pool.is_skipped(index.as_u16())) {
+            let warning = DecodeError::with_context(DecodeErrorKind::InvalidMutf8, Context::ConstantPool);
+            let warning = path
+                .iter()
+                .rev()
+                .fold(warning, |warning, segment| warning.within(segment.clone()));
+            self.warnings.push(warning);
+        }
+    }
+
+    fn check_attributes(&mut self, attributes: DecodeMany<'input, Attribute<'input>, u16>, path: &[PathSegment]) {
+        for (index, attribute) in (0..).zip(attributes) {
+            let Ok(attribute) = attribute else { break };
+            let path = [path, &[PathSegment::Attribute { index, name: None }]].concat();
+            self.check(&[attribute.name()], &path);
+        }
+    }
+}
+
 impl<'input> fmt::Debug for Class<'input> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Class").finish()
@@ -261,4 +364,128 @@ mod tests {
 
         assert!(read_header(&mut decoder).is_err());
     }
+
+    #[test]
+    fn errors_have_paths() {
+        use crate::reader::attributes::Code;
+        use crate::writer::{encoding::*, ClassWriter};
+
+        let bytes = ClassWriter::new()
+            .version(Version::V8)
+            .unwrap()
+            .access_flags(AccessFlags::PUBLIC)
+            .unwrap()
+            .this_class("Example")
+            .unwrap()
+            .super_class("java/lang/Object")
+            .unwrap()
+            .interfaces(|_| Ok(()))
+            .unwrap()
+            .fields(|_| Ok(()))
+            .unwrap()
+            .methods(|methods| {
+                methods.begin(|method| {
+                    method
+                        .access_flags(AccessFlags::PUBLIC | AccessFlags::STATIC)?
+                        .name("run")?
+                        .descriptor("()V")?
+                        .attributes(|attributes| {
+                            attributes
+                                .begin(|attribute| {
+                                    // the exception count is cut off
+                                    attribute.content("Exceptions", |ctx| {
+                                        ctx.encoder().write(0u8)?;
+                                        Ok(())
+                                    })
+                                })?
+                                .begin(|attribute| {
+                                    attribute.content("Code", |ctx| {
+                                        // a nop followed by an invalid opcode
+                                        ctx.encoder()
+                                            .write(0u16)?
+                                            .write(0u16)?
+                                            .write(2u32)?
+                                            .write([0x00u8, 0xff].as_ref())?
+                                            .write(0u16)?
+                                            .write(0u16)?;
+                                        Ok(())
+                                    })
+                                })?;
+                            Ok(())
+                        })
+                })?;
+                Ok(())
+            })
+            .unwrap()
+            .attributes(|_| Ok(()))
+            .unwrap()
+            .into_bytes()
+            .unwrap();
+
+        let class = Class::new(&bytes).unwrap();
+        let method = class.methods().into_iter().next().unwrap().unwrap();
+        let exceptions = method.attributes().into_iter().next().unwrap().unwrap();
+        let error = exceptions.read_content(class.pool()).unwrap_err();
+        assert_eq!(error.path().to_string(), "attribute `Exceptions`");
+
+        let code: Code<'_> = method.attributes().find_attribute(class.pool()).unwrap().unwrap();
+        let error = code.raw_instructions().nth(1).unwrap().unwrap_err();
+        assert_eq!(error.path().to_string(), "instruction at pc 1");
+    }
+
+    #[test]
+    fn lenient_reports_skipped_references() {
+        use crate::writer::ClassWriter;
+
+        let mut bytes = ClassWriter::new()
+            .version(Version::V8)
+            .unwrap()
+            .access_flags(AccessFlags::PUBLIC)
+            .unwrap()
+            .this_class("Example")
+            .unwrap()
+            .super_class("java/lang/Object")
+            .unwrap()
+            .interfaces(|_| Ok(()))
+            .unwrap()
+            .fields(|fields| {
+                fields.begin(|field| {
+                    field
+                        .access_flags(AccessFlags::PRIVATE)?
+                        .name("broken")?
+                        .descriptor("I")?
+                        .attributes(|_| Ok(()))
+                })?;
+                Ok(())
+            })
+            .unwrap()
+            .methods(|_| Ok(()))
+            .unwrap()
+            .attributes(|_| Ok(()))
+            .unwrap()
+            .into_bytes()
+            .unwrap();
+
+        // make the name of the field invalid modified UTF-8
+        let position = bytes.windows(6).position(|window| window == b"broken").unwrap();
+        bytes[position] = 0xFF;
+
+        assert!(Class::new(&bytes).is_err());
+        let (class, warnings) = Class::new_lenient(&bytes).unwrap();
+        let warnings: Vec<_> = warnings
+            .iter()
+            .map(|warning| (warning.kind(), warning.path().to_string()))
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                (DecodeErrorKind::InvalidMutf8, "constant pool entry #5".to_owned()),
+                (DecodeErrorKind::InvalidMutf8, "field 0".to_owned()),
+            ]
+        );
+
+        let field = class.fields().into_iter().next().unwrap().unwrap();
+        let error = class.pool().get(field.name()).unwrap_err();
+        assert_eq!(error.kind(), DecodeErrorKind::InvalidMutf8);
+    }
 }
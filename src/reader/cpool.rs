@@ -10,6 +10,8 @@ use std::{fmt, hash::Hash, marker::PhantomData, num::NonZeroU16};
 #[derive(Clone)]
 pub struct ConstantPool<'input> {
     content: Vec<Option<Item<'input>>>,
+    /// The ascending indices of the entries skipped because of their invalid content.
+    skipped: Vec<u16>,
 }
 
 impl<'input> ConstantPool<'input> {
@@ -27,6 +29,12 @@ impl<'input> ConstantPool<'input> {
                 return I::try_from_item(item)
                     .ok_or_else(|| DecodeError::with_context(DecodeErrorKind::TagMismatch, Context::ConstantPool));
             }
+            if self.is_skipped(at.as_u16()) {
+                return Err(DecodeError::with_context(
+                    DecodeErrorKind::InvalidMutf8,
+                    Context::ConstantPool,
+                ));
+            }
         }
 
         Err(DecodeError::with_context(
@@ -47,8 +55,14 @@ impl<'input> ConstantPool<'input> {
     }
 }
 
-impl<'input> Decode<'input> for ConstantPool<'input> {
-    fn decode(decoder: &mut Decoder<'input>) -> Result<ConstantPool<'input>, DecodeError> {
+impl<'input> ConstantPool<'input> {
+    /// Decodes the constant pool, skipping entries with invalid content if warnings are collected.
+    ///
+    /// Skipped entries are treated like the unusable entries following longs and doubles.
+    pub(crate) fn decode_lenient(
+        decoder: &mut Decoder<'input>,
+        mut warnings: Option<&mut Vec<DecodeError>>,
+    ) -> Result<ConstantPool<'input>, DecodeError> {
         decoder.set_context(Context::ConstantPool);
         let length = decoder.read::<u16>()?;
         if length == 0 {
@@ -56,8 +70,30 @@ impl<'input> Decode<'input> for ConstantPool<'input> {
         }
         let length = length as usize - 1;
         let mut content = Vec::with_capacity(length);
+        let mut skipped = Vec::new();
         while content.len() < length {
-            let item = decoder.read()?;
+            let start = decoder.clone();
+            let item = match decoder.read() {
+                Ok(item) => item,
+                Err(err) => match warnings.as_deref_mut() {
+                    Some(warnings) if err.kind() == DecodeErrorKind::InvalidMutf8 => {
+                        // the tag and length were already read successfully, so the entry can be skipped
+                        *decoder = start.clone();
+                        decoder.advance(1)?;
+                        let len: u16 = decoder.read()?;
+                        decoder.advance(len as usize)?;
+                        let index = content.len() as u16 + 1;
+                        warnings.push(
+                            DecodeError::from_decoder(DecodeErrorKind::InvalidMutf8, &start)
+                                .within(PathSegment::ConstantPool { index }),
+                        );
+                        content.push(None);
+                        skipped.push(index);
+                        continue;
+                    }
+                    _ => return Err(err),
+                },
+            };
             let push_extra = matches!(item, Item::Long(_) | Item::Double(_));
 
             content.push(Some(item));
@@ -66,7 +102,18 @@ impl<'input> Decode<'input> for ConstantPool<'input> {
             }
         }
 
-        Ok(ConstantPool { content })
+        Ok(ConstantPool { content, skipped })
+    }
+
+    /// Whether an entry was skipped by [`decode_lenient`](ConstantPool::decode_lenient).
+    pub(crate) fn is_skipped(&self, index: u16) -> bool {
+        self.skipped.binary_search(&index).is_ok()
+    }
+}
+
+impl<'input> Decode<'input> for ConstantPool<'input> {
+    fn decode(decoder: &mut Decoder<'input>) -> Result<ConstantPool<'input>, DecodeError> {
+        ConstantPool::decode_lenient(decoder, None)
     }
 }
 
@@ -117,7 +164,7 @@ impl<I> Eq for Index<I> {}
 
 impl<I> PartialOrd for Index<I> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.index.cmp(&other.index))
+        Some(self.cmp(other))
     }
 }
 
@@ -371,6 +418,7 @@ pub struct Package<'input> {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MethodKind {
     GetField,
     GetStatic,
@@ -486,7 +534,10 @@ mod tests {
             })),
         ];
 
-        let pool = ConstantPool { content };
+        let pool = ConstantPool {
+            content,
+            skipped: Vec::new(),
+        };
         assert_eq!(pool.get(Index::new(1).unwrap()), Ok(&Integer { value: 2 }));
         assert_eq!(pool.get(Index::new(2).unwrap()), Ok(&Long { value: 3 }));
         assert_eq!(pool.get(Index::new(4).unwrap()), Ok(&Integer { value: 4 }));
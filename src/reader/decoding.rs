@@ -132,6 +132,11 @@ impl<'input> fmt::Debug for Decoder<'input> {
 
 pub trait Decode<'input>: Sized + 'input {
     fn decode(decoder: &mut Decoder<'input>) -> Result<Self, DecodeError>;
+
+    /// Decodes the item at an index of a [`DecodeMany`], which may add the item to the path of an error.
+    fn decode_item(decoder: &mut Decoder<'input>, _index: u16) -> Result<Self, DecodeError> {
+        Self::decode(decoder)
+    }
 }
 
 pub trait DecodeInto<'input>: Sized + 'input {
@@ -167,6 +172,8 @@ impl_decode! {
 pub struct DecodeManyIter<'input, T, Count> {
     decoder: Decoder<'input>,
     remaining: Count,
+    /// The index of the next item.
+    index: u16,
     _marker: PhantomData<fn() -> T>,
 }
 
@@ -187,6 +194,7 @@ where
         Ok(DecodeManyIter {
             decoder: old_decoder,
             remaining: count,
+            index: 0,
             _marker: PhantomData,
         })
     }
@@ -202,6 +210,7 @@ where
         Ok(DecodeManyIter {
             decoder,
             remaining,
+            index: 0,
             _marker: PhantomData,
         })
     }
@@ -216,7 +225,11 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.remaining.decrement() {
-            ControlFlow::Continue(()) => Some(self.decoder.read()),
+            ControlFlow::Continue(()) => {
+                let index = self.index;
+                self.index = self.index.wrapping_add(1);
+                Some(T::decode_item(&mut self.decoder, index))
+            }
             ControlFlow::Break(()) => None,
         }
     }
@@ -238,6 +251,7 @@ impl<'input, T, Count: Countdown> Clone for DecodeManyIter<'input, T, Count> {
         DecodeManyIter {
             decoder: self.decoder.clone(),
             remaining: self.remaining,
+            index: self.index,
             _marker: PhantomData,
         }
     }
@@ -387,6 +401,7 @@ macro_rules! dec_structure {
                 $field_name:ident : $field_type:ty
             ),* $(,)?
         }
+        $(item_segment: $segment:path;)?
     ) => {
         $(#[$meta])*
         #[derive(Clone)]
@@ -408,7 +423,7 @@ macro_rules! dec_structure {
             )*
         }
 
-        $crate::reader::decoding::dec_structure!(@decode $($into)? => $struct_name; $($field_name),*);
+        $crate::reader::decoding::dec_structure!(@decode $($into)? => $struct_name; $($field_name),*; $($segment)?);
 
         impl<'input> std::fmt::Debug for $struct_name<'input> {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -416,7 +431,7 @@ macro_rules! dec_structure {
             }
         }
     };
-    (@decode => $struct_name:ident; $($field_name:ident),*) => {
+    (@decode => $struct_name:ident; $($field_name:ident),*; $($segment:path)?) => {
         impl<'input> $crate::reader::decoding::Decode<'input> for $struct_name<'input> {
             fn decode(decoder: &mut $crate::reader::decoding::Decoder<'input>) -> Result<Self, $crate::error::DecodeError> {
                 Ok(Self {
@@ -424,9 +439,18 @@ macro_rules! dec_structure {
                     _marker: std::marker::PhantomData,
                 })
             }
+
+            $(
+                fn decode_item(
+                    decoder: &mut $crate::reader::decoding::Decoder<'input>,
+                    index: u16,
+                ) -> Result<Self, $crate::error::DecodeError> {
+                    Self::decode(decoder).map_err(|err| err.within($segment(index)))
+                }
+            )?
         }
     };
-    (@decode into => $struct_name:ident; $($field_name:ident),*) => {
+    (@decode into => $struct_name:ident; $($field_name:ident),*;) => {
         impl<'input> $crate::reader::decoding::DecodeInto<'input> for $struct_name<'input> {
             #[allow(unused_variables, unused_mut)]
             fn decode_into(mut decoder: $crate::reader::decoding::Decoder<'input>) -> Result<Self, $crate::error::DecodeError> {
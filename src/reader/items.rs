@@ -1,3 +1,4 @@
+use crate::error::PathSegment;
 use crate::header::AccessFlags;
 use crate::reader::decoding::*;
 use crate::reader::{cpool, Attribute};
@@ -9,6 +10,7 @@ dec_structure! {
         descriptor: cpool::Index<cpool::Utf8<'input>>,
         attributes: DecodeMany<'input, Attribute<'input>, u16>,
     }
+    item_segment: field_segment;
 }
 
 dec_structure! {
@@ -18,4 +20,17 @@ dec_structure! {
         descriptor: cpool::Index<cpool::Utf8<'input>>,
         attributes: DecodeMany<'input, Attribute<'input>, u16>,
     }
+    item_segment: method_segment;
+}
+
+fn field_segment(index: u16) -> PathSegment {
+    PathSegment::Field { index, name: None }
+}
+
+fn method_segment(index: u16) -> PathSegment {
+    PathSegment::Method {
+        index,
+        name: None,
+        descriptor: None,
+    }
 }
@@ -0,0 +1,1003 @@
+//! Detection of reflective accesses and resource lookups, as needed by native-image compilers and shrinkers.
+//!
+//! [`scan`] looks for calls of `Class.forName`, `ClassLoader.loadClass`, the member lookups of `Class`
+//! (e.g. `getMethod` or `getDeclaredField`), `ServiceLoader.load`, the `getResource` methods of `Class` and
+//! `ClassLoader`, and the `find` methods of `MethodHandles.Lookup`.
+//! Their arguments are resolved if they are constants, either passed directly or through local variables,
+//! arrays of classes and method types. Calling `getClass()` on `this` is assumed to return the class itself.
+//! The values of local variables are forgotten wherever control flow merges.
+//!
+//! The findings can be written as the `reflect-config.json` and `resource-config.json` files of GraalVM
+//! native-image with [`reflect_config`] and [`resource_config`].
+//!
+//! ```
+//! use noak::reader;
+//! use noak::reflect::{self, Usage};
+//! use noak::tree::{Attribute, Class, Code, Constant, Instruction, MemberRef, Method};
+//! use noak::AccessFlags;
+//!
+//! let get_resource = MemberRef {
+//!     class: "java/lang/Class".into(),
+//!     name: "getResource".into(),
+//!     descriptor: "(Ljava/lang/String;)Ljava/net/URL;".into(),
+//! };
+//! let class = Class {
+//!     methods: vec![Method {
+//!         access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC,
+//!         name: "config".into(),
+//!         descriptor: "()Ljava/net/URL;".into(),
+//!         attributes: vec![Attribute::Code(Code {
+//!             max_stack: 2,
+//!             max_locals: 1,
+//!             instructions: vec![
+//!                 Instruction::LdC { constant: Constant::String("config.properties".into()) },
+//!                 Instruction::AStore0,
+//!                 Instruction::LdC { constant: Constant::Class("com/example/Main".into()) },
+//!                 Instruction::ALoad0,
+//!                 Instruction::InvokeVirtual { method: get_resource },
+//!                 Instruction::AReturn,
+//!             ],
+//!             exception_handlers: Vec::new(),
+//!             attributes: Vec::new(),
+//!         })],
+//!     }],
+//!     ..Class::new("com/example/Main")
+//! };
+//! let bytes = class.to_bytes()?;
+//! let findings = reflect::scan(&reader::Class::new(&bytes)?)?;
+//!
+//! assert_eq!(
+//!     findings[0].usage,
+//!     Usage::Resource {
+//!         name: Some("com/example/config.properties".into())
+//!     }
+//! );
+//! assert!(reflect::resource_config(&findings).contains(r#"{"pattern": "\\Qcom/example/config.properties\\E"}"#));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::descriptor::{BaseType, MethodDescriptor, TypeDescriptor};
+use crate::error::*;
+use crate::header::AccessFlags;
+use crate::mutf8::{MStr, MString};
+use crate::reader::attributes::{Code, Index, RawInstruction};
+use crate::reader::cpool::{self, ConstantPool, Item};
+use crate::reader::Class;
+use crate::tree::NameAndType;
+
+/// A reflective access or resource lookup.
+///
+/// Classes are named like in Java source code, e.g. `java.lang.String[]` or `int`,
+/// except that nested classes are separated by `$`. Resources are named by their absolute path.
+/// Unknown arguments are `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Usage {
+    /// A class is loaded by its name.
+    Class { name: Option<MString> },
+    /// One or all constructors are looked up.
+    Constructor {
+        class: Option<MString>,
+        parameters: Option<Vec<MString>>,
+        /// Whether non-public constructors can be found.
+        declared: bool,
+    },
+    /// One or all methods are looked up, all of them if the name is unknown.
+    Method {
+        class: Option<MString>,
+        name: Option<MString>,
+        parameters: Option<Vec<MString>>,
+        /// Whether non-public methods can be found.
+        declared: bool,
+    },
+    /// One or all fields are looked up, all of them if the name is unknown.
+    Field {
+        class: Option<MString>,
+        name: Option<MString>,
+        /// Whether non-public fields can be found.
+        declared: bool,
+    },
+    /// The providers of a service are loaded.
+    Service { service: Option<MString> },
+    /// A resource is loaded.
+    Resource { name: Option<MString> },
+}
+
+/// A usage found in the code of a method.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Finding {
+    /// The name of the class containing the method.
+    pub class: MString,
+    pub method: NameAndType,
+    /// The position of the invoke instruction.
+    pub pc: Index,
+    pub usage: Usage,
+}
+
+/// Scans the code of all methods of a class for reflective accesses and resource lookups.
+pub fn scan(class: &Class<'_>) -> Result<Vec<Finding>, DecodeError> {
+    let pool = class.pool();
+    let name = pool.retrieve(class.this_class())?.name;
+    let mut findings = Vec::new();
+    for method in class.methods() {
+        let method = method?;
+        if let Some(code) = method.attributes().find_attribute::<Code<'_>>(pool)? {
+            let mut scanner = Scanner {
+                pool,
+                class: name,
+                method: NameAndType {
+                    name: pool.retrieve(method.name())?.into(),
+                    descriptor: pool.retrieve(method.descriptor())?.into(),
+                },
+                stack: Vec::new(),
+                locals: HashMap::new(),
+                arrays: Vec::new(),
+                findings: &mut findings,
+            };
+            scanner.code(&code, !method.access_flags().contains(AccessFlags::STATIC))?;
+        }
+    }
+    Ok(findings)
+}
+
+/// Writes the classes and members which are accessed reflectively as a `reflect-config.json` file.
+///
+/// Findings whose class is unknown are skipped.
+/// If the name or parameters of a member are unknown, all members of its kind are included.
+#[must_use]
+pub fn reflect_config(findings: &[Finding]) -> String {
+    #[derive(Default)]
+    struct ClassConfig {
+        flags: BTreeSet<&'static str>,
+        methods: BTreeSet<(MString, Vec<MString>)>,
+        fields: BTreeSet<MString>,
+    }
+
+    fn all(declared: bool, public: &'static str, any: &'static str) -> &'static str {
+        if declared {
+            any
+        } else {
+            public
+        }
+    }
+
+    let mut classes: BTreeMap<MString, ClassConfig> = BTreeMap::new();
+    for finding in findings {
+        match &finding.usage {
+            Usage::Class { name: Some(name) } => {
+                classes.entry(name.clone()).or_default();
+            }
+            Usage::Constructor {
+                class: Some(class),
+                parameters,
+                declared,
+            } => {
+                let config = classes.entry(class.clone()).or_default();
+                match parameters {
+                    Some(parameters) => {
+                        config.methods.insert(("<init>".into(), parameters.clone()));
+                    }
+                    None => {
+                        let flag = all(*declared, "allPublicConstructors", "allDeclaredConstructors");
+                        config.flags.insert(flag);
+                    }
+                }
+            }
+            Usage::Method {
+                class: Some(class),
+                name,
+                parameters,
+                declared,
+            } => {
+                let config = classes.entry(class.clone()).or_default();
+                match (name, parameters) {
+                    (Some(name), Some(parameters)) => {
+                        config.methods.insert((name.clone(), parameters.clone()));
+                    }
+                    _ => {
+                        config
+                            .flags
+                            .insert(all(*declared, "allPublicMethods", "allDeclaredMethods"));
+                    }
+                }
+            }
+            Usage::Field {
+                class: Some(class),
+                name,
+                declared,
+            } => {
+                let config = classes.entry(class.clone()).or_default();
+                match name {
+                    Some(name) => {
+                        config.fields.insert(name.clone());
+                    }
+                    None => {
+                        config
+                            .flags
+                            .insert(all(*declared, "allPublicFields", "allDeclaredFields"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = String::from("[");
+    for (index, (name, config)) in classes.iter().enumerate() {
+        out.push_str(if index == 0 { "\n" } else { ",\n" });
+        out.push_str("  {\n    \"name\": ");
+        json_string(&mut out, name.chars());
+        for flag in &config.flags {
+            let _ = write!(out, ",\n    \"{flag}\": true");
+        }
+        if !config.methods.is_empty() {
+            out.push_str(",\n    \"methods\": [");
+            for (index, (name, parameters)) in config.methods.iter().enumerate() {
+                out.push_str(if index == 0 { "\n" } else { ",\n" });
+                out.push_str("      {\"name\": ");
+                json_string(&mut out, name.chars());
+                out.push_str(", \"parameterTypes\": [");
+                for (index, parameter) in parameters.iter().enumerate() {
+                    if index != 0 {
+                        out.push_str(", ");
+                    }
+                    json_string(&mut out, parameter.chars());
+                }
+                out.push_str("]}");
+            }
+            out.push_str("\n    ]");
+        }
+        if !config.fields.is_empty() {
+            out.push_str(",\n    \"fields\": [");
+            for (index, name) in config.fields.iter().enumerate() {
+                out.push_str(if index == 0 { "\n" } else { ",\n" });
+                out.push_str("      {\"name\": ");
+                json_string(&mut out, name.chars());
+                out.push('}');
+            }
+            out.push_str("\n    ]");
+        }
+        out.push_str("\n  }");
+    }
+    out.push_str(if classes.is_empty() { "]\n" } else { "\n]\n" });
+    out
+}
+
+/// Writes the resources and service descriptors which are loaded as a `resource-config.json` file.
+///
+/// Findings whose resource or service is unknown are skipped.
+#[must_use]
+pub fn resource_config(findings: &[Finding]) -> String {
+    let mut resources = BTreeSet::new();
+    for finding in findings {
+        match &finding.usage {
+            Usage::Resource { name: Some(name) } => {
+                resources.insert(name.clone());
+            }
+            Usage::Service { service: Some(service) } => {
+                let mut name = MString::from("META-INF/services/");
+                for ch in service.chars() {
+                    // unpaired surrogates can't appear in the name of a class
+                    name.push(ch.unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                resources.insert(name);
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = String::from("{\n  \"resources\": {\n    \"includes\": [");
+    for (index, name) in resources.iter().enumerate() {
+        out.push_str(if index == 0 { "\n" } else { ",\n" });
+        out.push_str("      {\"pattern\": ");
+        // the name is quoted to match it literally
+        let pattern = "\\Q".chars().chain(name.chars().flatten()).chain("\\E".chars());
+        json_string(&mut out, pattern.map(Ok));
+        out.push('}');
+    }
+    out.push_str(if resources.is_empty() { "]\n" } else { "\n    ]\n" });
+    out.push_str("  }\n}\n");
+    out
+}
+
+fn json_string(out: &mut String, chars: impl Iterator<Item = Result<char, u32>>) {
+    out.push('"');
+    for ch in chars {
+        match ch {
+            Ok('"') => out.push_str("\\\""),
+            Ok('\\') => out.push_str("\\\\"),
+            Ok(ch) if ch < ' ' => {
+                let _ = write!(out, "\\u{:04x}", u32::from(ch));
+            }
+            Ok(ch) => out.push(ch),
+            Err(surrogate) => {
+                let _ = write!(out, "\\u{surrogate:04x}");
+            }
+        }
+    }
+    out.push('"');
+}
+
+/// A value on the operand stack or in a local variable.
+#[derive(Debug, Clone)]
+enum Value {
+    Unknown,
+    Int(i32),
+    String(MString),
+    Class(MString),
+    /// An array of classes, as an index into the arrays of the scanner.
+    Array(usize),
+    /// The parameters of a method type.
+    MethodType(Vec<MString>),
+    This,
+}
+
+impl Value {
+    fn string(&self) -> Option<MString> {
+        match self {
+            Value::String(string) => Some(string.clone()),
+            _ => None,
+        }
+    }
+
+    fn class(&self) -> Option<MString> {
+        match self {
+            Value::Class(class) => Some(class.clone()),
+            _ => None,
+        }
+    }
+}
+
+struct Scanner<'a, 'input> {
+    pool: &'a ConstantPool<'input>,
+    class: &'input MStr,
+    method: NameAndType,
+    stack: Vec<Value>,
+    locals: HashMap<u16, Value>,
+    arrays: Vec<Vec<Option<MString>>>,
+    findings: &'a mut Vec<Finding>,
+}
+
+impl<'a, 'input> Scanner<'a, 'input> {
+    fn code(&mut self, code: &Code<'input>, has_this: bool) -> Result<(), DecodeError> {
+        use RawInstruction::*;
+
+        let instructions = code.raw_instructions().collect::<Result<Vec<_>, _>>()?;
+        let mut targets: HashSet<u32> = code
+            .exception_handlers()
+            .map(|handler| handler.handler().as_u32())
+            .collect();
+        for (pc, instruction) in &instructions {
+            targets.extend(jump_targets(*pc, instruction));
+        }
+        // `this` can't change unless the first local variable is overwritten
+        let keeps_this = has_this
+            && !instructions
+                .iter()
+                .any(|(_, instruction)| matches!(instruction, AStore0 | AStore { index: 0 } | AStoreW { index: 0 }));
+
+        if has_this {
+            self.locals.insert(0, Value::This);
+        }
+        for (pc, instruction) in instructions {
+            if targets.contains(&pc.as_u32()) {
+                self.stack.clear();
+                self.locals.clear();
+                if keeps_this {
+                    self.locals.insert(0, Value::This);
+                }
+            }
+            self.instruction(pc, instruction)?;
+        }
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap_or(Value::Unknown)
+    }
+
+    fn store(&mut self, index: u16, value: Value) {
+        self.locals.insert(index, value);
+    }
+
+    /// Forgets the values of local variables overwritten by a primitive.
+    fn store_primitive(&mut self, index: u16, wide: bool) {
+        self.pop();
+        self.locals.remove(&index);
+        // there is no second slot after the last one, such a store is rejected by the verifier anyway
+        if let Some(next) = index.checked_add(1).filter(|_| wide) {
+            self.locals.remove(&next);
+        }
+    }
+
+    fn load(&mut self, index: u16) {
+        let value = self.locals.get(&index).cloned().unwrap_or(Value::Unknown);
+        self.stack.push(value);
+    }
+
+    fn instruction(&mut self, pc: Index, instruction: RawInstruction<'input>) -> Result<(), DecodeError> {
+        use RawInstruction::*;
+
+        match instruction {
+            AConstNull
+            | DConst0
+            | DConst1
+            | FConst0
+            | FConst1
+            | FConst2
+            | LConst0
+            | LConst1
+            | LdC2W { .. }
+            | New { .. } => {
+                self.stack.push(Value::Unknown);
+            }
+            DLoad { .. } | DLoadW { .. } | DLoad0 | DLoad1 | DLoad2 | DLoad3 => self.stack.push(Value::Unknown),
+            FLoad { .. } | FLoadW { .. } | FLoad0 | FLoad1 | FLoad2 | FLoad3 => self.stack.push(Value::Unknown),
+            ILoad { .. } | ILoadW { .. } | ILoad0 | ILoad1 | ILoad2 | ILoad3 => self.stack.push(Value::Unknown),
+            LLoad { .. } | LLoadW { .. } | LLoad0 | LLoad1 | LLoad2 | LLoad3 => self.stack.push(Value::Unknown),
+            IConstM1 => self.stack.push(Value::Int(-1)),
+            IConst0 => self.stack.push(Value::Int(0)),
+            IConst1 => self.stack.push(Value::Int(1)),
+            IConst2 => self.stack.push(Value::Int(2)),
+            IConst3 => self.stack.push(Value::Int(3)),
+            IConst4 => self.stack.push(Value::Int(4)),
+            IConst5 => self.stack.push(Value::Int(5)),
+            BIPush { value } => self.stack.push(Value::Int(value.into())),
+            SIPush { value } => self.stack.push(Value::Int(value.into())),
+            LdC { index } | LdCW { index } => {
+                let value = self.constant(index)?;
+                self.stack.push(value);
+            }
+            ALoad { index } => self.load(index.into()),
+            ALoadW { index } => self.load(index),
+            ALoad0 => self.load(0),
+            ALoad1 => self.load(1),
+            ALoad2 => self.load(2),
+            ALoad3 => self.load(3),
+            AStore { index } => {
+                let value = self.pop();
+                self.store(index.into(), value);
+            }
+            AStoreW { index } => {
+                let value = self.pop();
+                self.store(index, value);
+            }
+            AStore0 | AStore1 | AStore2 | AStore3 => {
+                let index = match instruction {
+                    AStore0 => 0,
+                    AStore1 => 1,
+                    AStore2 => 2,
+                    _ => 3,
+                };
+                let value = self.pop();
+                self.store(index, value);
+            }
+            FStore { index } | IStore { index } => self.store_primitive(index.into(), false),
+            FStoreW { index } | IStoreW { index } => self.store_primitive(index, false),
+            FStore0 | IStore0 => self.store_primitive(0, false),
+            FStore1 | IStore1 => self.store_primitive(1, false),
+            FStore2 | IStore2 => self.store_primitive(2, false),
+            FStore3 | IStore3 => self.store_primitive(3, false),
+            DStore { index } | LStore { index } => self.store_primitive(index.into(), true),
+            DStoreW { index } | LStoreW { index } => self.store_primitive(index, true),
+            DStore0 | LStore0 => self.store_primitive(0, true),
+            DStore1 | LStore1 => self.store_primitive(1, true),
+            DStore2 | LStore2 => self.store_primitive(2, true),
+            DStore3 | LStore3 => self.store_primitive(3, true),
+            Dup => {
+                let value = self.pop();
+                self.stack.push(value.clone());
+                self.stack.push(value);
+            }
+            Pop => {
+                self.pop();
+            }
+            CheckCast { .. } | IInc { .. } | IIncW { .. } | Nop => {}
+            ANewArray { index } => {
+                let count = self.pop();
+                let class = self.pool.retrieve(index)?.name;
+                let value = match count {
+                    Value::Int(count @ 0..=255) if class == "java/lang/Class" => {
+                        self.arrays.push(vec![None; count as usize]);
+                        Value::Array(self.arrays.len() - 1)
+                    }
+                    _ => Value::Unknown,
+                };
+                self.stack.push(value);
+            }
+            AAStore => {
+                let value = self.pop();
+                let index = self.pop();
+                let array = self.pop();
+                if let (Value::Array(array), Value::Int(index)) = (array, index) {
+                    if let Some(element) = usize::try_from(index)
+                        .ok()
+                        .and_then(|index| self.arrays[array].get_mut(index))
+                    {
+                        *element = value.class();
+                    }
+                }
+            }
+            AALoad => {
+                self.pop();
+                self.pop();
+                self.stack.push(Value::Unknown);
+            }
+            GetStatic { index } => {
+                let field = self.pool.retrieve(index)?;
+                let value = match primitive_class(field.class.name) {
+                    Some(primitive) if field.name_and_type.name == "TYPE" => Value::Class(primitive.into()),
+                    _ => Value::Unknown,
+                };
+                self.stack.push(value);
+            }
+            GetField { .. } => {
+                self.pop();
+                self.stack.push(Value::Unknown);
+            }
+            PutStatic { .. } => {
+                self.pop();
+            }
+            PutField { .. } => {
+                self.pop();
+                self.pop();
+            }
+            InvokeVirtual { index } => {
+                let method = self.pool.retrieve(index)?;
+                self.invoke(pc, method.class.name, method.name_and_type, false)?;
+            }
+            InvokeInterface { index, .. } => {
+                let method = self.pool.retrieve(index)?;
+                self.invoke(pc, method.class.name, method.name_and_type, false)?;
+            }
+            InvokeSpecial { index } | InvokeStatic { index } => {
+                let (class, name_and_type) = match self.pool.get(index)? {
+                    Item::MethodRef(cpool::MethodRef { class, name_and_type })
+                    | Item::InterfaceMethodRef(cpool::InterfaceMethodRef { class, name_and_type }) => {
+                        (*class, *name_and_type)
+                    }
+                    _ => {
+                        return Err(DecodeError::with_context(
+                            DecodeErrorKind::TagMismatch,
+                            Context::ConstantPool,
+                        ))
+                    }
+                };
+                let class = self.pool.retrieve(class)?.name;
+                let name_and_type = self.pool.retrieve(name_and_type)?;
+                self.invoke(pc, class, name_and_type, matches!(instruction, InvokeStatic { .. }))?;
+            }
+            InvokeDynamic { index } => {
+                let call_site = self.pool.retrieve(index)?;
+                let descriptor = MethodDescriptor::parse(call_site.name_and_type.descriptor)?;
+                for _ in descriptor.parameters() {
+                    self.pop();
+                }
+                if descriptor.return_type().is_some() {
+                    self.stack.push(Value::Unknown);
+                }
+            }
+            // the effect of any other instruction on the operand stack isn't tracked
+            _ => self.stack.clear(),
+        }
+        Ok(())
+    }
+
+    fn constant(&mut self, index: cpool::Index<Item<'input>>) -> Result<Value, DecodeError> {
+        Ok(match self.pool.get(index)? {
+            Item::Integer(integer) => Value::Int(integer.value),
+            Item::String(string) => Value::String(self.pool.retrieve(string.string)?.into()),
+            Item::Class(class) => Value::Class(java_name(self.pool.retrieve(class.name)?)),
+            Item::MethodType(method_type) => {
+                let descriptor = MethodDescriptor::parse(self.pool.retrieve(method_type.descriptor)?)?;
+                Value::MethodType(descriptor.parameters().map(|parameter| type_name(&parameter)).collect())
+            }
+            _ => Value::Unknown,
+        })
+    }
+
+    fn invoke(
+        &mut self,
+        pc: Index,
+        class: &MStr,
+        method: cpool::value::NameAndType<'input>,
+        is_static: bool,
+    ) -> Result<(), DecodeError> {
+        let descriptor = MethodDescriptor::parse(method.descriptor)?;
+        let mut arguments: Vec<Value> = descriptor.parameters().map(|_| self.pop()).collect();
+        arguments.reverse();
+        let receiver = if is_static { Value::Unknown } else { self.pop() };
+
+        let result = match (class.to_str(), method.name.to_str()) {
+            (Some(class), Some(name)) => self.call(pc, class, name, &arguments, receiver),
+            _ => Value::Unknown,
+        };
+        if descriptor.return_type().is_some() {
+            self.stack.push(result);
+        }
+        Ok(())
+    }
+
+    /// Records the usage of a method call, if it is reflective, and returns its result.
+    fn call(&mut self, pc: Index, class: &str, name: &str, arguments: &[Value], receiver: Value) -> Value {
+        let string = |index: usize| arguments.get(index).and_then(Value::string);
+        let class_argument = |index: usize| arguments.get(index).and_then(Value::class);
+        let declared = name.starts_with("getDeclared");
+
+        let usage = match (class, name) {
+            ("java/lang/Class", "forName") | ("java/lang/ClassLoader", "loadClass") => Usage::Class { name: string(0) },
+            ("java/lang/Class", "getConstructor" | "getDeclaredConstructor") => Usage::Constructor {
+                class: receiver.class(),
+                parameters: arguments.first().and_then(|value| self.classes(value)),
+                declared,
+            },
+            ("java/lang/Class", "getConstructors" | "getDeclaredConstructors") => Usage::Constructor {
+                class: receiver.class(),
+                parameters: None,
+                declared,
+            },
+            ("java/lang/Class", "getMethod" | "getDeclaredMethod") => Usage::Method {
+                class: receiver.class(),
+                name: string(0),
+                parameters: arguments.get(1).and_then(|value| self.classes(value)),
+                declared,
+            },
+            ("java/lang/Class", "getMethods" | "getDeclaredMethods") => Usage::Method {
+                class: receiver.class(),
+                name: None,
+                parameters: None,
+                declared,
+            },
+            ("java/lang/Class", "getField" | "getDeclaredField") => Usage::Field {
+                class: receiver.class(),
+                name: string(0),
+                declared,
+            },
+            ("java/lang/Class", "getFields" | "getDeclaredFields") => Usage::Field {
+                class: receiver.class(),
+                name: None,
+                declared,
+            },
+            ("java/lang/Class", "getResource" | "getResourceAsStream") => Usage::Resource {
+                name: string(0).and_then(|name| absolute_resource(receiver.class().as_deref(), &name)),
+            },
+            (
+                "java/lang/ClassLoader",
+                "getResource"
+                | "getResourceAsStream"
+                | "getResources"
+                | "getSystemResource"
+                | "getSystemResourceAsStream"
+                | "getSystemResources",
+            ) => Usage::Resource { name: string(0) },
+            ("java/util/ServiceLoader", "load" | "loadInstalled") => Usage::Service {
+                service: arguments.iter().find_map(Value::class),
+            },
+            ("java/lang/invoke/MethodHandles$Lookup", "findVirtual" | "findStatic" | "findSpecial") => Usage::Method {
+                class: class_argument(0),
+                name: string(1),
+                parameters: match arguments.get(2) {
+                    Some(Value::MethodType(parameters)) => Some(parameters.clone()),
+                    _ => None,
+                },
+                declared: true,
+            },
+            ("java/lang/invoke/MethodHandles$Lookup", "findConstructor") => Usage::Constructor {
+                class: class_argument(0),
+                parameters: match arguments.get(1) {
+                    Some(Value::MethodType(parameters)) => Some(parameters.clone()),
+                    _ => None,
+                },
+                declared: true,
+            },
+            (
+                "java/lang/invoke/MethodHandles$Lookup",
+                "findGetter"
+                | "findSetter"
+                | "findStaticGetter"
+                | "findStaticSetter"
+                | "findVarHandle"
+                | "findStaticVarHandle",
+            ) => Usage::Field {
+                class: class_argument(0),
+                name: string(1),
+                declared: true,
+            },
+            ("java/lang/invoke/MethodHandles$Lookup", "findClass") => Usage::Class { name: string(0) },
+            ("java/lang/invoke/MethodType", "methodType") => return self.method_type(arguments),
+            (_, "getClass") if matches!(receiver, Value::This) && arguments.is_empty() => {
+                return Value::Class(java_name(self.class));
+            }
+            _ => return Value::Unknown,
+        };
+
+        let result = match &usage {
+            Usage::Class { name: Some(name) } => Value::Class(name.clone()),
+            _ => Value::Unknown,
+        };
+        self.findings.push(Finding {
+            class: self.class.into(),
+            method: self.method.clone(),
+            pc,
+            usage,
+        });
+        result
+    }
+
+    /// The classes of an array, if all of them are known.
+    fn classes(&self, value: &Value) -> Option<Vec<MString>> {
+        match value {
+            Value::Array(array) => self.arrays[*array].iter().cloned().collect(),
+            _ => None,
+        }
+    }
+
+    /// The result of `MethodType.methodType`, whose first argument is the return type.
+    fn method_type(&self, arguments: &[Value]) -> Value {
+        let mut parameters = Vec::new();
+        for argument in arguments.iter().skip(1) {
+            match argument {
+                Value::Class(class) => parameters.push(class.clone()),
+                Value::MethodType(types) => parameters.extend(types.iter().cloned()),
+                Value::Array(_) => match self.classes(argument) {
+                    Some(classes) => parameters.extend(classes),
+                    None => return Value::Unknown,
+                },
+                _ => return Value::Unknown,
+            }
+        }
+        Value::MethodType(parameters)
+    }
+}
+
+fn jump_targets(pc: Index, instruction: &RawInstruction<'_>) -> Vec<u32> {
+    use RawInstruction::*;
+
+    let offsets: Vec<i32> = match instruction {
+        Goto { offset }
+        | IfACmpEq { offset }
+        | IfACmpNe { offset }
+        | IfICmpEq { offset }
+        | IfICmpNe { offset }
+        | IfICmpLt { offset }
+        | IfICmpGe { offset }
+        | IfICmpGt { offset }
+        | IfICmpLe { offset }
+        | IfEq { offset }
+        | IfNe { offset }
+        | IfLt { offset }
+        | IfGe { offset }
+        | IfGt { offset }
+        | IfLe { offset }
+        | IfNonNull { offset }
+        | IfNull { offset }
+        | JSr { offset } => vec![(*offset).into()],
+        GotoW { offset } | JSrW { offset } => vec![*offset],
+        LookupSwitch(switch) => std::iter::once(switch.default_offset())
+            .chain(switch.pairs().map(|pair| pair.offset()))
+            .collect(),
+        TableSwitch(switch) => std::iter::once(switch.default_offset())
+            .chain(switch.pairs().map(|pair| pair.offset()))
+            .collect(),
+        _ => Vec::new(),
+    };
+    offsets
+        .into_iter()
+        .filter_map(|offset| u32::try_from(i64::from(pc.as_u32()) + i64::from(offset)).ok())
+        .collect()
+}
+
+/// The primitive class whose wrapper class has the given name.
+fn primitive_class(wrapper: &MStr) -> Option<&'static str> {
+    Some(match wrapper.to_str()? {
+        "java/lang/Boolean" => "boolean",
+        "java/lang/Byte" => "byte",
+        "java/lang/Character" => "char",
+        "java/lang/Short" => "short",
+        "java/lang/Integer" => "int",
+        "java/lang/Long" => "long",
+        "java/lang/Float" => "float",
+        "java/lang/Double" => "double",
+        "java/lang/Void" => "void",
+        _ => return None,
+    })
+}
+
+/// Converts the name of a class as used in the constant pool to the name used in Java source code.
+fn java_name(name: &MStr) -> MString {
+    if name.as_bytes().starts_with(b"[") {
+        if let Ok(descriptor) = TypeDescriptor::parse(name) {
+            return type_name(&descriptor);
+        }
+    }
+    let bytes = name
+        .as_bytes()
+        .iter()
+        .map(|&byte| if byte == b'/' { b'.' } else { byte })
+        .collect();
+    // replacing an ASCII character with another one keeps the encoding valid
+    MString::from_mutf8(bytes).unwrap_or_else(|_| name.into())
+}
+
+fn type_name(descriptor: &TypeDescriptor<'_>) -> MString {
+    let mut name = match descriptor.base {
+        BaseType::Boolean => "boolean".into(),
+        BaseType::Byte => "byte".into(),
+        BaseType::Short => "short".into(),
+        BaseType::Integer => "int".into(),
+        BaseType::Long => "long".into(),
+        BaseType::Float => "float".into(),
+        BaseType::Double => "double".into(),
+        BaseType::Char => "char".into(),
+        BaseType::Object(class) => java_name(class),
+    };
+    for _ in 0..descriptor.dimensions {
+        name.push('[');
+        name.push(']');
+    }
+    name
+}
+
+/// Resolves the name of a resource passed to `Class.getResource`, which is relative to the package of the class
+/// unless it starts with a slash.
+fn absolute_resource(class: Option<&MStr>, name: &MStr) -> Option<MString> {
+    if let Some(absolute) = name.as_bytes().strip_prefix(b"/") {
+        return Some(name[name.len() - absolute.len()..].into());
+    }
+    let class = class?;
+    let package = class.as_bytes().iter().rposition(|&byte| byte == b'.').unwrap_or(0);
+    let mut bytes: Vec<u8> = class.as_bytes()[..package]
+        .iter()
+        .map(|&byte| if byte == b'.' { b'/' } else { byte })
+        .collect();
+    if !bytes.is_empty() {
+        bytes.push(b'/');
+    }
+    bytes.extend_from_slice(name.as_bytes());
+    MString::from_mutf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{self, Attribute, Constant, Instruction, MemberRef, Method};
+
+    fn finding(usage: Usage) -> Finding {
+        Finding {
+            class: "Main".into(),
+            method: NameAndType {
+                name: "main".into(),
+                descriptor: "([Ljava/lang/String;)V".into(),
+            },
+            pc: Index::new(0),
+            usage,
+        }
+    }
+
+    #[test]
+    fn reflect_config_json() {
+        let findings = [
+            finding(Usage::Method {
+                class: Some("com.example.Plugin".into()),
+                name: Some("run".into()),
+                parameters: Some(vec!["java.lang.String".into(), "int[]".into()]),
+                declared: false,
+            }),
+            finding(Usage::Field {
+                class: Some("com.example.Plugin".into()),
+                name: None,
+                declared: true,
+            }),
+            finding(Usage::Class {
+                name: Some("com.example.\"Quoted\"".into()),
+            }),
+            finding(Usage::Class { name: None }),
+        ];
+        let expected = r#"[
+  {
+    "name": "com.example.\"Quoted\""
+  },
+  {
+    "name": "com.example.Plugin",
+    "allDeclaredFields": true,
+    "methods": [
+      {"name": "run", "parameterTypes": ["java.lang.String", "int[]"]}
+    ]
+  }
+]
+"#;
+        assert_eq!(reflect_config(&findings), expected);
+        assert_eq!(reflect_config(&[]), "[]\n");
+    }
+
+    /// Scans a static method and returns the names of the classes loaded by `Class.forName`.
+    fn loaded_classes(instructions: Vec<Instruction>) -> Vec<Option<MString>> {
+        let class = tree::Class {
+            methods: vec![Method {
+                access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC,
+                name: "main".into(),
+                descriptor: "()V".into(),
+                attributes: vec![Attribute::Code(tree::Code {
+                    max_stack: 2,
+                    max_locals: 4,
+                    instructions,
+                    exception_handlers: Vec::new(),
+                    attributes: Vec::new(),
+                })],
+            }],
+            ..tree::Class::new("Main")
+        };
+        let bytes = class.to_bytes().unwrap();
+        scan(&Class::new(&bytes).unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|finding| match finding.usage {
+                Usage::Class { name } => name,
+                usage => panic!("unexpected usage {usage:?}"),
+            })
+            .collect()
+    }
+
+    fn string(value: &str) -> Instruction {
+        Instruction::LdC {
+            constant: Constant::String(value.into()),
+        }
+    }
+
+    fn for_name() -> Instruction {
+        Instruction::InvokeStatic {
+            method: MemberRef {
+                class: "java/lang/Class".into(),
+                name: "forName".into(),
+                descriptor: "(Ljava/lang/String;)Ljava/lang/Class;".into(),
+            },
+            interface: false,
+        }
+    }
+
+    #[test]
+    fn locals_through_stores_and_loads() {
+        let names = loaded_classes(vec![
+            string("com.example.Plugin"),
+            Instruction::AStore1,
+            Instruction::ALoad { index: 1 },
+            for_name(),
+            Instruction::Pop,
+            // a primitive overwrites the string
+            Instruction::IConst0,
+            Instruction::IStore1,
+            Instruction::ALoad1,
+            for_name(),
+            Instruction::Pop,
+            Instruction::Return,
+        ]);
+        assert_eq!(names, [Some("com.example.Plugin".into()), None]);
+    }
+
+    #[test]
+    fn wide_stores() {
+        let names = loaded_classes(vec![
+            string("com.example.First"),
+            Instruction::AStore1,
+            string("com.example.Second"),
+            Instruction::AStore2,
+            // the long covers the slots 0 and 1
+            Instruction::LConst0,
+            Instruction::LStore0,
+            Instruction::ALoad1,
+            for_name(),
+            Instruction::Pop,
+            Instruction::ALoad2,
+            for_name(),
+            Instruction::Pop,
+            // a double in the last slot has no second slot to forget
+            Instruction::DConst0,
+            Instruction::DStoreW { index: u16::MAX },
+            Instruction::Return,
+        ]);
+        assert_eq!(names, [None, Some("com.example.Second".into())]);
+    }
+}
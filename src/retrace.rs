@@ -0,0 +1,1051 @@
+//! Retracing of obfuscated stack traces using ProGuard/R8 mapping files.
+//!
+//! A [`Mapping`] is parsed from the text format written by ProGuard and R8.
+//! The [`Retracer`] translates frames of an obfuscated stack trace back to the original classes, methods and lines.
+//! Inlined frames are expanded into every method they were inlined from.
+//! If a frame cannot be attributed to a single method, every candidate is reported.
+//!
+//! The obfuscated class files can optionally be supplied to the retracer.
+//! Their `LineNumberTable` attributes are used to rule out overloads which do not contain the line of a frame,
+//! and their `SourceFile` attributes are used if the mapping file does not record a source file.
+//!
+//! ```
+//! use noak::retrace::{Mapping, Retracer};
+//!
+//! let mapping = Mapping::parse(
+//!     "com.example.Main -> a:\n    1:3:void run():10:12 -> a\n",
+//! )?;
+//! let retracer = Retracer::new(&mapping);
+//! let trace = retracer.retrace_stack_trace("java.lang.Error\n\tat a.a(SourceFile:2)\n");
+//! assert_eq!(trace, "java.lang.Error\n\tat com.example.Main.run(Main.java:11)\n");
+//! # Ok::<(), noak::retrace::MappingError>(())
+//! ```
+
+use std::collections::HashMap;
+use std::{error::Error, fmt};
+
+use crate::descriptor::{BaseType, MethodDescriptor, TypeDescriptor};
+use crate::error::DecodeError;
+use crate::reader::attributes::{Code, LineNumberTable, SourceFile};
+use crate::reader::Class;
+
+/// A parsed ProGuard/R8 mapping file.
+#[derive(Debug, Clone, Default)]
+pub struct Mapping {
+    classes: Vec<ClassMapping>,
+    by_obfuscated: HashMap<String, usize>,
+    by_original: HashMap<String, usize>,
+}
+
+impl Mapping {
+    /// Parses a mapping file.
+    ///
+    /// Comments are ignored, except for the R8 `sourceFile` metadata which is recorded for the preceding class.
+    pub fn parse(input: &str) -> Result<Mapping, MappingError> {
+        let mut mapping = Mapping::default();
+
+        for (number, line) in input.lines().enumerate() {
+            let number = number + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if let Some(comment) = trimmed.strip_prefix('#') {
+                if let (Some(class), Some(file_name)) = (mapping.classes.last_mut(), parse_source_file_comment(comment))
+                {
+                    class.source_file = Some(file_name);
+                }
+                continue;
+            }
+
+            if line.starts_with(char::is_whitespace) {
+                let class = mapping.classes.last_mut().ok_or(MappingError {
+                    line: number,
+                    kind: MappingErrorKind::MemberOutsideOfClass,
+                })?;
+                class.parse_member(trimmed, number)?;
+            } else {
+                let (original, obfuscated) = trimmed
+                    .strip_suffix(':')
+                    .and_then(|line| line.split_once(" -> "))
+                    .ok_or(MappingError {
+                        line: number,
+                        kind: MappingErrorKind::InvalidClass,
+                    })?;
+
+                let index = mapping.classes.len();
+                mapping.by_obfuscated.insert(obfuscated.trim().to_owned(), index);
+                mapping.by_original.insert(original.trim().to_owned(), index);
+                mapping.classes.push(ClassMapping {
+                    original: original.trim().to_owned(),
+                    obfuscated: obfuscated.trim().to_owned(),
+                    source_file: None,
+                    fields: Vec::new(),
+                    methods: Vec::new(),
+                });
+            }
+        }
+
+        Ok(mapping)
+    }
+
+    /// Returns an iterator over all class mappings in the order they were declared.
+    pub fn classes(&self) -> impl Iterator<Item = &ClassMapping> {
+        self.classes.iter()
+    }
+
+    /// Looks up a class by its obfuscated binary name (e.g. `a.b`).
+    #[must_use]
+    pub fn class_by_obfuscated(&self, name: &str) -> Option<&ClassMapping> {
+        self.by_obfuscated.get(name).map(|&index| &self.classes[index])
+    }
+
+    /// Looks up a class by its original binary name (e.g. `com.example.Main`).
+    #[must_use]
+    pub fn class_by_original(&self, name: &str) -> Option<&ClassMapping> {
+        self.by_original.get(name).map(|&index| &self.classes[index])
+    }
+
+    /// Translates an obfuscated binary class name to its original name.
+    /// Names which are not part of the mapping are returned unchanged.
+    #[must_use]
+    pub fn original_class_name<'a>(&'a self, obfuscated: &'a str) -> &'a str {
+        self.class_by_obfuscated(obfuscated)
+            .map_or(obfuscated, |class| class.original.as_str())
+    }
+
+    /// Translates a descriptor of the obfuscated class files into the type names used by the mapping file.
+    fn original_type_name(&self, ty: &TypeDescriptor<'_>) -> String {
+        let mut name = match &ty.base {
+            BaseType::Boolean => "boolean".to_owned(),
+            BaseType::Byte => "byte".to_owned(),
+            BaseType::Short => "short".to_owned(),
+            BaseType::Integer => "int".to_owned(),
+            BaseType::Long => "long".to_owned(),
+            BaseType::Float => "float".to_owned(),
+            BaseType::Double => "double".to_owned(),
+            BaseType::Char => "char".to_owned(),
+            BaseType::Object(name) => {
+                let name = name.display().to_string().replace('/', ".");
+                self.original_class_name(&name).to_owned()
+            }
+        };
+        for _ in 0..ty.dimensions {
+            name.push_str("[]");
+        }
+        name
+    }
+}
+
+/// The mapping of a single class and its members.
+#[derive(Debug, Clone)]
+pub struct ClassMapping {
+    original: String,
+    obfuscated: String,
+    source_file: Option<String>,
+    fields: Vec<FieldMapping>,
+    methods: Vec<MethodMapping>,
+}
+
+impl ClassMapping {
+    /// The original binary name, e.g. `com.example.Main`.
+    #[must_use]
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// The obfuscated binary name, e.g. `a.b`.
+    #[must_use]
+    pub fn obfuscated(&self) -> &str {
+        &self.obfuscated
+    }
+
+    /// The source file recorded by R8, if any.
+    #[must_use]
+    pub fn source_file(&self) -> Option<&str> {
+        self.source_file.as_deref()
+    }
+
+    #[must_use]
+    pub fn fields(&self) -> &[FieldMapping] {
+        &self.fields
+    }
+
+    #[must_use]
+    pub fn methods(&self) -> &[MethodMapping] {
+        &self.methods
+    }
+
+    fn parse_member(&mut self, line: &str, number: usize) -> Result<(), MappingError> {
+        let error = |kind| MappingError { line: number, kind };
+
+        let (original, obfuscated) = line
+            .split_once(" -> ")
+            .ok_or_else(|| error(MappingErrorKind::InvalidMember))?;
+        let obfuscated = obfuscated.trim().to_owned();
+
+        let Some(open) = original.find('(') else {
+            let (type_, name) = original
+                .trim()
+                .split_once(' ')
+                .ok_or_else(|| error(MappingErrorKind::InvalidMember))?;
+            self.fields.push(FieldMapping {
+                type_: type_.to_owned(),
+                original: name.trim().to_owned(),
+                obfuscated,
+            });
+            return Ok(());
+        };
+        let close = original
+            .rfind(')')
+            .filter(|&close| close > open)
+            .ok_or_else(|| error(MappingErrorKind::InvalidMember))?;
+
+        // `start:end:` in front of the return type
+        let head = &original[..open];
+        let mut head_parts = head.rsplitn(3, ':');
+        let signature = head_parts.next().unwrap_or_default();
+        let obfuscated_range = match (head_parts.next(), head_parts.next()) {
+            (Some(end), Some(start)) => Some(LineRange {
+                start: parse_line(start, number)?,
+                end: parse_line(end, number)?,
+            }),
+            (None, None) => None,
+            _ => return Err(error(MappingErrorKind::InvalidLineRange)),
+        };
+
+        let (return_type, qualified_name) = signature
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| error(MappingErrorKind::InvalidMember))?;
+        let (original_class, original_name) = match qualified_name.rsplit_once('.') {
+            Some((class, name)) => (Some(class.to_owned()), name.to_owned()),
+            None => (None, qualified_name.to_owned()),
+        };
+
+        let parameters = original[open + 1..close]
+            .split(',')
+            .map(str::trim)
+            .filter(|parameter| !parameter.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        // `:start:end` or `:start` after the parameter list
+        let tail = &original[close + 1..];
+        let original_range = if tail.is_empty() {
+            None
+        } else {
+            let mut tail_parts = tail
+                .strip_prefix(':')
+                .ok_or_else(|| error(MappingErrorKind::InvalidLineRange))?
+                .split(':');
+            let start = parse_line(tail_parts.next().unwrap_or_default(), number)?;
+            let end = match tail_parts.next() {
+                Some(end) => parse_line(end, number)?,
+                None => start,
+            };
+            Some(LineRange { start, end })
+        };
+
+        self.methods.push(MethodMapping {
+            obfuscated_range,
+            original_range,
+            return_type: return_type.to_owned(),
+            original_class,
+            original: original_name,
+            parameters,
+            obfuscated,
+        });
+        Ok(())
+    }
+}
+
+/// A field entry of a class mapping.
+#[derive(Debug, Clone)]
+pub struct FieldMapping {
+    type_: String,
+    original: String,
+    obfuscated: String,
+}
+
+impl FieldMapping {
+    /// The original type in Java source notation, e.g. `java.lang.String[]`.
+    #[must_use]
+    pub fn type_(&self) -> &str {
+        &self.type_
+    }
+
+    #[must_use]
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    #[must_use]
+    pub fn obfuscated(&self) -> &str {
+        &self.obfuscated
+    }
+}
+
+/// A method entry of a class mapping.
+///
+/// Several consecutive entries with the same obfuscated line range describe an inlined call chain,
+/// starting with the innermost method.
+#[derive(Debug, Clone)]
+pub struct MethodMapping {
+    obfuscated_range: Option<LineRange>,
+    original_range: Option<LineRange>,
+    return_type: String,
+    original_class: Option<String>,
+    original: String,
+    parameters: Vec<String>,
+    obfuscated: String,
+}
+
+impl MethodMapping {
+    /// The range of lines inside the obfuscated class file which belong to this entry.
+    #[must_use]
+    pub fn obfuscated_range(&self) -> Option<LineRange> {
+        self.obfuscated_range
+    }
+
+    /// The range of lines inside the original source file.
+    #[must_use]
+    pub fn original_range(&self) -> Option<LineRange> {
+        self.original_range
+    }
+
+    /// The original return type in Java source notation.
+    #[must_use]
+    pub fn return_type(&self) -> &str {
+        &self.return_type
+    }
+
+    /// The class this method was declared in, if it was inlined from another class.
+    #[must_use]
+    pub fn original_class(&self) -> Option<&str> {
+        self.original_class.as_deref()
+    }
+
+    #[must_use]
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// The original parameter types in Java source notation.
+    #[must_use]
+    pub fn parameters(&self) -> &[String] {
+        &self.parameters
+    }
+
+    #[must_use]
+    pub fn obfuscated(&self) -> &str {
+        &self.obfuscated
+    }
+
+    /// Maps a line of the obfuscated class file to the original line.
+    #[must_use]
+    pub fn original_line(&self, line: u32) -> u32 {
+        match (self.obfuscated_range, self.original_range) {
+            (Some(obfuscated), Some(original)) => {
+                if obfuscated.end - obfuscated.start == original.end - original.start {
+                    original.start + line.saturating_sub(obfuscated.start)
+                } else {
+                    original.start
+                }
+            }
+            (None, Some(original)) => original.start,
+            (_, None) => line,
+        }
+    }
+
+    fn same_signature(&self, other: &MethodMapping) -> bool {
+        self.original_class == other.original_class
+            && self.original == other.original
+            && self.parameters == other.parameters
+            && self.return_type == other.return_type
+    }
+}
+
+/// An inclusive range of source lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LineRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl LineRange {
+    #[must_use]
+    pub fn contains(self, line: u32) -> bool {
+        self.start <= line && line <= self.end
+    }
+}
+
+/// A single `at` line of a stack trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// The binary name of the class, e.g. `com.example.Main`.
+    pub class: String,
+    pub method: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+impl Frame {
+    /// Parses a stack trace line such as `at a.b.c(SourceFile:12)`.
+    ///
+    /// Leading whitespace and module prefixes such as `java.base/` are accepted.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Frame> {
+        let rest = line.trim_start().strip_prefix("at ")?.trim();
+        let open = rest.find('(')?;
+        let location = rest[open + 1..].strip_suffix(')')?;
+        let qualified = &rest[..open];
+        // drop class loader and module prefixes
+        let qualified = qualified.rsplit_once('/').map_or(qualified, |(_, qualified)| qualified);
+        let (class, method) = qualified.rsplit_once('.')?;
+
+        let (file, line) = match location.rsplit_once(':') {
+            Some((file, line)) => match line.parse() {
+                Ok(line) => (Some(file), Some(line)),
+                Err(_) => (Some(location), None),
+            },
+            None => (Some(location), None),
+        };
+        let file = file.filter(|file| *file != "Unknown Source" && *file != "Native Method");
+
+        Some(Frame {
+            class: class.to_owned(),
+            method: method.to_owned(),
+            file: file.map(str::to_owned),
+            line,
+        })
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}(", self.class, self.method)?;
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{})", file, line),
+            (Some(file), None) => write!(f, "{})", file),
+            (None, Some(line)) => write!(f, "Unknown Source:{})", line),
+            (None, None) => write!(f, "Unknown Source)"),
+        }
+    }
+}
+
+/// A frame translated back to the original source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetracedFrame {
+    /// The original binary class name.
+    pub class: String,
+    pub method: String,
+    /// The original return type and parameter types, if known.
+    pub signature: Option<(String, Vec<String>)>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+impl RetracedFrame {
+    fn as_frame(&self) -> Frame {
+        Frame {
+            class: self.class.clone(),
+            method: self.method.clone(),
+            file: self.file.clone(),
+            line: self.line,
+        }
+    }
+}
+
+impl fmt::Display for RetracedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_frame())
+    }
+}
+
+/// One possible interpretation of an obfuscated frame.
+///
+/// Inlined methods result in several frames, ordered from the innermost method to the method the code was inlined into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub frames: Vec<RetracedFrame>,
+}
+
+/// Line information of a method of an obfuscated class file.
+#[derive(Debug, Clone)]
+struct MethodLines {
+    descriptor: String,
+    lines: Vec<u32>,
+}
+
+/// Information gathered from an obfuscated class file.
+#[derive(Debug, Clone, Default)]
+struct ClassInfo {
+    source_file: Option<String>,
+    methods: HashMap<String, Vec<MethodLines>>,
+}
+
+/// Translates obfuscated stack traces using a [`Mapping`].
+#[derive(Debug, Clone)]
+pub struct Retracer<'m> {
+    mapping: &'m Mapping,
+    classes: HashMap<String, ClassInfo>,
+}
+
+impl<'m> Retracer<'m> {
+    #[must_use]
+    pub fn new(mapping: &'m Mapping) -> Retracer<'m> {
+        Retracer {
+            mapping,
+            classes: HashMap::new(),
+        }
+    }
+
+    /// Registers an obfuscated class file.
+    ///
+    /// The `LineNumberTable` of its methods is used to narrow down ambiguous overloads
+    /// and its `SourceFile` is used as a fallback for the source file name.
+    pub fn add_class(&mut self, class: &Class<'_>) -> Result<&mut Self, DecodeError> {
+        let pool = class.pool();
+        let name = pool
+            .retrieve(class.this_class())?
+            .name
+            .display()
+            .to_string()
+            .replace('/', ".");
+
+        let mut info = ClassInfo {
+            source_file: class
+                .attributes()
+                .find_attribute::<SourceFile<'_>>(pool)?
+                .map(|source_file| pool.retrieve(source_file.source_file()))
+                .transpose()?
+                .map(|file| file.display().to_string()),
+            methods: HashMap::new(),
+        };
+
+        for method in class.methods() {
+            let method = method?;
+            let mut lines = Vec::new();
+            if let Some(code) = method.attributes().find_attribute::<Code<'_>>(pool)? {
+                for attribute in code.attributes() {
+                    let attribute = attribute?;
+                    if pool.retrieve(attribute.name())? == "LineNumberTable" {
+                        let table: LineNumberTable<'_> = attribute.read_content(pool)?.try_into()?;
+                        for line in table.lines() {
+                            lines.push(u32::from(line?.line_number()));
+                        }
+                    }
+                }
+            }
+
+            info.methods
+                .entry(pool.retrieve(method.name())?.display().to_string())
+                .or_default()
+                .push(MethodLines {
+                    descriptor: pool.retrieve(method.descriptor())?.display().to_string(),
+                    lines,
+                });
+        }
+
+        self.classes.insert(name, info);
+        Ok(self)
+    }
+
+    /// Returns every possible original interpretation of an obfuscated frame.
+    ///
+    /// If the class is not part of the mapping, the frame is returned unchanged.
+    #[must_use]
+    pub fn retrace_frame(&self, frame: &Frame) -> Vec<Candidate> {
+        let Some(class) = self.mapping.class_by_obfuscated(&frame.class) else {
+            return vec![Candidate {
+                frames: vec![RetracedFrame {
+                    class: frame.class.clone(),
+                    method: frame.method.clone(),
+                    signature: None,
+                    file: frame.file.clone(),
+                    line: frame.line,
+                }],
+            }];
+        };
+
+        let methods: Vec<&MethodMapping> = class
+            .methods
+            .iter()
+            .filter(|method| method.obfuscated == frame.method)
+            .collect();
+
+        let mut chains: Vec<Vec<&MethodMapping>> = Vec::new();
+        if let Some(line) = frame.line {
+            // entries whose obfuscated range contains the line; consecutive entries with an equal range are inlined,
+            // unless they share the name of the previous entry, in which case they are overloads
+            for method in &methods {
+                let Some(range) = method.obfuscated_range.filter(|range| range.contains(line)) else {
+                    continue;
+                };
+                match chains.last_mut() {
+                    Some(chain)
+                        if chain.last().is_some_and(|last| {
+                            last.obfuscated_range == Some(range)
+                                && (last.original_class != method.original_class || last.original != method.original)
+                        }) =>
+                    {
+                        chain.push(method);
+                    }
+                    _ => chains.push(vec![method]),
+                }
+            }
+        }
+
+        if chains.is_empty() {
+            for method in &methods {
+                if method.original_class.is_some() {
+                    continue;
+                }
+                if !chains.iter().any(|chain| chain[0].same_signature(method)) {
+                    chains.push(vec![method]);
+                }
+            }
+        }
+
+        if chains.len() > 1 {
+            chains = self.filter_by_line_table(&frame.class, frame, chains);
+        }
+
+        if chains.is_empty() {
+            return vec![Candidate {
+                frames: vec![RetracedFrame {
+                    class: class.original.clone(),
+                    method: frame.method.clone(),
+                    signature: None,
+                    file: Some(self.source_file(class, None, frame)),
+                    line: frame.line,
+                }],
+            }];
+        }
+
+        chains
+            .into_iter()
+            .map(|chain| Candidate {
+                frames: chain
+                    .into_iter()
+                    .map(|method| {
+                        let class_name = method.original_class.as_deref();
+                        RetracedFrame {
+                            class: class_name.unwrap_or(&class.original).to_owned(),
+                            method: method.original.clone(),
+                            signature: Some((method.return_type.clone(), method.parameters.clone())),
+                            file: Some(self.source_file(class, class_name, frame)),
+                            line: frame
+                                .line
+                                .filter(|_| method.obfuscated_range.is_some() || method.original_range.is_some())
+                                .map(|line| method.original_line(line)),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Removes every candidate chain whose outermost method does not contain the line of the frame.
+    /// The candidates are left untouched if no class file was registered or the line tables do not help.
+    fn filter_by_line_table<'a>(
+        &self,
+        obfuscated_class: &str,
+        frame: &Frame,
+        chains: Vec<Vec<&'a MethodMapping>>,
+    ) -> Vec<Vec<&'a MethodMapping>> {
+        let (Some(info), Some(line)) = (self.classes.get(obfuscated_class), frame.line) else {
+            return chains;
+        };
+        let Some(methods) = info.methods.get(&frame.method) else {
+            return chains;
+        };
+
+        let matching: Vec<(String, Vec<String>)> = methods
+            .iter()
+            .filter(|method| method.lines.contains(&line))
+            .filter_map(|method| {
+                let descriptor = crate::MString::from(method.descriptor.as_str());
+                let descriptor = MethodDescriptor::parse(&descriptor).ok()?;
+                let return_type = descriptor
+                    .return_type()
+                    .map_or_else(|| "void".to_owned(), |ty| self.mapping.original_type_name(&ty));
+                let parameters = descriptor
+                    .parameters()
+                    .map(|ty| self.mapping.original_type_name(&ty))
+                    .collect();
+                Some((return_type, parameters))
+            })
+            .collect();
+
+        let filtered: Vec<_> = chains
+            .iter()
+            .filter(|chain| {
+                let outer = chain.last().expect("chains are never empty");
+                matching.iter().any(|(return_type, parameters)| {
+                    *return_type == outer.return_type && *parameters == outer.parameters
+                })
+            })
+            .cloned()
+            .collect();
+
+        if filtered.is_empty() {
+            chains
+        } else {
+            filtered
+        }
+    }
+
+    fn source_file(&self, class: &ClassMapping, inlined_from: Option<&str>, frame: &Frame) -> String {
+        if let Some(inlined_from) = inlined_from {
+            if let Some(file) = self
+                .mapping
+                .class_by_original(inlined_from)
+                .and_then(|class| class.source_file.clone())
+            {
+                return file;
+            }
+            return default_source_file(inlined_from);
+        }
+
+        if let Some(file) = &class.source_file {
+            return file.clone();
+        }
+
+        // obfuscators usually replace the source file with a dummy value such as `SourceFile`
+        let recorded = self
+            .classes
+            .get(&frame.class)
+            .and_then(|info| info.source_file.clone())
+            .or_else(|| frame.file.clone());
+        match recorded {
+            Some(file) if file != "SourceFile" && !file.is_empty() => file,
+            _ => default_source_file(&class.original),
+        }
+    }
+
+    /// Retraces a complete stack trace.
+    ///
+    /// Frames are replaced by their original frames. Every additional candidate of an ambiguous frame
+    /// is written on its own line prefixed by `<OR>`. Class names at the start of exception lines are deobfuscated as well.
+    #[must_use]
+    pub fn retrace_stack_trace(&self, trace: &str) -> String {
+        let mut output = String::with_capacity(trace.len());
+        for line in trace.split_inclusive('\n') {
+            let (content, newline) = match line.strip_suffix('\n') {
+                Some(content) => (content.strip_suffix('\r').unwrap_or(content), &line[content.len()..]),
+                None => (line, ""),
+            };
+
+            if let Some(frame) = Frame::parse(content) {
+                let indent = &content[..content.len() - content.trim_start().len()];
+                let candidates = self.retrace_frame(&frame);
+                for (i, candidate) in candidates.iter().enumerate() {
+                    for retraced in &candidate.frames {
+                        if i == 0 {
+                            output.push_str(indent);
+                            output.push_str("at ");
+                        } else {
+                            output.push_str(indent);
+                            output.push_str("<OR> at ");
+                        }
+                        output.push_str(&retraced.to_string());
+                        output.push_str(if newline.is_empty() { "\n" } else { newline });
+                    }
+                }
+                if newline.is_empty() && output.ends_with('\n') {
+                    output.pop();
+                }
+            } else {
+                output.push_str(&self.retrace_exception_line(content));
+                output.push_str(newline);
+            }
+        }
+        output
+    }
+
+    /// Deobfuscates the exception class name of lines such as `Caused by: a.b: message`.
+    fn retrace_exception_line(&self, line: &str) -> String {
+        let (prefix, rest) = match line.find("Caused by: ") {
+            Some(index) => line.split_at(index + "Caused by: ".len()),
+            None => match line.find("Suppressed: ") {
+                Some(index) => line.split_at(index + "Suppressed: ".len()),
+                None => ("", line),
+            },
+        };
+        let (name, message) = match rest.find(": ") {
+            Some(index) => rest.split_at(index),
+            None => (rest, ""),
+        };
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return line.to_owned();
+        }
+        format!("{}{}{}", prefix, self.mapping.original_class_name(name), message)
+    }
+}
+
+/// Derives the source file name javac would record for a class.
+fn default_source_file(class: &str) -> String {
+    let simple = class.rsplit('.').next().unwrap_or(class);
+    let outer = simple.split('$').next().unwrap_or(simple);
+    format!("{}.java", outer)
+}
+
+/// Extracts the file name out of R8 metadata comments like `{"id":"sourceFile","fileName":"Main.kt"}`.
+fn parse_source_file_comment(comment: &str) -> Option<String> {
+    let comment = comment.trim();
+    if !comment.starts_with('{') || !comment.contains("\"sourceFile\"") {
+        return None;
+    }
+    let (_, rest) = comment.split_once("\"fileName\"")?;
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+    let (file_name, _) = rest.split_once('"')?;
+    Some(file_name.to_owned())
+}
+
+fn parse_line(s: &str, line: usize) -> Result<u32, MappingError> {
+    s.trim().parse().map_err(|_| MappingError {
+        line,
+        kind: MappingErrorKind::InvalidLineRange,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MappingErrorKind {
+    InvalidClass,
+    InvalidMember,
+    InvalidLineRange,
+    MemberOutsideOfClass,
+}
+
+impl fmt::Display for MappingErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use MappingErrorKind::*;
+
+        match *self {
+            InvalidClass => write!(f, "invalid class mapping"),
+            InvalidMember => write!(f, "invalid member mapping"),
+            InvalidLineRange => write!(f, "invalid line range"),
+            MemberOutsideOfClass => write!(f, "member mapping outside of a class"),
+        }
+    }
+}
+
+/// An error that occurred while parsing a mapping file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappingError {
+    line: usize,
+    kind: MappingErrorKind,
+}
+
+impl MappingError {
+    #[must_use]
+    pub fn kind(&self) -> MappingErrorKind {
+        self.kind
+    }
+
+    /// The 1-based line number at which the error occurred.
+    #[must_use]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl Error for MappingError {}
+
+impl fmt::Display for MappingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in line {}", self.kind, self.line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EncodeError;
+    use crate::writer::ClassWriter;
+    use crate::{AccessFlags, Version};
+
+    const MAPPING: &str = "\
+# compiler: R8
+com.example.Main -> a.a:
+# {\"id\":\"sourceFile\",\"fileName\":\"Main.kt\"}
+    java.lang.String name -> a
+    1:4:void run():10:13 -> a
+    5:5:int com.example.Util.compute(int):40:40 -> a
+    5:5:void run():14 -> a
+    6:6:void process(int):20:20 -> b
+    6:6:void process(java.lang.String):30:30 -> b
+    void helper() -> c
+com.example.Util -> a.b:
+    int compute(int) -> a
+";
+
+    #[test]
+    fn parse() {
+        let mapping = Mapping::parse(MAPPING).unwrap();
+        let class = mapping.class_by_obfuscated("a.a").unwrap();
+        assert_eq!(class.original(), "com.example.Main");
+        assert_eq!(class.source_file(), Some("Main.kt"));
+        assert_eq!(class.fields().len(), 1);
+        assert_eq!(class.fields()[0].type_(), "java.lang.String");
+        assert_eq!(class.methods().len(), 6);
+
+        let inlined = &class.methods()[1];
+        assert_eq!(inlined.original_class(), Some("com.example.Util"));
+        assert_eq!(inlined.original(), "compute");
+        assert_eq!(inlined.parameters(), ["int"]);
+        assert_eq!(inlined.obfuscated_range(), Some(LineRange { start: 5, end: 5 }));
+
+        assert!(class.methods()[5].obfuscated_range().is_none());
+        assert_eq!(mapping.original_class_name("a.b"), "com.example.Util");
+        assert_eq!(mapping.original_class_name("x.y"), "x.y");
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            Mapping::parse("    void run() -> a\n").unwrap_err().kind(),
+            MappingErrorKind::MemberOutsideOfClass
+        );
+        assert_eq!(
+            Mapping::parse("a -> b:\n    x:2:void run() -> a\n").unwrap_err().line(),
+            2
+        );
+    }
+
+    #[test]
+    fn frames() {
+        let frame = Frame::parse("\tat java.base/a.a.b(SourceFile:6)").unwrap();
+        assert_eq!(frame.class, "a.a");
+        assert_eq!(frame.method, "b");
+        assert_eq!(frame.line, Some(6));
+
+        let frame = Frame::parse("  at a.a.c(Unknown Source)").unwrap();
+        assert_eq!(frame.file, None);
+        assert_eq!(frame.line, None);
+
+        assert!(Frame::parse("java.lang.Error: at nothing").is_none());
+    }
+
+    #[test]
+    fn retrace() {
+        let mapping = Mapping::parse(MAPPING).unwrap();
+        let retracer = Retracer::new(&mapping);
+
+        let candidates = retracer.retrace_frame(&Frame::parse("at a.a.a(SourceFile:3)").unwrap());
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].frames[0].to_string(), "com.example.Main.run(Main.kt:12)");
+
+        // inlined frames
+        let candidates = retracer.retrace_frame(&Frame::parse("at a.a.a(SourceFile:5)").unwrap());
+        assert_eq!(candidates.len(), 1);
+        let frames: Vec<_> = candidates[0].frames.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            frames,
+            [
+                "com.example.Util.compute(Util.java:40)",
+                "com.example.Main.run(Main.kt:14)"
+            ]
+        );
+
+        // ambiguous overloads
+        let candidates = retracer.retrace_frame(&Frame::parse("at a.a.b(SourceFile:6)").unwrap());
+        assert_eq!(candidates.len(), 2);
+
+        let trace =
+            retracer.retrace_stack_trace("Caused by: a.b: oops\n\tat a.a.b(SourceFile:6)\n\tat x.Y.z(Y.java:1)");
+        assert_eq!(
+            trace,
+            "Caused by: com.example.Util: oops\n\
+             \tat com.example.Main.process(Main.kt:20)\n\
+             \t<OR> at com.example.Main.process(Main.kt:30)\n\
+             \tat x.Y.z(Y.java:1)"
+        );
+    }
+
+    /// Writes an obfuscated class with methods named `b`, each having a single line in its `LineNumberTable`.
+    fn write_class(name: &str, source_file: &str, methods: &[(&str, u16)]) -> Result<Vec<u8>, EncodeError> {
+        ClassWriter::new()
+            .version(Version::V8)?
+            .access_flags(AccessFlags::PUBLIC | AccessFlags::SUPER)?
+            .this_class(name)?
+            .super_class("java/lang/Object")?
+            .interfaces(|_| Ok(()))?
+            .fields(|_| Ok(()))?
+            .methods(|writer| {
+                for &(descriptor, line) in methods {
+                    writer.begin(|method| {
+                        method
+                            .access_flags(AccessFlags::STATIC)?
+                            .name("b")?
+                            .descriptor(descriptor)?
+                            .attributes(|attributes| {
+                                attributes.begin(|attribute| {
+                                    attribute.code(|code| {
+                                        let mut start = None;
+                                        code.max_stack(0)?
+                                            .max_locals(1)?
+                                            .instructions(|instructions| {
+                                                let (label, label_ref) = instructions.new_label()?;
+                                                start = Some(label_ref);
+                                                instructions.label(label)?.return_()?;
+                                                Ok(())
+                                            })?
+                                            .exceptions(|_| Ok(()))?
+                                            .attributes(|attributes| {
+                                                attributes.begin(|attribute| {
+                                                    attribute.line_number_table(|lines| {
+                                                        lines.begin(|entry| {
+                                                            entry.start(start.take().unwrap())?.line_number(line)
+                                                        })?;
+                                                        Ok(())
+                                                    })
+                                                })?;
+                                                Ok(())
+                                            })
+                                    })
+                                })?;
+                                Ok(())
+                            })
+                    })?;
+                }
+                Ok(())
+            })?
+            .attributes(|attributes| {
+                attributes.begin(|attribute| attribute.source_file(source_file))?;
+                Ok(())
+            })?
+            .into_bytes()
+    }
+
+    #[test]
+    fn classes() {
+        let mapping = Mapping::parse(MAPPING).unwrap();
+        let mut retracer = Retracer::new(&mapping);
+
+        let main = write_class("a/a", "SourceFile", &[("(I)V", 6), ("(Ljava/lang/String;)V", 7)]).unwrap();
+        retracer.add_class(&Class::new(&main).unwrap()).unwrap();
+
+        // only the overload whose line number table contains the line remains
+        let candidates = retracer.retrace_frame(&Frame::parse("at a.a.b(SourceFile:6)").unwrap());
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(
+            candidates[0].frames[0].to_string(),
+            "com.example.Main.process(Main.kt:20)"
+        );
+        assert_eq!(
+            candidates[0].frames[0].signature,
+            Some((String::from("void"), vec![String::from("int")]))
+        );
+
+        // the mapping of `a.b` does not record a source file, so the one of the class file is used
+        let util = write_class("a/b", "Util.kt", &[]).unwrap();
+        retracer.add_class(&Class::new(&util).unwrap()).unwrap();
+        let candidates = retracer.retrace_frame(&Frame::parse("at a.b.a(SourceFile)").unwrap());
+        assert_eq!(candidates[0].frames[0].to_string(), "com.example.Util.compute(Util.kt)");
+    }
+}
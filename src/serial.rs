@@ -0,0 +1,398 @@
+//! The `serialVersionUID` of serializable classes.
+//!
+//! Classes which don't declare a `serialVersionUID` are assigned a default value by the serialization runtime,
+//! which is computed from the name, modifiers, interfaces and non-private members of the class
+//! as specified by `java.io.ObjectStreamClass`. The default value changes with almost every change of a class,
+//! which breaks the compatibility of serialized objects.
+//!
+//! Enums and records are serialized differently, so their default `serialVersionUID` is 0.
+//! Enums can't declare a `serialVersionUID` either, while records may still declare one.
+//!
+//! ```
+//! use noak::serial;
+//! use noak::tree::{Attribute, Class, Code, Field, Instruction, Method};
+//! use noak::AccessFlags;
+//!
+//! let field = |name: &str| Field {
+//!     access_flags: AccessFlags::PUBLIC,
+//!     name: name.into(),
+//!     descriptor: "I".into(),
+//!     attributes: Vec::new(),
+//! };
+//! // public class Point implements java.io.Serializable { public int x, y; public Point() {} }
+//! let class = Class {
+//!     interfaces: vec!["java/io/Serializable".into()],
+//!     fields: vec![field("x"), field("y")],
+//!     methods: vec![Method {
+//!         access_flags: AccessFlags::PUBLIC,
+//!         name: "<init>".into(),
+//!         descriptor: "()V".into(),
+//!         attributes: vec![Attribute::Code(Code {
+//!             max_stack: 0,
+//!             max_locals: 1,
+//!             instructions: vec![Instruction::Return],
+//!             exception_handlers: Vec::new(),
+//!             attributes: Vec::new(),
+//!         })],
+//!     }],
+//!     ..Class::new("Point")
+//! };
+//! let uid = serial::serial_version_uid_tree(&class);
+//! assert_eq!(uid.declared, None);
+//! assert_eq!(uid.default, -9130217029653031524);
+//! ```
+
+use std::cmp::Ordering;
+
+use crate::error::*;
+use crate::header::AccessFlags;
+use crate::mutf8::MStr;
+use crate::reader;
+use crate::tree::{Attribute, Class, Constant};
+
+/// The declared and default `serialVersionUID` of a class.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerialVersionUid {
+    /// The value of the `serialVersionUID` field.
+    ///
+    /// It is only known if the field is static and final and has a constant value.
+    /// The field of an enum is ignored by the serialization runtime, so it is `None` for enums.
+    pub declared: Option<i64>,
+    /// The value computed from the class, which is used if no `serialVersionUID` field is declared.
+    ///
+    /// It is 0 for enums and records.
+    pub default: i64,
+}
+
+impl SerialVersionUid {
+    /// The value used by the serialization runtime.
+    #[must_use]
+    pub fn value(&self) -> i64 {
+        self.declared.unwrap_or(self.default)
+    }
+}
+
+/// Computes the `serialVersionUID` of a class which was read from a class file.
+///
+/// It doesn't matter whether the class is actually serializable.
+pub fn serial_version_uid(class: &reader::Class<'_>) -> Result<SerialVersionUid, DecodeError> {
+    Ok(serial_version_uid_tree(&Class::from_reader(class)?))
+}
+
+/// Computes the `serialVersionUID` of a resolved class.
+///
+/// Classes with the `ACC_ENUM` flag, which includes the bodies of enum constants, are treated as enums.
+/// Direct subclasses of `java/lang/Record` are treated as records.
+#[must_use]
+pub fn serial_version_uid_tree(class: &Class) -> SerialVersionUid {
+    if class.access_flags.contains(AccessFlags::ENUM) {
+        return SerialVersionUid {
+            declared: None,
+            default: 0,
+        };
+    }
+
+    let declared = class
+        .fields
+        .iter()
+        .filter(|field| {
+            field.access_flags.contains(AccessFlags::STATIC | AccessFlags::FINAL)
+                && *field.name == *"serialVersionUID"
+                && *field.descriptor == *"J"
+        })
+        .flat_map(|field| &field.attributes)
+        .find_map(|attribute| match attribute {
+            Attribute::ConstantValue {
+                value: Constant::Long(value),
+            } => Some(*value),
+            _ => None,
+        });
+
+    let is_record = class
+        .super_class
+        .as_deref()
+        .is_some_and(|super_class| *super_class == *"java/lang/Record");
+    SerialVersionUid {
+        declared,
+        default: if is_record {
+            0
+        } else {
+            default_serial_version_uid(class)
+        },
+    }
+}
+
+fn default_serial_version_uid(class: &Class) -> i64 {
+    const CLASS_MODIFIERS: AccessFlags = AccessFlags::from_bits_truncate(
+        AccessFlags::PUBLIC.bits()
+            | AccessFlags::FINAL.bits()
+            | AccessFlags::INTERFACE.bits()
+            | AccessFlags::ABSTRACT.bits(),
+    );
+    const FIELD_MODIFIERS: AccessFlags = AccessFlags::from_bits_truncate(
+        AccessFlags::PUBLIC.bits()
+            | AccessFlags::PRIVATE.bits()
+            | AccessFlags::PROTECTED.bits()
+            | AccessFlags::STATIC.bits()
+            | AccessFlags::FINAL.bits()
+            | AccessFlags::VOLATILE.bits()
+            | AccessFlags::TRANSIENT.bits(),
+    );
+    const METHOD_MODIFIERS: AccessFlags = AccessFlags::from_bits_truncate(
+        AccessFlags::PUBLIC.bits()
+            | AccessFlags::PRIVATE.bits()
+            | AccessFlags::PROTECTED.bits()
+            | AccessFlags::STATIC.bits()
+            | AccessFlags::FINAL.bits()
+            | AccessFlags::SYNCHRONIZED.bits()
+            | AccessFlags::NATIVE.bits()
+            | AccessFlags::ABSTRACT.bits()
+            | AccessFlags::STRICT.bits(),
+    );
+
+    let mut data = Vec::new();
+    write_utf(&mut data, class.name.as_bytes(), true);
+
+    // nested classes have the modifiers they are declared with
+    let access_flags = class
+        .attributes
+        .iter()
+        .filter_map(|attribute| match attribute {
+            Attribute::InnerClasses(inner_classes) => Some(inner_classes),
+            _ => None,
+        })
+        .flatten()
+        .find(|inner| inner.inner_class == class.name)
+        .map_or(class.access_flags, |inner| inner.inner_access_flags);
+    let mut modifiers = access_flags & CLASS_MODIFIERS;
+    if modifiers.contains(AccessFlags::INTERFACE) {
+        let has_methods = class
+            .methods
+            .iter()
+            .any(|method| *method.name != *"<init>" && *method.name != *"<clinit>");
+        modifiers.set(AccessFlags::ABSTRACT, has_methods);
+    }
+    write_int(&mut data, modifiers);
+
+    let mut interfaces: Vec<&MStr> = class.interfaces.iter().map(|name| &**name).collect();
+    interfaces.sort_by(|a, b| compare(a, b));
+    for interface in interfaces {
+        write_utf(&mut data, interface.as_bytes(), true);
+    }
+
+    let mut fields: Vec<_> = class.fields.iter().collect();
+    fields.sort_by(|a, b| compare(&a.name, &b.name));
+    for field in fields {
+        let modifiers = field.access_flags & FIELD_MODIFIERS;
+        if !modifiers.contains(AccessFlags::PRIVATE)
+            || !modifiers.intersects(AccessFlags::STATIC | AccessFlags::TRANSIENT)
+        {
+            write_utf(&mut data, field.name.as_bytes(), false);
+            write_int(&mut data, modifiers);
+            write_utf(&mut data, field.descriptor.as_bytes(), false);
+        }
+    }
+
+    if class
+        .methods
+        .iter()
+        .any(|method| *method.name == *"<clinit>" && *method.descriptor == *"()V")
+    {
+        write_utf(&mut data, b"<clinit>", false);
+        write_int(&mut data, AccessFlags::STATIC);
+        write_utf(&mut data, b"()V", false);
+    }
+
+    let mut constructors: Vec<_> = class
+        .methods
+        .iter()
+        .filter(|method| *method.name == *"<init>")
+        .collect();
+    constructors.sort_by(|a, b| compare(&a.descriptor, &b.descriptor));
+    let mut methods: Vec<_> = class
+        .methods
+        .iter()
+        .filter(|method| *method.name != *"<init>" && *method.name != *"<clinit>")
+        .collect();
+    methods.sort_by(|a, b| compare(&a.name, &b.name).then_with(|| compare(&a.descriptor, &b.descriptor)));
+    for method in constructors.into_iter().chain(methods) {
+        let modifiers = method.access_flags & METHOD_MODIFIERS;
+        if !modifiers.contains(AccessFlags::PRIVATE) {
+            write_utf(&mut data, method.name.as_bytes(), false);
+            write_int(&mut data, modifiers);
+            write_utf(&mut data, method.descriptor.as_bytes(), true);
+        }
+    }
+
+    // the first eight bytes of the hash in little endian
+    let hash = sha1(&data);
+    i64::from_le_bytes([hash[0], hash[1], hash[2], hash[3], hash[4], hash[5], hash[6], hash[7]])
+}
+
+/// Compares strings like `String.compareTo` in Java does, by their UTF-16 code units.
+fn compare(a: &MStr, b: &MStr) -> Ordering {
+    fn utf16(s: &MStr) -> impl Iterator<Item = u16> + '_ {
+        s.chars().flat_map(|ch| {
+            let mut buffer = [0; 2];
+            match ch {
+                Ok(ch) => ch.encode_utf16(&mut buffer).to_vec(),
+                // unpaired surrogates are code units of their own
+                Err(surrogate) => vec![surrogate as u16],
+            }
+        })
+    }
+
+    utf16(a).cmp(utf16(b))
+}
+
+/// Writes a string like `DataOutputStream.writeUTF`, optionally replacing slashes with dots.
+fn write_utf(data: &mut Vec<u8>, bytes: &[u8], dotted: bool) {
+    // strings of the constant pool can't be longer than this
+    let length = u16::try_from(bytes.len()).unwrap_or(u16::MAX);
+    data.extend_from_slice(&length.to_be_bytes());
+    data.extend(
+        bytes
+            .iter()
+            .map(|&byte| if dotted && byte == b'/' { b'.' } else { byte }),
+    );
+}
+
+fn write_int(data: &mut Vec<u8>, modifiers: AccessFlags) {
+    data.extend_from_slice(&u32::from(modifiers.bits()).to_be_bytes());
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut words = [0u32; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in words.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    let mut hash = [0; 20];
+    for (bytes, value) in hash.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{Field, InnerClass};
+
+    fn serial_version_uid_field(value: i64) -> Field {
+        Field {
+            access_flags: AccessFlags::PRIVATE | AccessFlags::STATIC | AccessFlags::FINAL,
+            name: "serialVersionUID".into(),
+            descriptor: "J".into(),
+            attributes: vec![Attribute::ConstantValue {
+                value: Constant::Long(value),
+            }],
+        }
+    }
+
+    #[test]
+    fn enums_and_records() {
+        let enum_class = Class {
+            access_flags: AccessFlags::PUBLIC | AccessFlags::FINAL | AccessFlags::SUPER | AccessFlags::ENUM,
+            super_class: Some("java/lang/Enum".into()),
+            fields: vec![serial_version_uid_field(5)],
+            ..Class::new("Color")
+        };
+        let uid = serial_version_uid_tree(&enum_class);
+        assert_eq!((uid.declared, uid.default, uid.value()), (None, 0, 0));
+
+        let mut record = Class {
+            access_flags: AccessFlags::PUBLIC | AccessFlags::FINAL | AccessFlags::SUPER,
+            super_class: Some("java/lang/Record".into()),
+            ..Class::new("Point")
+        };
+        let uid = serial_version_uid_tree(&record);
+        assert_eq!((uid.declared, uid.default, uid.value()), (None, 0, 0));
+
+        record.fields.push(serial_version_uid_field(7));
+        let uid = serial_version_uid_tree(&record);
+        assert_eq!((uid.declared, uid.default, uid.value()), (Some(7), 0, 7));
+    }
+
+    #[test]
+    fn nested_classes() {
+        let inner = |inner_class: &str, inner_access_flags| InnerClass {
+            inner_class: inner_class.into(),
+            outer_class: Some("Outer".into()),
+            inner_name: Some("Inner".into()),
+            inner_access_flags,
+        };
+        // a protected nested class is public in its class file
+        let nested = Class {
+            attributes: vec![Attribute::InnerClasses(vec![
+                inner("Outer$Inner$Deeper", AccessFlags::PUBLIC | AccessFlags::FINAL),
+                inner("Outer$Inner", AccessFlags::PROTECTED | AccessFlags::STATIC),
+            ])],
+            ..Class::new("Outer$Inner")
+        };
+        let top_level = |access_flags| Class {
+            access_flags,
+            ..Class::new("Outer$Inner")
+        };
+
+        let default = serial_version_uid_tree(&nested).default;
+        assert_eq!(default, serial_version_uid_tree(&top_level(AccessFlags::SUPER)).default);
+        assert_ne!(
+            default,
+            serial_version_uid_tree(&top_level(AccessFlags::PUBLIC | AccessFlags::SUPER)).default
+        );
+    }
+
+    #[test]
+    fn sha1_digests() {
+        fn hex(data: &[u8]) -> String {
+            sha1(data).iter().map(|byte| format!("{byte:02x}")).collect()
+        }
+
+        assert_eq!(hex(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(hex(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+}
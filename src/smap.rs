@@ -0,0 +1,562 @@
+//! Parsing and generation of JSR-45 source maps, which are stored in the `SourceDebugExtension` attribute.
+//!
+//! A [`SourceMap`] maps the lines of the code compiled to a class file to the lines of the files it was generated from,
+//! e.g. JSP pages or the Kotlin files of inlined functions.
+//! Every [`Stratum`] is a separate view of these source files.
+//!
+//! ```
+//! use noak::smap::SourceMap;
+//!
+//! let smap = SourceMap::parse(
+//!     "SMAP\nMain.kt\nKotlin\n*S Kotlin\n*F\n+ 1 Main.kt\ncom/example/MainKt\n+ 2 Util.kt\ncom/example/UtilKt\n\
+//!      *L\n1#1,20:1\n5#2,3:21\n*E\n",
+//! )?;
+//! let stratum = smap.default_stratum().unwrap();
+//! let line = stratum.translate(22).unwrap();
+//! assert_eq!(line.file.name, "Util.kt");
+//! assert_eq!(line.file.path.as_deref(), Some("com/example/UtilKt"));
+//! assert_eq!(line.line, 6);
+//! assert_eq!(stratum.output_lines(2, 6), [22]);
+//!
+//! // generated source maps can be stored with `Attribute::SourceDebugExtension`
+//! assert_eq!(SourceMap::parse(&smap.to_string())?, smap);
+//! # Ok::<(), noak::smap::SmapError>(())
+//! ```
+
+use std::{error::Error, fmt};
+
+use crate::tree::{Attribute, Code, Label};
+
+/// A source map as specified by JSR-45.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceMap {
+    /// The name of the file which was compiled to the class file, e.g. `Main.java`.
+    pub output_file: String,
+    /// The name of the stratum used by debuggers unless another one is selected.
+    pub default_stratum: String,
+    pub strata: Vec<Stratum>,
+}
+
+impl SourceMap {
+    #[must_use]
+    pub fn new<O, S>(output_file: O, default_stratum: S) -> SourceMap
+    where
+        O: Into<String>,
+        S: Into<String>,
+    {
+        SourceMap {
+            output_file: output_file.into(),
+            default_stratum: default_stratum.into(),
+            strata: Vec::new(),
+        }
+    }
+
+    /// Parses a resolved source map, which doesn't contain embedded source maps.
+    ///
+    /// Unknown sections are ignored, as required by JSR-45. Any content after an end section is part of the
+    /// next stratum.
+    pub fn parse(input: &str) -> Result<SourceMap, SmapError> {
+        let mut lines = input
+            .lines()
+            .enumerate()
+            .map(|(number, line)| (number + 1, line.trim_end()));
+        let mut next = |kind| lines.next().ok_or(SmapError { line: 0, kind });
+
+        let (number, header) = next(SmapErrorKind::InvalidHeader)?;
+        if header != "SMAP" {
+            return Err(SmapError {
+                line: number,
+                kind: SmapErrorKind::InvalidHeader,
+            });
+        }
+        let (_, output_file) = next(SmapErrorKind::InvalidHeader)?;
+        let (_, default_stratum) = next(SmapErrorKind::InvalidHeader)?;
+        let mut smap = SourceMap::new(output_file, default_stratum);
+
+        let mut section = Section::Unknown;
+        // the file of a line section defaults to the file of the previous one
+        let mut file_id = 0;
+        let mut pending_file: Option<FileInfo> = None;
+        for (number, line) in lines {
+            if let Some(header) = line.strip_prefix('*') {
+                if pending_file.is_some() {
+                    return Err(SmapError {
+                        line: number,
+                        kind: SmapErrorKind::InvalidFile,
+                    });
+                }
+                section = match header.chars().next() {
+                    Some('S') => {
+                        let name = header[1..].trim();
+                        if name.is_empty() {
+                            return Err(SmapError {
+                                line: number,
+                                kind: SmapErrorKind::InvalidStratum,
+                            });
+                        }
+                        smap.strata.push(Stratum::new(name));
+                        file_id = 0;
+                        Section::Unknown
+                    }
+                    Some('F') => Section::Files,
+                    Some('L') => Section::Lines,
+                    Some('V') => Section::VendorId,
+                    Some('O' | 'C') => {
+                        return Err(SmapError {
+                            line: number,
+                            kind: SmapErrorKind::EmbeddedSourceMap,
+                        })
+                    }
+                    // the end section, which Kotlin writes after every stratum, and unknown sections
+                    _ => Section::Unknown,
+                };
+                continue;
+            }
+
+            let stratum = match section {
+                Section::Unknown => continue,
+                _ => smap.strata.last_mut().ok_or(SmapError {
+                    line: number,
+                    kind: SmapErrorKind::InvalidStratum,
+                })?,
+            };
+            match section {
+                Section::Files => {
+                    if let Some(mut file) = pending_file.take() {
+                        file.path = Some(line.to_owned());
+                        stratum.files.push(file);
+                        continue;
+                    }
+                    let (has_path, info) = match line.strip_prefix('+') {
+                        Some(info) => (true, info),
+                        None => (false, line),
+                    };
+                    let file = parse_file(info).ok_or(SmapError {
+                        line: number,
+                        kind: SmapErrorKind::InvalidFile,
+                    })?;
+                    if has_path {
+                        pending_file = Some(file);
+                    } else {
+                        stratum.files.push(file);
+                    }
+                }
+                Section::Lines => {
+                    let info = parse_line(line, &mut file_id).ok_or(SmapError {
+                        line: number,
+                        kind: SmapErrorKind::InvalidLine,
+                    })?;
+                    stratum.lines.push(info);
+                }
+                Section::VendorId => {
+                    stratum.vendor_sections.push(VendorSection {
+                        id: line.to_owned(),
+                        data: Vec::new(),
+                    });
+                    section = Section::Vendor;
+                }
+                Section::Vendor => {
+                    if let Some(vendor) = stratum.vendor_sections.last_mut() {
+                        vendor.data.push(line.to_owned());
+                    }
+                }
+                Section::Unknown => {}
+            }
+        }
+
+        if pending_file.is_some() {
+            return Err(SmapError {
+                line: 0,
+                kind: SmapErrorKind::InvalidFile,
+            });
+        }
+        Ok(smap)
+    }
+
+    #[must_use]
+    pub fn stratum(&self, name: &str) -> Option<&Stratum> {
+        self.strata.iter().find(|stratum| stratum.name == name)
+    }
+
+    #[must_use]
+    pub fn default_stratum(&self) -> Option<&Stratum> {
+        self.stratum(&self.default_stratum)
+    }
+
+    pub fn add_stratum(&mut self, stratum: Stratum) -> &mut Self {
+        self.strata.push(stratum);
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Section {
+    Files,
+    Lines,
+    VendorId,
+    Vendor,
+    Unknown,
+}
+
+/// Parses `<id> <name>`, which may be preceded by a `+` if the path follows in the next line.
+fn parse_file(info: &str) -> Option<FileInfo> {
+    let (id, name) = info.trim_start().split_once(' ')?;
+    Some(FileInfo {
+        id: id.parse().ok()?,
+        name: name.trim().to_owned(),
+        path: None,
+    })
+}
+
+/// Parses `<input start>[#<file id>][,<repeat count>]:<output start>[,<output increment>]`.
+fn parse_line(line: &str, file_id: &mut u32) -> Option<LineInfo> {
+    let (input, output) = line.split_once(':')?;
+    let (input, repeat_count) = match input.split_once(',') {
+        Some((input, count)) => (input, count.trim().parse().ok()?),
+        None => (input, 1),
+    };
+    let input_start = match input.split_once('#') {
+        Some((start, id)) => {
+            *file_id = id.trim().parse().ok()?;
+            start
+        }
+        None => input,
+    };
+    let (output_start, output_increment) = match output.split_once(',') {
+        Some((start, increment)) => (start, increment.trim().parse().ok()?),
+        None => (output, 1),
+    };
+    Some(LineInfo {
+        input_start: input_start.trim().parse().ok()?,
+        file_id: *file_id,
+        repeat_count,
+        output_start: output_start.trim().parse().ok()?,
+        output_increment,
+    })
+}
+
+impl fmt::Display for SourceMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "SMAP")?;
+        writeln!(f, "{}", self.output_file)?;
+        writeln!(f, "{}", self.default_stratum)?;
+        for stratum in &self.strata {
+            writeln!(f, "*S {}", stratum.name)?;
+            writeln!(f, "*F")?;
+            for file in &stratum.files {
+                match &file.path {
+                    Some(path) => writeln!(f, "+ {} {}\n{}", file.id, file.name, path)?,
+                    None => writeln!(f, "{} {}", file.id, file.name)?,
+                }
+            }
+            writeln!(f, "*L")?;
+            let mut file_id = 0;
+            for line in &stratum.lines {
+                write!(f, "{}", line.input_start)?;
+                if line.file_id != file_id {
+                    write!(f, "#{}", line.file_id)?;
+                    file_id = line.file_id;
+                }
+                if line.repeat_count != 1 {
+                    write!(f, ",{}", line.repeat_count)?;
+                }
+                write!(f, ":{}", line.output_start)?;
+                if line.output_increment != 1 {
+                    write!(f, ",{}", line.output_increment)?;
+                }
+                writeln!(f)?;
+            }
+            for vendor in &stratum.vendor_sections {
+                writeln!(f, "*V\n{}", vendor.id)?;
+                for data in &vendor.data {
+                    writeln!(f, "{data}")?;
+                }
+            }
+        }
+        writeln!(f, "*E")
+    }
+}
+
+/// A view of the source files, e.g. the JSP pages or the Java code generated from them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stratum {
+    pub name: String,
+    pub files: Vec<FileInfo>,
+    pub lines: Vec<LineInfo>,
+    pub vendor_sections: Vec<VendorSection>,
+}
+
+impl Stratum {
+    #[must_use]
+    pub fn new<S: Into<String>>(name: S) -> Stratum {
+        Stratum {
+            name: name.into(),
+            files: Vec::new(),
+            lines: Vec::new(),
+            vendor_sections: Vec::new(),
+        }
+    }
+
+    /// Adds a source file and returns its identifier.
+    pub fn add_file<N: Into<String>>(&mut self, name: N, path: Option<String>) -> u32 {
+        let id = self.files.iter().map(|file| file.id + 1).max().unwrap_or(1);
+        self.files.push(FileInfo {
+            id,
+            name: name.into(),
+            path,
+        });
+        id
+    }
+
+    /// Maps `count` consecutive lines of a source file to the lines starting at `output_start`.
+    pub fn add_lines(&mut self, file_id: u32, input_start: u32, count: u32, output_start: u32) -> &mut Self {
+        self.lines.push(LineInfo {
+            input_start,
+            file_id,
+            repeat_count: count,
+            output_start,
+            output_increment: 1,
+        });
+        self
+    }
+
+    #[must_use]
+    pub fn file(&self, id: u32) -> Option<&FileInfo> {
+        self.files.iter().find(|file| file.id == id)
+    }
+
+    /// Returns the source line which an output line was generated from.
+    ///
+    /// If several line sections contain the output line, the first one is used.
+    #[must_use]
+    pub fn translate(&self, output_line: u32) -> Option<SourceLine<'_>> {
+        self.lines.iter().find_map(|info| {
+            let offset = output_line.checked_sub(info.output_start)?;
+            let index = offset.checked_div(info.output_increment)?;
+            if index >= info.repeat_count {
+                return None;
+            }
+            Some(SourceLine {
+                file: self.file(info.file_id)?,
+                line: info.input_start.checked_add(index)?,
+            })
+        })
+    }
+
+    /// Returns every output line which was generated from a line of a source file, in ascending order.
+    #[must_use]
+    pub fn output_lines(&self, file_id: u32, input_line: u32) -> Vec<u32> {
+        let mut lines: Vec<u32> = self
+            .lines
+            .iter()
+            .filter(|info| info.file_id == file_id)
+            .filter_map(|info| {
+                let index = input_line.checked_sub(info.input_start)?;
+                if index >= info.repeat_count {
+                    return None;
+                }
+                let start = info
+                    .output_start
+                    .checked_add(index.checked_mul(info.output_increment)?)?;
+                Some(start..start.saturating_add(info.output_increment))
+            })
+            .flatten()
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    }
+
+    /// Translates the `LineNumberTable`s of a method.
+    ///
+    /// The lines are returned in the order of the tables.
+    #[must_use]
+    pub fn translate_code(&self, code: &Code) -> Vec<MappedLine<'_>> {
+        code.attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                Attribute::LineNumberTable(lines) => Some(lines),
+                _ => None,
+            })
+            .flatten()
+            .map(|line| {
+                let output_line = u32::from(line.line_number);
+                MappedLine {
+                    start: line.start,
+                    output_line,
+                    source: self.translate(output_line),
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileInfo {
+    pub id: u32,
+    /// The name of the file, as shown to the user.
+    pub name: String,
+    /// The path of the file relative to the source directory.
+    pub path: Option<String>,
+}
+
+/// A line section, which maps consecutive lines of a source file.
+///
+/// The `n`-th line starting at `input_start` is mapped to `output_increment` lines
+/// starting at `output_start + n * output_increment`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineInfo {
+    pub input_start: u32,
+    pub file_id: u32,
+    pub repeat_count: u32,
+    pub output_start: u32,
+    pub output_increment: u32,
+}
+
+/// Information specific to a vendor, which is kept as is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VendorSection {
+    pub id: String,
+    pub data: Vec<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SourceLine<'a> {
+    pub file: &'a FileInfo,
+    pub line: u32,
+}
+
+/// An entry of a `LineNumberTable` and the source line it was generated from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MappedLine<'a> {
+    /// The first instruction of the line.
+    pub start: Label,
+    /// The line in the output file, as stored in the `LineNumberTable`.
+    pub output_line: u32,
+    pub source: Option<SourceLine<'a>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SmapErrorKind {
+    InvalidHeader,
+    InvalidStratum,
+    InvalidFile,
+    InvalidLine,
+    EmbeddedSourceMap,
+}
+
+impl fmt::Display for SmapErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use SmapErrorKind::*;
+
+        match *self {
+            InvalidHeader => write!(f, "invalid header"),
+            InvalidStratum => write!(f, "invalid stratum section"),
+            InvalidFile => write!(f, "invalid file info"),
+            InvalidLine => write!(f, "invalid line info"),
+            EmbeddedSourceMap => write!(f, "unresolved embedded source map"),
+        }
+    }
+}
+
+/// An error that occurred while parsing a source map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmapError {
+    line: usize,
+    kind: SmapErrorKind,
+}
+
+impl SmapError {
+    #[must_use]
+    pub fn kind(&self) -> SmapErrorKind {
+        self.kind
+    }
+
+    /// The 1-based line number at which the error occurred, or 0 if the source map ended too early.
+    #[must_use]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl Error for SmapError {}
+
+impl fmt::Display for SmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in line {}", self.kind, self.line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{Instruction, LineNumber};
+
+    #[test]
+    fn kotlin_strata() {
+        // as written by the Kotlin compiler for inlined functions, with an end section for every stratum
+        let smap = SourceMap::parse(
+            "SMAP\nTypesJVM.kt\nKotlin\n*S Kotlin\n*F\n+ 1 TypesJVM.kt\nkotlin/reflect/ParameterizedTypeImpl\n\
+             + 2 ArraysJVM.kt\nkotlin/collections/ArraysKt__ArraysJVMKt\n*L\n1#1,230:1\n37#2,2:231\n*E\n\
+             *S KotlinDebug\n*F\n+ 1 TypesJVM.kt\nkotlin/reflect/ParameterizedTypeImpl\n*L\n190#1:231,2\n*E\n",
+        )
+        .unwrap();
+        assert_eq!(smap.strata.len(), 2);
+
+        let code = Code {
+            max_stack: 0,
+            max_locals: 0,
+            instructions: vec![Instruction::Return],
+            exception_handlers: Vec::new(),
+            attributes: vec![Attribute::LineNumberTable(vec![
+                LineNumber {
+                    start: Label(0),
+                    line_number: 190,
+                },
+                LineNumber {
+                    start: Label(4),
+                    line_number: 232,
+                },
+            ])],
+        };
+        let kotlin = smap.stratum("Kotlin").unwrap().translate_code(&code);
+        assert_eq!(kotlin[0].source.unwrap().line, 190);
+        assert_eq!(kotlin[1].source.unwrap().file.name, "ArraysJVM.kt");
+        assert_eq!(kotlin[1].source.unwrap().line, 38);
+        let debug = smap.stratum("KotlinDebug").unwrap();
+        assert_eq!(debug.translate(232).unwrap().line, 190);
+        assert_eq!(debug.output_lines(1, 190), [231, 232]);
+        assert!(debug.translate(233).is_none());
+    }
+
+    #[test]
+    fn errors() {
+        let error = |input: &str| SourceMap::parse(input).unwrap_err();
+        assert_eq!(error("SMAP\nA.java\n").kind(), SmapErrorKind::InvalidHeader);
+        assert_eq!(error("SMAP\nA.java\nJSP\n*S JSP\n*F\nx A.jsp\n").line(), 6);
+        assert_eq!(
+            error("SMAP\nA.java\nJSP\n*S JSP\n*L\n1#1:x\n").kind(),
+            SmapErrorKind::InvalidLine
+        );
+        assert_eq!(
+            error("SMAP\nA.java\nJSP\n*O JSP\n").kind(),
+            SmapErrorKind::EmbeddedSourceMap
+        );
+
+        let mut smap = SourceMap::new("A.java", "JSP");
+        let mut stratum = Stratum::new("JSP");
+        let file = stratum.add_file("a.jsp", None);
+        stratum.add_lines(file, 1, 3, 10);
+        smap.add_stratum(stratum);
+        assert_eq!(
+            smap.to_string(),
+            "SMAP\nA.java\nJSP\n*S JSP\n*F\n1 a.jsp\n*L\n1#1,3:10\n*E\n"
+        );
+    }
+}
@@ -0,0 +1,861 @@
+//! An owned representation of a class file with every constant pool reference resolved.
+//!
+//! A [`Class`] can be read from a class file, freely modified and written back out again.
+//! Jumps and other positions inside of code are represented by [`Label`]s, so instructions can be
+//! inserted or removed without recomputing any offsets.
+//!
+//! With the `serde` feature enabled, every type of this module can be serialized,
+//! for example to dump a class as JSON or YAML and reassemble it after editing the dump.
+//!
+//! ```
+//! use noak::tree::{Attribute, Class, Instruction};
+//! use noak::writer::ClassWriter;
+//! use noak::{AccessFlags, Version};
+//!
+//! let bytes = ClassWriter::new()
+//!     .version(Version::V8)?
+//!     .access_flags(AccessFlags::PUBLIC | AccessFlags::SUPER)?
+//!     .this_class("Example")?
+//!     .super_class("java/lang/Object")?
+//!     .interfaces(|_| Ok(()))?
+//!     .fields(|_| Ok(()))?
+//!     .methods(|methods| {
+//!         methods.begin(|method| {
+//!             method
+//!                 .access_flags(AccessFlags::PUBLIC | AccessFlags::STATIC)?
+//!                 .name("answer")?
+//!                 .descriptor("()I")?
+//!                 .attributes(|attributes| {
+//!                     attributes.begin(|attribute| {
+//!                         attribute.code(|code| {
+//!                             code.max_stack(1)?
+//!                                 .max_locals(0)?
+//!                                 .instructions(|insns| {
+//!                                     insns.bipush(42)?.ireturn()?;
+//!                                     Ok(())
+//!                                 })?
+//!                                 .exceptions(|_| Ok(()))?
+//!                                 .attributes(|_| Ok(()))
+//!                         })
+//!                     })?;
+//!                     Ok(())
+//!                 })
+//!         })?;
+//!         Ok(())
+//!     })?
+//!     .attributes(|_| Ok(()))?
+//!     .into_bytes()?;
+//!
+//! let mut class = Class::read(&bytes)?;
+//! if let Attribute::Code(code) = &mut class.methods[0].attributes[0] {
+//!     code.instructions[0] = Instruction::BIPush { value: 7 };
+//! }
+//! let modified = Class::read(&class.to_bytes()?)?;
+//! assert_eq!(*modified.methods[0].name, "answer");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+mod annotations;
+mod attributes;
+mod builder;
+mod code;
+mod constant;
+mod decode;
+mod encode;
+mod index;
+mod optimize;
+mod pool;
+mod validate;
+
+pub use annotations::Annotations;
+pub use attributes::{
+    Annotation, Attribute, BootstrapMethod, ElementValue, ElementValuePair, Export, InnerClass, LineNumber,
+    LocalVariable, LocalVariableTarget, LocalVariableType, MethodParameter, Module, Open, Provide, RecordComponent,
+    Require, TargetInfo, TypeAnnotation, TypePathSegment,
+};
+pub use builder::{Case, Catch, CodeBuilder, Condition, Local, LocalType};
+pub use code::{Code, ExceptionHandler, Instruction, Label, StackMapFrame, VerificationType};
+pub use constant::{Constant, DynamicConstant, MemberRef, MethodHandle, NameAndType};
+pub use index::{IndexedClass, IndexedMember};
+pub use pool::PoolOrder;
+
+use crate::error::{DecodeError, EncodeError};
+use crate::header::{AccessFlags, Version};
+use crate::mutf8::MString;
+use crate::reader;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Class {
+    pub version: Version,
+    pub access_flags: AccessFlags,
+    pub name: MString,
+    pub super_class: Option<MString>,
+    pub interfaces: Vec<MString>,
+    /// The entries of the constant pool.
+    ///
+    /// Every entry referenced by the rest of the class is added automatically when writing the class,
+    /// so this only needs to contain constants which should be kept even though they are not referenced.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub constant_pool: Vec<Constant>,
+    pub fields: Vec<Field>,
+    pub methods: Vec<Method>,
+    pub attributes: Vec<Attribute>,
+}
+
+impl Class {
+    /// Creates an empty public class for Java 8 which extends `java/lang/Object`.
+    ///
+    /// The other fields can be set with struct update syntax:
+    ///
+    /// ```
+    /// use noak::tree::{Class, Field};
+    /// use noak::AccessFlags;
+    ///
+    /// let class = Class {
+    ///     fields: vec![Field {
+    ///         access_flags: AccessFlags::PRIVATE,
+    ///         name: "count".into(),
+    ///         descriptor: "I".into(),
+    ///         attributes: Vec::new(),
+    ///     }],
+    ///     ..Class::new("Example")
+    /// };
+    /// assert_eq!(*class.name, "Example");
+    /// ```
+    #[must_use]
+    pub fn new(name: impl Into<MString>) -> Class {
+        Class {
+            version: Version::V8,
+            access_flags: AccessFlags::PUBLIC | AccessFlags::SUPER,
+            name: name.into(),
+            super_class: Some("java/lang/Object".into()),
+            interfaces: Vec::new(),
+            constant_pool: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Reads a class from the bytes of a class file.
+    pub fn read(bytes: &[u8]) -> Result<Class, DecodeError> {
+        Class::from_reader(&reader::Class::new(bytes)?)
+    }
+
+    /// Reads a class like the JVM does, skipping malformed parts which it ignores.
+    ///
+    /// Instead of failing, this skips constant pool entries which are invalid but unused,
+    /// attributes which can't be resolved, such as junk attributes added by obfuscators,
+    /// and invalid code of abstract and native methods.
+    /// Every part that was skipped is returned as a warning, whose [path](DecodeError::path) is its location.
+    ///
+    /// ```no_run
+    /// use noak::tree::Class;
+    ///
+    /// # let bytes = &[];
+    /// let (class, warnings) = Class::read_lenient(bytes)?;
+    /// for warning in warnings {
+    ///     println!("skipped {}: {}", warning.path(), warning.kind());
+    /// }
+    /// # Ok::<(), noak::error::DecodeError>(())
+    /// ```
+    pub fn read_lenient(bytes: &[u8]) -> Result<(Class, Vec<DecodeError>), DecodeError> {
+        let (class, mut warnings) = reader::Class::new_lenient(bytes)?;
+        let class = decode::class(&class, Some(&mut warnings))?;
+        Ok((class, warnings))
+    }
+
+    /// Resolves every part of a class which was already read.
+    pub fn from_reader(class: &reader::Class<'_>) -> Result<Class, DecodeError> {
+        decode::class(class, None)
+    }
+
+    /// Reads every part of a class eagerly and returns all errors that were found.
+    ///
+    /// This resolves every constant pool reference, checks all descriptors and decodes
+    /// all known attributes, including annotations and the instructions of code.
+    /// Checking continues after an error wherever the rest of the class can still be read.
+    /// The [path](DecodeError::path) of each error is the location at which it was found.
+    ///
+    /// ```no_run
+    /// use noak::{reader, tree};
+    ///
+    /// # let data = &[];
+    /// let class = reader::Class::new(data)?;
+    /// for error in tree::Class::validate(&class) {
+    ///     println!("{} at {}", error.kind(), error.path());
+    /// }
+    /// # Ok::<(), noak::error::DecodeError>(())
+    /// ```
+    #[must_use]
+    pub fn validate(class: &reader::Class<'_>) -> Vec<DecodeError> {
+        validate::class(class)
+    }
+
+    /// Writes this class to the bytes of a class file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        encode::class(self)
+    }
+
+    /// Removes every constant from the [constant pool](Class::constant_pool) which isn't referenced
+    /// by the rest of the class, such as the constants of removed methods or attributes.
+    ///
+    /// All references, including the ones inside of code, attributes and bootstrap method arguments,
+    /// point to the new indices once the class is written.
+    /// The content of [unknown attributes](Attribute::Unknown) is copied as-is, so constants only
+    /// referenced by them are removed as well and indices inside of them are not updated.
+    ///
+    /// ```
+    /// use noak::tree::{Class, Constant, PoolOrder};
+    ///
+    /// let mut class = Class {
+    ///     constant_pool: vec![Constant::Utf8("unused".into()), Constant::Class("Example".into())],
+    ///     ..Class::new("Example")
+    /// };
+    /// class.compact_constant_pool(PoolOrder::Preserve)?;
+    /// assert_eq!(class.constant_pool, [Constant::Class("Example".into())]);
+    /// # Ok::<(), noak::error::EncodeError>(())
+    /// ```
+    pub fn compact_constant_pool(&mut self, order: PoolOrder) -> Result<(), EncodeError> {
+        pool::compact(self, order)
+    }
+
+    /// Cleans up the code of every method, and removes the code which can't be reached.
+    ///
+    /// Jumps to jumps are shortened, branches on constants are resolved, and instructions which have no effect,
+    /// such as a load followed by a store to the same variable, are removed.
+    /// Exception handlers, line numbers, local variables and stack map frames are updated accordingly.
+    /// The code of a method is left unchanged if it can't be analyzed, for example if a label is not placed.
+    /// Neither `max_stack` nor `max_locals` are lowered.
+    ///
+    /// ```
+    /// use noak::tree::{Attribute, Class, Code, Instruction, Label, Method};
+    /// use noak::{AccessFlags, Version};
+    ///
+    /// let mut class = Class {
+    ///     version: Version::V6,
+    ///     methods: vec![Method {
+    ///         access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC,
+    ///         name: "run".into(),
+    ///         descriptor: "()V".into(),
+    ///         attributes: vec![Attribute::Code(Code {
+    ///             max_stack: 1,
+    ///             max_locals: 0,
+    ///             instructions: vec![
+    ///                 Instruction::IConst0,
+    ///                 Instruction::IfEq { target: Label(0) },
+    ///                 Instruction::Nop,
+    ///                 Instruction::Label(Label(0)),
+    ///                 Instruction::Return,
+    ///             ],
+    ///             exception_handlers: Vec::new(),
+    ///             attributes: Vec::new(),
+    ///         })],
+    ///     }],
+    ///     ..Class::new("Example")
+    /// };
+    ///
+    /// class.optimize();
+    /// let Attribute::Code(code) = &class.methods[0].attributes[0] else { unreachable!() };
+    /// assert_eq!(code.instructions, [Instruction::Label(Label(0)), Instruction::Return]);
+    /// ```
+    pub fn optimize(&mut self) {
+        for method in &mut self.methods {
+            optimize::method(&self.name, method);
+        }
+    }
+
+    #[must_use]
+    pub fn annotations(&self) -> Annotations<'_> {
+        Annotations::of(&self.attributes)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Field {
+    pub access_flags: AccessFlags,
+    pub name: MString,
+    pub descriptor: MString,
+    pub attributes: Vec<Attribute>,
+}
+
+impl Field {
+    #[must_use]
+    pub fn annotations(&self) -> Annotations<'_> {
+        Annotations::of(&self.attributes)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Method {
+    pub access_flags: AccessFlags,
+    pub name: MString,
+    pub descriptor: MString,
+    pub attributes: Vec<Attribute>,
+}
+
+impl Method {
+    #[must_use]
+    pub fn annotations(&self) -> Annotations<'_> {
+        Annotations::of(&self.attributes)
+    }
+
+    /// Returns the annotations of a parameter.
+    ///
+    /// Parameters which are added by the compiler, like the outer instance passed to the constructor of an inner
+    /// class, may not be counted.
+    #[must_use]
+    pub fn parameter_annotations(&self, index: usize) -> Annotations<'_> {
+        Annotations::of_parameter(&self.attributes, index)
+    }
+}
+
+/// Serialization of floating point numbers, as not every format supports infinities and NaN.
+#[cfg(feature = "serde")]
+mod float {
+    macro_rules! float_serde {
+        ($name:ident, $ty:ty) => {
+            pub(super) mod $name {
+                use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+                pub(in crate::tree) fn serialize<S: Serializer>(value: &$ty, serializer: S) -> Result<S::Ok, S::Error> {
+                    if value.is_nan() {
+                        serializer.serialize_str("NaN")
+                    } else if value.is_infinite() {
+                        serializer.serialize_str(if value.is_sign_positive() {
+                            "Infinity"
+                        } else {
+                            "-Infinity"
+                        })
+                    } else {
+                        value.serialize(serializer)
+                    }
+                }
+
+                pub(in crate::tree) fn deserialize<'de, D: Deserializer<'de>>(
+                    deserializer: D,
+                ) -> Result<$ty, D::Error> {
+                    #[derive(Deserialize)]
+                    #[serde(untagged)]
+                    enum Repr {
+                        Number($ty),
+                        Name(String),
+                    }
+
+                    match Repr::deserialize(deserializer)? {
+                        Repr::Number(value) => Ok(value),
+                        Repr::Name(name) => match name.as_str() {
+                            "NaN" => Ok(<$ty>::NAN),
+                            "Infinity" => Ok(<$ty>::INFINITY),
+                            "-Infinity" => Ok(<$ty>::NEG_INFINITY),
+                            _ => Err(de::Error::invalid_value(de::Unexpected::Str(&name), &"a number")),
+                        },
+                    }
+                }
+            }
+        };
+    }
+
+    float_serde!(single, f32);
+    float_serde!(double, f64);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::{DecodeErrorKind, EncodeErrorKind};
+    use crate::header::Feature;
+    use crate::writer::ClassWriter;
+
+    /// Writes a class with a method returning the larger of its two arguments.
+    fn max_class() -> Vec<u8> {
+        ClassWriter::new()
+            .version(Version::V8)
+            .unwrap()
+            .access_flags(AccessFlags::PUBLIC | AccessFlags::SUPER)
+            .unwrap()
+            .this_class("Example")
+            .unwrap()
+            .super_class("java/lang/Object")
+            .unwrap()
+            .interfaces(|_| Ok(()))
+            .unwrap()
+            .fields(|_| Ok(()))
+            .unwrap()
+            .methods(|methods| {
+                methods.begin(|method| {
+                    method
+                        .access_flags(AccessFlags::PUBLIC | AccessFlags::STATIC)?
+                        .name("max")?
+                        .descriptor("(II)I")?
+                        .attributes(|attributes| {
+                            attributes.begin(|attribute| {
+                                attribute.code(|mut code| {
+                                    let (second, second_ref) = code.new_label()?;
+                                    code.max_stack(2)?
+                                        .max_locals(2)?
+                                        .instructions(|insns| {
+                                            insns
+                                                .iload0()?
+                                                .iload1()?
+                                                .ificmplt(second_ref)?
+                                                .iload0()?
+                                                .ireturn()?
+                                                .label(second)?
+                                                .iload1()?
+                                                .ireturn()?;
+                                            Ok(())
+                                        })?
+                                        .exceptions(|_| Ok(()))?
+                                        .attributes(|attributes| {
+                                            attributes.begin(|attribute| {
+                                                attribute.stack_map_table(|frames| {
+                                                    frames.same(second_ref)?;
+                                                    Ok(())
+                                                })
+                                            })?;
+                                            Ok(())
+                                        })
+                                })
+                            })?;
+                            Ok(())
+                        })
+                })?;
+                Ok(())
+            })
+            .unwrap()
+            .attributes(|attributes| {
+                attributes.begin(|attribute| attribute.source_file("Example.java"))?;
+                Ok(())
+            })
+            .unwrap()
+            .into_bytes()
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = max_class();
+        let class = Class::read(&bytes).unwrap();
+        assert_eq!(*class.name, "Example");
+        let Attribute::Code(code) = &class.methods[0].attributes[0] else {
+            panic!("expected a code attribute");
+        };
+        assert_eq!(code.instructions[2], Instruction::IfICmpLt { target: Label(7) });
+        assert_eq!(code.instructions[5], Instruction::Label(Label(7)));
+
+        assert_eq!(class.to_bytes().unwrap(), bytes);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let bytes = max_class();
+        let class = Class::read(&bytes).unwrap();
+
+        let json = serde_json::to_string(&class).unwrap();
+        let deserialized: Class = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, class);
+        assert_eq!(deserialized.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn encode_error_path() {
+        let class = Class {
+            methods: vec![Method {
+                access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC,
+                name: "spin".into(),
+                descriptor: "()V".into(),
+                attributes: vec![Attribute::Code(Code {
+                    max_stack: 0,
+                    max_locals: 0,
+                    instructions: vec![Instruction::Goto { target: Label(3) }],
+                    exception_handlers: Vec::new(),
+                    attributes: Vec::new(),
+                })],
+            }],
+            ..Class::new("Example")
+        };
+
+        let error = class.to_bytes().unwrap_err();
+        assert_eq!(error.step(), Some("CodeWriter::instructions"));
+        assert_eq!(
+            error.path().segments().last(),
+            Some(&crate::error::PathSegment::Instruction { pc: 0 })
+        );
+    }
+
+    #[test]
+    fn read_lenient() {
+        use crate::writer::{cpool as wcpool, encoding::*};
+
+        let mut bytes = ClassWriter::new()
+            .version(Version::V8)
+            .unwrap()
+            .access_flags(AccessFlags::PUBLIC | AccessFlags::ABSTRACT)
+            .unwrap()
+            .this_class("Example")
+            .unwrap()
+            .super_class("java/lang/Object")
+            .unwrap()
+            .interfaces(|_| Ok(()))
+            .unwrap()
+            .fields(|_| Ok(()))
+            .unwrap()
+            .methods(|methods| {
+                methods.begin(|method| {
+                    method
+                        .access_flags(AccessFlags::PUBLIC | AccessFlags::ABSTRACT)?
+                        .name("run")?
+                        .descriptor("()V")?
+                        .attributes(|attributes| {
+                            attributes.begin(|attribute| {
+                                attribute.content("Code", |ctx| {
+                                    ctx.encoder().write(0u16)?.write(0u16)?.write(0xFFFFu32)?;
+                                    Ok(())
+                                })
+                            })?;
+                            Ok(())
+                        })
+                })?;
+                Ok(())
+            })
+            .unwrap()
+            .attributes(|attributes| {
+                attributes.begin(|attribute| {
+                    attribute.content("SourceFile", |ctx| {
+                        let _: wcpool::Index<wcpool::Utf8> = wcpool::Insertable::insert("unused", ctx)?;
+                        let index: wcpool::Index<wcpool::Class> = wcpool::Insertable::insert("Example", ctx)?;
+                        ctx.encoder().write(index)?;
+                        Ok(())
+                    })
+                })?;
+                Ok(())
+            })
+            .unwrap()
+            .into_bytes()
+            .unwrap();
+
+        // make the unused entry invalid modified UTF-8
+        let position = bytes.windows(6).position(|window| window == b"unused").unwrap();
+        bytes[position] = 0xFF;
+
+        assert!(Class::read(&bytes).is_err());
+        let (class, warnings) = Class::read_lenient(&bytes).unwrap();
+        assert!(class.methods[0].attributes.is_empty());
+        assert!(class.attributes.is_empty());
+
+        let warnings: Vec<_> = warnings
+            .iter()
+            .map(|warning| (warning.kind(), warning.path().to_string()))
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                (DecodeErrorKind::InvalidMutf8, "constant pool entry #9".to_owned()),
+                (
+                    DecodeErrorKind::UnexpectedEoi,
+                    "method `run()V` -> attribute `Code`".to_owned()
+                ),
+                (DecodeErrorKind::TagMismatch, "attribute `SourceFile`".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_lenient_record_component() {
+        use crate::writer::{cpool as wcpool, encoding::*};
+
+        let bytes = ClassWriter::new()
+            .version(Version::V17)
+            .unwrap()
+            .access_flags(AccessFlags::FINAL | AccessFlags::SUPER)
+            .unwrap()
+            .this_class("Point")
+            .unwrap()
+            .super_class("java/lang/Record")
+            .unwrap()
+            .interfaces(|_| Ok(()))
+            .unwrap()
+            .fields(|_| Ok(()))
+            .unwrap()
+            .methods(|_| Ok(()))
+            .unwrap()
+            .attributes(|attributes| {
+                attributes.begin(|attribute| {
+                    attribute.content("Record", |ctx| {
+                        let name: wcpool::Index<wcpool::Utf8> = wcpool::Insertable::insert("x", ctx)?;
+                        let descriptor: wcpool::Index<wcpool::Utf8> = wcpool::Insertable::insert("I", ctx)?;
+                        let signature: wcpool::Index<wcpool::Utf8> = wcpool::Insertable::insert("Signature", ctx)?;
+                        // the signature refers to a class instead of a string
+                        let class: wcpool::Index<wcpool::Class> = wcpool::Insertable::insert("Point", ctx)?;
+                        ctx.encoder()
+                            .write(1u16)?
+                            .write(name)?
+                            .write(descriptor)?
+                            .write(1u16)?
+                            .write(signature)?
+                            .write(2u32)?
+                            .write(class)?;
+                        Ok(())
+                    })
+                })?;
+                Ok(())
+            })
+            .unwrap()
+            .into_bytes()
+            .unwrap();
+
+        assert!(Class::read(&bytes).is_err());
+        let (class, warnings) = Class::read_lenient(&bytes).unwrap();
+        let [Attribute::Record(components)] = &class.attributes[..] else {
+            panic!("expected a record attribute");
+        };
+        assert_eq!(*components[0].name, "x");
+        assert!(components[0].attributes.is_empty());
+
+        let warnings: Vec<_> = warnings
+            .iter()
+            .map(|warning| (warning.kind(), warning.path().to_string()))
+            .collect();
+        assert_eq!(
+            warnings,
+            [(
+                DecodeErrorKind::TagMismatch,
+                "attribute `Record` -> entry 0 -> attribute `Signature`".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn reject_unsupported_attribute() {
+        let class = Class {
+            access_flags: AccessFlags::FINAL | AccessFlags::SUPER,
+            super_class: Some("java/lang/Record".into()),
+            attributes: vec![Attribute::Record(Vec::new())],
+            ..Class::new("Point")
+        };
+
+        let error = class.to_bytes().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            EncodeErrorKind::UnsupportedFeature {
+                feature: Feature::Attribute("Record"),
+                version: Version::V8,
+            }
+        ));
+    }
+
+    #[test]
+    fn reject_default_method() {
+        let method = |access_flags, name: &str, code: bool| Method {
+            access_flags,
+            name: name.into(),
+            descriptor: "()V".into(),
+            attributes: if code {
+                vec![Attribute::Code(Code {
+                    max_stack: 0,
+                    max_locals: 1,
+                    instructions: vec![Instruction::Return],
+                    exception_handlers: Vec::new(),
+                    attributes: Vec::new(),
+                })]
+            } else {
+                Vec::new()
+            },
+        };
+        let interface = |version, methods| Class {
+            version,
+            access_flags: AccessFlags::PUBLIC | AccessFlags::INTERFACE | AccessFlags::ABSTRACT,
+            methods,
+            ..Class::new("Greeter")
+        };
+
+        let class = interface(Version::V7, vec![method(AccessFlags::PUBLIC, "greet", true)]);
+        let error = class.to_bytes().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            EncodeErrorKind::UnsupportedFeature {
+                feature: Feature::DefaultMethods,
+                version: Version::V7,
+            }
+        ));
+
+        let class = interface(Version::V8, vec![method(AccessFlags::PUBLIC, "greet", true)]);
+        assert!(class.to_bytes().is_ok());
+
+        let class = interface(
+            Version::V7,
+            vec![
+                method(AccessFlags::PUBLIC | AccessFlags::ABSTRACT, "greet", false),
+                method(AccessFlags::STATIC, "<clinit>", true),
+            ],
+        );
+        assert!(class.to_bytes().is_ok());
+    }
+
+    #[test]
+    fn code_builder() {
+        let mut builder = CodeBuilder::new();
+        let args = builder.parameter("args", "[Ljava/lang/String;").unwrap();
+        builder.begin_scope();
+        let sum = builder.declare("sum", "D").unwrap();
+        builder.push_double(0.0).store(sum);
+        for i in 0..300 {
+            builder.push_string(format!("constant {i}").as_str());
+            builder.emit(Instruction::Pop);
+        }
+        builder.end_scope().unwrap();
+        let error = builder.end_scope().unwrap_err();
+        assert!(matches!(error.kind(), EncodeErrorKind::NotInScope));
+        let flag = builder.declare("flag", "Z").unwrap();
+        builder.push_int(1).store(flag).emit(Instruction::Return);
+        assert_eq!((args.index(), sum.index(), flag.index()), (0, 1, 1));
+
+        let class = Class {
+            version: Version::V6,
+            methods: vec![Method {
+                access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC,
+                name: "main".into(),
+                descriptor: "([Ljava/lang/String;)V".into(),
+                attributes: vec![Attribute::Code(builder.finish(2))],
+            }],
+            ..Class::new("Example")
+        };
+
+        let class = Class::read(&class.to_bytes().unwrap()).unwrap();
+        let Attribute::Code(code) = &class.methods[0].attributes[0] else {
+            panic!("expected a code attribute");
+        };
+        assert_eq!(code.max_locals, 3);
+        assert!(code
+            .instructions
+            .iter()
+            .any(|instruction| matches!(instruction, Instruction::LdCW { .. })));
+        let Attribute::LocalVariableTable(variables) = &code.attributes[0] else {
+            panic!("expected a local variable table");
+        };
+        let names: Vec<_> = variables
+            .iter()
+            .map(|variable| (variable.name.to_str().unwrap(), variable.index))
+            .collect();
+        assert_eq!(names, [("args", 0), ("sum", 1), ("flag", 1)]);
+    }
+
+    #[test]
+    fn annotation_lookup() {
+        let annotation = |type_: &str, pairs: Vec<ElementValuePair>| Annotation {
+            type_: type_.into(),
+            pairs,
+        };
+        let method = Method {
+            access_flags: AccessFlags::PUBLIC,
+            name: "inject".into(),
+            descriptor: "(Ljava/lang/String;I)V".into(),
+            attributes: vec![
+                Attribute::RuntimeInvisibleAnnotations(vec![annotation("Lorg/example/Internal;", Vec::new())]),
+                Attribute::RuntimeVisibleParameterAnnotations(vec![
+                    Vec::new(),
+                    vec![annotation(
+                        "Lorg/example/Range;",
+                        vec![ElementValuePair {
+                            name: "values".into(),
+                            value: ElementValue::Array(vec![ElementValue::Int(1), ElementValue::Int(10)]),
+                        }],
+                    )],
+                ]),
+            ],
+        };
+        assert_eq!(method.annotations().len(), 1);
+        assert!(method.annotations().visible().is_empty());
+        assert!(method.annotations().contains("Lorg/example/Internal;"));
+
+        assert!(method.parameter_annotations(0).is_empty());
+        assert!(method.parameter_annotations(2).is_empty());
+        let range = method.parameter_annotations(1).get("Lorg/example/Range;").unwrap();
+        let values = range.value("values").and_then(ElementValue::as_array).unwrap();
+        assert_eq!(values[1].as_int(), Some(10));
+        assert!(range.value("missing").is_none());
+    }
+
+    #[test]
+    fn validate_collects_all_errors() {
+        use crate::writer::{cpool as wcpool, encoding::*, ClassWriter};
+
+        let bytes = ClassWriter::new()
+            .version(Version::V8)
+            .unwrap()
+            .access_flags(AccessFlags::PUBLIC)
+            .unwrap()
+            .this_class("Example")
+            .unwrap()
+            .super_class("java/lang/Object")
+            .unwrap()
+            .interfaces(|_| Ok(()))
+            .unwrap()
+            .fields(|fields| {
+                fields.begin(|field| {
+                    field
+                        .access_flags(AccessFlags::PRIVATE)?
+                        .name("broken")?
+                        .descriptor("Q")?
+                        .attributes(|_| Ok(()))
+                })?;
+                Ok(())
+            })
+            .unwrap()
+            .methods(|methods| {
+                methods.begin(|method| {
+                    method
+                        .access_flags(AccessFlags::PUBLIC | AccessFlags::ABSTRACT)?
+                        .name("run")?
+                        .descriptor("()V")?
+                        .attributes(|attributes| {
+                            attributes.begin(|attribute| {
+                                attribute.content("Exceptions", |ctx| {
+                                    ctx.encoder().write(1u16)?.write(0x7FFFu16)?;
+                                    Ok(())
+                                })
+                            })?;
+                            Ok(())
+                        })
+                })?;
+                Ok(())
+            })
+            .unwrap()
+            .attributes(|attributes| {
+                attributes.begin(|attribute| {
+                    attribute.content("SourceFile", |ctx| {
+                        let index: wcpool::Index<wcpool::Class> = wcpool::Insertable::insert("Example", ctx)?;
+                        ctx.encoder().write(index)?;
+                        Ok(())
+                    })
+                })?;
+                Ok(())
+            })
+            .unwrap()
+            .into_bytes()
+            .unwrap();
+
+        let class = reader::Class::new(&bytes).unwrap();
+        let errors = Class::validate(&class);
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|error| (error.kind(), error.path().to_string()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (DecodeErrorKind::InvalidDescriptor, "field `broken`".to_owned()),
+                (
+                    DecodeErrorKind::InvalidIndex,
+                    "method `run()V` -> attribute `Exceptions`".to_owned()
+                ),
+                (DecodeErrorKind::TagMismatch, "attribute `SourceFile`".to_owned()),
+            ]
+        );
+    }
+}
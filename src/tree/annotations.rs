@@ -0,0 +1,231 @@
+use crate::mutf8::MStr;
+use crate::tree::{Annotation, Attribute, ElementValue};
+
+/// The annotations of a class, field, method, parameter or record component.
+///
+/// Both the annotations which are visible at runtime and the ones which are only stored in the class file are
+/// included, with the visible ones first. Annotations are identified by the descriptor of their type.
+///
+/// ```
+/// use noak::tree::{Annotation, Attribute, ElementValue, ElementValuePair, Field};
+/// use noak::AccessFlags;
+///
+/// // @Named("primary") String name;
+/// let field = Field {
+///     access_flags: AccessFlags::empty(),
+///     name: "name".into(),
+///     descriptor: "Ljava/lang/String;".into(),
+///     attributes: vec![Attribute::RuntimeVisibleAnnotations(vec![Annotation {
+///         type_: "Ljavax/inject/Named;".into(),
+///         pairs: vec![ElementValuePair {
+///             name: "value".into(),
+///             value: ElementValue::String("primary".into()),
+///         }],
+///     }])],
+/// };
+/// let named = field.annotations().get("Ljavax/inject/Named;").unwrap();
+/// assert_eq!(named.value("value").and_then(ElementValue::as_str).unwrap(), "primary");
+/// assert!(!field.annotations().contains("Ljavax/annotation/Nullable;"));
+/// ```
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Annotations<'a> {
+    visible: &'a [Annotation],
+    invisible: &'a [Annotation],
+}
+
+impl<'a> Annotations<'a> {
+    /// Collects the annotations stored in attributes.
+    #[must_use]
+    pub fn of(attributes: &'a [Attribute]) -> Annotations<'a> {
+        let mut annotations = Annotations::default();
+        for attribute in attributes {
+            match attribute {
+                Attribute::RuntimeVisibleAnnotations(visible) => annotations.visible = visible,
+                Attribute::RuntimeInvisibleAnnotations(invisible) => annotations.invisible = invisible,
+                _ => {}
+            }
+        }
+        annotations
+    }
+
+    /// Collects the annotations of a parameter stored in the attributes of a method.
+    ///
+    /// The index is the index in the parameter annotation attributes, which may not contain synthetic parameters.
+    #[must_use]
+    pub fn of_parameter(attributes: &'a [Attribute], index: usize) -> Annotations<'a> {
+        let mut annotations = Annotations::default();
+        for attribute in attributes {
+            match attribute {
+                Attribute::RuntimeVisibleParameterAnnotations(parameters) => {
+                    annotations.visible = parameters.get(index).map_or(&[], |visible| &visible[..]);
+                }
+                Attribute::RuntimeInvisibleParameterAnnotations(parameters) => {
+                    annotations.invisible = parameters.get(index).map_or(&[], |invisible| &invisible[..]);
+                }
+                _ => {}
+            }
+        }
+        annotations
+    }
+
+    /// Returns the annotation of a type, e.g. `Ljava/lang/Deprecated;`.
+    #[must_use]
+    pub fn get(&self, type_: &str) -> Option<&'a Annotation> {
+        self.iter().find(|annotation| *annotation.type_ == *type_)
+    }
+
+    #[must_use]
+    pub fn contains(&self, type_: &str) -> bool {
+        self.get(type_).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a Annotation> + 'a {
+        self.visible.iter().chain(self.invisible)
+    }
+
+    /// The annotations which are visible at runtime, which have the retention policy `RUNTIME`.
+    #[must_use]
+    pub fn visible(&self) -> &'a [Annotation] {
+        self.visible
+    }
+
+    /// The annotations which are only stored in the class file, which have the retention policy `CLASS`.
+    #[must_use]
+    pub fn invisible(&self) -> &'a [Annotation] {
+        self.invisible
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.visible.len() + self.invisible.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Annotation {
+    /// Returns the value of an element.
+    ///
+    /// Elements which are not stored, because they have the default value declared by the annotation interface,
+    /// are not found.
+    #[must_use]
+    pub fn value(&self, name: &str) -> Option<&ElementValue> {
+        self.pairs
+            .iter()
+            .find(|pair| *pair.name == *name)
+            .map(|pair| &pair.value)
+    }
+}
+
+impl ElementValue {
+    #[must_use]
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            ElementValue::Boolean(value) => Some(value != 0),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_byte(&self) -> Option<i8> {
+        match *self {
+            // the constant is truncated like by the JVM
+            ElementValue::Byte(value) => Some(value as i8),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_short(&self) -> Option<i16> {
+        match *self {
+            ElementValue::Short(value) => Some(value as i16),
+            _ => None,
+        }
+    }
+
+    /// Returns the UTF-16 code unit of a `char`.
+    #[must_use]
+    pub fn as_char(&self) -> Option<u16> {
+        match *self {
+            ElementValue::Char(value) => Some(value as u16),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_int(&self) -> Option<i32> {
+        match *self {
+            ElementValue::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_long(&self) -> Option<i64> {
+        match *self {
+            ElementValue::Long(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_float(&self) -> Option<f32> {
+        match *self {
+            ElementValue::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_double(&self) -> Option<f64> {
+        match *self {
+            ElementValue::Double(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> Option<&MStr> {
+        match self {
+            ElementValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the return descriptor of a class literal, e.g. `Ljava/lang/String;` or `V`.
+    #[must_use]
+    pub fn as_class(&self) -> Option<&MStr> {
+        match self {
+            ElementValue::Class(descriptor) => Some(descriptor),
+            _ => None,
+        }
+    }
+
+    /// Returns the descriptor of the type and the name of an enum constant.
+    #[must_use]
+    pub fn as_enum(&self) -> Option<(&MStr, &MStr)> {
+        match self {
+            ElementValue::Enum { type_name, const_name } => Some((type_name, const_name)),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_annotation(&self) -> Option<&Annotation> {
+        match self {
+            ElementValue::Annotation(annotation) => Some(annotation),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_array(&self) -> Option<&[ElementValue]> {
+        match self {
+            ElementValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
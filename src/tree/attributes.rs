@@ -0,0 +1,281 @@
+use crate::header::AccessFlags;
+use crate::mutf8::MString;
+use crate::reader::attributes::annotations::{SuperTypeIndex, TargetType, TypePathSegmentKind};
+use crate::tree::{Annotations, Code, Constant, Label, MethodHandle, NameAndType, StackMapFrame};
+
+/// An attribute with all of its content resolved.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Attribute {
+    AnnotationDefault {
+        value: ElementValue,
+    },
+    BootstrapMethods(Vec<BootstrapMethod>),
+    Code(Code),
+    ConstantValue {
+        value: Constant,
+    },
+    Deprecated,
+    EnclosingMethod {
+        class: MString,
+        method: Option<NameAndType>,
+    },
+    Exceptions(Vec<MString>),
+    InnerClasses(Vec<InnerClass>),
+    LineNumberTable(Vec<LineNumber>),
+    LocalVariableTable(Vec<LocalVariable>),
+    LocalVariableTypeTable(Vec<LocalVariableType>),
+    MethodParameters(Vec<MethodParameter>),
+    Module(Box<Module>),
+    ModuleMainClass(MString),
+    ModulePackages(Vec<MString>),
+    NestHost(MString),
+    NestMembers(Vec<MString>),
+    PermittedSubclasses(Vec<MString>),
+    Record(Vec<RecordComponent>),
+    RuntimeInvisibleAnnotations(Vec<Annotation>),
+    /// The annotations of each parameter.
+    RuntimeInvisibleParameterAnnotations(Vec<Vec<Annotation>>),
+    RuntimeInvisibleTypeAnnotations(Vec<TypeAnnotation>),
+    RuntimeVisibleAnnotations(Vec<Annotation>),
+    /// The annotations of each parameter.
+    RuntimeVisibleParameterAnnotations(Vec<Vec<Annotation>>),
+    RuntimeVisibleTypeAnnotations(Vec<TypeAnnotation>),
+    Signature(MString),
+    SourceDebugExtension(MString),
+    SourceFile(MString),
+    StackMapTable(Vec<StackMapFrame>),
+    Synthetic,
+    /// An attribute which is not known to noak, or which is not valid at its position.
+    Unknown {
+        name: MString,
+        content: Vec<u8>,
+    },
+}
+
+impl Attribute {
+    /// The name of the attribute in a class file.
+    #[must_use]
+    pub fn name(&self) -> MString {
+        let name = match self {
+            Attribute::AnnotationDefault { .. } => "AnnotationDefault",
+            Attribute::BootstrapMethods(_) => "BootstrapMethods",
+            Attribute::Code(_) => "Code",
+            Attribute::ConstantValue { .. } => "ConstantValue",
+            Attribute::Deprecated => "Deprecated",
+            Attribute::EnclosingMethod { .. } => "EnclosingMethod",
+            Attribute::Exceptions(_) => "Exceptions",
+            Attribute::InnerClasses(_) => "InnerClasses",
+            Attribute::LineNumberTable(_) => "LineNumberTable",
+            Attribute::LocalVariableTable(_) => "LocalVariableTable",
+            Attribute::LocalVariableTypeTable(_) => "LocalVariableTypeTable",
+            Attribute::MethodParameters(_) => "MethodParameters",
+            Attribute::Module(_) => "Module",
+            Attribute::ModuleMainClass(_) => "ModuleMainClass",
+            Attribute::ModulePackages(_) => "ModulePackages",
+            Attribute::NestHost(_) => "NestHost",
+            Attribute::NestMembers(_) => "NestMembers",
+            Attribute::PermittedSubclasses(_) => "PermittedSubclasses",
+            Attribute::Record(_) => "Record",
+            Attribute::RuntimeInvisibleAnnotations(_) => "RuntimeInvisibleAnnotations",
+            Attribute::RuntimeInvisibleParameterAnnotations(_) => "RuntimeInvisibleParameterAnnotations",
+            Attribute::RuntimeInvisibleTypeAnnotations(_) => "RuntimeInvisibleTypeAnnotations",
+            Attribute::RuntimeVisibleAnnotations(_) => "RuntimeVisibleAnnotations",
+            Attribute::RuntimeVisibleParameterAnnotations(_) => "RuntimeVisibleParameterAnnotations",
+            Attribute::RuntimeVisibleTypeAnnotations(_) => "RuntimeVisibleTypeAnnotations",
+            Attribute::Signature(_) => "Signature",
+            Attribute::SourceDebugExtension(_) => "SourceDebugExtension",
+            Attribute::SourceFile(_) => "SourceFile",
+            Attribute::StackMapTable(_) => "StackMapTable",
+            Attribute::Synthetic => "Synthetic",
+            Attribute::Unknown { name, .. } => return name.clone(),
+        };
+
+        MString::from(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BootstrapMethod {
+    pub method: MethodHandle,
+    pub arguments: Vec<Constant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InnerClass {
+    pub inner_class: MString,
+    pub outer_class: Option<MString>,
+    pub inner_name: Option<MString>,
+    pub inner_access_flags: AccessFlags,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineNumber {
+    pub start: Label,
+    pub line_number: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LocalVariable {
+    pub start: Label,
+    pub end: Label,
+    pub name: MString,
+    pub descriptor: MString,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LocalVariableType {
+    pub start: Label,
+    pub end: Label,
+    pub name: MString,
+    pub signature: MString,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MethodParameter {
+    pub name: Option<MString>,
+    pub access_flags: AccessFlags,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Module {
+    pub name: MString,
+    pub flags: AccessFlags,
+    pub version: Option<MString>,
+    pub requires: Vec<Require>,
+    pub exports: Vec<Export>,
+    pub opens: Vec<Open>,
+    pub uses: Vec<MString>,
+    pub provides: Vec<Provide>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Require {
+    pub module: MString,
+    pub flags: AccessFlags,
+    pub version: Option<MString>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Export {
+    pub package: MString,
+    pub flags: AccessFlags,
+    pub exports_to: Vec<MString>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Open {
+    pub package: MString,
+    pub flags: AccessFlags,
+    pub opens_to: Vec<MString>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Provide {
+    pub service: MString,
+    pub provides_with: Vec<MString>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordComponent {
+    pub name: MString,
+    pub descriptor: MString,
+    pub attributes: Vec<Attribute>,
+}
+
+impl RecordComponent {
+    #[must_use]
+    pub fn annotations(&self) -> Annotations<'_> {
+        Annotations::of(&self.attributes)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Annotation {
+    pub type_: MString,
+    pub pairs: Vec<ElementValuePair>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElementValuePair {
+    pub name: MString,
+    pub value: ElementValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElementValue {
+    Boolean(i32),
+    Byte(i32),
+    Short(i32),
+    Int(i32),
+    Long(i64),
+    Float(#[cfg_attr(feature = "serde", serde(with = "crate::tree::float::single"))] f32),
+    Double(#[cfg_attr(feature = "serde", serde(with = "crate::tree::float::double"))] f64),
+    Char(i32),
+    String(MString),
+    /// The return descriptor of a class literal.
+    Class(MString),
+    Enum {
+        type_name: MString,
+        const_name: MString,
+    },
+    Annotation(Annotation),
+    Array(Vec<ElementValue>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeAnnotation {
+    pub target_type: TargetType,
+    pub target_info: TargetInfo,
+    pub target_path: Vec<TypePathSegment>,
+    pub type_: MString,
+    pub pairs: Vec<ElementValuePair>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TargetInfo {
+    TypeParameter { parameter_index: u8 },
+    SuperType { supertype_index: SuperTypeIndex },
+    TypeParameterBound { type_parameter_index: u8, bound_index: u8 },
+    Empty,
+    FormalParameter { formal_parameter_index: u8 },
+    Throws { throws_type_index: u16 },
+    LocalVariable { table: Vec<LocalVariableTarget> },
+    Catch { exception_table_index: u16 },
+    Offset { offset: Label },
+    TypeArgument { offset: Label, type_argument_index: u8 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LocalVariableTarget {
+    pub start: Label,
+    pub end: Label,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypePathSegment {
+    pub kind: TypePathSegmentKind,
+    pub type_argument_index: u8,
+}
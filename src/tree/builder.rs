@@ -0,0 +1,518 @@
+use crate::descriptor::{BaseType, TypeDescriptor};
+use crate::error::*;
+use crate::mutf8::MString;
+use crate::tree::{Attribute, Code, Constant, ExceptionHandler, Instruction, Label, LocalVariable};
+
+mod flow;
+mod switch;
+
+pub use flow::{Catch, Condition};
+pub use switch::Case;
+
+/// The kind of value stored in a local variable, which determines the instructions used to access it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LocalType {
+    /// A `boolean`, `byte`, `char`, `short` or `int`.
+    Int,
+    Long,
+    Float,
+    Double,
+    /// An object or array.
+    Reference,
+}
+
+impl LocalType {
+    #[must_use]
+    pub fn of(descriptor: &TypeDescriptor<'_>) -> LocalType {
+        if descriptor.dimensions > 0 {
+            return LocalType::Reference;
+        }
+
+        match descriptor.base {
+            BaseType::Boolean | BaseType::Byte | BaseType::Char | BaseType::Short | BaseType::Integer => LocalType::Int,
+            BaseType::Long => LocalType::Long,
+            BaseType::Float => LocalType::Float,
+            BaseType::Double => LocalType::Double,
+            BaseType::Object(_) => LocalType::Reference,
+        }
+    }
+
+    /// The number of slots a value of this type takes up.
+    #[must_use]
+    pub fn size(self) -> u16 {
+        match self {
+            LocalType::Long | LocalType::Double => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// A local variable allocated by a [`CodeBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Local {
+    index: u16,
+    local_type: LocalType,
+}
+
+impl Local {
+    #[must_use]
+    pub fn index(self) -> u16 {
+        self.index
+    }
+
+    #[must_use]
+    pub fn local_type(self) -> LocalType {
+        self.local_type
+    }
+}
+
+/// A named local variable whose range is not known yet.
+struct Variable {
+    name: MString,
+    descriptor: MString,
+    local: Local,
+    /// Placed after the first store, as the variable only has a value from then on.
+    start: Option<Label>,
+}
+
+struct Scope {
+    first_slot: u16,
+    variables: Vec<Variable>,
+}
+
+/// Builds the code of a method without choosing the exact encoding of each instruction.
+///
+/// Local variables are allocated by their type, and the shortest instruction is chosen to access them
+/// and to push constants. Variables declared with a name are added to a `LocalVariableTable`,
+/// ranging from their first store to the end of the scope they were declared in.
+///
+/// ```
+/// use noak::tree::{CodeBuilder, Instruction};
+///
+/// let mut code = CodeBuilder::new();
+/// let count = code.parameter("count", "I")?;
+/// let total = code.declare("total", "J")?;
+/// code.push_long(0).store(total);
+/// code.load(total).load(count).emit(Instruction::I2L).emit(Instruction::LAdd).store(total);
+/// code.load(total).emit(Instruction::LReturn);
+///
+/// let code = code.finish(4);
+/// assert_eq!(code.max_locals, 3);
+/// assert_eq!(code.instructions[0], Instruction::Label(noak::tree::Label(0)));
+/// # Ok::<(), noak::error::EncodeError>(())
+/// ```
+pub struct CodeBuilder {
+    instructions: Vec<Instruction>,
+    exception_handlers: Vec<ExceptionHandler>,
+    attributes: Vec<Attribute>,
+    next_label: u32,
+    next_slot: u16,
+    max_locals: u16,
+    start: Label,
+    scopes: Vec<Scope>,
+    local_variables: Vec<LocalVariable>,
+    /// Whether the current position can be reached by falling through the previous instruction.
+    reachable: bool,
+    loops: Vec<flow::Loop>,
+    tries: Vec<flow::Try>,
+}
+
+impl CodeBuilder {
+    #[must_use]
+    pub fn new() -> CodeBuilder {
+        let mut builder = CodeBuilder {
+            instructions: Vec::new(),
+            exception_handlers: Vec::new(),
+            attributes: Vec::new(),
+            next_label: 0,
+            next_slot: 0,
+            max_locals: 0,
+            start: Label(0),
+            scopes: vec![Scope {
+                first_slot: 0,
+                variables: Vec::new(),
+            }],
+            local_variables: Vec::new(),
+            reachable: true,
+            loops: Vec::new(),
+            tries: Vec::new(),
+        };
+        builder.start = builder.new_label();
+        builder.place(builder.start);
+        builder
+    }
+
+    /// Creates a new label, which still has to be placed.
+    pub fn new_label(&mut self) -> Label {
+        let label = Label(self.next_label);
+        self.next_label += 1;
+        label
+    }
+
+    /// Places a label at the current position.
+    pub fn place(&mut self, label: Label) -> &mut Self {
+        self.emit(Instruction::Label(label))
+    }
+
+    /// Adds an instruction as-is.
+    pub fn emit(&mut self, instruction: Instruction) -> &mut Self {
+        match instruction {
+            Instruction::Label(_) => self.reachable = true,
+            Instruction::Goto { .. }
+            | Instruction::GotoW { .. }
+            | Instruction::TableSwitch { .. }
+            | Instruction::LookupSwitch { .. }
+            | Instruction::Ret { .. }
+            | Instruction::RetW { .. }
+            | Instruction::AThrow
+            | Instruction::Return
+            | Instruction::IReturn
+            | Instruction::LReturn
+            | Instruction::FReturn
+            | Instruction::DReturn
+            | Instruction::AReturn => self.reachable = false,
+            _ => {}
+        }
+        self.instructions.push(instruction);
+        self
+    }
+
+    /// Places a new label which is not the target of any jump, so it does not make the following code reachable.
+    fn marker(&mut self) -> Label {
+        let label = self.new_label();
+        self.instructions.push(Instruction::Label(label));
+        label
+    }
+
+    pub fn exception_handler(&mut self, handler: ExceptionHandler) -> &mut Self {
+        self.exception_handlers.push(handler);
+        self
+    }
+
+    /// Adds an attribute to the code, the `LocalVariableTable` is added automatically.
+    pub fn attribute(&mut self, attribute: Attribute) -> &mut Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    /// Allocates the local variable of the next parameter, including the receiver of instance methods.
+    ///
+    /// Parameters have to be allocated before any other variable, in the order of the method descriptor.
+    /// They are valid in the whole method.
+    pub fn parameter<N, D>(&mut self, name: N, descriptor: D) -> Result<Local, EncodeError>
+    where
+        N: Into<MString>,
+        D: Into<MString>,
+    {
+        let start = self.start;
+        let variable = self.variable(name.into(), descriptor.into())?;
+        let local = variable.local;
+        self.scopes[0].variables.push(Variable {
+            start: Some(start),
+            ..variable
+        });
+        Ok(local)
+    }
+
+    /// Allocates a named local variable in the current scope.
+    pub fn declare<N, D>(&mut self, name: N, descriptor: D) -> Result<Local, EncodeError>
+    where
+        N: Into<MString>,
+        D: Into<MString>,
+    {
+        let variable = self.variable(name.into(), descriptor.into())?;
+        let local = variable.local;
+        self.current_scope().variables.push(variable);
+        Ok(local)
+    }
+
+    /// Allocates an unnamed local variable in the current scope.
+    pub fn temporary(&mut self, local_type: LocalType) -> Result<Local, EncodeError> {
+        let index = self.next_slot;
+        self.next_slot = index
+            .checked_add(local_type.size())
+            .ok_or_else(|| EncodeError::with_context(EncodeErrorKind::TooManyItems, Context::Code))?;
+        self.max_locals = self.max_locals.max(self.next_slot);
+        Ok(Local { index, local_type })
+    }
+
+    fn variable(&mut self, name: MString, descriptor: MString) -> Result<Variable, EncodeError> {
+        let local_type = TypeDescriptor::parse(&descriptor)
+            .map(|descriptor| LocalType::of(&descriptor))
+            .map_err(|err| EncodeError::from_err(err, Context::Code))?;
+        let local = self.temporary(local_type)?;
+        Ok(Variable {
+            name,
+            descriptor,
+            local,
+            start: None,
+        })
+    }
+
+    fn current_scope(&mut self) -> &mut Scope {
+        // the outermost scope is never removed
+        self.scopes.last_mut().unwrap()
+    }
+
+    /// Begins a scope, whose variables are freed once it ends.
+    pub fn begin_scope(&mut self) -> &mut Self {
+        self.scopes.push(Scope {
+            first_slot: self.next_slot,
+            variables: Vec::new(),
+        });
+        self
+    }
+
+    /// Ends the innermost scope, so the slots of its variables can be reused.
+    ///
+    /// Fails with [`EncodeErrorKind::NotInScope`] if no scope was begun.
+    pub fn end_scope(&mut self) -> Result<&mut Self, EncodeError> {
+        if self.scopes.len() <= 1 {
+            return Err(EncodeError::with_context(EncodeErrorKind::NotInScope, Context::Code));
+        }
+        let scope = self.scopes.pop().unwrap();
+        self.close(scope.variables);
+        self.next_slot = scope.first_slot;
+        Ok(self)
+    }
+
+    /// Adds the variables that have a value to the local variable table.
+    fn close(&mut self, variables: Vec<Variable>) {
+        if variables.iter().all(|variable| variable.start.is_none()) {
+            return;
+        }
+
+        let end = self.marker();
+        for variable in variables {
+            if let Some(start) = variable.start {
+                self.local_variables.push(LocalVariable {
+                    start,
+                    end,
+                    name: variable.name,
+                    descriptor: variable.descriptor,
+                    index: variable.local.index,
+                });
+            }
+        }
+    }
+
+    /// Loads the value of a local variable onto the stack.
+    pub fn load(&mut self, local: Local) -> &mut Self {
+        let instruction = match (local.local_type, local.index) {
+            (LocalType::Int, 0) => Instruction::ILoad0,
+            (LocalType::Int, 1) => Instruction::ILoad1,
+            (LocalType::Int, 2) => Instruction::ILoad2,
+            (LocalType::Int, 3) => Instruction::ILoad3,
+            (LocalType::Long, 0) => Instruction::LLoad0,
+            (LocalType::Long, 1) => Instruction::LLoad1,
+            (LocalType::Long, 2) => Instruction::LLoad2,
+            (LocalType::Long, 3) => Instruction::LLoad3,
+            (LocalType::Float, 0) => Instruction::FLoad0,
+            (LocalType::Float, 1) => Instruction::FLoad1,
+            (LocalType::Float, 2) => Instruction::FLoad2,
+            (LocalType::Float, 3) => Instruction::FLoad3,
+            (LocalType::Double, 0) => Instruction::DLoad0,
+            (LocalType::Double, 1) => Instruction::DLoad1,
+            (LocalType::Double, 2) => Instruction::DLoad2,
+            (LocalType::Double, 3) => Instruction::DLoad3,
+            (LocalType::Reference, 0) => Instruction::ALoad0,
+            (LocalType::Reference, 1) => Instruction::ALoad1,
+            (LocalType::Reference, 2) => Instruction::ALoad2,
+            (LocalType::Reference, 3) => Instruction::ALoad3,
+            (local_type, index) => match (local_type, u8::try_from(index)) {
+                (LocalType::Int, Ok(index)) => Instruction::ILoad { index },
+                (LocalType::Long, Ok(index)) => Instruction::LLoad { index },
+                (LocalType::Float, Ok(index)) => Instruction::FLoad { index },
+                (LocalType::Double, Ok(index)) => Instruction::DLoad { index },
+                (LocalType::Reference, Ok(index)) => Instruction::ALoad { index },
+                (LocalType::Int, Err(_)) => Instruction::ILoadW { index },
+                (LocalType::Long, Err(_)) => Instruction::LLoadW { index },
+                (LocalType::Float, Err(_)) => Instruction::FLoadW { index },
+                (LocalType::Double, Err(_)) => Instruction::DLoadW { index },
+                (LocalType::Reference, Err(_)) => Instruction::ALoadW { index },
+            },
+        };
+        self.emit(instruction)
+    }
+
+    /// Stores the value on top of the stack in a local variable.
+    pub fn store(&mut self, local: Local) -> &mut Self {
+        let instruction = match (local.local_type, local.index) {
+            (LocalType::Int, 0) => Instruction::IStore0,
+            (LocalType::Int, 1) => Instruction::IStore1,
+            (LocalType::Int, 2) => Instruction::IStore2,
+            (LocalType::Int, 3) => Instruction::IStore3,
+            (LocalType::Long, 0) => Instruction::LStore0,
+            (LocalType::Long, 1) => Instruction::LStore1,
+            (LocalType::Long, 2) => Instruction::LStore2,
+            (LocalType::Long, 3) => Instruction::LStore3,
+            (LocalType::Float, 0) => Instruction::FStore0,
+            (LocalType::Float, 1) => Instruction::FStore1,
+            (LocalType::Float, 2) => Instruction::FStore2,
+            (LocalType::Float, 3) => Instruction::FStore3,
+            (LocalType::Double, 0) => Instruction::DStore0,
+            (LocalType::Double, 1) => Instruction::DStore1,
+            (LocalType::Double, 2) => Instruction::DStore2,
+            (LocalType::Double, 3) => Instruction::DStore3,
+            (LocalType::Reference, 0) => Instruction::AStore0,
+            (LocalType::Reference, 1) => Instruction::AStore1,
+            (LocalType::Reference, 2) => Instruction::AStore2,
+            (LocalType::Reference, 3) => Instruction::AStore3,
+            (local_type, index) => match (local_type, u8::try_from(index)) {
+                (LocalType::Int, Ok(index)) => Instruction::IStore { index },
+                (LocalType::Long, Ok(index)) => Instruction::LStore { index },
+                (LocalType::Float, Ok(index)) => Instruction::FStore { index },
+                (LocalType::Double, Ok(index)) => Instruction::DStore { index },
+                (LocalType::Reference, Ok(index)) => Instruction::AStore { index },
+                (LocalType::Int, Err(_)) => Instruction::IStoreW { index },
+                (LocalType::Long, Err(_)) => Instruction::LStoreW { index },
+                (LocalType::Float, Err(_)) => Instruction::FStoreW { index },
+                (LocalType::Double, Err(_)) => Instruction::DStoreW { index },
+                (LocalType::Reference, Err(_)) => Instruction::AStoreW { index },
+            },
+        };
+        self.emit(instruction);
+        self.started(local);
+        self
+    }
+
+    /// Starts the range of a variable after its first store.
+    fn started(&mut self, local: Local) {
+        let label = Label(self.next_label);
+        let variable = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.variables.iter_mut().rev())
+            .find(|variable| variable.local == local);
+        if let Some(variable @ Variable { start: None, .. }) = variable {
+            variable.start = Some(label);
+            self.marker();
+        }
+    }
+
+    /// Increments an `int` variable by a constant.
+    pub fn increment(&mut self, local: Local, value: i16) -> &mut Self {
+        let instruction = match (u8::try_from(local.index), i8::try_from(value)) {
+            (Ok(index), Ok(value)) => Instruction::IInc { index, value },
+            _ => Instruction::IIncW {
+                index: local.index,
+                value,
+            },
+        };
+        self.emit(instruction)
+    }
+
+    pub fn push_int(&mut self, value: i32) -> &mut Self {
+        let instruction = match value {
+            -1 => Instruction::IConstM1,
+            0 => Instruction::IConst0,
+            1 => Instruction::IConst1,
+            2 => Instruction::IConst2,
+            3 => Instruction::IConst3,
+            4 => Instruction::IConst4,
+            5 => Instruction::IConst5,
+            _ => {
+                if let Ok(value) = i8::try_from(value) {
+                    Instruction::BIPush { value }
+                } else if let Ok(value) = i16::try_from(value) {
+                    Instruction::SIPush { value }
+                } else {
+                    Instruction::LdC {
+                        constant: Constant::Integer(value),
+                    }
+                }
+            }
+        };
+        self.emit(instruction)
+    }
+
+    pub fn push_long(&mut self, value: i64) -> &mut Self {
+        let instruction = match value {
+            0 => Instruction::LConst0,
+            1 => Instruction::LConst1,
+            _ => Instruction::LdC2W {
+                constant: Constant::Long(value),
+            },
+        };
+        self.emit(instruction)
+    }
+
+    pub fn push_float(&mut self, value: f32) -> &mut Self {
+        // compared by bits, as the constant instructions push positive zero
+        let instruction = match value.to_bits() {
+            bits if bits == 0f32.to_bits() => Instruction::FConst0,
+            bits if bits == 1f32.to_bits() => Instruction::FConst1,
+            bits if bits == 2f32.to_bits() => Instruction::FConst2,
+            _ => Instruction::LdC {
+                constant: Constant::Float(value),
+            },
+        };
+        self.emit(instruction)
+    }
+
+    pub fn push_double(&mut self, value: f64) -> &mut Self {
+        let instruction = match value.to_bits() {
+            bits if bits == 0f64.to_bits() => Instruction::DConst0,
+            bits if bits == 1f64.to_bits() => Instruction::DConst1,
+            _ => Instruction::LdC2W {
+                constant: Constant::Double(value),
+            },
+        };
+        self.emit(instruction)
+    }
+
+    pub fn push_string<S: Into<MString>>(&mut self, value: S) -> &mut Self {
+        self.emit(Instruction::LdC {
+            constant: Constant::String(value.into()),
+        })
+    }
+
+    /// Pushes any loadable constant with the shortest instruction.
+    ///
+    /// Whether `ldc` or `ldc_w` is used is decided once the constant pool index is known.
+    pub fn push_constant(&mut self, constant: Constant) -> &mut Self {
+        match constant {
+            Constant::Integer(value) => self.push_int(value),
+            Constant::Long(value) => self.push_long(value),
+            Constant::Float(value) => self.push_float(value),
+            Constant::Double(value) => self.push_double(value),
+            constant => self.emit(Instruction::LdC { constant }),
+        }
+    }
+
+    /// Finishes the code, closing all scopes.
+    #[must_use]
+    pub fn finish(mut self, max_stack: u16) -> Code {
+        // the innermost scopes are closed first, down to the outermost one
+        while let Some(scope) = self.scopes.pop() {
+            self.close(scope.variables);
+        }
+
+        let mut attributes = self.attributes;
+        if !self.local_variables.is_empty() {
+            self.local_variables.sort_by_key(|variable| variable.index);
+            attributes.push(Attribute::LocalVariableTable(self.local_variables));
+        }
+
+        Code {
+            max_stack,
+            max_locals: self.max_locals,
+            instructions: self.instructions,
+            exception_handlers: self.exception_handlers,
+            attributes,
+        }
+    }
+}
+
+impl Default for CodeBuilder {
+    fn default() -> CodeBuilder {
+        CodeBuilder::new()
+    }
+}
+
+impl std::fmt::Debug for CodeBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CodeBuilder").finish()
+    }
+}
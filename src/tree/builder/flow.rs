@@ -0,0 +1,789 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::error::*;
+use crate::mutf8::MString;
+use crate::tree::{CodeBuilder, ExceptionHandler, Instruction, Label, Local, LocalType};
+
+type Finally = Rc<dyn Fn(&mut CodeBuilder) -> Result<(), EncodeError>>;
+type Handler<'a> = Box<dyn FnOnce(&mut CodeBuilder, Local) -> Result<(), EncodeError> + 'a>;
+
+/// A condition checked by a conditional jump.
+///
+/// The conditions without a `Cmp` compare the `int` on top of the stack with zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Condition {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Gt,
+    Le,
+    ICmpEq,
+    ICmpNe,
+    ICmpLt,
+    ICmpGe,
+    ICmpGt,
+    ICmpLe,
+    ACmpEq,
+    ACmpNe,
+    Null,
+    NonNull,
+}
+
+impl Condition {
+    #[must_use]
+    pub fn negate(self) -> Condition {
+        match self {
+            Condition::Eq => Condition::Ne,
+            Condition::Ne => Condition::Eq,
+            Condition::Lt => Condition::Ge,
+            Condition::Ge => Condition::Lt,
+            Condition::Gt => Condition::Le,
+            Condition::Le => Condition::Gt,
+            Condition::ICmpEq => Condition::ICmpNe,
+            Condition::ICmpNe => Condition::ICmpEq,
+            Condition::ICmpLt => Condition::ICmpGe,
+            Condition::ICmpGe => Condition::ICmpLt,
+            Condition::ICmpGt => Condition::ICmpLe,
+            Condition::ICmpLe => Condition::ICmpGt,
+            Condition::ACmpEq => Condition::ACmpNe,
+            Condition::ACmpNe => Condition::ACmpEq,
+            Condition::Null => Condition::NonNull,
+            Condition::NonNull => Condition::Null,
+        }
+    }
+
+    /// The instruction which jumps to the target if the condition holds.
+    #[must_use]
+    pub fn jump(self, target: Label) -> Instruction {
+        match self {
+            Condition::Eq => Instruction::IfEq { target },
+            Condition::Ne => Instruction::IfNe { target },
+            Condition::Lt => Instruction::IfLt { target },
+            Condition::Ge => Instruction::IfGe { target },
+            Condition::Gt => Instruction::IfGt { target },
+            Condition::Le => Instruction::IfLe { target },
+            Condition::ICmpEq => Instruction::IfICmpEq { target },
+            Condition::ICmpNe => Instruction::IfICmpNe { target },
+            Condition::ICmpLt => Instruction::IfICmpLt { target },
+            Condition::ICmpGe => Instruction::IfICmpGe { target },
+            Condition::ICmpGt => Instruction::IfICmpGt { target },
+            Condition::ICmpLe => Instruction::IfICmpLe { target },
+            Condition::ACmpEq => Instruction::IfACmpEq { target },
+            Condition::ACmpNe => Instruction::IfACmpNe { target },
+            Condition::Null => Instruction::IfNull { target },
+            Condition::NonNull => Instruction::IfNonNull { target },
+        }
+    }
+}
+
+/// A catch clause of a [`CodeBuilder::try_catch`] or [`CodeBuilder::try_finally`] block.
+///
+/// The handler gets the local variable the caught exception is stored in.
+pub struct Catch<'a> {
+    catch_type: Option<MString>,
+    handler: Handler<'a>,
+}
+
+impl<'a> Catch<'a> {
+    /// Catches exceptions of the given class and its subclasses.
+    pub fn new<C, F>(catch_type: C, handler: F) -> Catch<'a>
+    where
+        C: Into<MString>,
+        F: FnOnce(&mut CodeBuilder, Local) -> Result<(), EncodeError> + 'a,
+    {
+        Catch {
+            catch_type: Some(catch_type.into()),
+            handler: Box::new(handler),
+        }
+    }
+
+    /// Catches every exception.
+    pub fn any<F>(handler: F) -> Catch<'a>
+    where
+        F: FnOnce(&mut CodeBuilder, Local) -> Result<(), EncodeError> + 'a,
+    {
+        Catch {
+            catch_type: None,
+            handler: Box::new(handler),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Catch<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Catch").field("catch_type", &self.catch_type).finish()
+    }
+}
+
+pub(super) struct Loop {
+    break_label: Label,
+    continue_label: Label,
+    /// The number of enclosing try blocks, which are not left by jumping out of the loop.
+    tries: usize,
+}
+
+/// A try block whose code is currently emitted.
+pub(super) struct Try {
+    finally: Option<Finally>,
+    /// The protected ranges, which exclude the copies of the finally blocks.
+    ranges: Vec<(Label, Label)>,
+    /// The start of the current range and the number of instructions before it.
+    open: Option<(Label, usize)>,
+}
+
+impl CodeBuilder {
+    /// Emits code which is only run if the condition holds.
+    ///
+    /// The operands of the condition have to be on the stack already.
+    pub fn if_then<T>(&mut self, condition: Condition, then: T) -> Result<&mut Self, EncodeError>
+    where
+        T: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError>,
+    {
+        let skip = self.new_label();
+        self.emit(condition.negate().jump(skip));
+        self.scoped(then)?;
+        Ok(self.place(skip))
+    }
+
+    /// Emits code which runs either branch depending on the condition.
+    ///
+    /// The operands of the condition have to be on the stack already.
+    pub fn if_then_else<T, E>(&mut self, condition: Condition, then: T, otherwise: E) -> Result<&mut Self, EncodeError>
+    where
+        T: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError>,
+        E: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError>,
+    {
+        let otherwise_label = self.new_label();
+        let end = self.new_label();
+        self.emit(condition.negate().jump(otherwise_label));
+        self.scoped(then)?;
+        let falls_through = self.reachable;
+        if falls_through {
+            self.emit(Instruction::Goto { target: end });
+        }
+        self.place(otherwise_label);
+        self.scoped(otherwise)?;
+        if falls_through {
+            self.place(end);
+        }
+        Ok(self)
+    }
+
+    /// Emits a loop which runs as long as the condition holds.
+    ///
+    /// The condition pushes its operands and returns the condition to check.
+    ///
+    /// ```
+    /// use noak::tree::{CodeBuilder, Condition, Instruction, LocalType};
+    ///
+    /// let mut code = CodeBuilder::new();
+    /// let i = code.temporary(LocalType::Int)?;
+    /// code.push_int(0).store(i);
+    /// code.while_loop(
+    ///     |code| {
+    ///         code.load(i).push_int(10);
+    ///         Ok(Condition::ICmpLt)
+    ///     },
+    ///     |code| {
+    ///         code.increment(i, 1);
+    ///         Ok(())
+    ///     },
+    /// )?;
+    /// code.emit(Instruction::Return);
+    /// # Ok::<(), noak::error::EncodeError>(())
+    /// ```
+    pub fn while_loop<C, B>(&mut self, condition: C, body: B) -> Result<&mut Self, EncodeError>
+    where
+        C: FnOnce(&mut CodeBuilder) -> Result<Condition, EncodeError>,
+        B: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError>,
+    {
+        let top = self.new_label();
+        let end = self.new_label();
+        self.place(top);
+        let condition = condition(self)?;
+        self.emit(condition.negate().jump(end));
+        self.looped(top, end, body)?;
+        self.emit(Instruction::Goto { target: top });
+        Ok(self.place(end))
+    }
+
+    /// Emits a loop like a `for` statement.
+    ///
+    /// The value returned by `init`, usually the loop variables, is passed to the other parts.
+    /// The variables declared by `init` are only valid in the loop.
+    pub fn for_loop<T, I, C, U, B>(
+        &mut self,
+        init: I,
+        condition: C,
+        update: U,
+        body: B,
+    ) -> Result<&mut Self, EncodeError>
+    where
+        I: FnOnce(&mut CodeBuilder) -> Result<T, EncodeError>,
+        C: FnOnce(&mut CodeBuilder, &T) -> Result<Condition, EncodeError>,
+        U: FnOnce(&mut CodeBuilder, &T) -> Result<(), EncodeError>,
+        B: FnOnce(&mut CodeBuilder, &T) -> Result<(), EncodeError>,
+    {
+        let top = self.new_label();
+        let next = self.new_label();
+        let end = self.new_label();
+        self.begin_scope();
+        let state = init(self)?;
+        self.place(top);
+        let condition = condition(self, &state)?;
+        self.emit(condition.negate().jump(end));
+        self.looped(next, end, |code| body(code, &state))?;
+        self.place(next);
+        update(self, &state)?;
+        self.emit(Instruction::Goto { target: top });
+        self.place(end);
+        self.end_scope()
+    }
+
+    fn looped<B>(&mut self, continue_label: Label, break_label: Label, body: B) -> Result<(), EncodeError>
+    where
+        B: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError>,
+    {
+        self.loops.push(Loop {
+            break_label,
+            continue_label,
+            tries: self.tries.len(),
+        });
+        let result = self.scoped(body);
+        self.loops.pop();
+        result
+    }
+
+    pub(super) fn scoped<B>(&mut self, body: B) -> Result<(), EncodeError>
+    where
+        B: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError>,
+    {
+        self.begin_scope();
+        body(self)?;
+        self.end_scope()?;
+        Ok(())
+    }
+
+    /// Jumps to the end of the innermost loop, running the finally blocks in between.
+    ///
+    /// Fails with [`EncodeErrorKind::NotInLoop`] if not inside a loop.
+    pub fn break_loop(&mut self) -> Result<&mut Self, EncodeError> {
+        let target = self.innermost_loop()?;
+        let (label, tries) = (target.break_label, target.tries);
+        self.jump(tries, label)?;
+        Ok(self)
+    }
+
+    /// Jumps to the next iteration of the innermost loop, running the finally blocks in between.
+    ///
+    /// Fails with [`EncodeErrorKind::NotInLoop`] if not inside a loop.
+    pub fn continue_loop(&mut self) -> Result<&mut Self, EncodeError> {
+        let target = self.innermost_loop()?;
+        let (label, tries) = (target.continue_label, target.tries);
+        self.jump(tries, label)?;
+        Ok(self)
+    }
+
+    fn innermost_loop(&self) -> Result<&Loop, EncodeError> {
+        self.loops
+            .last()
+            .ok_or_else(|| EncodeError::with_context(EncodeErrorKind::NotInLoop, Context::Code))
+    }
+
+    /// Returns from a `void` method, running all finally blocks first.
+    pub fn return_void(&mut self) -> Result<&mut Self, EncodeError> {
+        let crossed = self.leave(0)?;
+        self.emit(Instruction::Return);
+        self.reenter(crossed);
+        Ok(self)
+    }
+
+    /// Returns the value on top of the stack, running all finally blocks first.
+    ///
+    /// The value is kept in a temporary variable while the finally blocks run.
+    pub fn return_value(&mut self, local_type: LocalType) -> Result<&mut Self, EncodeError> {
+        let instruction = match local_type {
+            LocalType::Int => Instruction::IReturn,
+            LocalType::Long => Instruction::LReturn,
+            LocalType::Float => Instruction::FReturn,
+            LocalType::Double => Instruction::DReturn,
+            LocalType::Reference => Instruction::AReturn,
+        };
+
+        if self.tries.iter().any(|block| block.finally.is_some()) {
+            let value = self.temporary(local_type)?;
+            self.store(value);
+            let crossed = self.leave(0)?;
+            self.load(value).emit(instruction);
+            self.reenter(crossed);
+        } else {
+            self.emit(instruction);
+        }
+        Ok(self)
+    }
+
+    /// Emits a try block with catch clauses.
+    ///
+    /// The exception handlers are added in the order of the catch clauses,
+    /// after the handlers of the try blocks nested in the body.
+    pub fn try_catch<B>(&mut self, body: B, catches: Vec<Catch<'_>>) -> Result<&mut Self, EncodeError>
+    where
+        B: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError>,
+    {
+        self.try_block(body, catches, None)
+    }
+
+    /// Emits a try block with catch clauses and a finally block.
+    ///
+    /// The finally block is copied to every exit of the body and the catch clauses,
+    /// including breaks, continues and returns, and is run by a handler catching every other exception.
+    /// As it may be emitted multiple times, it can't borrow anything.
+    ///
+    /// ```
+    /// use noak::tree::{Catch, CodeBuilder, Instruction, LocalType, MemberRef};
+    ///
+    /// let unlock = MemberRef {
+    ///     class: "Lock".into(),
+    ///     name: "unlock".into(),
+    ///     descriptor: "()V".into(),
+    /// };
+    ///
+    /// let mut code = CodeBuilder::new();
+    /// code.try_finally(
+    ///     |code| {
+    ///         code.push_int(42).return_value(LocalType::Int)?;
+    ///         Ok(())
+    ///     },
+    ///     vec![Catch::new("java/lang/IllegalStateException", |code, _| {
+    ///         code.push_int(-1).return_value(LocalType::Int)?;
+    ///         Ok(())
+    ///     })],
+    ///     move |code| {
+    ///         code.emit(Instruction::InvokeStatic {
+    ///             method: unlock.clone(),
+    ///             interface: false,
+    ///         });
+    ///         Ok(())
+    ///     },
+    /// )?;
+    ///
+    /// let code = code.finish(1);
+    /// assert_eq!(code.exception_handlers.len(), 3);
+    /// # Ok::<(), noak::error::EncodeError>(())
+    /// ```
+    pub fn try_finally<B, F>(&mut self, body: B, catches: Vec<Catch<'_>>, finally: F) -> Result<&mut Self, EncodeError>
+    where
+        B: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError>,
+        F: Fn(&mut CodeBuilder) -> Result<(), EncodeError> + 'static,
+    {
+        self.try_block(body, catches, Some(Rc::new(finally)))
+    }
+
+    fn try_block<B>(
+        &mut self,
+        body: B,
+        catches: Vec<Catch<'_>>,
+        finally: Option<Finally>,
+    ) -> Result<&mut Self, EncodeError>
+    where
+        B: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError>,
+    {
+        let depth = self.tries.len();
+        let end = self.new_label();
+        let mut reaches_end = false;
+
+        let mut block = Try {
+            finally: finally.clone(),
+            ranges: Vec::new(),
+            open: None,
+        };
+        self.open_range(&mut block);
+        self.tries.push(block);
+        self.scoped(body)?;
+        if self.reachable {
+            self.jump(depth, end)?;
+            reaches_end = true;
+        }
+        let mut block = self.tries.pop().unwrap();
+        self.close_range(&mut block);
+
+        // nothing can be thrown by an empty body
+        let body_ranges = std::mem::take(&mut block.ranges);
+        let catches = if body_ranges.is_empty() { Vec::new() } else { catches };
+        for catch in catches {
+            let handler = self.new_label();
+            self.exception_handlers
+                .extend(body_ranges.iter().map(|&(start, end)| ExceptionHandler {
+                    start,
+                    end,
+                    handler,
+                    catch_type: catch.catch_type.clone(),
+                }));
+
+            self.place(handler);
+            // the catch clauses are only protected by the finally block
+            let protected = finally.is_some();
+            if protected {
+                self.open_range(&mut block);
+                self.tries.push(block);
+            }
+            self.begin_scope();
+            let exception = self.temporary(LocalType::Reference)?;
+            self.store(exception);
+            (catch.handler)(self, exception)?;
+            if self.reachable {
+                self.jump(depth, end)?;
+                reaches_end = true;
+            }
+            self.end_scope()?;
+            block = if protected {
+                let mut block = self.tries.pop().unwrap();
+                self.close_range(&mut block);
+                block
+            } else {
+                Try {
+                    finally: None,
+                    ranges: Vec::new(),
+                    open: None,
+                }
+            };
+        }
+
+        if let Some(finally) = finally {
+            let handler = self.new_label();
+            let ranges = body_ranges.into_iter().chain(block.ranges);
+            let handlers_before = self.exception_handlers.len();
+            self.exception_handlers
+                .extend(ranges.map(|(start, end)| ExceptionHandler {
+                    start,
+                    end,
+                    handler,
+                    catch_type: None,
+                }));
+
+            if self.exception_handlers.len() > handlers_before {
+                self.place(handler);
+                self.begin_scope();
+                let exception = self.temporary(LocalType::Reference)?;
+                self.store(exception);
+                finally(self)?;
+                self.load(exception).emit(Instruction::AThrow);
+                self.end_scope()?;
+            }
+        }
+
+        if reaches_end {
+            self.place(end);
+        }
+        Ok(self)
+    }
+
+    /// Jumps to a label outside of the innermost try blocks, running their finally blocks.
+    ///
+    /// Only the outermost `depth` try blocks are not left.
+    fn jump(&mut self, depth: usize, target: Label) -> Result<(), EncodeError> {
+        let crossed = self.leave(depth)?;
+        self.emit(Instruction::Goto { target });
+        self.reenter(crossed);
+        Ok(())
+    }
+
+    /// Runs the finally blocks of the try blocks which are left, from the innermost one outwards.
+    ///
+    /// The copy of a finally block is not protected by its own try block, but by the try blocks around it.
+    fn leave(&mut self, depth: usize) -> Result<Vec<Try>, EncodeError> {
+        let mut crossed = self.tries.split_off(depth);
+        for block in crossed.iter_mut().rev() {
+            self.close_range(block);
+            if let Some(finally) = block.finally.clone() {
+                finally(self)?;
+            }
+        }
+        Ok(crossed)
+    }
+
+    /// Continues the try blocks after the jump out of them.
+    fn reenter(&mut self, crossed: Vec<Try>) {
+        for mut block in crossed {
+            self.open_range(&mut block);
+            self.tries.push(block);
+        }
+    }
+
+    fn open_range(&mut self, block: &mut Try) {
+        let start = self.marker();
+        block.open = Some((start, self.instructions.len()));
+    }
+
+    /// Ends the current range of a try block, dropping it if it does not contain any instructions.
+    fn close_range(&mut self, block: &mut Try) {
+        let Some((start, position)) = block.open.take() else {
+            return;
+        };
+
+        let empty = self.instructions[position..]
+            .iter()
+            .all(|instruction| matches!(instruction, Instruction::Label(_)));
+        if !empty {
+            let end = self.marker();
+            block.ranges.push((start, end));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::AccessFlags;
+    use crate::reader;
+    use crate::tree::{Attribute, Class, Code, MemberRef, Method, StackMapFrame, VerificationType};
+
+    /// Returns the index of the instruction placing the label.
+    fn position(code: &Code, label: Label) -> usize {
+        code.instructions
+            .iter()
+            .position(|instruction| *instruction == Instruction::Label(label))
+            .unwrap()
+    }
+
+    /// Returns the positions and targets of all `goto` instructions.
+    fn gotos(code: &Code) -> Vec<(usize, Label)> {
+        code.instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, instruction)| match instruction {
+                Instruction::Goto { target } => Some((i, *target)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn unlock() -> Instruction {
+        Instruction::InvokeStatic {
+            method: MemberRef {
+                class: "Lock".into(),
+                name: "unlock".into(),
+                descriptor: "()V".into(),
+            },
+            interface: false,
+        }
+    }
+
+    /// Builds `while (i < 10) { try { i++; if (i == 5) continue; if (i == 8) break; } finally { Lock.unlock(); } }`.
+    fn loop_with_finally() -> Code {
+        let mut code = CodeBuilder::new();
+        let i = code.temporary(LocalType::Int).unwrap();
+        code.push_int(0).store(i);
+        code.while_loop(
+            |code| {
+                code.load(i).push_int(10);
+                Ok(Condition::ICmpLt)
+            },
+            |code| {
+                code.try_finally(
+                    |code| {
+                        code.increment(i, 1).load(i).push_int(5);
+                        code.if_then(Condition::ICmpEq, |code| code.continue_loop().map(|_| ()))?;
+                        code.load(i).push_int(8);
+                        code.if_then(Condition::ICmpEq, |code| code.break_loop().map(|_| ()))?;
+                        Ok(())
+                    },
+                    Vec::new(),
+                    |code| {
+                        code.emit(unlock());
+                        Ok(())
+                    },
+                )?;
+                Ok(())
+            },
+        )
+        .unwrap();
+        code.emit(Instruction::Return);
+        code.finish(2)
+    }
+
+    #[test]
+    fn loop_exit_outside_of_loop() {
+        let mut code = CodeBuilder::new();
+        let error = code.break_loop().unwrap_err();
+        assert!(matches!(error.kind(), EncodeErrorKind::NotInLoop));
+        let error = code.continue_loop().unwrap_err();
+        assert!(matches!(error.kind(), EncodeErrorKind::NotInLoop));
+    }
+
+    #[test]
+    fn nested_loops() {
+        // while (i < 10) { i++; if (i == 5) continue; while (j < i) { if (j == 3) break; j++; } }
+        let mut code = CodeBuilder::new();
+        let i = code.temporary(LocalType::Int).unwrap();
+        let j = code.temporary(LocalType::Int).unwrap();
+        code.push_int(0).store(i).push_int(0).store(j);
+        code.while_loop(
+            |code| {
+                code.load(i).push_int(10);
+                Ok(Condition::ICmpLt)
+            },
+            |code| {
+                code.increment(i, 1).load(i).push_int(5);
+                code.if_then(Condition::ICmpEq, |code| code.continue_loop().map(|_| ()))?;
+                code.while_loop(
+                    |code| {
+                        code.load(j).load(i);
+                        Ok(Condition::ICmpLt)
+                    },
+                    |code| {
+                        code.load(j).push_int(3);
+                        code.if_then(Condition::ICmpEq, |code| code.break_loop().map(|_| ()))?;
+                        code.increment(j, 1);
+                        Ok(())
+                    },
+                )?;
+                Ok(())
+            },
+        )
+        .unwrap();
+        code.emit(Instruction::Return);
+        let code = code.finish(2);
+
+        let [(_, continue_target), (_, break_target), (inner_back, inner_top), (_, outer_top)] = gotos(&code)[..]
+        else {
+            panic!("expected four jumps");
+        };
+        assert_eq!(continue_target, outer_top);
+        assert_ne!(inner_top, outer_top);
+        // the inner loop ends right after its jump back to the top
+        assert_eq!(position(&code, break_target), inner_back + 1);
+    }
+
+    #[test]
+    fn loop_exits_run_finally() {
+        let code = loop_with_finally();
+
+        // copies for continue, break and the end of the body, and one in the handler
+        let copies: Vec<_> = code
+            .instructions
+            .iter()
+            .enumerate()
+            .filter(|(_, instruction)| **instruction == unlock())
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(copies.len(), 4);
+        let [(_, continue_target), (_, break_target), .., (_, top)] = gotos(&code)[..] else {
+            panic!("expected jumps");
+        };
+        assert_eq!(continue_target, top);
+        assert_ne!(break_target, top);
+        for (goto, _) in &gotos(&code)[..2] {
+            assert!(copies.contains(&(goto - 1)));
+        }
+
+        // the copies are not protected by the handler
+        // the range between the break and the end of the body is empty, so it is dropped
+        assert_eq!(code.exception_handlers.len(), 2);
+        for handler in &code.exception_handlers {
+            let range = position(&code, handler.start)..position(&code, handler.end);
+            assert!(copies.iter().all(|copy| !range.contains(copy)));
+        }
+
+        // leaving a loop inside of the try block does not leave the try block
+        let mut code = CodeBuilder::new();
+        code.try_finally(
+            |code| {
+                code.while_loop(
+                    |code| {
+                        code.push_int(1);
+                        Ok(Condition::Ne)
+                    },
+                    |code| code.break_loop().map(|_| ()),
+                )?;
+                Ok(())
+            },
+            Vec::new(),
+            |code| {
+                code.emit(unlock());
+                Ok(())
+            },
+        )
+        .unwrap();
+        code.emit(Instruction::Return);
+        let code = code.finish(1);
+        assert_eq!(
+            code.instructions
+                .iter()
+                .filter(|instruction| **instruction == unlock())
+                .count(),
+            2
+        );
+    }
+
+    /// The targets of the jumps and exception handlers of the code, in the order of the code.
+    fn jump_targets(code: &Code) -> Vec<Label> {
+        let mut targets: Vec<_> = code
+            .instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::Goto { target }
+                | Instruction::IfICmpEq { target }
+                | Instruction::IfICmpNe { target }
+                | Instruction::IfICmpLt { target }
+                | Instruction::IfICmpGe { target } => Some(*target),
+                _ => None,
+            })
+            .chain(code.exception_handlers.iter().map(|handler| handler.handler))
+            .collect();
+        targets.sort_by_key(|target| position(code, *target));
+        targets.dedup();
+        targets
+    }
+
+    #[test]
+    fn loop_frames() {
+        let mut code = loop_with_finally();
+        let handlers: Vec<_> = code.exception_handlers.iter().map(|handler| handler.handler).collect();
+        let targets = jump_targets(&code);
+        let frames = targets
+            .iter()
+            .map(|target| StackMapFrame::Full {
+                label: *target,
+                locals: vec![VerificationType::Integer],
+                stack: if handlers.contains(target) {
+                    vec![VerificationType::Object("java/lang/Throwable".into())]
+                } else {
+                    Vec::new()
+                },
+            })
+            .collect();
+        code.attributes.push(Attribute::StackMapTable(frames));
+
+        let class = Class {
+            methods: vec![Method {
+                access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC,
+                name: "run".into(),
+                descriptor: "()V".into(),
+                attributes: vec![Attribute::Code(code)],
+            }],
+            ..Class::new("Example")
+        };
+        let bytes = class.to_bytes().unwrap();
+        assert!(Class::validate(&reader::Class::new(&bytes).unwrap()).is_empty());
+
+        // every jump target and handler still has its frame after reading the class back
+        let class = Class::read(&bytes).unwrap();
+        let Attribute::Code(code) = &class.methods[0].attributes[0] else {
+            panic!("expected a code attribute");
+        };
+        let Some(Attribute::StackMapTable(frames)) = code.attributes.first() else {
+            panic!("expected a stack map table");
+        };
+        let framed: Vec<_> = frames
+            .iter()
+            .map(|frame| match frame {
+                StackMapFrame::Full { label, .. } => *label,
+                frame => panic!("unexpected frame {frame:?}"),
+            })
+            .collect();
+        assert_eq!(framed, jump_targets(code));
+    }
+}
@@ -0,0 +1,565 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::error::*;
+use crate::mutf8::{MStr, MString};
+use crate::tree::{CodeBuilder, Instruction, Label, LocalType, MemberRef};
+
+type Body<'a> = Box<dyn FnOnce(&mut CodeBuilder) -> Result<(), EncodeError> + 'a>;
+
+/// A case of a switch, which is run if the value equals any of its keys.
+///
+/// The cases don't fall through into each other.
+pub struct Case<'a, K> {
+    keys: Vec<K>,
+    body: Body<'a>,
+}
+
+impl<'a, K> Case<'a, K> {
+    pub fn new<I, F>(keys: I, body: F) -> Case<'a, K>
+    where
+        I: IntoIterator,
+        I::Item: Into<K>,
+        F: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError> + 'a,
+    {
+        Case {
+            keys: keys.into_iter().map(Into::into).collect(),
+            body: Box::new(body),
+        }
+    }
+}
+
+impl<'a, K: fmt::Debug> fmt::Debug for Case<'a, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Case").field("keys", &self.keys).finish()
+    }
+}
+
+impl CodeBuilder {
+    /// Emits a switch on the `int` on top of the stack.
+    ///
+    /// The keys are sorted, and either a `tableswitch` or a `lookupswitch` is used,
+    /// depending on which is cheaper by the same estimate javac uses.
+    ///
+    /// ```
+    /// use noak::tree::{Case, CodeBuilder, Instruction, LocalType};
+    ///
+    /// let mut code = CodeBuilder::new();
+    /// let value = code.parameter("value", "I")?;
+    /// code.load(value).switch(
+    ///     vec![
+    ///         Case::new([3, 1, 2], |code| {
+    ///             code.push_int(1);
+    ///             Ok(())
+    ///         }),
+    ///         Case::new([5], |code| {
+    ///             code.push_int(2);
+    ///             Ok(())
+    ///         }),
+    ///     ],
+    ///     |code| {
+    ///         code.push_int(0);
+    ///         Ok(())
+    ///     },
+    /// )?;
+    /// code.return_value(LocalType::Int)?;
+    ///
+    /// let code = code.finish(1);
+    /// assert!(code.instructions.iter().any(|insn| matches!(insn, Instruction::TableSwitch { low: 1, .. })));
+    /// # Ok::<(), noak::error::EncodeError>(())
+    /// ```
+    pub fn switch<D>(&mut self, cases: Vec<Case<'_, i32>>, default: D) -> Result<&mut Self, EncodeError>
+    where
+        D: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError>,
+    {
+        let default_label = self.new_label();
+        let end = self.new_label();
+
+        let mut pairs = Vec::new();
+        let mut bodies = Vec::new();
+        for case in cases.into_iter().filter(|case| !case.keys.is_empty()) {
+            let label = self.new_label();
+            pairs.extend(case.keys.iter().map(|&key| (key, label)));
+            bodies.push((label, case.body));
+        }
+        pairs.sort_unstable_by_key(|&(key, _)| key);
+        if pairs.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err(EncodeError::with_context(EncodeErrorKind::DuplicateKey, Context::Code));
+        }
+        self.emit(switch_instruction(default_label, pairs));
+
+        let mut reaches_end = false;
+        for (label, body) in bodies {
+            self.place(label);
+            self.scoped(body)?;
+            if self.reachable {
+                self.emit(Instruction::Goto { target: end });
+                reaches_end = true;
+            }
+        }
+        self.place(default_label);
+        self.scoped(default)?;
+        if reaches_end {
+            self.place(end);
+        }
+        Ok(self)
+    }
+
+    /// Emits a switch on the `String` on top of the stack, like javac does.
+    ///
+    /// The string is matched by its hash code first, and then compared with the keys of that hash code.
+    /// A `null` string causes a `NullPointerException`.
+    pub fn switch_string<D>(&mut self, cases: Vec<Case<'_, MString>>, default: D) -> Result<&mut Self, EncodeError>
+    where
+        D: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError>,
+    {
+        let mut buckets: BTreeMap<i32, Vec<(MString, i32)>> = BTreeMap::new();
+        let mut seen = HashSet::new();
+        let mut bodies = Vec::new();
+        for (position, case) in (0..).zip(cases) {
+            for key in case.keys {
+                if !seen.insert(key.clone()) {
+                    return Err(EncodeError::with_context(EncodeErrorKind::DuplicateKey, Context::Code));
+                }
+                buckets.entry(hash_code(&key)).or_default().push((key, position));
+            }
+            bodies.push(Case {
+                keys: vec![position],
+                body: case.body,
+            });
+        }
+
+        self.begin_scope();
+        let string = self.temporary(LocalType::Reference)?;
+        let position = self.temporary(LocalType::Int)?;
+        self.store(string).push_int(-1).store(position);
+
+        self.load(string).emit(Instruction::InvokeVirtual {
+            method: MemberRef {
+                class: "java/lang/String".into(),
+                name: "hashCode".into(),
+                descriptor: "()I".into(),
+            },
+        });
+        let matches = buckets
+            .into_iter()
+            .map(|(hash, keys)| {
+                Case::new([hash], move |code: &mut CodeBuilder| {
+                    let found = code.new_label();
+                    for (key, index) in keys {
+                        let next = code.new_label();
+                        code.load(string).push_string(key).emit(Instruction::InvokeVirtual {
+                            method: MemberRef {
+                                class: "java/lang/String".into(),
+                                name: "equals".into(),
+                                descriptor: "(Ljava/lang/Object;)Z".into(),
+                            },
+                        });
+                        code.emit(Instruction::IfEq { target: next });
+                        code.push_int(index).store(position);
+                        code.emit(Instruction::Goto { target: found });
+                        code.place(next);
+                    }
+                    code.place(found);
+                    Ok(())
+                })
+            })
+            .collect();
+        self.switch(matches, |_| Ok(()))?;
+
+        self.load(position).switch(bodies, default)?;
+        self.end_scope()
+    }
+
+    /// Emits a switch on the enum constant on top of the stack, by the ordinals of the constants.
+    ///
+    /// The constants have to be given in the order they are declared in, so their ordinals are known.
+    /// Unlike with the switch map javac generates, the switch has to be rebuilt if that order changes.
+    /// A `null` constant causes a `NullPointerException`.
+    pub fn switch_enum<C, D>(
+        &mut self,
+        enum_class: C,
+        constants: &[&MStr],
+        cases: Vec<Case<'_, MString>>,
+        default: D,
+    ) -> Result<&mut Self, EncodeError>
+    where
+        C: Into<MString>,
+        D: FnOnce(&mut CodeBuilder) -> Result<(), EncodeError>,
+    {
+        let cases = cases
+            .into_iter()
+            .map(|case| {
+                let ordinals = case
+                    .keys
+                    .iter()
+                    .map(|key| {
+                        constants
+                            .iter()
+                            .position(|constant| **constant == **key)
+                            .and_then(|ordinal| i32::try_from(ordinal).ok())
+                            .ok_or_else(|| {
+                                EncodeError::with_context(EncodeErrorKind::UnknownEnumConstant, Context::Code)
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Case {
+                    keys: ordinals,
+                    body: case.body,
+                })
+            })
+            .collect::<Result<Vec<_>, EncodeError>>()?;
+
+        self.emit(Instruction::InvokeVirtual {
+            method: MemberRef {
+                class: enum_class.into(),
+                name: "ordinal".into(),
+                descriptor: "()I".into(),
+            },
+        });
+        self.switch(cases, default)
+    }
+}
+
+/// Chooses the cheaper switch instruction for the sorted pairs, using the estimate of javac.
+fn switch_instruction(default: Label, pairs: Vec<(i32, Label)>) -> Instruction {
+    let (Some(&(low, _)), Some(&(high, _))) = (pairs.first(), pairs.last()) else {
+        return Instruction::LookupSwitch { default, pairs };
+    };
+
+    let count = pairs.len() as i64;
+    let table_space = 4 + (i64::from(high) - i64::from(low) + 1);
+    let table_time = 3;
+    let lookup_space = 3 + 2 * count;
+    let lookup_time = count;
+    if table_space + 3 * table_time > lookup_space + 3 * lookup_time {
+        return Instruction::LookupSwitch { default, pairs };
+    }
+
+    let mut targets = vec![default; (i64::from(high) - i64::from(low) + 1) as usize];
+    for (key, label) in pairs {
+        targets[(i64::from(key) - i64::from(low)) as usize] = label;
+    }
+    Instruction::TableSwitch { default, low, targets }
+}
+
+/// Computes the result of `String.hashCode`, which is used to look up the keys.
+fn hash_code(string: &MStr) -> i32 {
+    let mut hash = 0i32;
+    for ch in string.chars() {
+        let mut buffer = [0; 2];
+        let units = match ch {
+            Ok(ch) => &*ch.encode_utf16(&mut buffer),
+            // unpaired surrogates are single code units
+            Err(code) => {
+                buffer[0] = code as u16;
+                &buffer[..1]
+            }
+        };
+        for &unit in units {
+            hash = hash.wrapping_mul(31).wrapping_add(i32::from(unit));
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::{AccessFlags, Version};
+    use crate::tree::{Attribute, Class, Code, Constant, Method};
+
+    fn instruction(keys: &[i32]) -> Instruction {
+        switch_instruction(Label(0), keys.iter().map(|&key| (key, Label(1))).collect())
+    }
+
+    /// Writes a method switching on its argument and reads the switch back.
+    fn round_trip(keys: &[i32]) -> Instruction {
+        let mut code = CodeBuilder::new();
+        let value = code.parameter("value", "I").unwrap();
+        let cases = keys
+            .iter()
+            .map(|&key| {
+                Case::new([key], move |code: &mut CodeBuilder| {
+                    code.push_int(key);
+                    Ok(())
+                })
+            })
+            .collect();
+        code.load(value)
+            .switch(cases, |code| {
+                code.push_int(0);
+                Ok(())
+            })
+            .unwrap();
+        code.emit(Instruction::IReturn);
+
+        let class = Class {
+            version: Version::V6,
+            methods: vec![Method {
+                access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC,
+                name: "select".into(),
+                descriptor: "(I)I".into(),
+                attributes: vec![Attribute::Code(code.finish(1))],
+            }],
+            ..Class::new("Example")
+        };
+        let class = Class::read(&class.to_bytes().unwrap()).unwrap();
+        let Attribute::Code(code) = &class.methods[0].attributes[0] else {
+            panic!("expected a code attribute");
+        };
+        code.instructions
+            .iter()
+            .find(|instruction| {
+                matches!(
+                    instruction,
+                    Instruction::TableSwitch { .. } | Instruction::LookupSwitch { .. }
+                )
+            })
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn table_or_lookup() {
+        // a table is used while it spans at most 5 * count - 10 keys
+        assert!(matches!(
+            instruction(&[0, 1, 4]),
+            Instruction::TableSwitch { low: 0, .. }
+        ));
+        assert!(matches!(instruction(&[0, 1, 5]), Instruction::LookupSwitch { .. }));
+        assert!(matches!(
+            instruction(&[-5, 0, 1, 4]),
+            Instruction::TableSwitch { low: -5, .. }
+        ));
+        assert!(matches!(instruction(&[-5, 0, 1, 6]), Instruction::LookupSwitch { .. }));
+
+        let Instruction::TableSwitch { targets, .. } = instruction(&[0, 1, 4]) else {
+            unreachable!();
+        };
+        assert_eq!(targets, [Label(1), Label(1), Label(0), Label(0), Label(1)]);
+    }
+
+    #[test]
+    fn extreme_keys() {
+        assert!(matches!(
+            instruction(&[i32::MIN, i32::MAX]),
+            Instruction::LookupSwitch { .. }
+        ));
+        assert!(matches!(
+            instruction(&[i32::MIN, i32::MIN + 1, i32::MIN + 2]),
+            Instruction::TableSwitch { low: i32::MIN, .. }
+        ));
+
+        let Instruction::TableSwitch { low, targets, .. } = round_trip(&[i32::MAX - 2, i32::MAX - 1, i32::MAX]) else {
+            panic!("expected a table switch");
+        };
+        assert_eq!((low, targets.len()), (i32::MAX - 2, 3));
+        let Instruction::LookupSwitch { pairs, .. } = round_trip(&[i32::MAX, 0, i32::MIN]) else {
+            panic!("expected a lookup switch");
+        };
+        let keys: Vec<_> = pairs.iter().map(|&(key, _)| key).collect();
+        assert_eq!(keys, [i32::MIN, 0, i32::MAX]);
+    }
+
+    #[test]
+    fn empty_switch() {
+        assert!(matches!(
+            instruction(&[]),
+            Instruction::LookupSwitch { pairs, .. } if pairs.is_empty()
+        ));
+        assert!(matches!(
+            round_trip(&[]),
+            Instruction::LookupSwitch { pairs, .. } if pairs.is_empty()
+        ));
+
+        // cases without keys are left out entirely
+        let mut code = CodeBuilder::new();
+        code.push_int(1)
+            .switch(vec![Case::new(Vec::<i32>::new(), |_| unreachable!())], |code| {
+                code.emit(Instruction::Return);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(
+            code.finish(1)
+                .instructions
+                .iter()
+                .filter(|instruction| matches!(instruction, Instruction::Label(_)))
+                .count(),
+            2
+        );
+    }
+
+    /// Returns the switch instructions of the code.
+    fn switches(code: &Code) -> Vec<&Instruction> {
+        code.instructions
+            .iter()
+            .filter(|instruction| {
+                matches!(
+                    instruction,
+                    Instruction::TableSwitch { .. } | Instruction::LookupSwitch { .. }
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the instruction following the label.
+    fn after(code: &Code, label: Label) -> &Instruction {
+        let index = code
+            .instructions
+            .iter()
+            .position(|instruction| *instruction == Instruction::Label(label))
+            .unwrap();
+        code.instructions[index + 1..]
+            .iter()
+            .find(|instruction| !matches!(instruction, Instruction::Label(_)))
+            .unwrap()
+    }
+
+    fn push(value: i32) -> impl FnOnce(&mut CodeBuilder) -> Result<(), EncodeError> {
+        move |code| {
+            code.push_int(value);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn string_switch() {
+        assert_eq!(hash_code(&MString::from("Aa")), 2112);
+        assert_eq!(hash_code(&MString::from("BB")), 2112);
+
+        let mut code = CodeBuilder::new();
+        let value = code.parameter("value", "Ljava/lang/String;").unwrap();
+        code.load(value)
+            .switch_string(
+                vec![
+                    Case::new(["Aa"], push(1)),
+                    Case::new(["BB", "C"], push(2)),
+                    Case::new(["D"], push(3)),
+                ],
+                push(0),
+            )
+            .unwrap();
+        code.emit(Instruction::IReturn);
+        let code = code.finish(2);
+
+        // "Aa" and "BB" share a hash code, so they are compared one after the other in the same case
+        let [Instruction::LookupSwitch { pairs, .. }, Instruction::TableSwitch {
+            default,
+            low: 0,
+            targets,
+        }] = &switches(&code)[..]
+        else {
+            panic!("expected a switch on the hash code and one on the position");
+        };
+        let hashes: Vec<_> = pairs.iter().map(|&(hash, _)| hash).collect();
+        assert_eq!(hashes, [67, 68, 2112]);
+        let strings: Vec<_> = code
+            .instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::LdC {
+                    constant: Constant::String(string),
+                } => Some(string.display().to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(strings, ["C", "D", "Aa", "BB"]);
+        let positions: Vec<_> = code
+            .instructions
+            .windows(2)
+            .filter(|pair| matches!(pair[0], Instruction::IfEq { .. }))
+            .map(|pair| pair[1].clone())
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                Instruction::IConst1,
+                Instruction::IConst2,
+                Instruction::IConst0,
+                Instruction::IConst1
+            ]
+        );
+
+        // strings without a case keep the position -1, which leads to the default
+        assert!(code.instructions.contains(&Instruction::IConstM1));
+        assert_eq!(*after(&code, *default), Instruction::IConst0);
+        let bodies: Vec<_> = targets.iter().map(|&target| after(&code, target).clone()).collect();
+        assert_eq!(
+            bodies,
+            [Instruction::IConst1, Instruction::IConst2, Instruction::IConst3]
+        );
+
+        let class = Class {
+            methods: vec![Method {
+                access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC,
+                name: "select".into(),
+                descriptor: "(Ljava/lang/String;)I".into(),
+                attributes: vec![Attribute::Code(code)],
+            }],
+            ..Class::new("Example")
+        };
+        assert!(class.to_bytes().is_ok());
+    }
+
+    #[test]
+    fn enum_switch() {
+        let constants: Vec<_> = ["RED", "GREEN", "BLUE"].into_iter().map(MString::from).collect();
+        let constants: Vec<&MStr> = constants.iter().map(|constant| &**constant).collect();
+
+        let mut code = CodeBuilder::new();
+        let value = code.parameter("value", "LColor;").unwrap();
+        code.load(value)
+            .switch_enum(
+                "Color",
+                &constants,
+                vec![Case::new(["BLUE"], push(1)), Case::new(["RED", "GREEN"], push(2))],
+                push(0),
+            )
+            .unwrap();
+        let code = code.finish(1);
+        assert!(code.instructions.contains(&Instruction::InvokeVirtual {
+            method: MemberRef {
+                class: "Color".into(),
+                name: "ordinal".into(),
+                descriptor: "()I".into(),
+            },
+        }));
+        let [Instruction::TableSwitch { low: 0, targets, .. }] = &switches(&code)[..] else {
+            panic!("expected a table switch on the ordinal");
+        };
+        let bodies: Vec<_> = targets.iter().map(|&target| after(&code, target).clone()).collect();
+        assert_eq!(
+            bodies,
+            [Instruction::IConst2, Instruction::IConst2, Instruction::IConst1]
+        );
+
+        let mut code = CodeBuilder::new();
+        let error = code
+            .switch_enum("Color", &constants, vec![Case::new(["PURPLE"], push(1))], push(0))
+            .unwrap_err();
+        assert!(matches!(error.kind(), EncodeErrorKind::UnknownEnumConstant));
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let mut code = CodeBuilder::new();
+        let error = code
+            .switch(vec![Case::new([1, 2], push(1)), Case::new([2], push(2))], push(0))
+            .unwrap_err();
+        assert!(matches!(error.kind(), EncodeErrorKind::DuplicateKey));
+
+        let mut code = CodeBuilder::new();
+        let error = code
+            .switch_string(vec![Case::new(["a"], push(1)), Case::new(["b", "a"], push(2))], push(0))
+            .unwrap_err();
+        assert!(matches!(error.kind(), EncodeErrorKind::DuplicateKey));
+
+        // keys with the same hash code are not duplicates
+        let mut code = CodeBuilder::new();
+        assert!(code
+            .switch_string(vec![Case::new(["Aa"], push(1)), Case::new(["BB"], push(2))], push(0))
+            .is_ok());
+    }
+}
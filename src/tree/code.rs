@@ -0,0 +1,496 @@
+use crate::mutf8::MString;
+use crate::reader::attributes::ArrayType;
+use crate::tree::{Attribute, Constant, DynamicConstant, MemberRef};
+
+/// The body of a method.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Code {
+    pub max_stack: u16,
+    pub max_locals: u16,
+    pub instructions: Vec<Instruction>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub exception_handlers: Vec<ExceptionHandler>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub attributes: Vec<Attribute>,
+}
+
+/// A position in the code of a method.
+///
+/// Labels read from a class file are named after the offset of the instruction they mark.
+/// As they are placed by [`Instruction::Label`], instructions may be inserted or removed freely.
+/// Outside of code, for example in the type annotations of a method, the label is the offset itself.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Label(pub u32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExceptionHandler {
+    pub start: Label,
+    pub end: Label,
+    pub handler: Label,
+    /// The class of the caught exceptions, `None` if every exception is caught.
+    pub catch_type: Option<MString>,
+}
+
+/// An instruction with its operands resolved.
+///
+/// The variants are named like those of [`RawInstruction`](crate::reader::attributes::RawInstruction),
+/// with jump offsets replaced by labels and constant pool indices replaced by the constants.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Instruction {
+    /// Marks the position of the label, this is not an actual instruction.
+    Label(Label),
+    AALoad,
+    AAStore,
+    AConstNull,
+    ALoad {
+        index: u8,
+    },
+    ALoadW {
+        index: u16,
+    },
+    ALoad0,
+    ALoad1,
+    ALoad2,
+    ALoad3,
+    ANewArray {
+        class: MString,
+    },
+    AReturn,
+    ArrayLength,
+    AStore {
+        index: u8,
+    },
+    AStoreW {
+        index: u16,
+    },
+    AStore0,
+    AStore1,
+    AStore2,
+    AStore3,
+    AThrow,
+    BALoad,
+    BAStore,
+    BIPush {
+        value: i8,
+    },
+    CALoad,
+    CAStore,
+    CheckCast {
+        class: MString,
+    },
+    D2F,
+    D2I,
+    D2L,
+    DAdd,
+    DALoad,
+    DAStore,
+    DCmpG,
+    DCmpL,
+    DConst0,
+    DConst1,
+    DDiv,
+    DLoad {
+        index: u8,
+    },
+    DLoadW {
+        index: u16,
+    },
+    DLoad0,
+    DLoad1,
+    DLoad2,
+    DLoad3,
+    DMul,
+    DNeg,
+    DRem,
+    DReturn,
+    DStore {
+        index: u8,
+    },
+    DStoreW {
+        index: u16,
+    },
+    DStore0,
+    DStore1,
+    DStore2,
+    DStore3,
+    DSub,
+    Dup,
+    DupX1,
+    DupX2,
+    Dup2,
+    Dup2X1,
+    Dup2X2,
+    F2D,
+    F2I,
+    F2L,
+    FAdd,
+    FALoad,
+    FAStore,
+    FCmpG,
+    FCmpL,
+    FConst0,
+    FConst1,
+    FConst2,
+    FDiv,
+    FLoad {
+        index: u8,
+    },
+    FLoadW {
+        index: u16,
+    },
+    FLoad0,
+    FLoad1,
+    FLoad2,
+    FLoad3,
+    FMul,
+    FNeg,
+    FRem,
+    FReturn,
+    FStore {
+        index: u8,
+    },
+    FStoreW {
+        index: u16,
+    },
+    FStore0,
+    FStore1,
+    FStore2,
+    FStore3,
+    FSub,
+    GetField {
+        field: MemberRef,
+    },
+    GetStatic {
+        field: MemberRef,
+    },
+    Goto {
+        target: Label,
+    },
+    GotoW {
+        target: Label,
+    },
+    I2B,
+    I2C,
+    I2D,
+    I2F,
+    I2L,
+    I2S,
+    IAdd,
+    IALoad,
+    IAnd,
+    IAStore,
+    IConstM1,
+    IConst0,
+    IConst1,
+    IConst2,
+    IConst3,
+    IConst4,
+    IConst5,
+    IDiv,
+    IfACmpEq {
+        target: Label,
+    },
+    IfACmpNe {
+        target: Label,
+    },
+    IfICmpEq {
+        target: Label,
+    },
+    IfICmpNe {
+        target: Label,
+    },
+    IfICmpLt {
+        target: Label,
+    },
+    IfICmpGe {
+        target: Label,
+    },
+    IfICmpGt {
+        target: Label,
+    },
+    IfICmpLe {
+        target: Label,
+    },
+    IfEq {
+        target: Label,
+    },
+    IfNe {
+        target: Label,
+    },
+    IfLt {
+        target: Label,
+    },
+    IfGe {
+        target: Label,
+    },
+    IfGt {
+        target: Label,
+    },
+    IfLe {
+        target: Label,
+    },
+    IfNonNull {
+        target: Label,
+    },
+    IfNull {
+        target: Label,
+    },
+    IInc {
+        index: u8,
+        value: i8,
+    },
+    IIncW {
+        index: u16,
+        value: i16,
+    },
+    ILoad {
+        index: u8,
+    },
+    ILoadW {
+        index: u16,
+    },
+    ILoad0,
+    ILoad1,
+    ILoad2,
+    ILoad3,
+    IMul,
+    INeg,
+    InstanceOf {
+        class: MString,
+    },
+    InvokeDynamic {
+        call_site: DynamicConstant,
+    },
+    InvokeInterface {
+        method: MemberRef,
+        count: u8,
+    },
+    InvokeSpecial {
+        method: MemberRef,
+        interface: bool,
+    },
+    InvokeStatic {
+        method: MemberRef,
+        interface: bool,
+    },
+    InvokeVirtual {
+        method: MemberRef,
+    },
+    IOr,
+    IRem,
+    IReturn,
+    IShL,
+    IShR,
+    IStore {
+        index: u8,
+    },
+    IStoreW {
+        index: u16,
+    },
+    IStore0,
+    IStore1,
+    IStore2,
+    IStore3,
+    ISub,
+    IUShR,
+    IXor,
+    JSr {
+        target: Label,
+    },
+    JSrW {
+        target: Label,
+    },
+    L2D,
+    L2F,
+    L2I,
+    LAdd,
+    LALoad,
+    LAnd,
+    LAStore,
+    LCmp,
+    LConst0,
+    LConst1,
+    LdC {
+        constant: Constant,
+    },
+    LdCW {
+        constant: Constant,
+    },
+    LdC2W {
+        constant: Constant,
+    },
+    LDiv,
+    LLoad {
+        index: u8,
+    },
+    LLoadW {
+        index: u16,
+    },
+    LLoad0,
+    LLoad1,
+    LLoad2,
+    LLoad3,
+    LMul,
+    LNeg,
+    LookupSwitch {
+        default: Label,
+        pairs: Vec<(i32, Label)>,
+    },
+    LOr,
+    LRem,
+    LReturn,
+    LShL,
+    LShR,
+    LStore {
+        index: u8,
+    },
+    LStoreW {
+        index: u16,
+    },
+    LStore0,
+    LStore1,
+    LStore2,
+    LStore3,
+    LSub,
+    LUShR,
+    LXor,
+    MonitorEnter,
+    MonitorExit,
+    MultiANewArray {
+        class: MString,
+        dimensions: u8,
+    },
+    New {
+        class: MString,
+    },
+    NewArray {
+        atype: ArrayType,
+    },
+    Nop,
+    Pop,
+    Pop2,
+    PutField {
+        field: MemberRef,
+    },
+    PutStatic {
+        field: MemberRef,
+    },
+    Ret {
+        index: u8,
+    },
+    RetW {
+        index: u16,
+    },
+    Return,
+    SALoad,
+    SAStore,
+    SIPush {
+        value: i16,
+    },
+    Swap,
+    TableSwitch {
+        default: Label,
+        low: i32,
+        targets: Vec<Label>,
+    },
+}
+
+impl Instruction {
+    /// The labels this instruction may jump to.
+    pub(crate) fn targets_mut(&mut self) -> Vec<&mut Label> {
+        match self {
+            Instruction::Goto { target }
+            | Instruction::GotoW { target }
+            | Instruction::JSr { target }
+            | Instruction::JSrW { target }
+            | Instruction::IfACmpEq { target }
+            | Instruction::IfACmpNe { target }
+            | Instruction::IfICmpEq { target }
+            | Instruction::IfICmpNe { target }
+            | Instruction::IfICmpLt { target }
+            | Instruction::IfICmpGe { target }
+            | Instruction::IfICmpGt { target }
+            | Instruction::IfICmpLe { target }
+            | Instruction::IfEq { target }
+            | Instruction::IfNe { target }
+            | Instruction::IfLt { target }
+            | Instruction::IfGe { target }
+            | Instruction::IfGt { target }
+            | Instruction::IfLe { target }
+            | Instruction::IfNonNull { target }
+            | Instruction::IfNull { target } => vec![target],
+            Instruction::TableSwitch { default, targets, .. } => std::iter::once(default).chain(targets).collect(),
+            Instruction::LookupSwitch { default, pairs } => std::iter::once(default)
+                .chain(pairs.iter_mut().map(|(_, target)| target))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StackMapFrame {
+    Same {
+        label: Label,
+    },
+    SameExtended {
+        label: Label,
+    },
+    Same1 {
+        label: Label,
+        stack: VerificationType,
+    },
+    Same1Extended {
+        label: Label,
+        stack: VerificationType,
+    },
+    Chop {
+        label: Label,
+        to_chop: u8,
+    },
+    Append {
+        label: Label,
+        locals: Vec<VerificationType>,
+    },
+    Full {
+        label: Label,
+        locals: Vec<VerificationType>,
+        stack: Vec<VerificationType>,
+    },
+}
+
+impl StackMapFrame {
+    /// The position this frame describes.
+    #[must_use]
+    pub fn label(&self) -> Label {
+        match self {
+            StackMapFrame::Same { label }
+            | StackMapFrame::SameExtended { label }
+            | StackMapFrame::Same1 { label, .. }
+            | StackMapFrame::Same1Extended { label, .. }
+            | StackMapFrame::Chop { label, .. }
+            | StackMapFrame::Append { label, .. }
+            | StackMapFrame::Full { label, .. } => *label,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VerificationType {
+    Top,
+    Integer,
+    Float,
+    Double,
+    Long,
+    Null,
+    UninitializedThis,
+    Object(MString),
+    /// An object created by the `new` instruction at the label, whose constructor was not yet invoked.
+    Uninitialized(Label),
+}
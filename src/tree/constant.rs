@@ -0,0 +1,120 @@
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+use crate::mutf8::MString;
+use crate::reader::cpool::MethodKind;
+
+/// A constant pool entry with all of its references resolved.
+///
+/// Floating point constants are only equal if their bits are equal, just like in the constant pool.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Constant {
+    Utf8(MString),
+    Integer(i32),
+    Float(#[cfg_attr(feature = "serde", serde(with = "crate::tree::float::single"))] f32),
+    Long(i64),
+    Double(#[cfg_attr(feature = "serde", serde(with = "crate::tree::float::double"))] f64),
+    Class(MString),
+    String(MString),
+    FieldRef(MemberRef),
+    MethodRef(MemberRef),
+    InterfaceMethodRef(MemberRef),
+    NameAndType(NameAndType),
+    MethodHandle(MethodHandle),
+    MethodType(MString),
+    Dynamic(DynamicConstant),
+    InvokeDynamic(DynamicConstant),
+    Module(MString),
+    Package(MString),
+}
+
+impl PartialEq for Constant {
+    fn eq(&self, other: &Constant) -> bool {
+        match (self, other) {
+            (Constant::Utf8(a), Constant::Utf8(b)) => a == b,
+            (Constant::Integer(a), Constant::Integer(b)) => a == b,
+            (Constant::Float(a), Constant::Float(b)) => a.to_bits() == b.to_bits(),
+            (Constant::Long(a), Constant::Long(b)) => a == b,
+            (Constant::Double(a), Constant::Double(b)) => a.to_bits() == b.to_bits(),
+            (Constant::Class(a), Constant::Class(b)) => a == b,
+            (Constant::String(a), Constant::String(b)) => a == b,
+            (Constant::FieldRef(a), Constant::FieldRef(b)) => a == b,
+            (Constant::MethodRef(a), Constant::MethodRef(b)) => a == b,
+            (Constant::InterfaceMethodRef(a), Constant::InterfaceMethodRef(b)) => a == b,
+            (Constant::NameAndType(a), Constant::NameAndType(b)) => a == b,
+            (Constant::MethodHandle(a), Constant::MethodHandle(b)) => a == b,
+            (Constant::MethodType(a), Constant::MethodType(b)) => a == b,
+            (Constant::Dynamic(a), Constant::Dynamic(b)) => a == b,
+            (Constant::InvokeDynamic(a), Constant::InvokeDynamic(b)) => a == b,
+            (Constant::Module(a), Constant::Module(b)) => a == b,
+            (Constant::Package(a), Constant::Package(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Constant {}
+
+impl Hash for Constant {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            Constant::Integer(value) => value.hash(state),
+            Constant::Float(value) => value.to_bits().hash(state),
+            Constant::Long(value) => value.hash(state),
+            Constant::Double(value) => value.to_bits().hash(state),
+            Constant::Utf8(value)
+            | Constant::Class(value)
+            | Constant::String(value)
+            | Constant::MethodType(value)
+            | Constant::Module(value)
+            | Constant::Package(value) => value.hash(state),
+            Constant::FieldRef(value) | Constant::MethodRef(value) | Constant::InterfaceMethodRef(value) => {
+                value.hash(state);
+            }
+            Constant::NameAndType(value) => value.hash(state),
+            Constant::MethodHandle(value) => value.hash(state),
+            Constant::Dynamic(value) | Constant::InvokeDynamic(value) => value.hash(state),
+        }
+    }
+}
+
+/// A reference to a field or method of a class.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemberRef {
+    pub class: MString,
+    pub name: MString,
+    pub descriptor: MString,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NameAndType {
+    pub name: MString,
+    pub descriptor: MString,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MethodHandle {
+    pub kind: MethodKind,
+    pub reference: MemberRef,
+    /// Whether the referenced method is declared in an interface.
+    ///
+    /// This is only meaningful for the `InvokeStatic` and `InvokeSpecial` kinds,
+    /// as the kind already determines the type of reference for every other kind.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub interface: bool,
+}
+
+/// A dynamically-computed constant or call site.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DynamicConstant {
+    /// The index into the `BootstrapMethods` attribute of the class.
+    pub bootstrap_method: u16,
+    pub name: MString,
+    pub descriptor: MString,
+}
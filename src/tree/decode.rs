@@ -0,0 +1,1249 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::error::*;
+use crate::mutf8::MString;
+use crate::reader::attributes::{self as raw_code, annotations as raw_annotations, AttributeContent, RawInstruction};
+use crate::reader::cpool::{self, ConstantPool};
+use crate::reader::{self, DecodeMany};
+use crate::tree::*;
+
+/// Resolves a class.
+///
+/// If warnings are collected, malformed parts of the class which the JVM ignores are skipped and recorded
+/// as warnings instead of failing.
+pub(super) fn class(
+    class: &reader::Class<'_>,
+    mut warnings: Option<&mut Vec<DecodeError>>,
+) -> Result<Class, DecodeError> {
+    let pool = class.pool();
+
+    let mut constant_pool = Vec::new();
+    for (index, _) in pool.iter_indices() {
+        let segment = PathSegment::ConstantPool { index: index.as_u16() };
+        match constant(pool, index) {
+            Ok(constant) => constant_pool.push(constant),
+            // entries are only checked once they are used, which is done separately
+            Err(err) => tolerate(&mut warnings, err.within(segment))?,
+        }
+    }
+
+    let name = class_name(pool, class.this_class()).map_err(|err| err.within(PathSegment::ClassInfo))?;
+    let super_class = class
+        .super_class()
+        .map(|index| class_name(pool, index))
+        .transpose()
+        .map_err(|err| err.within(PathSegment::ClassInfo))?;
+
+    let interfaces = (0..)
+        .zip(class.interfaces())
+        .map(|(index, interface)| {
+            class_name(pool, interface?).map_err(|err| err.within(PathSegment::Interface { index }))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let mut fields = Vec::new();
+    for (index, field) in (0..).zip(class.fields()) {
+        let field = field?;
+        let name = utf8(pool, field.name());
+        let segment = PathSegment::Field {
+            index,
+            name: name.as_ref().ok().cloned(),
+        };
+        fields.push(within(&mut warnings, segment, |warnings| {
+            Ok(Field {
+                access_flags: field.access_flags(),
+                name: name?,
+                descriptor: utf8(pool, field.descriptor())?,
+                attributes: attributes(pool, field.attributes(), &mut Labels::outside_code(), None, warnings)?,
+            })
+        })?);
+    }
+
+    let mut methods = Vec::new();
+    for (index, method) in (0..).zip(class.methods()) {
+        let method = method?;
+        let name = utf8(pool, method.name());
+        let descriptor = utf8(pool, method.descriptor());
+        let segment = PathSegment::Method {
+            index,
+            name: name.as_ref().ok().cloned(),
+            descriptor: descriptor.as_ref().ok().cloned(),
+        };
+        methods.push(within(&mut warnings, segment, |warnings| {
+            let access_flags = method.access_flags();
+            Ok(Method {
+                access_flags,
+                name: name?,
+                descriptor: descriptor?,
+                attributes: attributes(
+                    pool,
+                    method.attributes(),
+                    &mut Labels::outside_code(),
+                    Some(access_flags),
+                    warnings,
+                )?,
+            })
+        })?);
+    }
+
+    let attributes = attributes(pool, class.attributes(), &mut Labels::outside_code(), None, warnings)?;
+
+    Ok(Class {
+        version: class.version(),
+        access_flags: class.access_flags(),
+        name,
+        super_class,
+        interfaces,
+        constant_pool,
+        fields,
+        methods,
+        attributes,
+    })
+}
+
+/// Records an error as a warning if warnings are collected, and returns it otherwise.
+///
+/// Warnings which were already recorded, such as by the lenient reader, are not recorded twice.
+fn tolerate(warnings: &mut Option<&mut Vec<DecodeError>>, err: DecodeError) -> Result<(), DecodeError> {
+    match warnings {
+        Some(warnings) => {
+            if !warnings.contains(&err) {
+                warnings.push(err);
+            }
+            Ok(())
+        }
+        None => Err(err),
+    }
+}
+
+/// Resolves a part of the class, adding a segment to the path of its error and of the warnings it records.
+fn within<T>(
+    warnings: &mut Option<&mut Vec<DecodeError>>,
+    segment: PathSegment,
+    f: impl FnOnce(Option<&mut Vec<DecodeError>>) -> Result<T, DecodeError>,
+) -> Result<T, DecodeError> {
+    let start = warnings.as_ref().map_or(0, |warnings| warnings.len());
+    let result = f(warnings.as_deref_mut());
+    if let Some(warnings) = warnings {
+        let added: Vec<_> = warnings.drain(start..).collect();
+        warnings.extend(added.into_iter().map(|warning| warning.within(segment.clone())));
+    }
+    result.map_err(|err| err.within(segment))
+}
+
+/// The positions referenced in the code of a method.
+pub(super) struct Labels {
+    /// The offsets of all instructions and the length of the code, `None` outside of code.
+    code: Option<(HashSet<u32>, u32)>,
+    used: BTreeSet<u32>,
+}
+
+impl Labels {
+    pub(super) fn outside_code() -> Labels {
+        Labels {
+            code: None,
+            used: BTreeSet::new(),
+        }
+    }
+
+    /// Creates the labels of code with instructions at the given offsets.
+    pub(super) fn for_code(offsets: impl IntoIterator<Item = u32>, length: u32) -> Labels {
+        Labels {
+            code: Some((offsets.into_iter().collect(), length)),
+            used: BTreeSet::new(),
+        }
+    }
+
+    fn at(&mut self, offset: u32) -> Result<Label, DecodeError> {
+        if let Some((instructions, length)) = &self.code {
+            if offset != *length && !instructions.contains(&offset) {
+                return Err(DecodeError::with_context(DecodeErrorKind::InvalidIndex, Context::Code));
+            }
+            self.used.insert(offset);
+        }
+
+        Ok(Label(offset))
+    }
+
+    pub(super) fn get(&mut self, index: raw_code::Index) -> Result<Label, DecodeError> {
+        self.at(index.as_u32())
+    }
+
+    fn jump(&mut self, from: raw_code::Index, offset: i32) -> Result<Label, DecodeError> {
+        let target = u32::try_from(i64::from(from.as_u32()) + i64::from(offset))
+            .map_err(|_| DecodeError::with_context(DecodeErrorKind::InvalidIndex, Context::Code))?;
+        self.at(target)
+    }
+}
+
+/// Resolves a list of attributes.
+///
+/// The access flags have to be passed for the attributes of a method.
+/// Attributes that can't be resolved are skipped if warnings are collected, except for the code of a
+/// method that is neither abstract nor native.
+fn attributes<'input>(
+    pool: &ConstantPool<'input>,
+    attributes: DecodeMany<'input, reader::Attribute<'input>, u16>,
+    labels: &mut Labels,
+    method: Option<AccessFlags>,
+    mut warnings: Option<&mut Vec<DecodeError>>,
+) -> Result<Vec<Attribute>, DecodeError> {
+    let mut resolved = Vec::new();
+    for (index, attribute) in (0..).zip(attributes) {
+        let attribute = match attribute {
+            Ok(attribute) => attribute,
+            Err(err) => {
+                // the length of the attribute is unknown, so no other attribute can be read
+                tolerate(&mut warnings, err)?;
+                break;
+            }
+        };
+        let name = utf8(pool, attribute.name()).ok();
+        let is_code = method.is_some() && name.as_deref().is_some_and(|name| name == "Code");
+        let has_code = method.is_some_and(|flags| !flags.intersects(AccessFlags::ABSTRACT | AccessFlags::NATIVE));
+        let segment = PathSegment::Attribute { index, name };
+
+        let result = within(&mut warnings, segment, |warnings| {
+            self::attribute(pool, &attribute, labels, method.is_some(), warnings)
+        });
+        match result {
+            Ok(attribute) => resolved.push(attribute),
+            Err(err) if is_code && has_code => return Err(err),
+            Err(err) => tolerate(&mut warnings, err)?,
+        }
+    }
+    Ok(resolved)
+}
+
+pub(super) fn attribute<'input>(
+    pool: &ConstantPool<'input>,
+    attribute: &reader::Attribute<'input>,
+    labels: &mut Labels,
+    in_method: bool,
+    warnings: Option<&mut Vec<DecodeError>>,
+) -> Result<Attribute, DecodeError> {
+    let name = pool.get(attribute.name())?.content;
+    if !in_method && name == "Code" {
+        return Ok(unknown(name.to_owned(), attribute));
+    }
+
+    let content = match attribute.decode_content(pool) {
+        Ok(content) => content,
+        Err(err) if err.kind() == DecodeErrorKind::UnknownAttributeName => {
+            return Ok(unknown(name.to_owned(), attribute));
+        }
+        Err(err) => return Err(err),
+    };
+
+    let attribute = match content {
+        AttributeContent::AnnotationDefault(attr) => Attribute::AnnotationDefault {
+            value: element_value(pool, &attr.value())?,
+        },
+        AttributeContent::BootstrapMethods(attr) => Attribute::BootstrapMethods(
+            attr.methods()
+                .iter()
+                .map(|method| {
+                    let method = method?;
+                    Ok(BootstrapMethod {
+                        method: method_handle(pool, pool.get(method.method_ref())?)?,
+                        arguments: method
+                            .arguments()
+                            .iter()
+                            .map(|argument| constant(pool, argument?))
+                            .collect::<Result<_, _>>()?,
+                    })
+                })
+                .collect::<Result<_, DecodeError>>()?,
+        ),
+        AttributeContent::Code(attr) => Attribute::Code(code(pool, &attr, warnings)?),
+        AttributeContent::ConstantValue(attr) => Attribute::ConstantValue {
+            value: constant(pool, attr.value())?,
+        },
+        AttributeContent::Deprecated(_) => Attribute::Deprecated,
+        AttributeContent::EnclosingMethod(attr) => Attribute::EnclosingMethod {
+            class: class_name(pool, attr.class())?,
+            method: attr.method().map(|method| name_and_type(pool, method)).transpose()?,
+        },
+        AttributeContent::Exceptions(attr) => Attribute::Exceptions(class_names(pool, attr.exceptions())?),
+        AttributeContent::InnerClasses(attr) => Attribute::InnerClasses(
+            attr.classes()
+                .iter()
+                .map(|class| {
+                    let class = class?;
+                    Ok(InnerClass {
+                        inner_class: class_name(pool, class.inner_class())?,
+                        outer_class: class.outer_class().map(|index| class_name(pool, index)).transpose()?,
+                        inner_name: class.inner_name().map(|index| utf8(pool, index)).transpose()?,
+                        inner_access_flags: class.inner_access_flags(),
+                    })
+                })
+                .collect::<Result<_, DecodeError>>()?,
+        ),
+        AttributeContent::LineNumberTable(attr) => Attribute::LineNumberTable(
+            attr.lines()
+                .iter()
+                .map(|line| {
+                    let line = line?;
+                    Ok(LineNumber {
+                        start: labels.get(line.start())?,
+                        line_number: line.line_number(),
+                    })
+                })
+                .collect::<Result<_, DecodeError>>()?,
+        ),
+        AttributeContent::LocalVariableTable(attr) => Attribute::LocalVariableTable(
+            attr.locals()
+                .iter()
+                .map(|local| {
+                    let local = local?;
+                    Ok(LocalVariable {
+                        start: labels.get(local.range().start)?,
+                        end: labels.get(local.range().end)?,
+                        name: utf8(pool, local.name())?,
+                        descriptor: utf8(pool, local.descriptor())?,
+                        index: local.index(),
+                    })
+                })
+                .collect::<Result<_, DecodeError>>()?,
+        ),
+        AttributeContent::LocalVariableTypeTable(attr) => Attribute::LocalVariableTypeTable(
+            attr.locals()
+                .iter()
+                .map(|local| {
+                    let local = local?;
+                    Ok(LocalVariableType {
+                        start: labels.get(local.range().start)?,
+                        end: labels.get(local.range().end)?,
+                        name: utf8(pool, local.name())?,
+                        signature: utf8(pool, local.signature())?,
+                        index: local.index(),
+                    })
+                })
+                .collect::<Result<_, DecodeError>>()?,
+        ),
+        AttributeContent::MethodParameters(attr) => Attribute::MethodParameters(
+            attr.parameters()
+                .iter()
+                .map(|parameter| {
+                    let parameter = parameter?;
+                    Ok(MethodParameter {
+                        name: parameter.name().map(|index| utf8(pool, index)).transpose()?,
+                        access_flags: parameter.access_flags(),
+                    })
+                })
+                .collect::<Result<_, DecodeError>>()?,
+        ),
+        AttributeContent::Module(attr) => Attribute::Module(Box::new(module(pool, &attr)?)),
+        AttributeContent::ModuleMainClass(attr) => Attribute::ModuleMainClass(class_name(pool, attr.main_class())?),
+        AttributeContent::ModulePackages(attr) => Attribute::ModulePackages(
+            attr.packages()
+                .iter()
+                .map(|package| utf8(pool, pool.get(package?)?.name))
+                .collect::<Result<_, _>>()?,
+        ),
+        AttributeContent::NestHost(attr) => Attribute::NestHost(class_name(pool, attr.host_class())?),
+        AttributeContent::NestMembers(attr) => Attribute::NestMembers(class_names(pool, attr.classes())?),
+        AttributeContent::PermittedSubclasses(attr) => {
+            Attribute::PermittedSubclasses(class_names(pool, attr.classes())?)
+        }
+        AttributeContent::Record(attr) => {
+            let mut warnings = warnings;
+            let mut components = Vec::new();
+            for (index, component) in (0..).zip(attr.components()) {
+                components.push(within(&mut warnings, PathSegment::Entry { index }, |warnings| {
+                    let component = component?;
+                    Ok(RecordComponent {
+                        name: utf8(pool, component.name())?,
+                        descriptor: utf8(pool, component.descriptor())?,
+                        attributes: attributes(
+                            pool,
+                            component.attributes(),
+                            &mut Labels::outside_code(),
+                            None,
+                            warnings,
+                        )?,
+                    })
+                })?);
+            }
+            Attribute::Record(components)
+        }
+        AttributeContent::RuntimeInvisibleAnnotations(attr) => {
+            Attribute::RuntimeInvisibleAnnotations(annotations(pool, attr.annotations())?)
+        }
+        AttributeContent::RuntimeInvisibleParameterAnnotations(attr) => {
+            Attribute::RuntimeInvisibleParameterAnnotations(parameter_annotations(pool, attr.parameters())?)
+        }
+        AttributeContent::RuntimeInvisibleTypeAnnotations(attr) => {
+            Attribute::RuntimeInvisibleTypeAnnotations(type_annotations(pool, attr.annotations(), labels)?)
+        }
+        AttributeContent::RuntimeVisibleAnnotations(attr) => {
+            Attribute::RuntimeVisibleAnnotations(annotations(pool, attr.annotations())?)
+        }
+        AttributeContent::RuntimeVisibleParameterAnnotations(attr) => {
+            Attribute::RuntimeVisibleParameterAnnotations(parameter_annotations(pool, attr.parameters())?)
+        }
+        AttributeContent::RuntimeVisibleTypeAnnotations(attr) => {
+            Attribute::RuntimeVisibleTypeAnnotations(type_annotations(pool, attr.annotations(), labels)?)
+        }
+        AttributeContent::Signature(attr) => Attribute::Signature(utf8(pool, attr.signature())?),
+        AttributeContent::SourceDebugExtension(attr) => Attribute::SourceDebugExtension(attr.content().to_owned()),
+        AttributeContent::SourceFile(attr) => Attribute::SourceFile(utf8(pool, attr.source_file())?),
+        AttributeContent::StackMapTable(attr) => Attribute::StackMapTable(
+            attr.iter()
+                .map(|frame| stack_map_frame(pool, labels, frame?))
+                .collect::<Result<_, _>>()?,
+        ),
+        AttributeContent::Synthetic(_) => Attribute::Synthetic,
+    };
+
+    Ok(attribute)
+}
+
+fn unknown(name: MString, attribute: &reader::Attribute<'_>) -> Attribute {
+    Attribute::Unknown {
+        name,
+        content: attribute.content().to_vec(),
+    }
+}
+
+fn code<'input>(
+    pool: &ConstantPool<'input>,
+    code: &raw_code::Code<'input>,
+    warnings: Option<&mut Vec<DecodeError>>,
+) -> Result<Code, DecodeError> {
+    let raw_instructions = code.raw_instructions();
+    let length = u32::try_from(raw_instructions.decoder.bytes_remaining())
+        .map_err(|_| DecodeError::with_context(DecodeErrorKind::InvalidLength, Context::Code))?;
+    let raw_instructions = raw_instructions.collect::<Result<Vec<_>, _>>()?;
+
+    let mut labels = Labels::for_code(raw_instructions.iter().map(|(pc, _)| pc.as_u32()), length);
+
+    let mut resolved = Vec::with_capacity(raw_instructions.len());
+    for (pc, raw) in raw_instructions {
+        let instruction = instruction(pool, &mut labels, pc, raw)
+            .map_err(|err| err.within(PathSegment::Instruction { pc: pc.as_u32() }))?;
+        resolved.push((pc.as_u32(), instruction));
+    }
+
+    let exception_handlers = (0..)
+        .zip(code.exception_handlers())
+        .map(|(index, handler)| {
+            (|| {
+                Ok(ExceptionHandler {
+                    start: labels.get(handler.start())?,
+                    end: labels.get(handler.end())?,
+                    handler: labels.get(handler.handler())?,
+                    catch_type: handler.catch_type().map(|index| class_name(pool, index)).transpose()?,
+                })
+            })()
+            .map_err(|err: DecodeError| err.within(PathSegment::ExceptionHandler { index }))
+        })
+        .collect::<Result<_, DecodeError>>()?;
+
+    let attributes = attributes(pool, code.attributes(), &mut labels, None, warnings)?;
+
+    let mut instructions = Vec::with_capacity(resolved.len() + labels.used.len());
+    for (pc, instruction) in resolved {
+        if labels.used.contains(&pc) {
+            instructions.push(Instruction::Label(Label(pc)));
+        }
+        instructions.push(instruction);
+    }
+    if labels.used.contains(&length) {
+        instructions.push(Instruction::Label(Label(length)));
+    }
+
+    Ok(Code {
+        max_stack: code.max_stack(),
+        max_locals: code.max_locals(),
+        instructions,
+        exception_handlers,
+        attributes,
+    })
+}
+
+pub(super) fn instruction<'input>(
+    pool: &ConstantPool<'input>,
+    labels: &mut Labels,
+    pc: raw_code::Index,
+    instruction: RawInstruction<'input>,
+) -> Result<Instruction, DecodeError> {
+    let instruction = match instruction {
+        RawInstruction::AALoad => Instruction::AALoad,
+        RawInstruction::AAStore => Instruction::AAStore,
+        RawInstruction::AConstNull => Instruction::AConstNull,
+        RawInstruction::ALoad { index } => Instruction::ALoad { index },
+        RawInstruction::ALoadW { index } => Instruction::ALoadW { index },
+        RawInstruction::ALoad0 => Instruction::ALoad0,
+        RawInstruction::ALoad1 => Instruction::ALoad1,
+        RawInstruction::ALoad2 => Instruction::ALoad2,
+        RawInstruction::ALoad3 => Instruction::ALoad3,
+        RawInstruction::ANewArray { index } => Instruction::ANewArray {
+            class: operand("class", class_name(pool, index))?,
+        },
+        RawInstruction::AReturn => Instruction::AReturn,
+        RawInstruction::ArrayLength => Instruction::ArrayLength,
+        RawInstruction::AStore { index } => Instruction::AStore { index },
+        RawInstruction::AStoreW { index } => Instruction::AStoreW { index },
+        RawInstruction::AStore0 => Instruction::AStore0,
+        RawInstruction::AStore1 => Instruction::AStore1,
+        RawInstruction::AStore2 => Instruction::AStore2,
+        RawInstruction::AStore3 => Instruction::AStore3,
+        RawInstruction::AThrow => Instruction::AThrow,
+        RawInstruction::BALoad => Instruction::BALoad,
+        RawInstruction::BAStore => Instruction::BAStore,
+        RawInstruction::BIPush { value } => Instruction::BIPush { value },
+        RawInstruction::CALoad => Instruction::CALoad,
+        RawInstruction::CAStore => Instruction::CAStore,
+        RawInstruction::CheckCast { index } => Instruction::CheckCast {
+            class: operand("class", class_name(pool, index))?,
+        },
+        RawInstruction::D2F => Instruction::D2F,
+        RawInstruction::D2I => Instruction::D2I,
+        RawInstruction::D2L => Instruction::D2L,
+        RawInstruction::DAdd => Instruction::DAdd,
+        RawInstruction::DALoad => Instruction::DALoad,
+        RawInstruction::DAStore => Instruction::DAStore,
+        RawInstruction::DCmpG => Instruction::DCmpG,
+        RawInstruction::DCmpL => Instruction::DCmpL,
+        RawInstruction::DConst0 => Instruction::DConst0,
+        RawInstruction::DConst1 => Instruction::DConst1,
+        RawInstruction::DDiv => Instruction::DDiv,
+        RawInstruction::DLoad { index } => Instruction::DLoad { index },
+        RawInstruction::DLoadW { index } => Instruction::DLoadW { index },
+        RawInstruction::DLoad0 => Instruction::DLoad0,
+        RawInstruction::DLoad1 => Instruction::DLoad1,
+        RawInstruction::DLoad2 => Instruction::DLoad2,
+        RawInstruction::DLoad3 => Instruction::DLoad3,
+        RawInstruction::DMul => Instruction::DMul,
+        RawInstruction::DNeg => Instruction::DNeg,
+        RawInstruction::DRem => Instruction::DRem,
+        RawInstruction::DReturn => Instruction::DReturn,
+        RawInstruction::DStore { index } => Instruction::DStore { index },
+        RawInstruction::DStoreW { index } => Instruction::DStoreW { index },
+        RawInstruction::DStore0 => Instruction::DStore0,
+        RawInstruction::DStore1 => Instruction::DStore1,
+        RawInstruction::DStore2 => Instruction::DStore2,
+        RawInstruction::DStore3 => Instruction::DStore3,
+        RawInstruction::DSub => Instruction::DSub,
+        RawInstruction::Dup => Instruction::Dup,
+        RawInstruction::DupX1 => Instruction::DupX1,
+        RawInstruction::DupX2 => Instruction::DupX2,
+        RawInstruction::Dup2 => Instruction::Dup2,
+        RawInstruction::Dup2X1 => Instruction::Dup2X1,
+        RawInstruction::Dup2X2 => Instruction::Dup2X2,
+        RawInstruction::F2D => Instruction::F2D,
+        RawInstruction::F2I => Instruction::F2I,
+        RawInstruction::F2L => Instruction::F2L,
+        RawInstruction::FAdd => Instruction::FAdd,
+        RawInstruction::FALoad => Instruction::FALoad,
+        RawInstruction::FAStore => Instruction::FAStore,
+        RawInstruction::FCmpG => Instruction::FCmpG,
+        RawInstruction::FCmpL => Instruction::FCmpL,
+        RawInstruction::FConst0 => Instruction::FConst0,
+        RawInstruction::FConst1 => Instruction::FConst1,
+        RawInstruction::FConst2 => Instruction::FConst2,
+        RawInstruction::FDiv => Instruction::FDiv,
+        RawInstruction::FLoad { index } => Instruction::FLoad { index },
+        RawInstruction::FLoadW { index } => Instruction::FLoadW { index },
+        RawInstruction::FLoad0 => Instruction::FLoad0,
+        RawInstruction::FLoad1 => Instruction::FLoad1,
+        RawInstruction::FLoad2 => Instruction::FLoad2,
+        RawInstruction::FLoad3 => Instruction::FLoad3,
+        RawInstruction::FMul => Instruction::FMul,
+        RawInstruction::FNeg => Instruction::FNeg,
+        RawInstruction::FRem => Instruction::FRem,
+        RawInstruction::FReturn => Instruction::FReturn,
+        RawInstruction::FStore { index } => Instruction::FStore { index },
+        RawInstruction::FStoreW { index } => Instruction::FStoreW { index },
+        RawInstruction::FStore0 => Instruction::FStore0,
+        RawInstruction::FStore1 => Instruction::FStore1,
+        RawInstruction::FStore2 => Instruction::FStore2,
+        RawInstruction::FStore3 => Instruction::FStore3,
+        RawInstruction::FSub => Instruction::FSub,
+        RawInstruction::GetField { index } => Instruction::GetField {
+            field: operand("field", field_ref(pool, index))?,
+        },
+        RawInstruction::GetStatic { index } => Instruction::GetStatic {
+            field: operand("field", field_ref(pool, index))?,
+        },
+        RawInstruction::Goto { offset } => Instruction::Goto {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::GotoW { offset } => Instruction::GotoW {
+            target: operand("target", labels.jump(pc, offset))?,
+        },
+        RawInstruction::I2B => Instruction::I2B,
+        RawInstruction::I2C => Instruction::I2C,
+        RawInstruction::I2D => Instruction::I2D,
+        RawInstruction::I2F => Instruction::I2F,
+        RawInstruction::I2L => Instruction::I2L,
+        RawInstruction::I2S => Instruction::I2S,
+        RawInstruction::IAdd => Instruction::IAdd,
+        RawInstruction::IALoad => Instruction::IALoad,
+        RawInstruction::IAnd => Instruction::IAnd,
+        RawInstruction::IAStore => Instruction::IAStore,
+        RawInstruction::IConstM1 => Instruction::IConstM1,
+        RawInstruction::IConst0 => Instruction::IConst0,
+        RawInstruction::IConst1 => Instruction::IConst1,
+        RawInstruction::IConst2 => Instruction::IConst2,
+        RawInstruction::IConst3 => Instruction::IConst3,
+        RawInstruction::IConst4 => Instruction::IConst4,
+        RawInstruction::IConst5 => Instruction::IConst5,
+        RawInstruction::IDiv => Instruction::IDiv,
+        RawInstruction::IfACmpEq { offset } => Instruction::IfACmpEq {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfACmpNe { offset } => Instruction::IfACmpNe {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfICmpEq { offset } => Instruction::IfICmpEq {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfICmpNe { offset } => Instruction::IfICmpNe {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfICmpLt { offset } => Instruction::IfICmpLt {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfICmpGe { offset } => Instruction::IfICmpGe {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfICmpGt { offset } => Instruction::IfICmpGt {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfICmpLe { offset } => Instruction::IfICmpLe {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfEq { offset } => Instruction::IfEq {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfNe { offset } => Instruction::IfNe {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfLt { offset } => Instruction::IfLt {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfGe { offset } => Instruction::IfGe {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfGt { offset } => Instruction::IfGt {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfLe { offset } => Instruction::IfLe {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfNonNull { offset } => Instruction::IfNonNull {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IfNull { offset } => Instruction::IfNull {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::IInc { index, value } => Instruction::IInc { index, value },
+        RawInstruction::IIncW { index, value } => Instruction::IIncW { index, value },
+        RawInstruction::ILoad { index } => Instruction::ILoad { index },
+        RawInstruction::ILoadW { index } => Instruction::ILoadW { index },
+        RawInstruction::ILoad0 => Instruction::ILoad0,
+        RawInstruction::ILoad1 => Instruction::ILoad1,
+        RawInstruction::ILoad2 => Instruction::ILoad2,
+        RawInstruction::ILoad3 => Instruction::ILoad3,
+        RawInstruction::IMul => Instruction::IMul,
+        RawInstruction::INeg => Instruction::INeg,
+        RawInstruction::InstanceOf { index } => Instruction::InstanceOf {
+            class: operand("class", class_name(pool, index))?,
+        },
+        RawInstruction::InvokeDynamic { index } => Instruction::InvokeDynamic {
+            call_site: operand("call_site", invoke_dynamic(pool, index))?,
+        },
+        RawInstruction::InvokeInterface { index, count } => Instruction::InvokeInterface {
+            method: operand("method", interface_method_ref(pool, index))?,
+            count,
+        },
+        RawInstruction::InvokeSpecial { index } => {
+            let (method, interface) = operand("method", any_method_ref(pool, index))?;
+            Instruction::InvokeSpecial { method, interface }
+        }
+        RawInstruction::InvokeStatic { index } => {
+            let (method, interface) = operand("method", any_method_ref(pool, index))?;
+            Instruction::InvokeStatic { method, interface }
+        }
+        RawInstruction::InvokeVirtual { index } => Instruction::InvokeVirtual {
+            method: operand("method", method_ref(pool, index))?,
+        },
+        RawInstruction::IOr => Instruction::IOr,
+        RawInstruction::IRem => Instruction::IRem,
+        RawInstruction::IReturn => Instruction::IReturn,
+        RawInstruction::IShL => Instruction::IShL,
+        RawInstruction::IShR => Instruction::IShR,
+        RawInstruction::IStore { index } => Instruction::IStore { index },
+        RawInstruction::IStoreW { index } => Instruction::IStoreW { index },
+        RawInstruction::IStore0 => Instruction::IStore0,
+        RawInstruction::IStore1 => Instruction::IStore1,
+        RawInstruction::IStore2 => Instruction::IStore2,
+        RawInstruction::IStore3 => Instruction::IStore3,
+        RawInstruction::ISub => Instruction::ISub,
+        RawInstruction::IUShR => Instruction::IUShR,
+        RawInstruction::IXor => Instruction::IXor,
+        RawInstruction::JSr { offset } => Instruction::JSr {
+            target: operand("target", labels.jump(pc, offset.into()))?,
+        },
+        RawInstruction::JSrW { offset } => Instruction::JSrW {
+            target: operand("target", labels.jump(pc, offset))?,
+        },
+        RawInstruction::L2D => Instruction::L2D,
+        RawInstruction::L2F => Instruction::L2F,
+        RawInstruction::L2I => Instruction::L2I,
+        RawInstruction::LAdd => Instruction::LAdd,
+        RawInstruction::LALoad => Instruction::LALoad,
+        RawInstruction::LAnd => Instruction::LAnd,
+        RawInstruction::LAStore => Instruction::LAStore,
+        RawInstruction::LCmp => Instruction::LCmp,
+        RawInstruction::LConst0 => Instruction::LConst0,
+        RawInstruction::LConst1 => Instruction::LConst1,
+        RawInstruction::LdC { index } => Instruction::LdC {
+            constant: operand("constant", constant(pool, index))?,
+        },
+        RawInstruction::LdCW { index } => Instruction::LdCW {
+            constant: operand("constant", constant(pool, index))?,
+        },
+        RawInstruction::LdC2W { index } => Instruction::LdC2W {
+            constant: operand("constant", constant(pool, index))?,
+        },
+        RawInstruction::LDiv => Instruction::LDiv,
+        RawInstruction::LLoad { index } => Instruction::LLoad { index },
+        RawInstruction::LLoadW { index } => Instruction::LLoadW { index },
+        RawInstruction::LLoad0 => Instruction::LLoad0,
+        RawInstruction::LLoad1 => Instruction::LLoad1,
+        RawInstruction::LLoad2 => Instruction::LLoad2,
+        RawInstruction::LLoad3 => Instruction::LLoad3,
+        RawInstruction::LMul => Instruction::LMul,
+        RawInstruction::LNeg => Instruction::LNeg,
+        RawInstruction::LOr => Instruction::LOr,
+        RawInstruction::LRem => Instruction::LRem,
+        RawInstruction::LReturn => Instruction::LReturn,
+        RawInstruction::LShL => Instruction::LShL,
+        RawInstruction::LShR => Instruction::LShR,
+        RawInstruction::LStore { index } => Instruction::LStore { index },
+        RawInstruction::LStoreW { index } => Instruction::LStoreW { index },
+        RawInstruction::LStore0 => Instruction::LStore0,
+        RawInstruction::LStore1 => Instruction::LStore1,
+        RawInstruction::LStore2 => Instruction::LStore2,
+        RawInstruction::LStore3 => Instruction::LStore3,
+        RawInstruction::LSub => Instruction::LSub,
+        RawInstruction::LUShR => Instruction::LUShR,
+        RawInstruction::LXor => Instruction::LXor,
+        RawInstruction::MonitorEnter => Instruction::MonitorEnter,
+        RawInstruction::MonitorExit => Instruction::MonitorExit,
+        RawInstruction::MultiANewArray { index, dimensions } => Instruction::MultiANewArray {
+            class: operand("class", class_name(pool, index))?,
+            dimensions,
+        },
+        RawInstruction::New { index } => Instruction::New {
+            class: operand("class", class_name(pool, index))?,
+        },
+        RawInstruction::NewArray { atype } => Instruction::NewArray { atype },
+        RawInstruction::Nop => Instruction::Nop,
+        RawInstruction::Pop => Instruction::Pop,
+        RawInstruction::Pop2 => Instruction::Pop2,
+        RawInstruction::PutField { index } => Instruction::PutField {
+            field: operand("field", field_ref(pool, index))?,
+        },
+        RawInstruction::PutStatic { index } => Instruction::PutStatic {
+            field: operand("field", field_ref(pool, index))?,
+        },
+        RawInstruction::Ret { index } => Instruction::Ret { index },
+        RawInstruction::RetW { index } => Instruction::RetW { index },
+        RawInstruction::Return => Instruction::Return,
+        RawInstruction::SALoad => Instruction::SALoad,
+        RawInstruction::SAStore => Instruction::SAStore,
+        RawInstruction::SIPush { value } => Instruction::SIPush { value },
+        RawInstruction::Swap => Instruction::Swap,
+        RawInstruction::LookupSwitch(switch) => Instruction::LookupSwitch {
+            default: operand("default", labels.jump(pc, switch.default_offset()))?,
+            pairs: operand(
+                "pairs",
+                switch
+                    .pairs()
+                    .map(|pair| Ok((pair.key(), labels.jump(pc, pair.offset())?)))
+                    .collect(),
+            )?,
+        },
+        RawInstruction::TableSwitch(switch) => Instruction::TableSwitch {
+            default: operand("default", labels.jump(pc, switch.default_offset()))?,
+            low: switch.low(),
+            targets: operand(
+                "targets",
+                switch.pairs().map(|pair| labels.jump(pc, pair.offset())).collect(),
+            )?,
+        },
+    };
+
+    Ok(instruction)
+}
+
+/// Adds the name of an operand to the path of an error.
+fn operand<T>(name: &'static str, result: Result<T, DecodeError>) -> Result<T, DecodeError> {
+    result.map_err(|err| err.within(PathSegment::Operand { name }))
+}
+
+fn stack_map_frame<'input>(
+    pool: &ConstantPool<'input>,
+    labels: &mut Labels,
+    (index, frame): (raw_code::Index, raw_code::StackMapFrame<'input>),
+) -> Result<StackMapFrame, DecodeError> {
+    let label = labels.get(index)?;
+    let frame = match frame {
+        raw_code::StackMapFrame::Same => StackMapFrame::Same { label },
+        raw_code::StackMapFrame::SameExtended => StackMapFrame::SameExtended { label },
+        raw_code::StackMapFrame::Same1 { stack } => StackMapFrame::Same1 {
+            label,
+            stack: verification_type(pool, labels, stack)?,
+        },
+        raw_code::StackMapFrame::Same1Extended { stack } => StackMapFrame::Same1Extended {
+            label,
+            stack: verification_type(pool, labels, stack)?,
+        },
+        raw_code::StackMapFrame::Chop { to_chop } => StackMapFrame::Chop { label, to_chop },
+        raw_code::StackMapFrame::Append { locals } => StackMapFrame::Append {
+            label,
+            locals: verification_types(pool, labels, locals)?,
+        },
+        raw_code::StackMapFrame::Full { locals, stack } => StackMapFrame::Full {
+            label,
+            locals: verification_types(pool, labels, locals)?,
+            stack: verification_types(pool, labels, stack)?,
+        },
+    };
+
+    Ok(frame)
+}
+
+fn verification_types<'input>(
+    pool: &ConstantPool<'input>,
+    labels: &mut Labels,
+    types: raw_code::VerificationTypeIter<'input>,
+) -> Result<Vec<VerificationType>, DecodeError> {
+    types.map(|ty| verification_type(pool, labels, ty?)).collect()
+}
+
+fn verification_type<'input>(
+    pool: &ConstantPool<'input>,
+    labels: &mut Labels,
+    ty: raw_code::VerificationType<'input>,
+) -> Result<VerificationType, DecodeError> {
+    let ty = match ty {
+        raw_code::VerificationType::Top => VerificationType::Top,
+        raw_code::VerificationType::Integer => VerificationType::Integer,
+        raw_code::VerificationType::Float => VerificationType::Float,
+        raw_code::VerificationType::Double => VerificationType::Double,
+        raw_code::VerificationType::Long => VerificationType::Long,
+        raw_code::VerificationType::Null => VerificationType::Null,
+        raw_code::VerificationType::UninitializedThis => VerificationType::UninitializedThis,
+        raw_code::VerificationType::Object(class) => VerificationType::Object(class_name(pool, class)?),
+        raw_code::VerificationType::UninitializedVariable(index) => VerificationType::Uninitialized(labels.get(index)?),
+    };
+
+    Ok(ty)
+}
+
+fn module<'input>(
+    pool: &ConstantPool<'input>,
+    module: &reader::attributes::Module<'input>,
+) -> Result<Module, DecodeError> {
+    Ok(Module {
+        name: module_name(pool, module.name())?,
+        flags: module.flags(),
+        version: module.version().map(|index| utf8(pool, index)).transpose()?,
+        requires: module
+            .requires()
+            .iter()
+            .map(|require| {
+                let require = require?;
+                Ok(Require {
+                    module: module_name(pool, require.index())?,
+                    flags: require.flags(),
+                    version: require.version().map(|index| utf8(pool, index)).transpose()?,
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?,
+        exports: module
+            .exports()
+            .iter()
+            .map(|export| {
+                let export = export?;
+                Ok(Export {
+                    package: package_name(pool, export.index())?,
+                    flags: export.flags(),
+                    exports_to: module_names(pool, export.exports_to())?,
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?,
+        opens: module
+            .opens()
+            .iter()
+            .map(|open| {
+                let open = open?;
+                Ok(Open {
+                    package: package_name(pool, open.index())?,
+                    flags: open.flags(),
+                    opens_to: module_names(pool, open.opens_to())?,
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?,
+        uses: class_names(pool, module.uses())?,
+        provides: module
+            .provides()
+            .iter()
+            .map(|provide| {
+                let provide = provide?;
+                Ok(Provide {
+                    service: class_name(pool, provide.index())?,
+                    provides_with: class_names(pool, provide.provides_with())?,
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?,
+    })
+}
+
+pub(super) fn annotations<'input>(
+    pool: &ConstantPool<'input>,
+    annotations: DecodeMany<'input, raw_annotations::Annotation<'input>, u16>,
+) -> Result<Vec<Annotation>, DecodeError> {
+    annotations
+        .iter()
+        .map(|annotation| self::annotation(pool, &annotation?))
+        .collect()
+}
+
+pub(super) fn parameter_annotations<'input>(
+    pool: &ConstantPool<'input>,
+    parameters: DecodeMany<'input, raw_annotations::ParameterAnnotations<'input>, u8>,
+) -> Result<Vec<Vec<Annotation>>, DecodeError> {
+    parameters
+        .iter()
+        .map(|parameter| annotations(pool, parameter?.annotations()))
+        .collect()
+}
+
+fn annotation<'input>(
+    pool: &ConstantPool<'input>,
+    annotation: &raw_annotations::Annotation<'input>,
+) -> Result<Annotation, DecodeError> {
+    Ok(Annotation {
+        type_: utf8(pool, annotation.type_())?,
+        pairs: element_value_pairs(pool, annotation.pairs())?,
+    })
+}
+
+fn element_value_pairs<'input>(
+    pool: &ConstantPool<'input>,
+    pairs: DecodeMany<'input, raw_annotations::ElementValuePair<'input>, u16>,
+) -> Result<Vec<ElementValuePair>, DecodeError> {
+    pairs
+        .iter()
+        .map(|pair| {
+            let pair = pair?;
+            Ok(ElementValuePair {
+                name: utf8(pool, pair.name())?,
+                value: element_value(pool, &pair.value())?,
+            })
+        })
+        .collect()
+}
+
+fn element_value<'input>(
+    pool: &ConstantPool<'input>,
+    value: &raw_annotations::ElementValue<'input>,
+) -> Result<ElementValue, DecodeError> {
+    use raw_annotations::ElementValue as Raw;
+
+    let value = match value {
+        Raw::Boolean(index) => ElementValue::Boolean(pool.get(*index)?.value),
+        Raw::Byte(index) => ElementValue::Byte(pool.get(*index)?.value),
+        Raw::Short(index) => ElementValue::Short(pool.get(*index)?.value),
+        Raw::Int(index) => ElementValue::Int(pool.get(*index)?.value),
+        Raw::Long(index) => ElementValue::Long(pool.get(*index)?.value),
+        Raw::Float(index) => ElementValue::Float(pool.get(*index)?.value),
+        Raw::Double(index) => ElementValue::Double(pool.get(*index)?.value),
+        Raw::Char(index) => ElementValue::Char(pool.get(*index)?.value),
+        Raw::String(index) => ElementValue::String(utf8(pool, *index)?),
+        Raw::Class(index) => ElementValue::Class(utf8(pool, *index)?),
+        Raw::Enum { type_name, const_name } => ElementValue::Enum {
+            type_name: utf8(pool, *type_name)?,
+            const_name: utf8(pool, *const_name)?,
+        },
+        Raw::Annotation(annotation) => ElementValue::Annotation(self::annotation(pool, annotation)?),
+        Raw::Array(values) => ElementValue::Array(
+            values
+                .iter()
+                .map(|value| element_value(pool, &value?))
+                .collect::<Result<_, _>>()?,
+        ),
+    };
+
+    Ok(value)
+}
+
+fn type_annotations<'input>(
+    pool: &ConstantPool<'input>,
+    annotations: DecodeMany<'input, raw_annotations::TypeAnnotation<'input>, u16>,
+    labels: &mut Labels,
+) -> Result<Vec<TypeAnnotation>, DecodeError> {
+    annotations
+        .iter()
+        .map(|annotation| type_annotation(pool, &annotation?, labels))
+        .collect()
+}
+
+fn type_annotation<'input>(
+    pool: &ConstantPool<'input>,
+    annotation: &raw_annotations::TypeAnnotation<'input>,
+    labels: &mut Labels,
+) -> Result<TypeAnnotation, DecodeError> {
+    use raw_annotations::TargetInfo as Raw;
+
+    let target_info = match annotation.target_info() {
+        Raw::TypeParameter { parameter_index } => TargetInfo::TypeParameter {
+            parameter_index: *parameter_index,
+        },
+        Raw::SuperType { supertype_index } => TargetInfo::SuperType {
+            supertype_index: *supertype_index,
+        },
+        Raw::TypeParameterBound {
+            type_parameter_index,
+            bound_index,
+        } => TargetInfo::TypeParameterBound {
+            type_parameter_index: *type_parameter_index,
+            bound_index: *bound_index,
+        },
+        Raw::Empty => TargetInfo::Empty,
+        Raw::FormalParameter { formal_parameter_index } => TargetInfo::FormalParameter {
+            formal_parameter_index: *formal_parameter_index,
+        },
+        Raw::Throws { throws_type_index } => TargetInfo::Throws {
+            throws_type_index: *throws_type_index,
+        },
+        Raw::LocalVariable { table } => TargetInfo::LocalVariable {
+            table: table
+                .iter()
+                .map(|local| {
+                    let local = local?;
+                    Ok(LocalVariableTarget {
+                        start: labels.get(local.range().start)?,
+                        end: labels.get(local.range().end)?,
+                        index: local.index(),
+                    })
+                })
+                .collect::<Result<_, DecodeError>>()?,
+        },
+        Raw::Catch { exception_table_index } => TargetInfo::Catch {
+            exception_table_index: *exception_table_index,
+        },
+        Raw::Offset { offset } => TargetInfo::Offset {
+            offset: labels.get(*offset)?,
+        },
+        Raw::TypeArgument {
+            offset,
+            type_argument_index,
+        } => TargetInfo::TypeArgument {
+            offset: labels.get(*offset)?,
+            type_argument_index: *type_argument_index,
+        },
+    };
+
+    Ok(TypeAnnotation {
+        target_type: annotation.target_type(),
+        target_info,
+        target_path: annotation
+            .target_path()
+            .iter()
+            .map(|segment| {
+                let segment = segment?;
+                Ok(TypePathSegment {
+                    kind: segment.kind(),
+                    type_argument_index: segment.type_argument_index(),
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?,
+        type_: utf8(pool, annotation.type_())?,
+        pairs: element_value_pairs(pool, annotation.pairs())?,
+    })
+}
+
+pub(super) fn constant<'input>(
+    pool: &ConstantPool<'input>,
+    index: cpool::Index<cpool::Item<'input>>,
+) -> Result<Constant, DecodeError> {
+    let constant = match pool.get(index)? {
+        cpool::Item::Utf8(item) => Constant::Utf8(item.content.to_owned()),
+        cpool::Item::Integer(item) => Constant::Integer(item.value),
+        cpool::Item::Float(item) => Constant::Float(item.value),
+        cpool::Item::Long(item) => Constant::Long(item.value),
+        cpool::Item::Double(item) => Constant::Double(item.value),
+        cpool::Item::Class(item) => Constant::Class(utf8(pool, item.name)?),
+        cpool::Item::String(item) => Constant::String(utf8(pool, item.string)?),
+        cpool::Item::FieldRef(item) => Constant::FieldRef(member_ref(pool, item.class, item.name_and_type)?),
+        cpool::Item::MethodRef(item) => Constant::MethodRef(member_ref(pool, item.class, item.name_and_type)?),
+        cpool::Item::InterfaceMethodRef(item) => {
+            Constant::InterfaceMethodRef(member_ref(pool, item.class, item.name_and_type)?)
+        }
+        cpool::Item::NameAndType(item) => Constant::NameAndType(NameAndType {
+            name: utf8(pool, item.name)?,
+            descriptor: utf8(pool, item.descriptor)?,
+        }),
+        cpool::Item::MethodHandle(item) => Constant::MethodHandle(method_handle(pool, item)?),
+        cpool::Item::MethodType(item) => Constant::MethodType(utf8(pool, item.descriptor)?),
+        cpool::Item::Dynamic(item) => {
+            Constant::Dynamic(dynamic_constant(pool, item.bootstrap_method_attr, item.name_and_type)?)
+        }
+        cpool::Item::InvokeDynamic(item) => {
+            Constant::InvokeDynamic(dynamic_constant(pool, item.bootstrap_method_attr, item.name_and_type)?)
+        }
+        cpool::Item::Module(item) => Constant::Module(utf8(pool, item.name)?),
+        cpool::Item::Package(item) => Constant::Package(utf8(pool, item.name)?),
+    };
+
+    Ok(constant)
+}
+
+pub(super) fn utf8<'input>(
+    pool: &ConstantPool<'input>,
+    index: cpool::Index<cpool::Utf8<'input>>,
+) -> Result<MString, DecodeError> {
+    Ok(pool.get(index)?.content.to_owned())
+}
+
+pub(super) fn class_name<'input>(
+    pool: &ConstantPool<'input>,
+    index: cpool::Index<cpool::Class<'input>>,
+) -> Result<MString, DecodeError> {
+    utf8(pool, pool.get(index)?.name)
+}
+
+fn class_names<'input>(
+    pool: &ConstantPool<'input>,
+    classes: DecodeMany<'input, cpool::Index<cpool::Class<'input>>, u16>,
+) -> Result<Vec<MString>, DecodeError> {
+    classes.iter().map(|class| class_name(pool, class?)).collect()
+}
+
+fn module_name<'input>(
+    pool: &ConstantPool<'input>,
+    index: cpool::Index<cpool::Module<'input>>,
+) -> Result<MString, DecodeError> {
+    utf8(pool, pool.get(index)?.name)
+}
+
+fn module_names<'input>(
+    pool: &ConstantPool<'input>,
+    modules: DecodeMany<'input, cpool::Index<cpool::Module<'input>>, u16>,
+) -> Result<Vec<MString>, DecodeError> {
+    modules.iter().map(|module| module_name(pool, module?)).collect()
+}
+
+fn package_name<'input>(
+    pool: &ConstantPool<'input>,
+    index: cpool::Index<cpool::Package<'input>>,
+) -> Result<MString, DecodeError> {
+    utf8(pool, pool.get(index)?.name)
+}
+
+fn name_and_type<'input>(
+    pool: &ConstantPool<'input>,
+    index: cpool::Index<cpool::NameAndType<'input>>,
+) -> Result<NameAndType, DecodeError> {
+    let name_and_type = pool.get(index)?;
+    Ok(NameAndType {
+        name: utf8(pool, name_and_type.name)?,
+        descriptor: utf8(pool, name_and_type.descriptor)?,
+    })
+}
+
+fn member_ref<'input>(
+    pool: &ConstantPool<'input>,
+    class: cpool::Index<cpool::Class<'input>>,
+    name_and_type: cpool::Index<cpool::NameAndType<'input>>,
+) -> Result<MemberRef, DecodeError> {
+    let NameAndType { name, descriptor } = self::name_and_type(pool, name_and_type)?;
+    Ok(MemberRef {
+        class: class_name(pool, class)?,
+        name,
+        descriptor,
+    })
+}
+
+fn field_ref<'input>(
+    pool: &ConstantPool<'input>,
+    index: cpool::Index<cpool::FieldRef<'input>>,
+) -> Result<MemberRef, DecodeError> {
+    let item = pool.get(index)?;
+    member_ref(pool, item.class, item.name_and_type)
+}
+
+fn method_ref<'input>(
+    pool: &ConstantPool<'input>,
+    index: cpool::Index<cpool::MethodRef<'input>>,
+) -> Result<MemberRef, DecodeError> {
+    let item = pool.get(index)?;
+    member_ref(pool, item.class, item.name_and_type)
+}
+
+fn interface_method_ref<'input>(
+    pool: &ConstantPool<'input>,
+    index: cpool::Index<cpool::InterfaceMethodRef<'input>>,
+) -> Result<MemberRef, DecodeError> {
+    let item = pool.get(index)?;
+    member_ref(pool, item.class, item.name_and_type)
+}
+
+/// Resolves a reference to a method which may be declared in either a class or an interface.
+fn any_method_ref<'input>(
+    pool: &ConstantPool<'input>,
+    index: cpool::Index<cpool::Item<'input>>,
+) -> Result<(MemberRef, bool), DecodeError> {
+    match pool.get(index)? {
+        cpool::Item::MethodRef(item) => Ok((member_ref(pool, item.class, item.name_and_type)?, false)),
+        cpool::Item::InterfaceMethodRef(item) => Ok((member_ref(pool, item.class, item.name_and_type)?, true)),
+        _ => Err(DecodeError::with_context(
+            DecodeErrorKind::TagMismatch,
+            Context::ConstantPool,
+        )),
+    }
+}
+
+fn method_handle<'input>(
+    pool: &ConstantPool<'input>,
+    handle: &cpool::MethodHandle<'input>,
+) -> Result<MethodHandle, DecodeError> {
+    let (reference, interface) = match pool.get(handle.reference)? {
+        cpool::Item::FieldRef(item) => (member_ref(pool, item.class, item.name_and_type)?, false),
+        _ => any_method_ref(pool, handle.reference)?,
+    };
+
+    Ok(MethodHandle {
+        kind: handle.kind,
+        reference,
+        interface,
+    })
+}
+
+fn invoke_dynamic<'input>(
+    pool: &ConstantPool<'input>,
+    index: cpool::Index<cpool::InvokeDynamic<'input>>,
+) -> Result<DynamicConstant, DecodeError> {
+    let item = pool.get(index)?;
+    dynamic_constant(pool, item.bootstrap_method_attr, item.name_and_type)
+}
+
+fn dynamic_constant<'input>(
+    pool: &ConstantPool<'input>,
+    bootstrap_method: u16,
+    name_and_type: cpool::Index<cpool::NameAndType<'input>>,
+) -> Result<DynamicConstant, DecodeError> {
+    let NameAndType { name, descriptor } = self::name_and_type(pool, name_and_type)?;
+    Ok(DynamicConstant {
+        bootstrap_method,
+        name,
+        descriptor,
+    })
+}
@@ -0,0 +1,1172 @@
+use std::collections::HashMap;
+
+use crate::error::*;
+use crate::mutf8::MString;
+use crate::reader::attributes::annotations::{SuperTypeIndex, TargetType, TypePathSegmentKind};
+use crate::reader::cpool::MethodKind;
+use crate::tree::*;
+use crate::writer::attributes::code::{CodeWriter, CodeWriterState, InstructionWriter, LabelRef};
+use crate::writer::attributes::{AttributeWriter, AttributeWriterState};
+use crate::writer::cpool::{self, Insertable};
+use crate::writer::encoding::*;
+use crate::writer::{self, ClassWriter};
+
+pub(super) fn class(class: &Class) -> Result<Vec<u8>, EncodeError> {
+    // the constant pool can only be written to after the version
+    let mut writer = ClassWriter::new().version(class.version)?;
+    for constant in &class.constant_pool {
+        constant.insert(&mut writer)?;
+    }
+
+    let writer = writer
+        .access_flags(class.access_flags)?
+        .this_class(class.name.clone())?;
+    let writer = match &class.super_class {
+        Some(super_class) => writer.super_class(super_class.clone())?,
+        None => writer.no_super_class()?,
+    };
+
+    writer
+        .interfaces(|interfaces| {
+            for interface in &class.interfaces {
+                interfaces.begin(|writer| writer.interface(interface.clone()))?;
+            }
+            Ok(())
+        })?
+        .fields(|fields| {
+            for (index, field) in (0..).zip(&class.fields) {
+                let segment = PathSegment::Field {
+                    index,
+                    name: Some(field.name.clone()),
+                };
+                fields
+                    .begin(|writer| {
+                        writer
+                            .access_flags(field.access_flags)?
+                            .name(field.name.clone())?
+                            .descriptor(field.descriptor.clone())?
+                            .attributes(|attributes| {
+                                for attr in &field.attributes {
+                                    attributes.begin(|writer| attribute(writer, attr, None))?;
+                                }
+                                Ok(())
+                            })
+                    })
+                    .map_err(|err| err.within(segment))?;
+            }
+            Ok(())
+        })?
+        .methods(|methods| {
+            for (index, method) in (0..).zip(&class.methods) {
+                let segment = PathSegment::Method {
+                    index,
+                    name: Some(method.name.clone()),
+                    descriptor: Some(method.descriptor.clone()),
+                };
+                methods
+                    .begin(|writer| {
+                        writer
+                            .access_flags(method.access_flags)?
+                            .name(method.name.clone())?
+                            .descriptor(method.descriptor.clone())?
+                            .attributes(|attributes| {
+                                for attr in &method.attributes {
+                                    attributes.begin(|writer| match attr {
+                                        Attribute::Code(code) => writer.code(|writer| self::code(writer, code)),
+                                        _ => attribute(writer, attr, None),
+                                    })?;
+                                }
+                                Ok(())
+                            })
+                    })
+                    .map_err(|err| err.within(segment))?;
+            }
+            Ok(())
+        })?
+        .attributes(|attributes| {
+            for attr in &class.attributes {
+                attributes.begin(|writer| attribute(writer, attr, None))?;
+            }
+            Ok(())
+        })?
+        .into_bytes()
+}
+
+/// The labels placed in the code of a method.
+struct Labels {
+    labels: HashMap<Label, (Option<writer::attributes::code::Label>, LabelRef)>,
+}
+
+impl Labels {
+    fn get(&self, label: Label) -> Result<LabelRef, EncodeError> {
+        self.labels
+            .get(&label)
+            .map(|(_, label_ref)| *label_ref)
+            .ok_or_else(|| EncodeError::with_context(EncodeErrorKind::LabelNotFound, Context::Code))
+    }
+
+    /// Returns the label to place, which can only be done once for every label.
+    fn place(&mut self, label: Label) -> Result<writer::attributes::code::Label, EncodeError> {
+        self.labels
+            .get_mut(&label)
+            .and_then(|(label, _)| label.take())
+            .ok_or_else(|| EncodeError::with_context(EncodeErrorKind::CantChangeAnymore, Context::Code))
+    }
+}
+
+fn code<Ctx: EncoderContext>(
+    mut writer: CodeWriter<Ctx, CodeWriterState::MaxStack>,
+    code: &Code,
+) -> Result<CodeWriter<Ctx, CodeWriterState::End>, EncodeError> {
+    let mut labels = Labels { labels: HashMap::new() };
+    for instruction in &code.instructions {
+        if let Instruction::Label(label) = instruction {
+            let (writer_label, label_ref) = writer.new_label()?;
+            labels.labels.insert(*label, (Some(writer_label), label_ref));
+        }
+    }
+
+    let writer = writer
+        .max_stack(code.max_stack)?
+        .max_locals(code.max_locals)?
+        .instructions(|insns| {
+            for instruction in &code.instructions {
+                self::instruction(insns, &mut labels, instruction)?;
+            }
+            Ok(())
+        })?;
+
+    let mut positions = HashMap::with_capacity(labels.labels.len());
+    for (label, (_, label_ref)) in &labels.labels {
+        positions.insert(*label, writer.get_label_position(*label_ref)?);
+    }
+
+    writer
+        .exceptions(|handlers| {
+            for handler in &code.exception_handlers {
+                handlers.begin(|writer| {
+                    let writer = writer
+                        .start(labels.get(handler.start)?)?
+                        .end(labels.get(handler.end)?)?
+                        .handler(labels.get(handler.handler)?)?;
+                    match &handler.catch_type {
+                        Some(catch_type) => writer.catch_type(catch_type.clone()),
+                        None => writer.catch_all(),
+                    }
+                })?;
+            }
+            Ok(())
+        })?
+        .attributes(|attributes| {
+            for attr in &code.attributes {
+                attributes.begin(|writer| attribute(writer, attr, Some(&positions)))?;
+            }
+            Ok(())
+        })
+}
+
+fn instruction<Ctx: EncoderContext>(
+    insns: &mut InstructionWriter<Ctx>,
+    labels: &mut Labels,
+    instruction: &Instruction,
+) -> Result<(), EncodeError> {
+    match instruction {
+        Instruction::Label(label) => insns.label(labels.place(*label)?)?,
+        Instruction::AALoad => insns.aaload()?,
+        Instruction::AAStore => insns.aastore()?,
+        Instruction::AConstNull => insns.aconstnull()?,
+        Instruction::ALoad { index } => insns.aload(*index)?,
+        Instruction::ALoadW { index } => insns.aload_wide(*index)?,
+        Instruction::ALoad0 => insns.aload0()?,
+        Instruction::ALoad1 => insns.aload1()?,
+        Instruction::ALoad2 => insns.aload2()?,
+        Instruction::ALoad3 => insns.aload3()?,
+        Instruction::ANewArray { class } => insns.anewarray(class.clone())?,
+        Instruction::AReturn => insns.areturn()?,
+        Instruction::ArrayLength => insns.arraylength()?,
+        Instruction::AStore { index } => insns.astore(*index)?,
+        Instruction::AStoreW { index } => insns.astore_wide(*index)?,
+        Instruction::AStore0 => insns.astore0()?,
+        Instruction::AStore1 => insns.astore1()?,
+        Instruction::AStore2 => insns.astore2()?,
+        Instruction::AStore3 => insns.astore3()?,
+        Instruction::AThrow => insns.athrow()?,
+        Instruction::BALoad => insns.baload()?,
+        Instruction::BAStore => insns.bastore()?,
+        Instruction::BIPush { value } => insns.bipush(*value)?,
+        Instruction::CALoad => insns.caload()?,
+        Instruction::CAStore => insns.castore()?,
+        Instruction::CheckCast { class } => insns.checkcast(class.clone())?,
+        Instruction::D2F => insns.d2f()?,
+        Instruction::D2I => insns.d2i()?,
+        Instruction::D2L => insns.d2l()?,
+        Instruction::DAdd => insns.dadd()?,
+        Instruction::DALoad => insns.daload()?,
+        Instruction::DAStore => insns.dastore()?,
+        Instruction::DCmpG => insns.dcmpg()?,
+        Instruction::DCmpL => insns.dcmpl()?,
+        Instruction::DConst0 => insns.dconst0()?,
+        Instruction::DConst1 => insns.dconst1()?,
+        Instruction::DDiv => insns.ddiv()?,
+        Instruction::DLoad { index } => insns.dload(*index)?,
+        Instruction::DLoadW { index } => insns.dload_wide(*index)?,
+        Instruction::DLoad0 => insns.dload0()?,
+        Instruction::DLoad1 => insns.dload1()?,
+        Instruction::DLoad2 => insns.dload2()?,
+        Instruction::DLoad3 => insns.dload3()?,
+        Instruction::DMul => insns.dmul()?,
+        Instruction::DNeg => insns.dneg()?,
+        Instruction::DRem => insns.drem()?,
+        Instruction::DReturn => insns.dreturn()?,
+        Instruction::DStore { index } => insns.dstore(*index)?,
+        Instruction::DStoreW { index } => insns.dstore_wide(*index)?,
+        Instruction::DStore0 => insns.dstore0()?,
+        Instruction::DStore1 => insns.dstore1()?,
+        Instruction::DStore2 => insns.dstore2()?,
+        Instruction::DStore3 => insns.dstore3()?,
+        Instruction::DSub => insns.dsub()?,
+        Instruction::Dup => insns.dup()?,
+        Instruction::DupX1 => insns.dupx1()?,
+        Instruction::DupX2 => insns.dupx2()?,
+        Instruction::Dup2 => insns.dup2()?,
+        Instruction::Dup2X1 => insns.dup2x1()?,
+        Instruction::Dup2X2 => insns.dup2x2()?,
+        Instruction::F2D => insns.f2d()?,
+        Instruction::F2I => insns.f2i()?,
+        Instruction::F2L => insns.f2l()?,
+        Instruction::FAdd => insns.fadd()?,
+        Instruction::FALoad => insns.faload()?,
+        Instruction::FAStore => insns.fastore()?,
+        Instruction::FCmpG => insns.fcmpg()?,
+        Instruction::FCmpL => insns.fcmpl()?,
+        Instruction::FConst0 => insns.fconst0()?,
+        Instruction::FConst1 => insns.fconst1()?,
+        Instruction::FConst2 => insns.fconst2()?,
+        Instruction::FDiv => insns.fdiv()?,
+        Instruction::FLoad { index } => insns.fload(*index)?,
+        Instruction::FLoadW { index } => insns.fload_wide(*index)?,
+        Instruction::FLoad0 => insns.fload0()?,
+        Instruction::FLoad1 => insns.fload1()?,
+        Instruction::FLoad2 => insns.fload2()?,
+        Instruction::FLoad3 => insns.fload3()?,
+        Instruction::FMul => insns.fmul()?,
+        Instruction::FNeg => insns.fneg()?,
+        Instruction::FRem => insns.frem()?,
+        Instruction::FReturn => insns.freturn()?,
+        Instruction::FStore { index } => insns.fstore(*index)?,
+        Instruction::FStoreW { index } => insns.fstore_wide(*index)?,
+        Instruction::FStore0 => insns.fstore0()?,
+        Instruction::FStore1 => insns.fstore1()?,
+        Instruction::FStore2 => insns.fstore2()?,
+        Instruction::FStore3 => insns.fstore3()?,
+        Instruction::FSub => insns.fsub()?,
+        Instruction::GetField { field } => insns.getfield(field)?,
+        Instruction::GetStatic { field } => insns.getstatic(field)?,
+        Instruction::Goto { target } => insns.goto(labels.get(*target)?)?,
+        Instruction::GotoW { target } => insns.gotow(labels.get(*target)?)?,
+        Instruction::I2B => insns.i2b()?,
+        Instruction::I2C => insns.i2c()?,
+        Instruction::I2D => insns.i2d()?,
+        Instruction::I2F => insns.i2f()?,
+        Instruction::I2L => insns.i2l()?,
+        Instruction::I2S => insns.i2s()?,
+        Instruction::IAdd => insns.iadd()?,
+        Instruction::IALoad => insns.iaload()?,
+        Instruction::IAnd => insns.iand()?,
+        Instruction::IAStore => insns.iastore()?,
+        Instruction::IConstM1 => insns.iconstm1()?,
+        Instruction::IConst0 => insns.iconst0()?,
+        Instruction::IConst1 => insns.iconst1()?,
+        Instruction::IConst2 => insns.iconst2()?,
+        Instruction::IConst3 => insns.iconst3()?,
+        Instruction::IConst4 => insns.iconst4()?,
+        Instruction::IConst5 => insns.iconst5()?,
+        Instruction::IDiv => insns.idiv()?,
+        Instruction::IfACmpEq { target } => insns.ifacmpeq(labels.get(*target)?)?,
+        Instruction::IfACmpNe { target } => insns.ifacmpne(labels.get(*target)?)?,
+        Instruction::IfICmpEq { target } => insns.ificmpeq(labels.get(*target)?)?,
+        Instruction::IfICmpNe { target } => insns.ificmpne(labels.get(*target)?)?,
+        Instruction::IfICmpLt { target } => insns.ificmplt(labels.get(*target)?)?,
+        Instruction::IfICmpGe { target } => insns.ificmpge(labels.get(*target)?)?,
+        Instruction::IfICmpGt { target } => insns.ificmpgt(labels.get(*target)?)?,
+        Instruction::IfICmpLe { target } => insns.ificmple(labels.get(*target)?)?,
+        Instruction::IfEq { target } => insns.ifeq(labels.get(*target)?)?,
+        Instruction::IfNe { target } => insns.ifne(labels.get(*target)?)?,
+        Instruction::IfLt { target } => insns.iflt(labels.get(*target)?)?,
+        Instruction::IfGe { target } => insns.ifge(labels.get(*target)?)?,
+        Instruction::IfGt { target } => insns.ifgt(labels.get(*target)?)?,
+        Instruction::IfLe { target } => insns.ifle(labels.get(*target)?)?,
+        Instruction::IfNonNull { target } => insns.ifnonnull(labels.get(*target)?)?,
+        Instruction::IfNull { target } => insns.ifnull(labels.get(*target)?)?,
+        Instruction::IInc { index, value } => insns.iinc(*index, *value)?,
+        Instruction::IIncW { index, value } => insns.iinc_wide(*index, *value)?,
+        Instruction::ILoad { index } => insns.iload(*index)?,
+        Instruction::ILoadW { index } => insns.iload_wide(*index)?,
+        Instruction::ILoad0 => insns.iload0()?,
+        Instruction::ILoad1 => insns.iload1()?,
+        Instruction::ILoad2 => insns.iload2()?,
+        Instruction::ILoad3 => insns.iload3()?,
+        Instruction::IMul => insns.imul()?,
+        Instruction::INeg => insns.ineg()?,
+        Instruction::InstanceOf { class } => insns.instanceof(class.clone())?,
+        Instruction::InvokeDynamic { call_site } => insns.invokedynamic(call_site)?,
+        Instruction::InvokeInterface { method, count } => insns.invokeinterface(method, *count)?,
+        Instruction::InvokeSpecial { method, interface } => insns.invokespecial(any_method_ref(method, *interface))?,
+        Instruction::InvokeStatic { method, interface } => insns.invokestatic(any_method_ref(method, *interface))?,
+        Instruction::InvokeVirtual { method } => insns.invokevirtual(method)?,
+        Instruction::IOr => insns.ior()?,
+        Instruction::IRem => insns.irem()?,
+        Instruction::IReturn => insns.ireturn()?,
+        Instruction::IShL => insns.ishl()?,
+        Instruction::IShR => insns.ishr()?,
+        Instruction::IStore { index } => insns.istore(*index)?,
+        Instruction::IStoreW { index } => insns.istore_wide(*index)?,
+        Instruction::IStore0 => insns.istore0()?,
+        Instruction::IStore1 => insns.istore1()?,
+        Instruction::IStore2 => insns.istore2()?,
+        Instruction::IStore3 => insns.istore3()?,
+        Instruction::ISub => insns.isub()?,
+        Instruction::IUShR => insns.iushr()?,
+        Instruction::IXor => insns.ixor()?,
+        Instruction::JSr { target } => insns.jsr(labels.get(*target)?)?,
+        Instruction::JSrW { target } => insns.jsrw(labels.get(*target)?)?,
+        Instruction::L2D => insns.l2d()?,
+        Instruction::L2F => insns.l2f()?,
+        Instruction::L2I => insns.l2i()?,
+        Instruction::LAdd => insns.ladd()?,
+        Instruction::LALoad => insns.laload()?,
+        Instruction::LAnd => insns.land()?,
+        Instruction::LAStore => insns.lastore()?,
+        Instruction::LCmp => insns.lcmp()?,
+        Instruction::LConst0 => insns.lconst0()?,
+        Instruction::LConst1 => insns.lconst1()?,
+        Instruction::LdC { constant } => {
+            // the index of the constant may not fit into a single byte anymore
+            let index = constant.insert(insns)?;
+            if index.as_u16() <= u16::from(u8::MAX) {
+                insns.ldc(index)?
+            } else {
+                insns.ldcw(index)?
+            }
+        }
+        Instruction::LdCW { constant } => insns.ldcw(constant)?,
+        Instruction::LdC2W { constant } => insns.ldc2w(constant)?,
+        Instruction::LDiv => insns.ldiv()?,
+        Instruction::LLoad { index } => insns.lload(*index)?,
+        Instruction::LLoadW { index } => insns.lload_wide(*index)?,
+        Instruction::LLoad0 => insns.lload0()?,
+        Instruction::LLoad1 => insns.lload1()?,
+        Instruction::LLoad2 => insns.lload2()?,
+        Instruction::LLoad3 => insns.lload3()?,
+        Instruction::LMul => insns.lmul()?,
+        Instruction::LNeg => insns.lneg()?,
+        Instruction::LOr => insns.lor()?,
+        Instruction::LRem => insns.lrem()?,
+        Instruction::LReturn => insns.lreturn()?,
+        Instruction::LShL => insns.lshl()?,
+        Instruction::LShR => insns.lshr()?,
+        Instruction::LStore { index } => insns.lstore(*index)?,
+        Instruction::LStoreW { index } => insns.lstore_wide(*index)?,
+        Instruction::LStore0 => insns.lstore0()?,
+        Instruction::LStore1 => insns.lstore1()?,
+        Instruction::LStore2 => insns.lstore2()?,
+        Instruction::LStore3 => insns.lstore3()?,
+        Instruction::LSub => insns.lsub()?,
+        Instruction::LUShR => insns.lushr()?,
+        Instruction::LXor => insns.lxor()?,
+        Instruction::MonitorEnter => insns.monitorenter()?,
+        Instruction::MonitorExit => insns.monitorexit()?,
+        Instruction::MultiANewArray { class, dimensions } => insns.multianewarray(class.clone(), *dimensions)?,
+        Instruction::New { class } => insns.new(class.clone())?,
+        Instruction::NewArray { atype } => insns.newarray(*atype)?,
+        Instruction::Nop => insns.nop()?,
+        Instruction::Pop => insns.pop()?,
+        Instruction::Pop2 => insns.pop2()?,
+        Instruction::PutField { field } => insns.putfield(field)?,
+        Instruction::PutStatic { field } => insns.putstatic(field)?,
+        Instruction::Ret { index } => insns.ret(*index)?,
+        Instruction::RetW { index } => insns.ret_wide(*index)?,
+        Instruction::Return => insns.return_()?,
+        Instruction::SALoad => insns.saload()?,
+        Instruction::SAStore => insns.sastore()?,
+        Instruction::SIPush { value } => insns.sipush(*value)?,
+        Instruction::Swap => insns.swap()?,
+        Instruction::LookupSwitch { default, pairs } => {
+            let labels = &*labels;
+            insns.lookupswitch(|writer| {
+                let mut writer = writer.default(labels.get(*default)?)?;
+                for (key, target) in pairs {
+                    writer = writer.pair(*key, labels.get(*target)?)?;
+                }
+                Ok(writer)
+            })?
+        }
+        Instruction::TableSwitch { default, low, targets } => {
+            let high = i64::from(*low) + targets.len() as i64 - 1;
+            let high = i32::try_from(high)
+                .map_err(|_| EncodeError::with_context(EncodeErrorKind::IncorrectBounds, Context::Code))?;
+            let labels = &*labels;
+            insns.tableswitch(|writer| {
+                let mut writer = writer.default(labels.get(*default)?)?.low(*low)?.high(high)?;
+                for target in targets {
+                    writer = writer.jump(labels.get(*target)?)?;
+                }
+                Ok(writer)
+            })?
+        }
+    };
+
+    Ok(())
+}
+
+/// Writes any attribute except for `Code`.
+///
+/// The positions of the labels are only passed for the attributes of code,
+/// everywhere else labels are written as is.
+fn attribute<Ctx: EncoderContext>(
+    writer: AttributeWriter<Ctx, AttributeWriterState::Start>,
+    attribute: &Attribute,
+    positions: Option<&HashMap<Label, u32>>,
+) -> Result<AttributeWriter<Ctx, AttributeWriterState::End>, EncodeError> {
+    writer.content(attribute_name(attribute)?, |ctx| {
+        attribute_content(ctx, attribute, positions)
+    })
+}
+
+fn attribute_name(attribute: &Attribute) -> Result<MString, EncodeError> {
+    if let Attribute::Code(_) = attribute {
+        // code can only be written through the code writer of a method
+        return Err(EncodeError::with_context(
+            EncodeErrorKind::Other("a Code attribute is only allowed on methods".into()),
+            Context::Attributes,
+        ));
+    }
+    Ok(attribute.name())
+}
+
+fn attribute_content<Ctx: EncoderContext>(
+    ctx: &mut Ctx,
+    attribute: &Attribute,
+    positions: Option<&HashMap<Label, u32>>,
+) -> Result<(), EncodeError> {
+    match attribute {
+        Attribute::AnnotationDefault { value } => element_value(ctx, value)?,
+        Attribute::BootstrapMethods(methods) => {
+            write_count(ctx, methods.len())?;
+            for method in methods {
+                let index = (&method.method).insert(ctx)?;
+                ctx.encoder().write(index)?;
+                write_count(ctx, method.arguments.len())?;
+                for argument in &method.arguments {
+                    let index = argument.insert(ctx)?;
+                    ctx.encoder().write(index)?;
+                }
+            }
+        }
+        Attribute::Code(_) => unreachable!("the name of code attributes is rejected"),
+        Attribute::ConstantValue { value } => {
+            let index = value.insert(ctx)?;
+            ctx.encoder().write(index)?;
+        }
+        Attribute::Deprecated | Attribute::Synthetic => {}
+        Attribute::EnclosingMethod { class, method } => {
+            let class = class_index(ctx, class)?;
+            let method = method.as_ref().map(|method| method.insert(ctx)).transpose()?;
+            ctx.encoder().write(class)?.write(method)?;
+        }
+        Attribute::Exceptions(classes) | Attribute::NestMembers(classes) | Attribute::PermittedSubclasses(classes) => {
+            class_indices(ctx, classes)?
+        }
+        Attribute::InnerClasses(classes) => {
+            write_count(ctx, classes.len())?;
+            for class in classes {
+                let inner_class = class_index(ctx, &class.inner_class)?;
+                let outer_class = class
+                    .outer_class
+                    .as_ref()
+                    .map(|outer_class| class_index(ctx, outer_class))
+                    .transpose()?;
+                let inner_name = class
+                    .inner_name
+                    .as_ref()
+                    .map(|inner_name| utf8_index(ctx, inner_name))
+                    .transpose()?;
+                ctx.encoder()
+                    .write(inner_class)?
+                    .write(outer_class)?
+                    .write(inner_name)?
+                    .write(class.inner_access_flags)?;
+            }
+        }
+        Attribute::LineNumberTable(lines) => {
+            write_count(ctx, lines.len())?;
+            for line in lines {
+                let start = position_u16(positions, line.start)?;
+                ctx.encoder().write(start)?.write(line.line_number)?;
+            }
+        }
+        Attribute::LocalVariableTable(locals) => {
+            write_count(ctx, locals.len())?;
+            for local in locals {
+                let (start, length) = range(positions, local.start, local.end)?;
+                let name = utf8_index(ctx, &local.name)?;
+                let descriptor = utf8_index(ctx, &local.descriptor)?;
+                ctx.encoder()
+                    .write(start)?
+                    .write(length)?
+                    .write(name)?
+                    .write(descriptor)?
+                    .write(local.index)?;
+            }
+        }
+        Attribute::LocalVariableTypeTable(locals) => {
+            write_count(ctx, locals.len())?;
+            for local in locals {
+                let (start, length) = range(positions, local.start, local.end)?;
+                let name = utf8_index(ctx, &local.name)?;
+                let signature = utf8_index(ctx, &local.signature)?;
+                ctx.encoder()
+                    .write(start)?
+                    .write(length)?
+                    .write(name)?
+                    .write(signature)?
+                    .write(local.index)?;
+            }
+        }
+        Attribute::MethodParameters(parameters) => {
+            write_small_count(ctx, parameters.len())?;
+            for parameter in parameters {
+                let name = parameter.name.as_ref().map(|name| utf8_index(ctx, name)).transpose()?;
+                ctx.encoder().write(name)?.write(parameter.access_flags)?;
+            }
+        }
+        Attribute::Module(module) => self::module(ctx, module)?,
+        Attribute::ModuleMainClass(class) | Attribute::NestHost(class) => {
+            let index = class_index(ctx, class)?;
+            ctx.encoder().write(index)?;
+        }
+        Attribute::ModulePackages(packages) => {
+            write_count(ctx, packages.len())?;
+            for package in packages {
+                let index = package_index(ctx, package)?;
+                ctx.encoder().write(index)?;
+            }
+        }
+        Attribute::Record(components) => {
+            write_count(ctx, components.len())?;
+            for component in components {
+                let name = utf8_index(ctx, &component.name)?;
+                let descriptor = utf8_index(ctx, &component.descriptor)?;
+                ctx.encoder().write(name)?.write(descriptor)?;
+                write_count(ctx, component.attributes.len())?;
+                for attribute in &component.attributes {
+                    let name = utf8_index(ctx, &attribute_name(attribute)?)?;
+                    ctx.encoder().write(name)?;
+                    let length_writer = LengthWriter::new(ctx)?;
+                    attribute_content(ctx, attribute, None)?;
+                    length_writer.finish(ctx)?;
+                }
+            }
+        }
+        Attribute::RuntimeInvisibleAnnotations(annotations) | Attribute::RuntimeVisibleAnnotations(annotations) => {
+            self::annotations(ctx, annotations)?;
+        }
+        Attribute::RuntimeInvisibleParameterAnnotations(parameters)
+        | Attribute::RuntimeVisibleParameterAnnotations(parameters) => {
+            write_small_count(ctx, parameters.len())?;
+            for annotations in parameters {
+                self::annotations(ctx, annotations)?;
+            }
+        }
+        Attribute::RuntimeInvisibleTypeAnnotations(annotations)
+        | Attribute::RuntimeVisibleTypeAnnotations(annotations) => {
+            write_count(ctx, annotations.len())?;
+            for annotation in annotations {
+                type_annotation(ctx, annotation, positions)?;
+            }
+        }
+        Attribute::Signature(value) | Attribute::SourceFile(value) => {
+            let index = utf8_index(ctx, value)?;
+            ctx.encoder().write(index)?;
+        }
+        Attribute::SourceDebugExtension(content) => {
+            ctx.encoder().write(content.as_bytes())?;
+        }
+        Attribute::StackMapTable(frames) => stack_map_table(ctx, frames, positions)?,
+        Attribute::Unknown { content, .. } => {
+            ctx.encoder().write(content.as_slice())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn module<Ctx: EncoderContext>(ctx: &mut Ctx, module: &Module) -> Result<(), EncodeError> {
+    let name = module_index(ctx, &module.name)?;
+    let version = module
+        .version
+        .as_ref()
+        .map(|version| utf8_index(ctx, version))
+        .transpose()?;
+    ctx.encoder().write(name)?.write(module.flags)?.write(version)?;
+
+    write_count(ctx, module.requires.len())?;
+    for require in &module.requires {
+        let index = module_index(ctx, &require.module)?;
+        let version = require
+            .version
+            .as_ref()
+            .map(|version| utf8_index(ctx, version))
+            .transpose()?;
+        ctx.encoder().write(index)?.write(require.flags)?.write(version)?;
+    }
+
+    write_count(ctx, module.exports.len())?;
+    for export in &module.exports {
+        let index = package_index(ctx, &export.package)?;
+        ctx.encoder().write(index)?.write(export.flags)?;
+        module_indices(ctx, &export.exports_to)?;
+    }
+
+    write_count(ctx, module.opens.len())?;
+    for open in &module.opens {
+        let index = package_index(ctx, &open.package)?;
+        ctx.encoder().write(index)?.write(open.flags)?;
+        module_indices(ctx, &open.opens_to)?;
+    }
+
+    class_indices(ctx, &module.uses)?;
+
+    write_count(ctx, module.provides.len())?;
+    for provide in &module.provides {
+        let index = class_index(ctx, &provide.service)?;
+        ctx.encoder().write(index)?;
+        class_indices(ctx, &provide.provides_with)?;
+    }
+
+    Ok(())
+}
+
+fn stack_map_table<Ctx: EncoderContext>(
+    ctx: &mut Ctx,
+    frames: &[StackMapFrame],
+    positions: Option<&HashMap<Label, u32>>,
+) -> Result<(), EncodeError> {
+    write_count(ctx, frames.len())?;
+
+    let mut previous = None;
+    for frame in frames {
+        let position = position(positions, frame.label())?;
+        // The first frame is located at its offset delta, while every subsequent frame is located
+        // at `previous + delta + 1`.
+        let delta = match previous {
+            Some(previous) if position <= previous => {
+                return Err(EncodeError::with_context(
+                    EncodeErrorKind::NegativeOffset,
+                    Context::AttributeContent,
+                ));
+            }
+            Some(previous) => position - previous - 1,
+            None => position,
+        };
+        let delta = u16::try_from(delta)
+            .map_err(|_| EncodeError::with_context(EncodeErrorKind::LabelTooFar, Context::AttributeContent))?;
+        previous = Some(position);
+
+        match frame {
+            StackMapFrame::Same { .. } if delta < 64 => {
+                ctx.encoder().write(delta as u8)?;
+            }
+            StackMapFrame::Same { .. } | StackMapFrame::SameExtended { .. } => {
+                ctx.encoder().write(251u8)?.write(delta)?;
+            }
+            StackMapFrame::Same1 { stack, .. } if delta < 64 => {
+                ctx.encoder().write(64 + delta as u8)?;
+                verification_type(ctx, stack, positions)?;
+            }
+            StackMapFrame::Same1 { stack, .. } | StackMapFrame::Same1Extended { stack, .. } => {
+                ctx.encoder().write(247u8)?.write(delta)?;
+                verification_type(ctx, stack, positions)?;
+            }
+            StackMapFrame::Chop { to_chop, .. } => {
+                if !(1..=3).contains(to_chop) {
+                    return Err(EncodeError::with_context(
+                        EncodeErrorKind::IncorrectBounds,
+                        Context::AttributeContent,
+                    ));
+                }
+                ctx.encoder().write(251 - to_chop)?.write(delta)?;
+            }
+            StackMapFrame::Append { locals, .. } => {
+                if !(1..=3).contains(&locals.len()) {
+                    return Err(EncodeError::with_context(
+                        EncodeErrorKind::IncorrectBounds,
+                        Context::AttributeContent,
+                    ));
+                }
+                ctx.encoder().write(251 + locals.len() as u8)?.write(delta)?;
+                for local in locals {
+                    verification_type(ctx, local, positions)?;
+                }
+            }
+            StackMapFrame::Full { locals, stack, .. } => {
+                ctx.encoder().write(255u8)?.write(delta)?;
+                write_count(ctx, locals.len())?;
+                for local in locals {
+                    verification_type(ctx, local, positions)?;
+                }
+                write_count(ctx, stack.len())?;
+                for item in stack {
+                    verification_type(ctx, item, positions)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn verification_type<Ctx: EncoderContext>(
+    ctx: &mut Ctx,
+    ty: &VerificationType,
+    positions: Option<&HashMap<Label, u32>>,
+) -> Result<(), EncodeError> {
+    match ty {
+        VerificationType::Top => ctx.encoder().write(0u8)?,
+        VerificationType::Integer => ctx.encoder().write(1u8)?,
+        VerificationType::Float => ctx.encoder().write(2u8)?,
+        VerificationType::Double => ctx.encoder().write(3u8)?,
+        VerificationType::Long => ctx.encoder().write(4u8)?,
+        VerificationType::Null => ctx.encoder().write(5u8)?,
+        VerificationType::UninitializedThis => ctx.encoder().write(6u8)?,
+        VerificationType::Object(class) => {
+            let index = class_index(ctx, class)?;
+            ctx.encoder().write(7u8)?.write(index)?
+        }
+        VerificationType::Uninitialized(label) => {
+            let offset = position_u16(positions, *label)?;
+            ctx.encoder().write(8u8)?.write(offset)?
+        }
+    };
+
+    Ok(())
+}
+
+fn annotations<Ctx: EncoderContext>(ctx: &mut Ctx, annotations: &[Annotation]) -> Result<(), EncodeError> {
+    write_count(ctx, annotations.len())?;
+    for annotation in annotations {
+        self::annotation(ctx, annotation)?;
+    }
+
+    Ok(())
+}
+
+fn annotation<Ctx: EncoderContext>(ctx: &mut Ctx, annotation: &Annotation) -> Result<(), EncodeError> {
+    let type_ = utf8_index(ctx, &annotation.type_)?;
+    ctx.encoder().write(type_)?;
+    element_value_pairs(ctx, &annotation.pairs)
+}
+
+fn element_value_pairs<Ctx: EncoderContext>(ctx: &mut Ctx, pairs: &[ElementValuePair]) -> Result<(), EncodeError> {
+    write_count(ctx, pairs.len())?;
+    for pair in pairs {
+        let name = utf8_index(ctx, &pair.name)?;
+        ctx.encoder().write(name)?;
+        element_value(ctx, &pair.value)?;
+    }
+
+    Ok(())
+}
+
+fn element_value<Ctx: EncoderContext>(ctx: &mut Ctx, value: &ElementValue) -> Result<(), EncodeError> {
+    match value {
+        ElementValue::Boolean(value) => integer(ctx, b'Z', *value)?,
+        ElementValue::Byte(value) => integer(ctx, b'B', *value)?,
+        ElementValue::Short(value) => integer(ctx, b'S', *value)?,
+        ElementValue::Int(value) => integer(ctx, b'I', *value)?,
+        ElementValue::Char(value) => integer(ctx, b'C', *value)?,
+        ElementValue::Long(value) => {
+            let index = ctx.insert_constant(cpool::Long { value: *value })?;
+            ctx.encoder().write(b'J')?.write(index)?;
+        }
+        ElementValue::Float(value) => {
+            let index = ctx.insert_constant(cpool::Float { value: *value })?;
+            ctx.encoder().write(b'F')?.write(index)?;
+        }
+        ElementValue::Double(value) => {
+            let index = ctx.insert_constant(cpool::Double { value: *value })?;
+            ctx.encoder().write(b'D')?.write(index)?;
+        }
+        ElementValue::String(value) => {
+            let index = utf8_index(ctx, value)?;
+            ctx.encoder().write(b's')?.write(index)?;
+        }
+        ElementValue::Class(value) => {
+            let index = utf8_index(ctx, value)?;
+            ctx.encoder().write(b'c')?.write(index)?;
+        }
+        ElementValue::Enum { type_name, const_name } => {
+            let type_name = utf8_index(ctx, type_name)?;
+            let const_name = utf8_index(ctx, const_name)?;
+            ctx.encoder().write(b'e')?.write(type_name)?.write(const_name)?;
+        }
+        ElementValue::Annotation(annotation) => {
+            ctx.encoder().write(b'@')?;
+            self::annotation(ctx, annotation)?;
+        }
+        ElementValue::Array(values) => {
+            ctx.encoder().write(b'[')?;
+            write_count(ctx, values.len())?;
+            for value in values {
+                element_value(ctx, value)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn integer<Ctx: EncoderContext>(ctx: &mut Ctx, tag: u8, value: i32) -> Result<(), EncodeError> {
+    let index = ctx.insert_constant(cpool::Integer { value })?;
+    ctx.encoder().write(tag)?.write(index)?;
+    Ok(())
+}
+
+fn type_annotation<Ctx: EncoderContext>(
+    ctx: &mut Ctx,
+    annotation: &TypeAnnotation,
+    positions: Option<&HashMap<Label, u32>>,
+) -> Result<(), EncodeError> {
+    ctx.encoder().write(target_type_tag(annotation.target_type))?;
+    match &annotation.target_info {
+        TargetInfo::TypeParameter { parameter_index } => {
+            ctx.encoder().write(*parameter_index)?;
+        }
+        TargetInfo::SuperType { supertype_index } => {
+            let index = match supertype_index {
+                SuperTypeIndex::Class => u16::MAX,
+                SuperTypeIndex::Interface { index } => *index,
+            };
+            ctx.encoder().write(index)?;
+        }
+        TargetInfo::TypeParameterBound {
+            type_parameter_index,
+            bound_index,
+        } => {
+            ctx.encoder().write(*type_parameter_index)?.write(*bound_index)?;
+        }
+        TargetInfo::Empty => {}
+        TargetInfo::FormalParameter { formal_parameter_index } => {
+            ctx.encoder().write(*formal_parameter_index)?;
+        }
+        TargetInfo::Throws { throws_type_index } => {
+            ctx.encoder().write(*throws_type_index)?;
+        }
+        TargetInfo::LocalVariable { table } => {
+            write_count(ctx, table.len())?;
+            for local in table {
+                let (start, length) = range(positions, local.start, local.end)?;
+                ctx.encoder().write(start)?.write(length)?.write(local.index)?;
+            }
+        }
+        TargetInfo::Catch { exception_table_index } => {
+            ctx.encoder().write(*exception_table_index)?;
+        }
+        TargetInfo::Offset { offset } => {
+            let offset = position_u16(positions, *offset)?;
+            ctx.encoder().write(offset)?;
+        }
+        TargetInfo::TypeArgument {
+            offset,
+            type_argument_index,
+        } => {
+            let offset = position_u16(positions, *offset)?;
+            ctx.encoder().write(offset)?.write(*type_argument_index)?;
+        }
+    }
+
+    write_small_count(ctx, annotation.target_path.len())?;
+    for segment in &annotation.target_path {
+        let kind: u8 = match segment.kind {
+            TypePathSegmentKind::ArrayElement => 0,
+            TypePathSegmentKind::InnerType => 1,
+            TypePathSegmentKind::WildcardBound => 2,
+            TypePathSegmentKind::TypeArgument => 3,
+        };
+        ctx.encoder().write(kind)?.write(segment.type_argument_index)?;
+    }
+
+    let type_ = utf8_index(ctx, &annotation.type_)?;
+    ctx.encoder().write(type_)?;
+    element_value_pairs(ctx, &annotation.pairs)
+}
+
+fn target_type_tag(target_type: TargetType) -> u8 {
+    match target_type {
+        TargetType::ClassTypeParameter => 0x00,
+        TargetType::MethodTypeParameter => 0x01,
+        TargetType::ClassExtends => 0x10,
+        TargetType::ClassTypeParameterBound => 0x11,
+        TargetType::MethodTypeParameterBound => 0x12,
+        TargetType::Field => 0x13,
+        TargetType::MethodReturn => 0x14,
+        TargetType::MethodReceiver => 0x15,
+        TargetType::MethodFormalParameter => 0x16,
+        TargetType::Throws => 0x17,
+        TargetType::LocalVariable => 0x40,
+        TargetType::ResourceVariable => 0x41,
+        TargetType::ExceptionParameter => 0x42,
+        TargetType::InstanceOf => 0x43,
+        TargetType::New => 0x44,
+        TargetType::ConstructorReference => 0x45,
+        TargetType::MethodReference => 0x46,
+        TargetType::Cast => 0x47,
+        TargetType::ConstructorInvocationTypeArgument => 0x48,
+        TargetType::MethodInvocationTypeArgument => 0x49,
+        TargetType::ConstructorReferenceTypeArgument => 0x4A,
+        TargetType::MethodReferenceTypeArgument => 0x4B,
+    }
+}
+
+/// Returns the offset of a label, which is the label itself outside of code.
+fn position(positions: Option<&HashMap<Label, u32>>, label: Label) -> Result<u32, EncodeError> {
+    match positions {
+        Some(positions) => positions
+            .get(&label)
+            .copied()
+            .ok_or_else(|| EncodeError::with_context(EncodeErrorKind::LabelNotFound, Context::AttributeContent)),
+        None => Ok(label.0),
+    }
+}
+
+fn position_u16(positions: Option<&HashMap<Label, u32>>, label: Label) -> Result<u16, EncodeError> {
+    u16::try_from(position(positions, label)?)
+        .map_err(|_| EncodeError::with_context(EncodeErrorKind::LabelTooFar, Context::AttributeContent))
+}
+
+/// Returns the start and length of the range between two labels.
+fn range(positions: Option<&HashMap<Label, u32>>, start: Label, end: Label) -> Result<(u16, u16), EncodeError> {
+    let start = position_u16(positions, start)?;
+    let end = position_u16(positions, end)?;
+    let length = end
+        .checked_sub(start)
+        .ok_or_else(|| EncodeError::with_context(EncodeErrorKind::NegativeOffset, Context::AttributeContent))?;
+    Ok((start, length))
+}
+
+fn write_count<Ctx: EncoderContext>(ctx: &mut Ctx, count: usize) -> Result<(), EncodeError> {
+    let count = u16::try_from(count)
+        .map_err(|_| EncodeError::with_context(EncodeErrorKind::TooManyItems, Context::AttributeContent))?;
+    ctx.encoder().write(count)?;
+    Ok(())
+}
+
+fn write_small_count<Ctx: EncoderContext>(ctx: &mut Ctx, count: usize) -> Result<(), EncodeError> {
+    let count = u8::try_from(count)
+        .map_err(|_| EncodeError::with_context(EncodeErrorKind::TooManyItems, Context::AttributeContent))?;
+    ctx.encoder().write(count)?;
+    Ok(())
+}
+
+fn utf8_index<Ctx: EncoderContext>(ctx: &mut Ctx, content: &MString) -> Result<cpool::Index<cpool::Utf8>, EncodeError> {
+    ctx.insert_constant(cpool::Utf8 {
+        content: content.clone(),
+    })
+}
+
+fn class_index<Ctx: EncoderContext>(ctx: &mut Ctx, name: &MString) -> Result<cpool::Index<cpool::Class>, EncodeError> {
+    let name = utf8_index(ctx, name)?;
+    ctx.insert_constant(cpool::Class { name })
+}
+
+fn class_indices<Ctx: EncoderContext>(ctx: &mut Ctx, names: &[MString]) -> Result<(), EncodeError> {
+    write_count(ctx, names.len())?;
+    for name in names {
+        let index = class_index(ctx, name)?;
+        ctx.encoder().write(index)?;
+    }
+
+    Ok(())
+}
+
+fn module_index<Ctx: EncoderContext>(
+    ctx: &mut Ctx,
+    name: &MString,
+) -> Result<cpool::Index<cpool::Module>, EncodeError> {
+    let name = utf8_index(ctx, name)?;
+    ctx.insert_constant(cpool::Module { name })
+}
+
+fn module_indices<Ctx: EncoderContext>(ctx: &mut Ctx, names: &[MString]) -> Result<(), EncodeError> {
+    write_count(ctx, names.len())?;
+    for name in names {
+        let index = module_index(ctx, name)?;
+        ctx.encoder().write(index)?;
+    }
+
+    Ok(())
+}
+
+fn package_index<Ctx: EncoderContext>(
+    ctx: &mut Ctx,
+    name: &MString,
+) -> Result<cpool::Index<cpool::Package>, EncodeError> {
+    let name = utf8_index(ctx, name)?;
+    ctx.insert_constant(cpool::Package { name })
+}
+
+fn name_and_type_index<Ctx: EncoderContext>(
+    ctx: &mut Ctx,
+    name: &MString,
+    descriptor: &MString,
+) -> Result<cpool::Index<cpool::NameAndType>, EncodeError> {
+    let name = utf8_index(ctx, name)?;
+    let descriptor = utf8_index(ctx, descriptor)?;
+    ctx.insert_constant(cpool::NameAndType { name, descriptor })
+}
+
+impl Insertable<cpool::Item> for &Constant {
+    fn insert<Ctx: EncoderContext>(self, context: &mut Ctx) -> Result<cpool::Index<cpool::Item>, EncodeError> {
+        let index = match self {
+            Constant::Utf8(content) => utf8_index(context, content)?.as_item(),
+            Constant::Integer(value) => context.insert_constant(cpool::Integer { value: *value })?.as_item(),
+            Constant::Float(value) => context.insert_constant(cpool::Float { value: *value })?.as_item(),
+            Constant::Long(value) => context.insert_constant(cpool::Long { value: *value })?.as_item(),
+            Constant::Double(value) => context.insert_constant(cpool::Double { value: *value })?.as_item(),
+            Constant::Class(name) => class_index(context, name)?.as_item(),
+            Constant::String(string) => {
+                let string = utf8_index(context, string)?;
+                context.insert_constant(cpool::String { string })?.as_item()
+            }
+            Constant::FieldRef(reference) => Insertable::<cpool::FieldRef>::insert(reference, context)?.as_item(),
+            Constant::MethodRef(reference) => Insertable::<cpool::MethodRef>::insert(reference, context)?.as_item(),
+            Constant::InterfaceMethodRef(reference) => {
+                Insertable::<cpool::InterfaceMethodRef>::insert(reference, context)?.as_item()
+            }
+            Constant::NameAndType(name_and_type) => name_and_type.insert(context)?.as_item(),
+            Constant::MethodHandle(handle) => handle.insert(context)?.as_item(),
+            Constant::MethodType(descriptor) => {
+                let descriptor = utf8_index(context, descriptor)?;
+                context.insert_constant(cpool::MethodType { descriptor })?.as_item()
+            }
+            Constant::Dynamic(dynamic) => {
+                let name_and_type = name_and_type_index(context, &dynamic.name, &dynamic.descriptor)?;
+                context
+                    .insert_constant(cpool::Dynamic {
+                        bootstrap_method_attr: dynamic.bootstrap_method,
+                        name_and_type,
+                    })?
+                    .as_item()
+            }
+            Constant::InvokeDynamic(dynamic) => Insertable::<cpool::InvokeDynamic>::insert(dynamic, context)?.as_item(),
+            Constant::Module(name) => module_index(context, name)?.as_item(),
+            Constant::Package(name) => package_index(context, name)?.as_item(),
+        };
+
+        Ok(index)
+    }
+}
+
+impl Insertable<cpool::FieldRef> for &MemberRef {
+    fn insert<Ctx: EncoderContext>(self, context: &mut Ctx) -> Result<cpool::Index<cpool::FieldRef>, EncodeError> {
+        let class = class_index(context, &self.class)?;
+        let name_and_type = name_and_type_index(context, &self.name, &self.descriptor)?;
+        context.insert_constant(cpool::FieldRef { class, name_and_type })
+    }
+}
+
+impl Insertable<cpool::MethodRef> for &MemberRef {
+    fn insert<Ctx: EncoderContext>(self, context: &mut Ctx) -> Result<cpool::Index<cpool::MethodRef>, EncodeError> {
+        let class = class_index(context, &self.class)?;
+        let name_and_type = name_and_type_index(context, &self.name, &self.descriptor)?;
+        context.insert_constant(cpool::MethodRef { class, name_and_type })
+    }
+}
+
+impl Insertable<cpool::InterfaceMethodRef> for &MemberRef {
+    fn insert<Ctx: EncoderContext>(
+        self,
+        context: &mut Ctx,
+    ) -> Result<cpool::Index<cpool::InterfaceMethodRef>, EncodeError> {
+        let class = class_index(context, &self.class)?;
+        let name_and_type = name_and_type_index(context, &self.name, &self.descriptor)?;
+        context.insert_constant(cpool::InterfaceMethodRef { class, name_and_type })
+    }
+}
+
+impl Insertable<cpool::NameAndType> for &NameAndType {
+    fn insert<Ctx: EncoderContext>(self, context: &mut Ctx) -> Result<cpool::Index<cpool::NameAndType>, EncodeError> {
+        name_and_type_index(context, &self.name, &self.descriptor)
+    }
+}
+
+impl Insertable<cpool::MethodHandle> for &MethodHandle {
+    fn insert<Ctx: EncoderContext>(self, context: &mut Ctx) -> Result<cpool::Index<cpool::MethodHandle>, EncodeError> {
+        let reference = &self.reference;
+        let (kind, reference) = match self.kind {
+            MethodKind::GetField => (cpool::MethodKind::GetField, field_ref(context, reference)?),
+            MethodKind::GetStatic => (cpool::MethodKind::GetStatic, field_ref(context, reference)?),
+            MethodKind::PutField => (cpool::MethodKind::PutField, field_ref(context, reference)?),
+            MethodKind::PutStatic => (cpool::MethodKind::PutStatic, field_ref(context, reference)?),
+            MethodKind::InvokeVirtual => (
+                cpool::MethodKind::InvokeVirtual,
+                any_method_ref(reference, false).insert(context)?,
+            ),
+            MethodKind::InvokeStatic => (
+                cpool::MethodKind::InvokeStatic,
+                any_method_ref(reference, self.interface).insert(context)?,
+            ),
+            MethodKind::InvokeSpecial => (
+                cpool::MethodKind::InvokeSpecial,
+                any_method_ref(reference, self.interface).insert(context)?,
+            ),
+            MethodKind::NewInvokeSpecial => (
+                cpool::MethodKind::NewInvokeSpecial,
+                any_method_ref(reference, false).insert(context)?,
+            ),
+            MethodKind::InvokeInterface => (
+                cpool::MethodKind::InvokeInterface,
+                any_method_ref(reference, true).insert(context)?,
+            ),
+        };
+
+        context.insert_constant(cpool::MethodHandle { kind, reference })
+    }
+}
+
+impl Insertable<cpool::InvokeDynamic> for &DynamicConstant {
+    fn insert<Ctx: EncoderContext>(self, context: &mut Ctx) -> Result<cpool::Index<cpool::InvokeDynamic>, EncodeError> {
+        let name_and_type = name_and_type_index(context, &self.name, &self.descriptor)?;
+        context.insert_constant(cpool::InvokeDynamic {
+            bootstrap_method_attr: self.bootstrap_method,
+            name_and_type,
+        })
+    }
+}
+
+fn field_ref<Ctx: EncoderContext>(
+    ctx: &mut Ctx,
+    reference: &MemberRef,
+) -> Result<cpool::Index<cpool::Item>, EncodeError> {
+    Ok(Insertable::<cpool::FieldRef>::insert(reference, ctx)?.as_item())
+}
+
+/// A reference to a method which is declared in either a class or an interface.
+struct AnyMethodRef<'a> {
+    reference: &'a MemberRef,
+    interface: bool,
+}
+
+fn any_method_ref(reference: &MemberRef, interface: bool) -> AnyMethodRef<'_> {
+    AnyMethodRef { reference, interface }
+}
+
+impl Insertable<cpool::Item> for AnyMethodRef<'_> {
+    fn insert<Ctx: EncoderContext>(self, context: &mut Ctx) -> Result<cpool::Index<cpool::Item>, EncodeError> {
+        if self.interface {
+            Ok(Insertable::<cpool::InterfaceMethodRef>::insert(self.reference, context)?.as_item())
+        } else {
+            Ok(Insertable::<cpool::MethodRef>::insert(self.reference, context)?.as_item())
+        }
+    }
+}
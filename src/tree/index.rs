@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+
+use crate::error::*;
+use crate::header::AccessFlags;
+use crate::mutf8::{MStr, MString};
+use crate::reader::attributes::{Code, Exceptions, NestHost, PermittedSubclasses, Record, Signature};
+use crate::reader::cpool::{ConstantPool, Index, Utf8};
+use crate::reader::{Attribute, AttributeContent, Class, DecodeMany};
+use crate::tree::{self, decode, Annotations};
+
+/// A view of a class whose fields and methods can be looked up by their name and descriptor.
+///
+/// The names and descriptors of all members are resolved once, and so are their annotations.
+/// Everything else is only read when it is accessed.
+///
+/// ```no_run
+/// use noak::reader::Class;
+/// use noak::tree::IndexedClass;
+///
+/// # let data = &[];
+/// let class = Class::new(data)?;
+/// let index = IndexedClass::new(&class)?;
+/// if let Some(main) = index.method("main", "([Ljava/lang/String;)V") {
+///     let code = main.code()?;
+///     println!("main throws {:?}", main.exceptions()?);
+/// }
+/// for getter in index.methods_named("get") {
+///     println!("get{}", getter.descriptor().display());
+/// }
+/// let named = index.annotations().get("Ljavax/inject/Named;");
+/// # Ok::<(), noak::error::DecodeError>(())
+/// ```
+#[derive(Debug)]
+pub struct IndexedClass<'a, 'input> {
+    class: &'a Class<'input>,
+    name: &'input MStr,
+    annotations: Vec<tree::Attribute>,
+    fields: Members<'a, 'input>,
+    methods: Members<'a, 'input>,
+}
+
+impl<'a, 'input> IndexedClass<'a, 'input> {
+    pub fn new(class: &'a Class<'input>) -> Result<IndexedClass<'a, 'input>, DecodeError> {
+        let pool = class.pool();
+        let mut fields = Members::default();
+        for field in class.fields() {
+            let field = field?;
+            fields.insert(IndexedMember::new(
+                pool,
+                field.access_flags(),
+                field.name(),
+                field.descriptor(),
+                field.attributes(),
+            )?);
+        }
+        let mut methods = Members::default();
+        for method in class.methods() {
+            let method = method?;
+            methods.insert(IndexedMember::new(
+                pool,
+                method.access_flags(),
+                method.name(),
+                method.descriptor(),
+                method.attributes(),
+            )?);
+        }
+
+        Ok(IndexedClass {
+            class,
+            name: pool.retrieve(class.this_class())?.name,
+            annotations: resolve_annotations(pool, class.attributes())?,
+            fields,
+            methods,
+        })
+    }
+
+    #[must_use]
+    pub fn class(&self) -> &'a Class<'input> {
+        self.class
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &'input MStr {
+        self.name
+    }
+
+    /// The fields in the order they are declared in.
+    #[must_use]
+    pub fn fields(&self) -> &[IndexedMember<'a, 'input>] {
+        &self.fields.members
+    }
+
+    /// The methods in the order they are declared in.
+    #[must_use]
+    pub fn methods(&self) -> &[IndexedMember<'a, 'input>] {
+        &self.methods.members
+    }
+
+    #[must_use]
+    pub fn field(&self, name: &str, descriptor: &str) -> Option<&IndexedMember<'a, 'input>> {
+        self.fields.get(name, descriptor)
+    }
+
+    #[must_use]
+    pub fn method(&self, name: &str, descriptor: &str) -> Option<&IndexedMember<'a, 'input>> {
+        self.methods.get(name, descriptor)
+    }
+
+    /// Returns every field of a name, which may be several in class files not written by `javac`.
+    pub fn fields_named(&self, name: &str) -> impl Iterator<Item = &IndexedMember<'a, 'input>> + '_ {
+        self.fields.named(name)
+    }
+
+    /// Returns every method of a name, which are the overloads of the method.
+    pub fn methods_named(&self, name: &str) -> impl Iterator<Item = &IndexedMember<'a, 'input>> + '_ {
+        self.methods.named(name)
+    }
+
+    /// The visible and invisible annotations of the class.
+    #[must_use]
+    pub fn annotations(&self) -> Annotations<'_> {
+        Annotations::of(&self.annotations)
+    }
+
+    /// The generic signature of the class.
+    pub fn signature(&self) -> Result<Option<&'input MStr>, DecodeError> {
+        signature(self.class.pool(), self.class.attributes())
+    }
+
+    /// Whether the class is a record, which extends `java.lang.Record` and has a `Record` attribute.
+    pub fn is_record(&self) -> Result<bool, DecodeError> {
+        let pool = self.class.pool();
+        let Some(super_class) = self.class.super_class() else {
+            return Ok(false);
+        };
+        if pool.retrieve(super_class)?.name != "java/lang/Record" {
+            return Ok(false);
+        }
+        Ok(self.class.attributes().find_attribute::<Record<'_>>(pool)?.is_some())
+    }
+
+    #[must_use]
+    pub fn is_enum(&self) -> bool {
+        self.class.access_flags().contains(AccessFlags::ENUM)
+    }
+
+    /// The names of the enum constants in the order they are declared in.
+    ///
+    /// These are the fields marked as enum constants, so classes which aren't enums have none.
+    #[must_use]
+    pub fn enum_constants(&self) -> Vec<&'input MStr> {
+        self.fields()
+            .iter()
+            .filter(|field| field.access_flags().contains(AccessFlags::ENUM))
+            .map(IndexedMember::name)
+            .collect()
+    }
+
+    /// The classes which may directly extend or implement a sealed class, or `None` if it isn't sealed.
+    pub fn permitted_subclasses(&self) -> Result<Option<Vec<&'input MStr>>, DecodeError> {
+        let pool = self.class.pool();
+        let Some(permitted) = self
+            .class
+            .attributes()
+            .find_attribute::<PermittedSubclasses<'_>>(pool)?
+        else {
+            return Ok(None);
+        };
+        let mut subclasses = Vec::new();
+        for class in permitted.classes() {
+            subclasses.push(pool.retrieve(class?)?.name);
+        }
+        Ok(Some(subclasses))
+    }
+
+    /// The host of the nest of a class, which may access the private members of the class and vice versa.
+    ///
+    /// A class without a `NestHost` attribute is the host of its own nest.
+    pub fn nest_host(&self) -> Result<&'input MStr, DecodeError> {
+        let pool = self.class.pool();
+        match self.class.attributes().find_attribute::<NestHost<'_>>(pool)? {
+            Some(host) => Ok(pool.retrieve(host.host_class())?.name),
+            None => Ok(self.name),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Members<'a, 'input> {
+    members: Vec<IndexedMember<'a, 'input>>,
+    by_name: HashMap<&'input MStr, Vec<usize>>,
+    by_name_and_descriptor: HashMap<(&'input MStr, &'input MStr), usize>,
+}
+
+impl<'a, 'input> Members<'a, 'input> {
+    fn insert(&mut self, member: IndexedMember<'a, 'input>) {
+        let index = self.members.len();
+        self.by_name.entry(member.name).or_default().push(index);
+        // duplicate members are invalid, the first one is found like by the JVM
+        self.by_name_and_descriptor
+            .entry((member.name, member.descriptor))
+            .or_insert(index);
+        self.members.push(member);
+    }
+
+    // the names are encoded, as NUL and supplementary characters are written differently in modified UTF-8
+    fn get(&self, name: &str, descriptor: &str) -> Option<&IndexedMember<'a, 'input>> {
+        let (name, descriptor) = (MString::from(name), MString::from(descriptor));
+        self.by_name_and_descriptor
+            .get(&(&*name, &*descriptor))
+            .map(|&index| &self.members[index])
+    }
+
+    fn named(&self, name: &str) -> impl Iterator<Item = &IndexedMember<'a, 'input>> + '_ {
+        self.by_name
+            .get(&*MString::from(name))
+            .into_iter()
+            .flatten()
+            .map(|&index| &self.members[index])
+    }
+}
+
+/// A field or method of an [`IndexedClass`].
+#[derive(Debug)]
+pub struct IndexedMember<'a, 'input> {
+    pool: &'a ConstantPool<'input>,
+    access_flags: AccessFlags,
+    name: &'input MStr,
+    descriptor: &'input MStr,
+    attributes: DecodeMany<'input, Attribute<'input>, u16>,
+    annotations: Vec<tree::Attribute>,
+}
+
+impl<'a, 'input> IndexedMember<'a, 'input> {
+    fn new(
+        pool: &'a ConstantPool<'input>,
+        access_flags: AccessFlags,
+        name: Index<Utf8<'input>>,
+        descriptor: Index<Utf8<'input>>,
+        attributes: DecodeMany<'input, Attribute<'input>, u16>,
+    ) -> Result<IndexedMember<'a, 'input>, DecodeError> {
+        Ok(IndexedMember {
+            pool,
+            access_flags,
+            name: pool.retrieve(name)?,
+            descriptor: pool.retrieve(descriptor)?,
+            annotations: resolve_annotations(pool, attributes.clone())?,
+            attributes,
+        })
+    }
+
+    #[must_use]
+    pub fn access_flags(&self) -> AccessFlags {
+        self.access_flags
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &'input MStr {
+        self.name
+    }
+
+    #[must_use]
+    pub fn descriptor(&self) -> &'input MStr {
+        self.descriptor
+    }
+
+    #[must_use]
+    pub fn attributes(&self) -> DecodeMany<'input, Attribute<'input>, u16> {
+        self.attributes.clone()
+    }
+
+    /// The code of a method, which abstract and native methods don't have.
+    pub fn code(&self) -> Result<Option<Code<'input>>, DecodeError> {
+        self.attributes.find_attribute(self.pool)
+    }
+
+    /// The checked exceptions declared to be thrown by a method.
+    pub fn exceptions(&self) -> Result<Vec<&'input MStr>, DecodeError> {
+        let mut exceptions = Vec::new();
+        if let Some(attribute) = self.attributes.find_attribute::<Exceptions<'_>>(self.pool)? {
+            for exception in attribute.exceptions() {
+                exceptions.push(self.pool.retrieve(exception?)?.name);
+            }
+        }
+        Ok(exceptions)
+    }
+
+    /// The generic signature of the member.
+    pub fn signature(&self) -> Result<Option<&'input MStr>, DecodeError> {
+        signature(self.pool, self.attributes.clone())
+    }
+
+    /// The visible and invisible annotations of the member.
+    #[must_use]
+    pub fn annotations(&self) -> Annotations<'_> {
+        Annotations::of(&self.annotations)
+    }
+
+    /// The visible and invisible annotations of a parameter of a method.
+    ///
+    /// Parameters which are added by the compiler may not be counted.
+    #[must_use]
+    pub fn parameter_annotations(&self, index: usize) -> Annotations<'_> {
+        Annotations::of_parameter(&self.annotations, index)
+    }
+}
+
+fn signature<'input>(
+    pool: &ConstantPool<'input>,
+    attributes: DecodeMany<'input, Attribute<'input>, u16>,
+) -> Result<Option<&'input MStr>, DecodeError> {
+    attributes
+        .find_attribute::<Signature<'_>>(pool)?
+        .map(|signature| pool.retrieve(signature.signature()))
+        .transpose()
+}
+
+/// Resolves the attributes which contain annotations, but not type annotations.
+fn resolve_annotations<'input>(
+    pool: &ConstantPool<'input>,
+    attributes: DecodeMany<'input, Attribute<'input>, u16>,
+) -> Result<Vec<tree::Attribute>, DecodeError> {
+    let mut resolved = Vec::new();
+    for attribute in attributes {
+        let attribute = attribute?;
+        let name = pool.retrieve(attribute.name())?;
+        if !name.as_bytes().starts_with(b"Runtime") || name.as_bytes().ends_with(b"TypeAnnotations") {
+            continue;
+        }
+        resolved.push(match attribute.read_content(pool)? {
+            AttributeContent::RuntimeVisibleAnnotations(attribute) => {
+                tree::Attribute::RuntimeVisibleAnnotations(decode::annotations(pool, attribute.annotations())?)
+            }
+            AttributeContent::RuntimeInvisibleAnnotations(attribute) => {
+                tree::Attribute::RuntimeInvisibleAnnotations(decode::annotations(pool, attribute.annotations())?)
+            }
+            AttributeContent::RuntimeVisibleParameterAnnotations(attribute) => {
+                tree::Attribute::RuntimeVisibleParameterAnnotations(decode::parameter_annotations(
+                    pool,
+                    attribute.parameters(),
+                )?)
+            }
+            AttributeContent::RuntimeInvisibleParameterAnnotations(attribute) => {
+                tree::Attribute::RuntimeInvisibleParameterAnnotations(decode::parameter_annotations(
+                    pool,
+                    attribute.parameters(),
+                )?)
+            }
+            _ => continue,
+        });
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::Version;
+    use crate::tree::{Annotation, Field, Method};
+
+    #[test]
+    fn lookup() {
+        let field = |name: &str| Field {
+            access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC | AccessFlags::FINAL | AccessFlags::ENUM,
+            name: name.into(),
+            descriptor: "LColor;".into(),
+            attributes: Vec::new(),
+        };
+        let method = |descriptor: &str, attributes: Vec<tree::Attribute>| Method {
+            access_flags: AccessFlags::PUBLIC | AccessFlags::ABSTRACT,
+            name: "mix".into(),
+            descriptor: descriptor.into(),
+            attributes,
+        };
+        let class = tree::Class {
+            version: Version::V17,
+            access_flags: AccessFlags::PUBLIC | AccessFlags::ENUM | AccessFlags::ABSTRACT,
+            super_class: Some("java/lang/Enum".into()),
+            fields: vec![field("RED"), field("GREEN")],
+            methods: vec![
+                method("(LColor;)LColor;", Vec::new()),
+                method(
+                    "(LColor;I)LColor;",
+                    vec![
+                        tree::Attribute::Exceptions(vec!["java/io/IOException".into()]),
+                        tree::Attribute::RuntimeVisibleAnnotations(vec![Annotation {
+                            type_: "Ljava/lang/Deprecated;".into(),
+                            pairs: Vec::new(),
+                        }]),
+                    ],
+                ),
+            ],
+            attributes: vec![tree::Attribute::NestMembers(vec!["Color$1".into()])],
+            ..tree::Class::new("Color")
+        };
+        let bytes = class.to_bytes().unwrap();
+        let class = Class::new(&bytes).unwrap();
+        let index = IndexedClass::new(&class).unwrap();
+
+        assert_eq!(index.name(), "Color");
+        assert!(index.is_enum());
+        assert!(!index.is_record().unwrap());
+        assert_eq!(index.enum_constants(), ["RED", "GREEN"]);
+        assert_eq!(index.nest_host().unwrap(), "Color");
+        assert!(index.permitted_subclasses().unwrap().is_none());
+
+        assert_eq!(index.methods_named("mix").count(), 2);
+        assert!(index.method("mix", "()V").is_none());
+        let mix = index.method("mix", "(LColor;I)LColor;").unwrap();
+        assert_eq!(mix.exceptions().unwrap(), ["java/io/IOException"]);
+        assert!(mix.annotations().contains("Ljava/lang/Deprecated;"));
+        assert!(mix.code().unwrap().is_none());
+        assert!(mix.signature().unwrap().is_none());
+        assert!(index.field("GREEN", "LColor;").is_some());
+    }
+
+    #[test]
+    fn lookup_encoded_names() {
+        let class = tree::Class {
+            fields: vec![Field {
+                access_flags: AccessFlags::PUBLIC,
+                name: "nul\0field".into(),
+                descriptor: "I".into(),
+                attributes: Vec::new(),
+            }],
+            methods: vec![Method {
+                access_flags: AccessFlags::PUBLIC | AccessFlags::ABSTRACT,
+                name: "smile\u{1F600}".into(),
+                descriptor: "(L\u{10400};)V".into(),
+                attributes: Vec::new(),
+            }],
+            ..tree::Class::new("Names")
+        };
+        let bytes = class.to_bytes().unwrap();
+        let class = Class::new(&bytes).unwrap();
+        let index = IndexedClass::new(&class).unwrap();
+
+        assert!(index.field("nul\0field", "I").is_some());
+        assert_eq!(index.fields_named("nul\0field").count(), 1);
+        assert!(index.method("smile\u{1F600}", "(L\u{10400};)V").is_some());
+        assert_eq!(index.methods_named("smile\u{1F600}").count(), 1);
+        assert_eq!(index.methods_named("smile").count(), 0);
+    }
+}
@@ -0,0 +1,1044 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::descriptor::{BaseType, MethodDescriptor, TypeDescriptor};
+use crate::mutf8::MStr;
+use crate::tree::{Attribute, Code, Constant, Instruction, Label, LocalType, Method, StackMapFrame, VerificationType};
+use crate::AccessFlags;
+
+pub(super) fn method(class_name: &MStr, method: &mut Method) {
+    let Method {
+        access_flags,
+        name,
+        descriptor,
+        attributes,
+    } = method;
+    for attribute in attributes {
+        if let Attribute::Code(code) = attribute {
+            let original = code.clone();
+            let initial_locals = || initial_locals(class_name, *access_flags, name, descriptor);
+            if self::code(code, initial_locals).is_none() {
+                *code = original;
+            }
+        }
+    }
+}
+
+/// Optimizes the code, returning `None` if it can't be analyzed.
+fn code(code: &mut Code, initial_locals: impl FnOnce() -> Option<Vec<VerificationType>>) -> Option<()> {
+    loop {
+        let mut changed = false;
+        while peephole(&mut code.instructions) | thread_jumps(&mut code.instructions)? {
+            changed = true;
+        }
+        changed |= eliminate_dead_code(code)?;
+        if !changed {
+            break;
+        }
+    }
+
+    let positions = Positions::new(&code.instructions);
+    let valid = |label: &Label| positions.get(*label).is_some_and(|position| position < positions.end);
+    code.exception_handlers.retain(|handler| {
+        matches!(
+            (positions.get(handler.start), positions.get(handler.end)),
+            (Some(start), Some(end)) if start < end
+        )
+    });
+
+    let mut initial_locals = Some(initial_locals);
+    for attribute in &mut code.attributes {
+        match attribute {
+            Attribute::LineNumberTable(lines) => lines.retain(|line| valid(&line.start)),
+            Attribute::LocalVariableTable(variables) => variables.retain(|variable| valid(&variable.start)),
+            Attribute::LocalVariableTypeTable(variables) => variables.retain(|variable| valid(&variable.start)),
+            Attribute::StackMapTable(frames) => {
+                // of the frames at the same position, only the last one is kept,
+                // as the states of the ones before it have to be assignable to it
+                let mut seen = HashSet::new();
+                let mut kept: Vec<bool> = frames
+                    .iter()
+                    .rev()
+                    .map(|frame| valid(&frame.label()) && seen.insert(positions.get(frame.label())))
+                    .collect();
+                kept.reverse();
+                if kept.iter().all(|&kept| kept) {
+                    continue;
+                }
+
+                let initial_locals = initial_locals.take()?()?;
+                let mut expanded = expand(frames, initial_locals.clone())?;
+                let mut kept = kept.into_iter();
+                expanded.retain(|_| kept.next().unwrap());
+                *frames = compress(expanded, initial_locals);
+            }
+            _ => {}
+        }
+    }
+
+    Some(())
+}
+
+/// The index of the instruction each label is placed before.
+struct Positions {
+    labels: HashMap<Label, usize>,
+    end: usize,
+}
+
+impl Positions {
+    fn new(instructions: &[Instruction]) -> Positions {
+        let mut labels = HashMap::new();
+        let mut position = 0;
+        for instruction in instructions {
+            match instruction {
+                Instruction::Label(label) => {
+                    labels.insert(*label, position);
+                }
+                _ => position += 1,
+            }
+        }
+        Positions { labels, end: position }
+    }
+
+    fn get(&self, label: Label) -> Option<usize> {
+        self.labels.get(&label).copied()
+    }
+}
+
+fn falls_through(instruction: &Instruction) -> bool {
+    !matches!(
+        instruction,
+        Instruction::Goto { .. }
+            | Instruction::GotoW { .. }
+            | Instruction::TableSwitch { .. }
+            | Instruction::LookupSwitch { .. }
+            | Instruction::Ret { .. }
+            | Instruction::RetW { .. }
+            | Instruction::AThrow
+            | Instruction::Return
+            | Instruction::IReturn
+            | Instruction::LReturn
+            | Instruction::FReturn
+            | Instruction::DReturn
+            | Instruction::AReturn
+    )
+}
+
+fn is_return(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Return
+            | Instruction::IReturn
+            | Instruction::LReturn
+            | Instruction::FReturn
+            | Instruction::DReturn
+            | Instruction::AReturn
+    )
+}
+
+/// Returns the type and index of the local variable a load or store accesses, and whether it stores.
+fn local_access(instruction: &Instruction) -> Option<(LocalType, u16, bool)> {
+    use Instruction::*;
+    use LocalType::*;
+
+    let access = match *instruction {
+        ILoad { index } => (Int, u16::from(index), false),
+        ILoadW { index } => (Int, index, false),
+        ILoad0 => (Int, 0, false),
+        ILoad1 => (Int, 1, false),
+        ILoad2 => (Int, 2, false),
+        ILoad3 => (Int, 3, false),
+        LLoad { index } => (Long, u16::from(index), false),
+        LLoadW { index } => (Long, index, false),
+        LLoad0 => (Long, 0, false),
+        LLoad1 => (Long, 1, false),
+        LLoad2 => (Long, 2, false),
+        LLoad3 => (Long, 3, false),
+        FLoad { index } => (Float, u16::from(index), false),
+        FLoadW { index } => (Float, index, false),
+        FLoad0 => (Float, 0, false),
+        FLoad1 => (Float, 1, false),
+        FLoad2 => (Float, 2, false),
+        FLoad3 => (Float, 3, false),
+        DLoad { index } => (Double, u16::from(index), false),
+        DLoadW { index } => (Double, index, false),
+        DLoad0 => (Double, 0, false),
+        DLoad1 => (Double, 1, false),
+        DLoad2 => (Double, 2, false),
+        DLoad3 => (Double, 3, false),
+        ALoad { index } => (Reference, u16::from(index), false),
+        ALoadW { index } => (Reference, index, false),
+        ALoad0 => (Reference, 0, false),
+        ALoad1 => (Reference, 1, false),
+        ALoad2 => (Reference, 2, false),
+        ALoad3 => (Reference, 3, false),
+        IStore { index } => (Int, u16::from(index), true),
+        IStoreW { index } => (Int, index, true),
+        IStore0 => (Int, 0, true),
+        IStore1 => (Int, 1, true),
+        IStore2 => (Int, 2, true),
+        IStore3 => (Int, 3, true),
+        LStore { index } => (Long, u16::from(index), true),
+        LStoreW { index } => (Long, index, true),
+        LStore0 => (Long, 0, true),
+        LStore1 => (Long, 1, true),
+        LStore2 => (Long, 2, true),
+        LStore3 => (Long, 3, true),
+        FStore { index } => (Float, u16::from(index), true),
+        FStoreW { index } => (Float, index, true),
+        FStore0 => (Float, 0, true),
+        FStore1 => (Float, 1, true),
+        FStore2 => (Float, 2, true),
+        FStore3 => (Float, 3, true),
+        DStore { index } => (Double, u16::from(index), true),
+        DStoreW { index } => (Double, index, true),
+        DStore0 => (Double, 0, true),
+        DStore1 => (Double, 1, true),
+        DStore2 => (Double, 2, true),
+        DStore3 => (Double, 3, true),
+        AStore { index } => (Reference, u16::from(index), true),
+        AStoreW { index } => (Reference, index, true),
+        AStore0 => (Reference, 0, true),
+        AStore1 => (Reference, 1, true),
+        AStore2 => (Reference, 2, true),
+        AStore3 => (Reference, 3, true),
+        _ => return None,
+    };
+    Some(access)
+}
+
+fn int_constant(instruction: &Instruction) -> Option<i32> {
+    match *instruction {
+        Instruction::IConstM1 => Some(-1),
+        Instruction::IConst0 => Some(0),
+        Instruction::IConst1 => Some(1),
+        Instruction::IConst2 => Some(2),
+        Instruction::IConst3 => Some(3),
+        Instruction::IConst4 => Some(4),
+        Instruction::IConst5 => Some(5),
+        Instruction::BIPush { value } => Some(i32::from(value)),
+        Instruction::SIPush { value } => Some(i32::from(value)),
+        Instruction::LdC {
+            constant: Constant::Integer(value),
+        }
+        | Instruction::LdCW {
+            constant: Constant::Integer(value),
+        } => Some(value),
+        _ => None,
+    }
+}
+
+/// Returns the size of the value an instruction pushes without any other effect.
+fn pure_push(instruction: &Instruction) -> Option<u8> {
+    if let Some((local_type, _, false)) = local_access(instruction) {
+        return Some(local_type.size() as u8);
+    }
+    if int_constant(instruction).is_some() {
+        return Some(1);
+    }
+
+    match instruction {
+        Instruction::AConstNull | Instruction::FConst0 | Instruction::FConst1 | Instruction::FConst2 => Some(1),
+        Instruction::LConst0 | Instruction::LConst1 | Instruction::DConst0 | Instruction::DConst1 => Some(2),
+        _ => None,
+    }
+}
+
+/// Evaluates a conditional jump whose operands are known, returning whether it jumps.
+fn evaluate(jump: &Instruction, operands: &[Instruction]) -> Option<bool> {
+    let zero = |value: i32| match jump {
+        Instruction::IfEq { .. } => Some(value == 0),
+        Instruction::IfNe { .. } => Some(value != 0),
+        Instruction::IfLt { .. } => Some(value < 0),
+        Instruction::IfGe { .. } => Some(value >= 0),
+        Instruction::IfGt { .. } => Some(value > 0),
+        Instruction::IfLe { .. } => Some(value <= 0),
+        _ => None,
+    };
+
+    match operands {
+        [Instruction::AConstNull] => match jump {
+            Instruction::IfNull { .. } => Some(true),
+            Instruction::IfNonNull { .. } => Some(false),
+            _ => None,
+        },
+        [value] => zero(int_constant(value)?),
+        [a, b] => {
+            let (a, b) = (int_constant(a)?, int_constant(b)?);
+            match jump {
+                Instruction::IfICmpEq { .. } => Some(a == b),
+                Instruction::IfICmpNe { .. } => Some(a != b),
+                Instruction::IfICmpLt { .. } => Some(a < b),
+                Instruction::IfICmpGe { .. } => Some(a >= b),
+                Instruction::IfICmpGt { .. } => Some(a > b),
+                Instruction::IfICmpLe { .. } => Some(a <= b),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The number of stack slots a conditional jump pops.
+fn jump_operands(instruction: &Instruction) -> Option<usize> {
+    match instruction {
+        Instruction::IfEq { .. }
+        | Instruction::IfNe { .. }
+        | Instruction::IfLt { .. }
+        | Instruction::IfGe { .. }
+        | Instruction::IfGt { .. }
+        | Instruction::IfLe { .. }
+        | Instruction::IfNull { .. }
+        | Instruction::IfNonNull { .. } => Some(1),
+        Instruction::IfICmpEq { .. }
+        | Instruction::IfICmpNe { .. }
+        | Instruction::IfICmpLt { .. }
+        | Instruction::IfICmpGe { .. }
+        | Instruction::IfICmpGt { .. }
+        | Instruction::IfICmpLe { .. }
+        | Instruction::IfACmpEq { .. }
+        | Instruction::IfACmpNe { .. } => Some(2),
+        _ => None,
+    }
+}
+
+/// Rewrites sequences of adjacent instructions which have no effect or a known result.
+///
+/// No sequence spans a label, so the state at every label stays the same.
+fn peephole(instructions: &mut Vec<Instruction>) -> bool {
+    let mut output: Vec<Instruction> = Vec::with_capacity(instructions.len());
+    let mut changed = false;
+    for instruction in instructions.drain(..) {
+        let previous = output.last();
+        match (previous, &instruction) {
+            (_, Instruction::Nop) => {}
+            (_, Instruction::IInc { value: 0, .. } | Instruction::IIncW { value: 0, .. }) => {}
+            (Some(Instruction::Dup), Instruction::Pop) | (Some(Instruction::Dup2), Instruction::Pop2) => {
+                output.pop();
+            }
+            (Some(previous), Instruction::Pop) if pure_push(previous) == Some(1) => {
+                output.pop();
+            }
+            (Some(previous), Instruction::Pop2) if pure_push(previous) == Some(2) => {
+                output.pop();
+            }
+            (Some(previous), _)
+                if matches!(
+                    (local_access(previous), local_access(&instruction)),
+                    (Some((a, x, false)), Some((b, y, true))) if a == b && x == y
+                ) =>
+            {
+                output.pop();
+            }
+            _ => {
+                let operands = jump_operands(&instruction).filter(|&count| count <= output.len());
+                let evaluated = operands.and_then(|count| evaluate(&instruction, &output[output.len() - count..]));
+                match evaluated {
+                    Some(jumps) => {
+                        output.truncate(output.len() - operands.unwrap());
+                        if jumps {
+                            let mut instruction = instruction;
+                            let target = *instruction.targets_mut()[0];
+                            output.push(Instruction::Goto { target });
+                        }
+                    }
+                    None => {
+                        output.push(instruction);
+                        continue;
+                    }
+                }
+            }
+        }
+        changed = true;
+    }
+    *instructions = output;
+
+    changed |= remove_jumps_to_next(instructions);
+    changed
+}
+
+/// Removes jumps to the instruction right after them.
+fn remove_jumps_to_next(instructions: &mut Vec<Instruction>) -> bool {
+    let mut changed = false;
+    let mut i = 0;
+    while i < instructions.len() {
+        let mut instruction = instructions[i].clone();
+        let next_labels: Vec<Label> = instructions[i + 1..]
+            .iter()
+            .map_while(|next| match next {
+                Instruction::Label(label) => Some(*label),
+                _ => None,
+            })
+            .collect();
+
+        let to_next = match instruction.targets_mut().as_slice() {
+            [target] => next_labels.contains(target),
+            _ => false,
+        };
+        if to_next {
+            match instruction {
+                Instruction::Goto { .. } | Instruction::GotoW { .. } => {
+                    instructions.remove(i);
+                    changed = true;
+                    continue;
+                }
+                _ => match jump_operands(&instruction) {
+                    Some(1) => {
+                        instructions[i] = Instruction::Pop;
+                        changed = true;
+                    }
+                    Some(2) => {
+                        instructions[i] = Instruction::Pop2;
+                        changed = true;
+                    }
+                    _ => {}
+                },
+            }
+        }
+        i += 1;
+    }
+    changed
+}
+
+/// Lets jumps to a `goto` jump to its target directly, and replaces a `goto` to a return with the return.
+///
+/// Returns `None` if a label is not placed.
+fn thread_jumps(instructions: &mut [Instruction]) -> Option<bool> {
+    let mut following: HashMap<Label, usize> = HashMap::new();
+    let mut pending = Vec::new();
+    for (index, instruction) in instructions.iter().enumerate() {
+        match instruction {
+            Instruction::Label(label) => pending.push(*label),
+            _ => {
+                for label in pending.drain(..) {
+                    following.insert(label, index);
+                }
+            }
+        }
+    }
+    let end = instructions.len();
+    for label in pending {
+        following.insert(label, end);
+    }
+
+    let resolve = |mut label: Label| -> Option<Label> {
+        let mut visited = HashSet::new();
+        while visited.insert(label) {
+            match instructions.get(*following.get(&label)?) {
+                Some(Instruction::Goto { target } | Instruction::GotoW { target }) => label = *target,
+                _ => break,
+            }
+        }
+        Some(label)
+    };
+
+    let mut changed = false;
+    let mut updates = Vec::new();
+    for (index, instruction) in instructions.iter().enumerate() {
+        let mut instruction = instruction.clone();
+        let mut replaced = false;
+        for target in instruction.targets_mut() {
+            let resolved = resolve(*target)?;
+            if resolved != *target {
+                *target = resolved;
+                replaced = true;
+            }
+        }
+
+        if let Instruction::Goto { target } | Instruction::GotoW { target } = instruction {
+            if let Some(next) = instructions.get(following[&target]).filter(|next| is_return(next)) {
+                instruction = next.clone();
+                replaced = true;
+            }
+        }
+        if replaced {
+            updates.push((index, instruction));
+        }
+    }
+
+    for (index, instruction) in updates {
+        instructions[index] = instruction;
+        changed = true;
+    }
+    Some(changed)
+}
+
+/// Removes the instructions which can't be reached from the start or from a reachable exception handler.
+///
+/// Returns `None` if a label is not placed.
+fn eliminate_dead_code(code: &mut Code) -> Option<bool> {
+    let instructions = &mut code.instructions;
+    let labels: HashMap<Label, usize> = instructions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, instruction)| match instruction {
+            Instruction::Label(label) => Some((*label, index)),
+            _ => None,
+        })
+        .collect();
+
+    let mut reachable = vec![false; instructions.len()];
+    let mut pending = vec![0];
+    let mut handlers_done = vec![false; code.exception_handlers.len()];
+    loop {
+        while let Some(index) = pending.pop() {
+            if index >= instructions.len() || reachable[index] {
+                continue;
+            }
+            reachable[index] = true;
+
+            let mut instruction = instructions[index].clone();
+            if falls_through(&instruction) {
+                pending.push(index + 1);
+            }
+            for target in instruction.targets_mut() {
+                pending.push(*labels.get(target)?);
+            }
+        }
+
+        // a handler is reachable once any instruction it protects is
+        for (handler, done) in code.exception_handlers.iter().zip(&mut handlers_done) {
+            if *done {
+                continue;
+            }
+            let (start, end) = (*labels.get(&handler.start)?, *labels.get(&handler.end)?);
+            let protects = (start..end.min(instructions.len()))
+                .any(|index| reachable[index] && !matches!(instructions[index], Instruction::Label(_)));
+            if protects {
+                *done = true;
+                pending.push(*labels.get(&handler.handler)?);
+            }
+        }
+        if pending.is_empty() {
+            break;
+        }
+    }
+
+    let mut changed = false;
+    let mut reachable = reachable.into_iter();
+    instructions.retain(|instruction| {
+        let keep = reachable.next().unwrap() || matches!(instruction, Instruction::Label(_));
+        changed |= !keep;
+        keep
+    });
+    Some(changed)
+}
+
+/// Returns the locals at the start of a method, or `None` if its descriptor is invalid.
+fn initial_locals(
+    class_name: &MStr,
+    access_flags: AccessFlags,
+    name: &MStr,
+    descriptor: &MStr,
+) -> Option<Vec<VerificationType>> {
+    let descriptor = MethodDescriptor::parse(descriptor).ok()?;
+    let mut locals = Vec::new();
+    if !access_flags.contains(AccessFlags::STATIC) {
+        if *name == *"<init>" && *class_name != *"java/lang/Object" {
+            locals.push(VerificationType::UninitializedThis);
+        } else {
+            locals.push(VerificationType::Object(class_name.into()));
+        }
+    }
+    locals.extend(descriptor.parameters().map(|parameter| verification_type(&parameter)));
+    Some(locals)
+}
+
+fn verification_type(descriptor: &TypeDescriptor<'_>) -> VerificationType {
+    if descriptor.dimensions > 0 {
+        return VerificationType::Object(descriptor.to_string().as_str().into());
+    }
+
+    match descriptor.base {
+        BaseType::Boolean | BaseType::Byte | BaseType::Char | BaseType::Short | BaseType::Integer => {
+            VerificationType::Integer
+        }
+        BaseType::Long => VerificationType::Long,
+        BaseType::Float => VerificationType::Float,
+        BaseType::Double => VerificationType::Double,
+        BaseType::Object(class) => VerificationType::Object(class.into()),
+    }
+}
+
+/// A frame with all of its locals and stack entries.
+struct FullFrame {
+    label: Label,
+    locals: Vec<VerificationType>,
+    stack: Vec<VerificationType>,
+}
+
+fn expand(frames: &[StackMapFrame], initial_locals: Vec<VerificationType>) -> Option<Vec<FullFrame>> {
+    let mut locals = initial_locals;
+    let mut expanded = Vec::with_capacity(frames.len());
+    for frame in frames {
+        let stack = match frame {
+            StackMapFrame::Same { .. } | StackMapFrame::SameExtended { .. } => Vec::new(),
+            StackMapFrame::Same1 { stack, .. } | StackMapFrame::Same1Extended { stack, .. } => vec![stack.clone()],
+            StackMapFrame::Chop { to_chop, .. } => {
+                locals.truncate(locals.len().checked_sub(usize::from(*to_chop))?);
+                Vec::new()
+            }
+            StackMapFrame::Append { locals: appended, .. } => {
+                locals.extend(appended.iter().cloned());
+                Vec::new()
+            }
+            StackMapFrame::Full {
+                locals: full, stack, ..
+            } => {
+                locals.clone_from(full);
+                stack.clone()
+            }
+        };
+        expanded.push(FullFrame {
+            label: frame.label(),
+            locals: locals.clone(),
+            stack,
+        });
+    }
+    Some(expanded)
+}
+
+fn compress(frames: Vec<FullFrame>, initial_locals: Vec<VerificationType>) -> Vec<StackMapFrame> {
+    let mut previous = initial_locals;
+    let mut compressed = Vec::with_capacity(frames.len());
+    for FullFrame { label, locals, stack } in frames {
+        let frame = match &previous {
+            previous if *previous == locals && stack.is_empty() => StackMapFrame::Same { label },
+            previous if *previous == locals && stack.len() == 1 => StackMapFrame::Same1 {
+                label,
+                stack: stack[0].clone(),
+            },
+            previous
+                if stack.is_empty()
+                    && (1..=3).contains(&locals.len().wrapping_sub(previous.len()))
+                    && locals.starts_with(previous) =>
+            {
+                StackMapFrame::Append {
+                    label,
+                    locals: locals[previous.len()..].to_vec(),
+                }
+            }
+            previous
+                if stack.is_empty()
+                    && (1..=3).contains(&previous.len().wrapping_sub(locals.len()))
+                    && previous.starts_with(&locals) =>
+            {
+                StackMapFrame::Chop {
+                    label,
+                    to_chop: (previous.len() - locals.len()) as u8,
+                }
+            }
+            _ => StackMapFrame::Full {
+                label,
+                locals: locals.clone(),
+                stack,
+            },
+        };
+        compressed.push(frame);
+        previous = locals;
+    }
+    compressed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mutf8::MString;
+    use crate::tree::{ExceptionHandler, LineNumber, LocalVariable, MemberRef};
+
+    fn call(name: &str) -> Instruction {
+        Instruction::InvokeStatic {
+            method: MemberRef {
+                class: "Example".into(),
+                name: name.into(),
+                descriptor: "()V".into(),
+            },
+            interface: false,
+        }
+    }
+
+    /// Optimizes the code of a static method taking an `int`.
+    fn optimize(code: Code) -> Code {
+        let mut method = Method {
+            access_flags: AccessFlags::STATIC,
+            name: "run".into(),
+            descriptor: "(I)V".into(),
+            attributes: vec![Attribute::Code(code)],
+        };
+        self::method(&MString::from("Example"), &mut method);
+        let Some(Attribute::Code(code)) = method.attributes.pop() else {
+            unreachable!();
+        };
+        code
+    }
+
+    fn code(instructions: Vec<Instruction>, exception_handlers: Vec<ExceptionHandler>) -> Code {
+        Code {
+            max_stack: 1,
+            max_locals: 1,
+            instructions,
+            exception_handlers,
+            attributes: Vec::new(),
+        }
+    }
+
+    fn peepholed(mut instructions: Vec<Instruction>) -> Vec<Instruction> {
+        peephole(&mut instructions);
+        instructions
+    }
+
+    #[test]
+    fn load_then_store() {
+        assert_eq!(
+            peepholed(vec![Instruction::ILoad1, Instruction::IStore1, Instruction::Return]),
+            [Instruction::Return]
+        );
+        assert_eq!(
+            peepholed(vec![
+                Instruction::ALoadW { index: 300 },
+                Instruction::AStoreW { index: 300 },
+                Instruction::Return
+            ]),
+            [Instruction::Return]
+        );
+        // a different slot or type is a real store
+        assert_eq!(
+            peepholed(vec![Instruction::ILoad1, Instruction::IStore2]),
+            [Instruction::ILoad1, Instruction::IStore2]
+        );
+        assert_eq!(
+            peepholed(vec![Instruction::ILoad1, Instruction::FStore1]),
+            [Instruction::ILoad1, Instruction::FStore1]
+        );
+    }
+
+    #[test]
+    fn dup_then_pop() {
+        assert_eq!(
+            peepholed(vec![
+                Instruction::ALoad0,
+                Instruction::Dup,
+                Instruction::Pop,
+                Instruction::AStore1
+            ]),
+            [Instruction::ALoad0, Instruction::AStore1]
+        );
+        assert_eq!(
+            peepholed(vec![
+                Instruction::LLoad1,
+                Instruction::Dup2,
+                Instruction::Pop2,
+                Instruction::LStore3
+            ]),
+            [Instruction::LLoad1, Instruction::LStore3]
+        );
+        assert_eq!(
+            peepholed(vec![Instruction::ALoad0, Instruction::Dup, Instruction::Pop2]),
+            [Instruction::ALoad0, Instruction::Dup, Instruction::Pop2]
+        );
+    }
+
+    #[test]
+    fn push_then_pop() {
+        assert_eq!(
+            peepholed(vec![
+                call("a"),
+                Instruction::IConst3,
+                Instruction::Pop,
+                Instruction::Return
+            ]),
+            [call("a"), Instruction::Return]
+        );
+        assert_eq!(
+            peepholed(vec![
+                Instruction::AConstNull,
+                Instruction::Pop,
+                Instruction::DConst1,
+                Instruction::Pop2,
+                Instruction::Return
+            ]),
+            [Instruction::Return]
+        );
+        assert_eq!(
+            peepholed(vec![Instruction::LLoad1, Instruction::Pop2, Instruction::Return]),
+            [Instruction::Return]
+        );
+        // the sizes have to match, and other instructions have an effect
+        assert_eq!(
+            peepholed(vec![Instruction::DConst1, Instruction::Pop, Instruction::Pop]),
+            [Instruction::DConst1, Instruction::Pop, Instruction::Pop]
+        );
+        assert_eq!(
+            peepholed(vec![
+                Instruction::ILoad0,
+                Instruction::IConst1,
+                Instruction::IAdd,
+                Instruction::Pop
+            ]),
+            [
+                Instruction::ILoad0,
+                Instruction::IConst1,
+                Instruction::IAdd,
+                Instruction::Pop
+            ]
+        );
+    }
+
+    #[test]
+    fn conditional_jump_to_next() {
+        let next = Label(1);
+        assert_eq!(
+            peepholed(vec![
+                call("a"),
+                Instruction::IfEq { target: next },
+                Instruction::Label(next),
+                Instruction::Return
+            ]),
+            [
+                call("a"),
+                Instruction::Pop,
+                Instruction::Label(next),
+                Instruction::Return
+            ]
+        );
+        assert_eq!(
+            peepholed(vec![
+                call("a"),
+                call("b"),
+                Instruction::IfACmpNe { target: next },
+                Instruction::Label(next),
+                Instruction::Return
+            ]),
+            [
+                call("a"),
+                call("b"),
+                Instruction::Pop2,
+                Instruction::Label(next),
+                Instruction::Return
+            ]
+        );
+        assert_eq!(
+            peepholed(vec![
+                Instruction::Goto { target: next },
+                Instruction::Label(next),
+                Instruction::Return
+            ]),
+            [Instruction::Label(next), Instruction::Return]
+        );
+        // a jump over an instruction stays
+        assert_eq!(
+            peepholed(vec![
+                call("a"),
+                Instruction::IfEq { target: next },
+                Instruction::Nop,
+                Instruction::Label(Label(2)),
+                call("b"),
+                Instruction::Label(next)
+            ]),
+            [
+                call("a"),
+                Instruction::IfEq { target: next },
+                Instruction::Label(Label(2)),
+                call("b"),
+                Instruction::Label(next)
+            ]
+        );
+    }
+
+    #[test]
+    fn constant_branches() {
+        let target = Label(1);
+        assert_eq!(
+            peepholed(vec![Instruction::IConst0, Instruction::IfEq { target }]),
+            [Instruction::Goto { target }]
+        );
+        assert!(peepholed(vec![Instruction::IConst1, Instruction::IfEq { target }]).is_empty());
+        assert_eq!(
+            peepholed(vec![
+                Instruction::IConst2,
+                Instruction::BIPush { value: 3 },
+                Instruction::IfICmpLt { target }
+            ]),
+            [Instruction::Goto { target }]
+        );
+        assert!(peepholed(vec![
+            Instruction::IConst2,
+            Instruction::IConst3,
+            Instruction::IfICmpGt { target }
+        ])
+        .is_empty());
+        assert_eq!(
+            peepholed(vec![Instruction::AConstNull, Instruction::IfNull { target }]),
+            [Instruction::Goto { target }]
+        );
+        assert!(peepholed(vec![Instruction::AConstNull, Instruction::IfNonNull { target }]).is_empty());
+        // an operand which is not constant is left alone
+        assert_eq!(
+            peepholed(vec![Instruction::ILoad0, Instruction::IfEq { target }]),
+            [Instruction::ILoad0, Instruction::IfEq { target }]
+        );
+    }
+
+    #[test]
+    fn label_between_pair() {
+        // another jump may reach the label with a different state, so the pairs are kept
+        let label = Instruction::Label(Label(0));
+        for instructions in [
+            vec![Instruction::ILoad1, label.clone(), Instruction::IStore1],
+            vec![Instruction::ALoad0, Instruction::Dup, label.clone(), Instruction::Pop],
+            vec![Instruction::IConst3, label.clone(), Instruction::Pop],
+            vec![
+                Instruction::IConst0,
+                label.clone(),
+                Instruction::IfEq { target: Label(1) },
+            ],
+        ] {
+            assert_eq!(peepholed(instructions.clone()), instructions);
+        }
+    }
+
+    #[test]
+    fn dead_code_in_try_range() {
+        let instructions = vec![
+            Instruction::Label(Label(0)),
+            call("live"),
+            Instruction::Goto { target: Label(3) },
+            Instruction::Label(Label(1)),
+            call("dead"),
+            Instruction::Label(Label(2)),
+            Instruction::Label(Label(3)),
+            Instruction::Return,
+            Instruction::Label(Label(4)),
+            Instruction::AThrow,
+        ];
+        let handler = |start| ExceptionHandler {
+            start,
+            end: Label(2),
+            handler: Label(4),
+            catch_type: None,
+        };
+
+        // the range still protects the live call, so the handler is kept
+        let optimized = optimize(code(instructions.clone(), vec![handler(Label(0))]));
+        assert_eq!(
+            optimized.instructions,
+            [
+                Instruction::Label(Label(0)),
+                call("live"),
+                Instruction::Return,
+                Instruction::Label(Label(1)),
+                Instruction::Label(Label(2)),
+                Instruction::Label(Label(3)),
+                Instruction::Label(Label(4)),
+                Instruction::AThrow,
+            ]
+        );
+        assert_eq!(optimized.exception_handlers, [handler(Label(0))]);
+
+        // a range of only dead code protects nothing, so the handler and its code are removed
+        let optimized = optimize(code(instructions, vec![handler(Label(1))]));
+        assert!(optimized.exception_handlers.is_empty());
+        assert!(!optimized.instructions.contains(&Instruction::AThrow));
+    }
+
+    #[test]
+    fn entries_of_removed_instructions() {
+        let variable = |start, name: &str| LocalVariable {
+            start,
+            end: Label(2),
+            name: name.into(),
+            descriptor: "I".into(),
+            index: 0,
+        };
+        let mut code = code(
+            vec![
+                Instruction::Label(Label(0)),
+                Instruction::Return,
+                Instruction::Label(Label(1)),
+                call("dead"),
+                Instruction::Label(Label(2)),
+            ],
+            Vec::new(),
+        );
+        code.attributes = vec![
+            Attribute::LineNumberTable(vec![
+                LineNumber {
+                    start: Label(0),
+                    line_number: 1,
+                },
+                LineNumber {
+                    start: Label(1),
+                    line_number: 2,
+                },
+            ]),
+            Attribute::LocalVariableTable(vec![variable(Label(0), "value"), variable(Label(1), "dead")]),
+        ];
+
+        let optimized = optimize(code);
+        assert_eq!(
+            optimized.instructions,
+            [
+                Instruction::Label(Label(0)),
+                Instruction::Return,
+                Instruction::Label(Label(1)),
+                Instruction::Label(Label(2)),
+            ]
+        );
+        assert_eq!(
+            optimized.attributes,
+            [
+                Attribute::LineNumberTable(vec![LineNumber {
+                    start: Label(0),
+                    line_number: 1,
+                }]),
+                Attribute::LocalVariableTable(vec![variable(Label(0), "value")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn thread_jump_across_frame() {
+        let instructions = |fall_through: bool| {
+            vec![
+                Instruction::Label(Label(0)),
+                Instruction::Goto { target: Label(3) },
+                Instruction::Label(Label(2)),
+                call("target"),
+                Instruction::Return,
+                Instruction::Label(Label(3)),
+                Instruction::ILoad0,
+                Instruction::IfEq { target: Label(1) },
+                if fall_through {
+                    call("other")
+                } else {
+                    Instruction::Return
+                },
+                Instruction::Label(Label(1)),
+                Instruction::Goto { target: Label(2) },
+            ]
+        };
+        let frames = vec![
+            StackMapFrame::Same { label: Label(2) },
+            StackMapFrame::Same { label: Label(3) },
+            StackMapFrame::Same { label: Label(1) },
+        ];
+
+        // the goto is still reached by falling through, so its frame is kept
+        let mut original = code(instructions(true), Vec::new());
+        original.attributes = vec![Attribute::StackMapTable(frames.clone())];
+        let optimized = optimize(original);
+        assert_eq!(optimized.instructions, {
+            let mut instructions = instructions(true);
+            instructions[7] = Instruction::IfEq { target: Label(2) };
+            instructions
+        });
+        assert_eq!(optimized.attributes, [Attribute::StackMapTable(frames.clone())]);
+
+        // once the goto is unreachable, its label is at the end of the code and loses its frame
+        let mut original = code(instructions(false), Vec::new());
+        original.attributes = vec![Attribute::StackMapTable(frames.clone())];
+        let optimized = optimize(original);
+        assert_eq!(optimized.instructions[7], Instruction::IfEq { target: Label(2) });
+        assert_eq!(optimized.instructions.last(), Some(&Instruction::Label(Label(1))));
+        assert_eq!(optimized.attributes, [Attribute::StackMapTable(frames[..2].to_vec())]);
+    }
+}
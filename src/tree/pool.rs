@@ -0,0 +1,257 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use crate::error::*;
+use crate::reader;
+use crate::tree::{decode, Class, Constant};
+
+/// The order of the constant pool entries of a compacted class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PoolOrder {
+    /// Entries which were in the constant pool before keep their relative order,
+    /// so the indices of the class change as little as possible.
+    #[default]
+    Preserve,
+    /// Entries are sorted by their kind and content, so equal classes are written identically
+    /// no matter in which order their constants were added.
+    Sorted,
+}
+
+pub(super) fn compact(class: &mut Class, order: PoolOrder) -> Result<(), EncodeError> {
+    let kept = std::mem::take(&mut class.constant_pool);
+    let referenced = match referenced(class) {
+        Ok(referenced) => referenced,
+        Err(err) => {
+            class.constant_pool = kept;
+            return Err(err);
+        }
+    };
+
+    class.constant_pool = match order {
+        PoolOrder::Preserve => {
+            let referenced: HashSet<_> = referenced.into_iter().collect();
+            kept.into_iter()
+                .filter(|constant| referenced.contains(constant))
+                .collect()
+        }
+        PoolOrder::Sorted => {
+            let mut referenced = referenced;
+            referenced.sort_by(compare);
+            referenced
+        }
+    };
+    Ok(())
+}
+
+/// Returns every constant which is referenced by the class.
+///
+/// Writing the class adds exactly the referenced constants to the constant pool,
+/// so they can be read back from the written class.
+fn referenced(class: &Class) -> Result<Vec<Constant>, EncodeError> {
+    let bytes = class.to_bytes()?;
+    let resolve = || {
+        let class = reader::Class::new(&bytes)?;
+        let pool = class.pool();
+        pool.iter_indices()
+            .map(|(index, _)| decode::constant(pool, index))
+            .collect::<Result<Vec<_>, DecodeError>>()
+    };
+    resolve().map_err(|err| EncodeError::from_err(err, Context::ConstantPool))
+}
+
+/// Orders constants by their kind, so every constant comes after the constants it references.
+fn rank(constant: &Constant) -> u8 {
+    match constant {
+        Constant::Utf8(_) => 0,
+        Constant::Integer(_) => 1,
+        Constant::Float(_) => 2,
+        Constant::Long(_) => 3,
+        Constant::Double(_) => 4,
+        Constant::Class(_) => 5,
+        Constant::String(_) => 6,
+        Constant::MethodType(_) => 7,
+        Constant::Module(_) => 8,
+        Constant::Package(_) => 9,
+        Constant::NameAndType(_) => 10,
+        Constant::FieldRef(_) => 11,
+        Constant::MethodRef(_) => 12,
+        Constant::InterfaceMethodRef(_) => 13,
+        Constant::MethodHandle(_) => 14,
+        Constant::Dynamic(_) => 15,
+        Constant::InvokeDynamic(_) => 16,
+    }
+}
+
+fn compare(a: &Constant, b: &Constant) -> Ordering {
+    rank(a).cmp(&rank(b)).then_with(|| match (a, b) {
+        (Constant::Integer(a), Constant::Integer(b)) => a.cmp(b),
+        (Constant::Float(a), Constant::Float(b)) => a.to_bits().cmp(&b.to_bits()),
+        (Constant::Long(a), Constant::Long(b)) => a.cmp(b),
+        (Constant::Double(a), Constant::Double(b)) => a.to_bits().cmp(&b.to_bits()),
+        (Constant::Utf8(a), Constant::Utf8(b))
+        | (Constant::Class(a), Constant::Class(b))
+        | (Constant::String(a), Constant::String(b))
+        | (Constant::MethodType(a), Constant::MethodType(b))
+        | (Constant::Module(a), Constant::Module(b))
+        | (Constant::Package(a), Constant::Package(b)) => a.cmp(b),
+        (Constant::NameAndType(a), Constant::NameAndType(b)) => a.cmp(b),
+        (Constant::FieldRef(a), Constant::FieldRef(b))
+        | (Constant::MethodRef(a), Constant::MethodRef(b))
+        | (Constant::InterfaceMethodRef(a), Constant::InterfaceMethodRef(b)) => a.cmp(b),
+        (Constant::MethodHandle(a), Constant::MethodHandle(b)) => a.cmp(b),
+        (Constant::Dynamic(a), Constant::Dynamic(b)) | (Constant::InvokeDynamic(a), Constant::InvokeDynamic(b)) => {
+            a.cmp(b)
+        }
+        _ => Ordering::Equal,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::AccessFlags;
+    use crate::tree::{Attribute, Code, Instruction, MemberRef, Method};
+
+    /// Creates a class with a single static method, whose pool starts with `unused` constants.
+    fn class(unused: usize, instructions: Vec<Instruction>) -> Class {
+        let mut constant_pool: Vec<_> = (0..unused)
+            .map(|i| Constant::Utf8(format!("unused {i}").as_str().into()))
+            .collect();
+        constant_pool.push(Constant::Long(0));
+        Class {
+            constant_pool,
+            methods: vec![Method {
+                access_flags: AccessFlags::PUBLIC | AccessFlags::STATIC,
+                name: "run".into(),
+                descriptor: "()V".into(),
+                attributes: vec![Attribute::Code(Code {
+                    max_stack: 4,
+                    max_locals: 0,
+                    instructions,
+                    exception_handlers: Vec::new(),
+                    attributes: Vec::new(),
+                })],
+            }],
+            ..Class::new("Example")
+        }
+    }
+
+    fn written(class: &Class) -> &[Instruction] {
+        let Attribute::Code(code) = &class.methods[0].attributes[0] else {
+            panic!("expected a code attribute");
+        };
+        &code.instructions
+    }
+
+    #[test]
+    fn remaps_every_index() {
+        let instructions = vec![
+            Instruction::LdC {
+                constant: Constant::String("hello".into()),
+            },
+            Instruction::GetStatic {
+                field: MemberRef {
+                    class: "java/lang/System".into(),
+                    name: "out".into(),
+                    descriptor: "Ljava/io/PrintStream;".into(),
+                },
+            },
+            Instruction::Swap,
+            Instruction::InvokeVirtual {
+                method: MemberRef {
+                    class: "java/io/PrintStream".into(),
+                    name: "println".into(),
+                    descriptor: "(Ljava/lang/String;)V".into(),
+                },
+            },
+            Instruction::Return,
+        ];
+
+        for order in [PoolOrder::Preserve, PoolOrder::Sorted] {
+            let mut class = class(10, instructions.clone());
+            class.constant_pool.push(Constant::String("hello".into()));
+            let original = class.clone();
+            class.compact_constant_pool(order).unwrap();
+            assert!(!class.constant_pool.contains(&Constant::Long(0)));
+
+            // every reference resolves to the same constant at its new index
+            let read = Class::read(&class.to_bytes().unwrap()).unwrap();
+            assert!(class
+                .constant_pool
+                .iter()
+                .all(|constant| read.constant_pool.contains(constant)));
+            assert!(!read.constant_pool.contains(&Constant::Utf8("unused 0".into())));
+            assert_eq!(read.methods, original.methods);
+            assert_eq!(read.name, original.name);
+            assert_eq!(read.super_class, original.super_class);
+        }
+    }
+
+    #[test]
+    fn ldc_promoted_above_255() {
+        let ldc = Instruction::LdC {
+            constant: Constant::Integer(1_000_000),
+        };
+        let mut class = class(300, vec![ldc.clone(), Instruction::Pop, Instruction::Return]);
+
+        // the unused constants push the integer past the indices which fit into a byte
+        let read = Class::read(&class.to_bytes().unwrap()).unwrap();
+        assert!(matches!(
+            written(&read)[0],
+            Instruction::LdCW {
+                constant: Constant::Integer(1_000_000)
+            }
+        ));
+
+        class.compact_constant_pool(PoolOrder::Preserve).unwrap();
+        let read = Class::read(&class.to_bytes().unwrap()).unwrap();
+        assert_eq!(written(&read)[0], ldc);
+    }
+
+    #[test]
+    fn long_and_double_slots() {
+        let instructions = vec![
+            Instruction::LdC2W {
+                constant: Constant::Double(2.5),
+            },
+            Instruction::Pop2,
+            Instruction::LdC2W {
+                constant: Constant::Long(-7),
+            },
+            Instruction::Pop2,
+            Instruction::LdC {
+                constant: Constant::Float(1.5),
+            },
+            Instruction::Pop,
+            Instruction::Return,
+        ];
+
+        for order in [PoolOrder::Preserve, PoolOrder::Sorted] {
+            let mut class = class(3, instructions.clone());
+            class.constant_pool.insert(0, Constant::Double(2.5));
+            class.constant_pool.insert(2, Constant::Double(9.0));
+            class.constant_pool.push(Constant::Long(-7));
+            class.compact_constant_pool(order).unwrap();
+            assert!(!class.constant_pool.contains(&Constant::Double(9.0)));
+            assert!(!class.constant_pool.contains(&Constant::Long(0)));
+
+            let bytes = class.to_bytes().unwrap();
+            let reader = reader::Class::new(&bytes).unwrap();
+            let indices: Vec<_> = reader.pool().iter_indices().map(|(index, _)| index.as_u16()).collect();
+            let read = Class::read(&bytes).unwrap();
+            assert!(read.constant_pool.starts_with(&class.constant_pool[..2]));
+            let mut expected = Vec::new();
+            let mut next = 1;
+            for constant in &read.constant_pool {
+                expected.push(next);
+                next += if matches!(constant, Constant::Long(_) | Constant::Double(_)) {
+                    2
+                } else {
+                    1
+                };
+            }
+            assert_eq!(indices, expected);
+            assert_eq!(written(&read), instructions);
+        }
+    }
+}
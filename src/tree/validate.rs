@@ -0,0 +1,244 @@
+use crate::descriptor::{MethodDescriptor, TypeDescriptor};
+use crate::error::*;
+use crate::reader::attributes::{self, AttributeContent};
+use crate::reader::cpool::{self, ConstantPool, MethodKind};
+use crate::reader::{Attribute, Class, DecodeMany};
+
+use super::decode::{self, Labels};
+
+pub(super) fn class(class: &Class<'_>) -> Vec<DecodeError> {
+    let mut validator = Validator {
+        pool: class.pool(),
+        path: Vec::new(),
+        errors: Vec::new(),
+    };
+    validator.class(class);
+    validator.errors
+}
+
+struct Validator<'a, 'input> {
+    pool: &'a ConstantPool<'input>,
+    path: Vec<PathSegment>,
+    errors: Vec<DecodeError>,
+}
+
+impl<'a, 'input> Validator<'a, 'input> {
+    /// Records the error of a result at the current path.
+    fn check<T>(&mut self, result: Result<T, DecodeError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                let error = self
+                    .path
+                    .iter()
+                    .rev()
+                    .fold(error, |error, segment| error.within(segment.clone()));
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    fn within(&mut self, segment: PathSegment, f: impl FnOnce(&mut Self)) {
+        self.path.push(segment);
+        f(self);
+        self.path.pop();
+    }
+
+    fn class(&mut self, class: &Class<'input>) {
+        let pool = self.pool;
+        for (index, item) in pool.iter_indices() {
+            self.within(PathSegment::ConstantPool { index: index.as_u16() }, |this| {
+                this.check(constant(pool, index, item));
+            });
+        }
+
+        self.within(PathSegment::ClassInfo, |this| {
+            this.check(decode::class_name(pool, class.this_class()));
+            if let Some(super_class) = class.super_class() {
+                this.check(decode::class_name(pool, super_class));
+            }
+        });
+
+        for (index, interface) in (0..).zip(class.interfaces()) {
+            self.within(PathSegment::Interface { index }, |this| {
+                if let Some(interface) = this.check(interface) {
+                    this.check(decode::class_name(pool, interface));
+                }
+            });
+        }
+
+        for (index, field) in (0..).zip(class.fields()) {
+            let Some(field) = self.check(field) else {
+                // the rest of the fields can't be read anymore
+                break;
+            };
+
+            let name = decode::utf8(pool, field.name());
+            let segment = PathSegment::Field {
+                index,
+                name: name.as_ref().ok().cloned(),
+            };
+            self.within(segment, |this| {
+                this.check(name);
+                if let Some(descriptor) = this.check(pool.get(field.descriptor())) {
+                    this.check(TypeDescriptor::parse(descriptor.content));
+                }
+                this.attributes(field.attributes(), None, false);
+            });
+        }
+
+        for (index, method) in (0..).zip(class.methods()) {
+            let Some(method) = self.check(method) else {
+                break;
+            };
+
+            let name = decode::utf8(pool, method.name());
+            let descriptor = decode::utf8(pool, method.descriptor());
+            let segment = PathSegment::Method {
+                index,
+                name: name.as_ref().ok().cloned(),
+                descriptor: descriptor.as_ref().ok().cloned(),
+            };
+            self.within(segment, |this| {
+                this.check(name);
+                if let Some(descriptor) = this.check(descriptor) {
+                    this.check(MethodDescriptor::parse(&descriptor));
+                }
+                this.attributes(method.attributes(), None, true);
+            });
+        }
+
+        self.attributes(class.attributes(), None, false);
+    }
+
+    /// Validates a list of attributes.
+    ///
+    /// The labels have to be passed for the attributes of code.
+    fn attributes(
+        &mut self,
+        attributes: DecodeMany<'input, Attribute<'input>, u16>,
+        mut labels: Option<&mut Labels>,
+        in_method: bool,
+    ) {
+        let pool = self.pool;
+        for (index, attribute) in (0..).zip(attributes) {
+            let Some(attribute) = self.check(attribute) else {
+                // the length of the attribute is unknown, so no other attribute can be read
+                break;
+            };
+
+            let name = decode::utf8(pool, attribute.name());
+            let segment = PathSegment::Attribute {
+                index,
+                name: name.as_ref().ok().cloned(),
+            };
+            self.within(segment, |this| {
+                let Some(name) = this.check(name) else {
+                    return;
+                };
+
+                if in_method && &*name == "Code" {
+                    if let Some(AttributeContent::Code(code)) = this.check(attribute.decode_content(pool)) {
+                        this.code(&code);
+                    }
+                } else {
+                    let mut outside_code = Labels::outside_code();
+                    let labels = labels.as_deref_mut().unwrap_or(&mut outside_code);
+                    this.check(decode::attribute(pool, &attribute, labels, in_method, None));
+                }
+            });
+        }
+    }
+
+    fn code(&mut self, code: &attributes::Code<'input>) {
+        let pool = self.pool;
+        let raw_instructions = code.raw_instructions();
+        let length = u32::try_from(raw_instructions.decoder.bytes_remaining())
+            .map_err(|_| DecodeError::with_context(DecodeErrorKind::InvalidLength, Context::Code));
+        let Some(length) = self.check(length) else {
+            return;
+        };
+
+        // the offsets of all instructions need to be known before any jump can be checked,
+        // so nothing else can be checked if the instructions themselves are invalid
+        let Some(raw_instructions) = self.check(raw_instructions.collect::<Result<Vec<_>, _>>()) else {
+            return;
+        };
+
+        let mut labels = Labels::for_code(raw_instructions.iter().map(|(pc, _)| pc.as_u32()), length);
+        for (pc, instruction) in raw_instructions {
+            self.within(PathSegment::Instruction { pc: pc.as_u32() }, |this| {
+                this.check(decode::instruction(pool, &mut labels, pc, instruction));
+            });
+        }
+
+        for (index, handler) in (0..).zip(code.exception_handlers()) {
+            self.within(PathSegment::ExceptionHandler { index }, |this| {
+                this.check(labels.get(handler.start()));
+                this.check(labels.get(handler.end()));
+                this.check(labels.get(handler.handler()));
+                if let Some(catch_type) = handler.catch_type() {
+                    this.check(decode::class_name(pool, catch_type));
+                }
+            });
+        }
+
+        self.attributes(code.attributes(), Some(&mut labels), false);
+    }
+}
+
+/// Checks the references of a constant pool entry, and whether its descriptor is valid.
+fn constant<'input>(
+    pool: &ConstantPool<'input>,
+    index: cpool::Index<cpool::Item<'input>>,
+    item: &cpool::Item<'input>,
+) -> Result<(), DecodeError> {
+    decode::constant(pool, index)?;
+
+    match item {
+        cpool::Item::FieldRef(item) => {
+            TypeDescriptor::parse(pool.get(pool.get(item.name_and_type)?.descriptor)?.content)?;
+        }
+        cpool::Item::MethodRef(cpool::MethodRef { name_and_type, .. })
+        | cpool::Item::InterfaceMethodRef(cpool::InterfaceMethodRef { name_and_type, .. }) => {
+            MethodDescriptor::parse(pool.get(pool.get(*name_and_type)?.descriptor)?.content)?;
+        }
+        cpool::Item::MethodType(item) => {
+            MethodDescriptor::parse(pool.get(item.descriptor)?.content)?;
+        }
+        cpool::Item::Dynamic(item) => {
+            TypeDescriptor::parse(pool.get(pool.get(item.name_and_type)?.descriptor)?.content)?;
+        }
+        cpool::Item::InvokeDynamic(item) => {
+            MethodDescriptor::parse(pool.get(pool.get(item.name_and_type)?.descriptor)?.content)?;
+        }
+        cpool::Item::MethodHandle(item) => {
+            let reference = pool.get(item.reference)?;
+            let matches = match item.kind {
+                MethodKind::GetField | MethodKind::GetStatic | MethodKind::PutField | MethodKind::PutStatic => {
+                    matches!(reference, cpool::Item::FieldRef(_))
+                }
+                MethodKind::InvokeVirtual | MethodKind::NewInvokeSpecial => {
+                    matches!(reference, cpool::Item::MethodRef(_))
+                }
+                MethodKind::InvokeStatic | MethodKind::InvokeSpecial => {
+                    matches!(
+                        reference,
+                        cpool::Item::MethodRef(_) | cpool::Item::InterfaceMethodRef(_)
+                    )
+                }
+                MethodKind::InvokeInterface => matches!(reference, cpool::Item::InterfaceMethodRef(_)),
+            };
+            if !matches {
+                return Err(DecodeError::with_context(
+                    DecodeErrorKind::TagMismatch,
+                    Context::ConstantPool,
+                ));
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
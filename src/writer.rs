@@ -7,7 +7,7 @@ mod interfaces;
 mod methods;
 
 pub use attributes::{AttributeWriter, AttributeWriterState};
-pub use class::{ClassWriter, ClassWriterState};
+pub use class::{ClassWriter, ClassWriterState, VersionCheck};
 pub use cpool::*;
 pub use encoding::{EncoderContext, ManyWriter};
 pub use fields::*;
@@ -26,6 +26,7 @@ impl<Ctx: EncoderContext> AttributeWriter<Ctx, AttributeWriterState::Start> {
         I: cpool::Insertable<cpool::Utf8>,
     {
         let index = name.insert(&mut self.context)?;
+        self.context.require_attribute(index)?;
         self.context.encoder().write(index)?;
 
         LengthWriter::new(&mut self.context)
@@ -42,6 +43,7 @@ impl<Ctx: EncoderContext> AttributeWriter<Ctx, AttributeWriterState::Start> {
         let length = u32::try_from(bytes.len())
             .map_err(|_| EncodeError::with_context(EncodeErrorKind::TooManyBytes, Context::AttributeContent))?;
         let index = name.insert(&mut self.context)?;
+        self.context.require_attribute(index)?;
         self.context.encoder().write(index)?.write(length)?.write(bytes)?;
 
         Ok(AttributeWriter {
@@ -51,6 +53,28 @@ impl<Ctx: EncoderContext> AttributeWriter<Ctx, AttributeWriterState::Start> {
     }
 }
 
+impl<Ctx: EncoderContext> AttributeWriter<Ctx, AttributeWriterState::Start> {
+    /// Writes an attribute whose content is encoded directly by the passed function.
+    pub(crate) fn content<I, F>(
+        mut self,
+        name: I,
+        f: F,
+    ) -> Result<AttributeWriter<Ctx, AttributeWriterState::End>, EncodeError>
+    where
+        I: cpool::Insertable<cpool::Utf8>,
+        F: FnOnce(&mut Ctx) -> Result<(), EncodeError>,
+    {
+        let length_writer = self.attribute_writer(name)?;
+        f(&mut self.context)?;
+        length_writer.finish(&mut self.context)?;
+
+        Ok(AttributeWriter {
+            context: self.context,
+            _marker: PhantomData,
+        })
+    }
+}
+
 impl<Ctx: EncoderContext> WriteAssembler for AttributeWriter<Ctx, AttributeWriterState::Start> {
     type Context = Ctx;
 
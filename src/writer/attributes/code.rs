@@ -13,6 +13,7 @@ pub use local_variable_type_table::{LocalVariableTypeWriter, LocalVariableTypeWr
 pub use stack_map::StackMapTableWriter;
 
 use crate::error::*;
+use crate::header::Feature;
 use crate::writer::cpool;
 use crate::writer::{
     attributes::{AttributeWriter, AttributeWriterState},
@@ -29,7 +30,9 @@ impl<Ctx: EncoderContext> AttributeWriter<Ctx, AttributeWriterState::Start> {
         ) -> Result<CodeWriter<Ctx, CodeWriterState::End>, EncodeError>,
     {
         let length_writer = self.attribute_writer("Code")?;
-        self.context = f(CodeWriter::new(self.context)?)?.finish()?;
+        self.context = f(CodeWriter::new(self.context)?)
+            .map_err(step("AttributeWriter::code"))?
+            .finish()?;
         length_writer.finish(&mut self.context)?;
 
         Ok(AttributeWriter {
@@ -77,8 +80,11 @@ impl<Ctx: EncoderContext> CodeWriter<Ctx, CodeWriterState::Instructions> {
     {
         let length_writer = LengthWriter::new(&mut self.context)?;
         let mut writer = <InstructionWriter<Ctx> as WriteAssembler>::new(self)?;
-        f(&mut writer)?;
-        self = writer.finish()?;
+        if let Err(err) = f(&mut writer) {
+            let pc = writer.last_instruction_offset();
+            return Err(err.within(PathSegment::Instruction { pc })).map_err(step("CodeWriter::instructions"));
+        }
+        self = writer.finish().map_err(step("CodeWriter::instructions"))?;
         length_writer.finish(&mut self.context)?;
 
         Ok(CodeWriter {
@@ -95,7 +101,7 @@ impl<Ctx: EncoderContext> CodeWriter<Ctx, CodeWriterState::ExceptionTable> {
         F: FnOnce(&mut ManyWriter<ExceptionWriter<Ctx, ExceptionWriterState::Start>, u16>) -> Result<(), EncodeError>,
     {
         let mut builder = ManyWriter::new(self)?;
-        f(&mut builder)?;
+        f(&mut builder).map_err(step("CodeWriter::exceptions"))?;
         self = builder.finish()?;
         Ok(CodeWriter {
             context: self.context,
@@ -116,7 +122,7 @@ impl<Ctx: EncoderContext> CodeWriter<Ctx, CodeWriterState::Attributes> {
         ) -> Result<(), EncodeError>,
     {
         let mut builder = ManyWriter::new(self)?;
-        f(&mut builder)?;
+        f(&mut builder).map_err(step("CodeWriter::attributes"))?;
         self = builder.finish()?;
 
         Ok(CodeWriter {
@@ -135,7 +141,7 @@ impl<Ctx: EncoderContext, State: CodeWriterState::State> CodeWriter<Ctx, State>
         Ok((Label(index), LabelRef(index)))
     }
 
-    fn get_label_position(&self, label: LabelRef) -> Result<u32, EncodeError> {
+    pub(crate) fn get_label_position(&self, label: LabelRef) -> Result<u32, EncodeError> {
         if let Some(pos) = self.label_positions[label.0 as usize] {
             Ok(pos.get() - 1)
         } else {
@@ -143,7 +149,7 @@ impl<Ctx: EncoderContext, State: CodeWriterState::State> CodeWriter<Ctx, State>
         }
     }
 
-    fn get_label_position_u16(&self, label: LabelRef) -> Result<u16, EncodeError> {
+    pub(crate) fn get_label_position_u16(&self, label: LabelRef) -> Result<u16, EncodeError> {
         let position = self.get_label_position(label)?;
         position
             .try_into()
@@ -161,6 +167,14 @@ impl<Ctx: EncoderContext, State: CodeWriterState::State> EncoderContext for Code
     fn insert_constant<I: Into<cpool::Item>>(&mut self, item: I) -> Result<cpool::Index<I>, EncodeError> {
         self.context.insert_constant(item)
     }
+
+    fn require(&mut self, feature: Feature, context: Context) -> Result<(), EncodeError> {
+        self.context.require(feature, context)
+    }
+
+    fn require_attribute(&mut self, name: cpool::Index<cpool::Utf8>) -> Result<(), EncodeError> {
+        self.context.require_attribute(name)
+    }
 }
 
 impl<Ctx: EncoderContext> WriteAssembler for CodeWriter<Ctx, CodeWriterState::MaxStack> {
@@ -208,3 +222,325 @@ impl fmt::Debug for LabelRef {
         f.debug_struct("LabelRef").finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{self, attributes::RawInstruction};
+    use crate::writer::{ClassWriter, ClassWriterState};
+    use crate::{AccessFlags, Version};
+
+    type Ctx = ClassWriter<ClassWriterState::Methods>;
+
+    /// Writes a class with a single method whose code is written by the passed function.
+    fn write_code_in<F>(version: Version, f: F) -> Result<Vec<u8>, EncodeError>
+    where
+        F: FnOnce(
+            CodeWriter<Ctx, CodeWriterState::MaxStack>,
+        ) -> Result<CodeWriter<Ctx, CodeWriterState::End>, EncodeError>,
+    {
+        ClassWriter::new()
+            .version(version)?
+            .access_flags(AccessFlags::PUBLIC | AccessFlags::SUPER)?
+            .this_class("Example")?
+            .super_class("java/lang/Object")?
+            .interfaces(|_| Ok(()))?
+            .fields(|_| Ok(()))?
+            .methods(|methods| {
+                methods.begin(|method| {
+                    method
+                        .access_flags(AccessFlags::STATIC)?
+                        .name("run")?
+                        .descriptor("()V")?
+                        .attributes(|attributes| {
+                            attributes.begin(|attribute| attribute.code(f))?;
+                            Ok(())
+                        })
+                })?;
+                Ok(())
+            })?
+            .attributes(|_| Ok(()))?
+            .into_bytes()
+    }
+
+    /// Writes code consisting of the passed instructions.
+    fn write_instructions<F>(f: F) -> Result<Vec<u8>, EncodeError>
+    where
+        F: for<'f> FnOnce(&'f mut InstructionWriter<Ctx>) -> Result<(), EncodeError>,
+    {
+        write_instructions_in(Version::V8, f)
+    }
+
+    fn write_instructions_in<F>(version: Version, f: F) -> Result<Vec<u8>, EncodeError>
+    where
+        F: for<'f> FnOnce(&'f mut InstructionWriter<Ctx>) -> Result<(), EncodeError>,
+    {
+        write_code_in(version, |code| {
+            code.max_stack(4)?
+                .max_locals(4)?
+                .instructions(f)?
+                .exceptions(|_| Ok(()))?
+                .attributes(|_| Ok(()))
+        })
+    }
+
+    fn read_code(bytes: &[u8]) -> reader::attributes::Code<'_> {
+        let class = reader::Class::new(bytes).unwrap();
+        let method = class.methods().into_iter().next().unwrap().unwrap();
+        method.attributes().find_attribute(class.pool()).unwrap().unwrap()
+    }
+
+    fn read_instructions(bytes: &[u8]) -> Vec<RawInstruction<'_>> {
+        read_code(bytes)
+            .raw_instructions()
+            .map(|instruction| instruction.unwrap().1)
+            .collect()
+    }
+
+    #[test]
+    fn checkcast() {
+        let bytes = write_instructions(|insns| {
+            insns.aconstnull()?.checkcast("java/lang/String")?.pop()?.return_()?;
+            Ok(())
+        })
+        .unwrap();
+        let instructions = read_instructions(&bytes);
+        assert!(matches!(instructions[1], RawInstruction::CheckCast { .. }));
+        assert_eq!(instructions.len(), 4);
+    }
+
+    #[test]
+    fn iinc_wide() {
+        let bytes = write_instructions(|insns| {
+            insns.iinc_wide(300, -1000)?.return_()?;
+            Ok(())
+        })
+        .unwrap();
+        let instructions = read_instructions(&bytes);
+        assert!(matches!(
+            instructions[..],
+            [
+                RawInstruction::IIncW {
+                    index: 300,
+                    value: -1000
+                },
+                RawInstruction::Return
+            ]
+        ));
+    }
+
+    #[test]
+    fn newarray() {
+        use crate::reader::attributes::ArrayType;
+
+        let bytes = write_instructions(|insns| {
+            insns.iconst2()?.newarray(ArrayType::Long)?.pop()?.return_()?;
+            Ok(())
+        })
+        .unwrap();
+        let instructions = read_instructions(&bytes);
+        assert!(matches!(
+            instructions[1],
+            RawInstruction::NewArray { atype: ArrayType::Long }
+        ));
+        assert_eq!(instructions.len(), 4);
+    }
+
+    #[test]
+    fn ret() {
+        // subroutines are only allowed before Java 7
+        let bytes = write_instructions_in(Version::V6, |insns| {
+            insns.ret(1)?.ret_wide(300)?;
+            Ok(())
+        })
+        .unwrap();
+        let instructions = read_instructions(&bytes);
+        assert!(matches!(
+            instructions[..],
+            [RawInstruction::Ret { index: 1 }, RawInstruction::RetW { index: 300 }]
+        ));
+    }
+
+    /// Writes code which pushes a key and switches to one of three returns.
+    fn write_switch<F>(f: F) -> Vec<u8>
+    where
+        F: for<'f> FnOnce(&'f mut InstructionWriter<Ctx>, [LabelRef; 3]) -> Result<(), EncodeError>,
+    {
+        write_instructions(|insns| {
+            let (first, first_ref) = insns.new_label()?;
+            let (second, second_ref) = insns.new_label()?;
+            let (third, third_ref) = insns.new_label()?;
+            insns.iconst0()?;
+            f(insns, [first_ref, second_ref, third_ref])?;
+            insns
+                .label(first)?
+                .return_()?
+                .label(second)?
+                .return_()?
+                .label(third)?
+                .return_()?;
+            Ok(())
+        })
+        .unwrap()
+    }
+
+    /// Returns the position of the switch and the positions of the returns.
+    fn switch_positions(bytes: &[u8]) -> (i32, Vec<i32>) {
+        let code = read_code(bytes);
+        let positions: Vec<_> = code
+            .raw_instructions()
+            .map(|instruction| instruction.unwrap().0.as_u32() as i32)
+            .collect();
+        (positions[1], positions[2..].to_vec())
+    }
+
+    #[test]
+    fn lookupswitch() {
+        let bytes = write_switch(|insns, [first, second, third]| {
+            insns.lookupswitch(|switch| switch.default(first)?.pair(-5, second)?.pair(1000, third))?;
+            Ok(())
+        });
+        let (switch_position, returns) = switch_positions(&bytes);
+        let instructions = read_instructions(&bytes);
+        let RawInstruction::LookupSwitch(switch) = &instructions[1] else {
+            panic!("expected a lookupswitch");
+        };
+        assert_eq!(switch_position + switch.default_offset(), returns[0]);
+        let pairs: Vec<_> = switch
+            .pairs()
+            .map(|pair| (pair.key(), switch_position + pair.offset()))
+            .collect();
+        assert_eq!(pairs, [(-5, returns[1]), (1000, returns[2])]);
+    }
+
+    #[test]
+    fn empty_lookupswitch() {
+        let bytes = write_switch(|insns, [first, ..]| {
+            insns.lookupswitch(|switch| switch.default(first))?;
+            Ok(())
+        });
+        let (switch_position, returns) = switch_positions(&bytes);
+        let instructions = read_instructions(&bytes);
+        let RawInstruction::LookupSwitch(switch) = &instructions[1] else {
+            panic!("expected a lookupswitch");
+        };
+        assert_eq!(switch_position + switch.default_offset(), returns[0]);
+        assert_eq!(switch.pairs().count(), 0);
+        assert_eq!(instructions.len(), 5);
+    }
+
+    #[test]
+    fn tableswitch() {
+        let bytes = write_switch(|insns, [first, second, third]| {
+            insns.tableswitch(|switch| {
+                switch
+                    .default(first)?
+                    .low(10)?
+                    .high(12)?
+                    .jump(second)?
+                    .jump(third)?
+                    .jump(first)
+            })?;
+            Ok(())
+        });
+        let (switch_position, returns) = switch_positions(&bytes);
+        let instructions = read_instructions(&bytes);
+        let RawInstruction::TableSwitch(switch) = &instructions[1] else {
+            panic!("expected a tableswitch");
+        };
+        assert_eq!(switch_position + switch.default_offset(), returns[0]);
+        let pairs: Vec<_> = switch
+            .pairs()
+            .map(|pair| (pair.key(), switch_position + pair.offset()))
+            .collect();
+        assert_eq!(pairs, [(10, returns[1]), (11, returns[2]), (12, returns[0])]);
+    }
+
+    #[test]
+    fn stack_map_table() {
+        use crate::reader::attributes::{StackMapFrame, StackMapTable, VerificationType};
+
+        let mut labels = None;
+        let bytes = write_code_in(Version::V8, |code| {
+            code.max_stack(2)?
+                .max_locals(1)?
+                .instructions(|insns| {
+                    let (first, first_ref) = insns.new_label()?;
+                    let (second, second_ref) = insns.new_label()?;
+                    let (third, third_ref) = insns.new_label()?;
+                    let (new, new_ref) = insns.new_label()?;
+                    let (fourth, fourth_ref) = insns.new_label()?;
+                    labels = Some([first_ref, second_ref, third_ref, new_ref, fourth_ref]);
+                    insns
+                        .label(first)?
+                        .nop()?
+                        .nop()?
+                        .nop()?
+                        .label(second)?
+                        .nop()?
+                        .label(third)?
+                        .label(new)?
+                        .new("java/lang/Object")?
+                        .label(fourth)?
+                        .pop()?
+                        .return_()?;
+                    Ok(())
+                })?
+                .exceptions(|_| Ok(()))?
+                .attributes(|attributes| {
+                    let [first, second, third, new, fourth] = labels.unwrap();
+                    attributes.begin(|attribute| {
+                        attribute.stack_map_table(|frames| {
+                            frames.same(first)?;
+                            frames.same_extended(second)?;
+                            frames.chop(third, 2)?;
+                            frames.full(fourth, |frame| {
+                                frame
+                                    .locals(|locals| {
+                                        locals.begin(|local| local.integer())?;
+                                        Ok(())
+                                    })?
+                                    .stack(|stack| {
+                                        stack.begin(|item| item.uninitialized(new))?;
+                                        Ok(())
+                                    })
+                            })
+                        })
+                    })?;
+                    Ok(())
+                })
+        })
+        .unwrap();
+
+        let class = reader::Class::new(&bytes).unwrap();
+        let method = class.methods().into_iter().next().unwrap().unwrap();
+        let code: reader::attributes::Code<'_> = method.attributes().find_attribute(class.pool()).unwrap().unwrap();
+        let table: StackMapTable<'_> = code.attributes().find_attribute(class.pool()).unwrap().unwrap();
+        let frames: Vec<_> = table.iter().map(Result::unwrap).collect();
+        let positions: Vec<_> = frames.iter().map(|(index, _)| index.as_u32()).collect();
+        assert_eq!(positions, [0, 3, 4, 7]);
+        assert!(matches!(frames[1].1, StackMapFrame::SameExtended));
+        assert!(matches!(frames[2].1, StackMapFrame::Chop { to_chop: 2 }));
+        let StackMapFrame::Full { locals, stack } = &frames[3].1 else {
+            panic!("expected a full frame");
+        };
+        assert!(matches!(locals.clone().next(), Some(Ok(VerificationType::Integer))));
+        let stack: Vec<_> = stack.clone().map(Result::unwrap).collect();
+        assert!(matches!(stack[..], [VerificationType::UninitializedVariable(index)] if index.as_u32() == 4));
+    }
+
+    #[test]
+    fn jump_too_far() {
+        let error = write_instructions(|insns| {
+            let (target, target_ref) = insns.new_label()?;
+            insns.goto(target_ref)?;
+            for _ in 0..40_000 {
+                insns.nop()?;
+            }
+            insns.label(target)?.return_()?;
+            Ok(())
+        })
+        .unwrap_err();
+        assert!(matches!(error.kind(), EncodeErrorKind::LabelTooFar));
+    }
+}
@@ -61,6 +61,16 @@ impl<Ctx: EncoderContext> ExceptionWriter<Ctx, ExceptionWriterState::CatchType>
             _marker: PhantomData,
         })
     }
+
+    /// Makes this handler catch every exception, as is done for `finally` blocks.
+    pub fn catch_all(mut self) -> Result<ExceptionWriter<Ctx, ExceptionWriterState::End>, EncodeError> {
+        self.context.encoder().write(0u16)?;
+
+        Ok(ExceptionWriter {
+            context: self.context,
+            _marker: PhantomData,
+        })
+    }
 }
 
 impl<Ctx: EncoderContext> WriteAssembler for ExceptionWriter<Ctx, ExceptionWriterState::Start> {
@@ -5,7 +5,11 @@ pub use lookupswitch::{LookupSwitchWriter, LookupSwitchWriterState};
 pub use tableswitch::{TableSwitchWriter, TableSwitchWriterState};
 
 use crate::error::*;
-use crate::reader::{attributes::RawInstruction, decoding::*};
+use crate::header::Feature;
+use crate::reader::{
+    attributes::{ArrayType, RawInstruction},
+    decoding::*,
+};
 use crate::writer::{attributes::code::*, cpool, encoding::*};
 
 pub struct InstructionWriter<Ctx> {
@@ -23,6 +27,26 @@ impl<Ctx: EncoderContext> InstructionWriter<Ctx> {
         self.code_writer.new_label()
     }
 
+    /// Returns the offset of the instruction which was written last, even if it was not written completely.
+    pub(crate) fn last_instruction_offset(&mut self) -> u32 {
+        let start_offset = self.start_offset;
+        let len = self.current_offset().get();
+        let mut decoder = Decoder::new(self.code_writer.encoder().buf(), Context::Code);
+        decoder
+            .advance(start_offset.get())
+            .expect("decoder failed to skip to the code");
+
+        let mut offset = 0;
+        while offset < len {
+            let prev_rem = decoder.bytes_remaining();
+            if RawInstruction::decode(&mut decoder, start_offset.get()).is_err() {
+                break;
+            }
+            offset += prev_rem - decoder.bytes_remaining();
+        }
+        offset as u32
+    }
+
     pub fn label(&mut self, label: Label) -> Result<&mut Self, EncodeError> {
         let offset = self
             .current_offset()
@@ -160,12 +184,12 @@ impl<Ctx: EncoderContext> InstructionWriter<Ctx> {
         Ok(self)
     }
 
-    pub fn checkcast<I>(&mut self, array_type: I) -> Result<&mut Self, EncodeError>
+    pub fn checkcast<I>(&mut self, type_: I) -> Result<&mut Self, EncodeError>
     where
         I: cpool::Insertable<cpool::Class>,
     {
-        let index = array_type.insert(&mut self.code_writer)?;
-        self.code_writer.encoder().write(0xbdu8)?.write(index)?;
+        let index = type_.insert(&mut self.code_writer)?;
+        self.code_writer.encoder().write(0xc0u8)?.write(index)?;
         Ok(self)
     }
 
@@ -755,10 +779,10 @@ impl<Ctx: EncoderContext> InstructionWriter<Ctx> {
         Ok(self)
     }
 
-    pub fn iinc_wide(&mut self, index: u16, value: i64) -> Result<&mut Self, EncodeError> {
+    pub fn iinc_wide(&mut self, index: u16, value: i16) -> Result<&mut Self, EncodeError> {
         self.code_writer
             .encoder()
-            .write(0x4fu8)?
+            .write(0xc4u8)?
             .write(0x84u8)?
             .write(index)?
             .write(value)?;
@@ -941,6 +965,7 @@ impl<Ctx: EncoderContext> InstructionWriter<Ctx> {
 
     pub fn jsr(&mut self, label: LabelRef) -> Result<&mut Self, EncodeError> {
         if let Ok(i) = u16::try_from(label.0) {
+            self.code_writer.require(Feature::Subroutines, Context::Code)?;
             self.code_writer.encoder().write(0xa8u8)?.write(i)?;
             Ok(self)
         } else {
@@ -949,6 +974,7 @@ impl<Ctx: EncoderContext> InstructionWriter<Ctx> {
     }
 
     pub fn jsrw(&mut self, label: LabelRef) -> Result<&mut Self, EncodeError> {
+        self.code_writer.require(Feature::Subroutines, Context::Code)?;
         self.code_writer.encoder().write(0xc9u8)?.write(label.0)?;
         Ok(self)
     }
@@ -1190,12 +1216,8 @@ impl<Ctx: EncoderContext> InstructionWriter<Ctx> {
         Ok(self)
     }
 
-    pub fn newarray<I>(&mut self, array_type: I) -> Result<&mut Self, EncodeError>
-    where
-        I: cpool::Insertable<cpool::Class>,
-    {
-        let index = array_type.insert(&mut self.code_writer)?;
-        self.code_writer.encoder().write(0xbcu8)?.write(index)?;
+    pub fn newarray(&mut self, array_type: ArrayType) -> Result<&mut Self, EncodeError> {
+        self.code_writer.encoder().write(0xbcu8)?.write(array_type as u8)?;
         Ok(self)
     }
 
@@ -1232,12 +1254,14 @@ impl<Ctx: EncoderContext> InstructionWriter<Ctx> {
         Ok(self)
     }
 
-    pub fn ret<I>(&mut self, index: u8) -> Result<&mut Self, EncodeError> {
+    pub fn ret(&mut self, index: u8) -> Result<&mut Self, EncodeError> {
+        self.code_writer.require(Feature::Subroutines, Context::Code)?;
         self.code_writer.encoder().write(0xa9u8)?.write(index)?;
         Ok(self)
     }
 
-    pub fn ret_wide<I>(&mut self, index: u16) -> Result<&mut Self, EncodeError> {
+    pub fn ret_wide(&mut self, index: u16) -> Result<&mut Self, EncodeError> {
+        self.code_writer.require(Feature::Subroutines, Context::Code)?;
         self.code_writer.encoder().write(0xc4u8)?.write(0xa9u8)?.write(index)?;
         Ok(self)
     }
@@ -1262,6 +1286,11 @@ impl<Ctx: EncoderContext> InstructionWriter<Ctx> {
         Ok(self)
     }
 
+    pub fn swap(&mut self) -> Result<&mut Self, EncodeError> {
+        self.code_writer.encoder().write(0x5fu8)?;
+        Ok(self)
+    }
+
     pub fn tableswitch<F>(&mut self, f: F) -> Result<&mut Self, EncodeError>
     where
         F: for<'f> FnOnce(
@@ -1313,14 +1342,19 @@ impl<Ctx: EncoderContext> WriteDisassembler for InstructionWriter<Ctx> {
                 ($jump_offset:expr) => {{
                     let label_position = self
                         .code_writer
-                        .get_label_position(LabelRef($jump_offset as u16 as u32))?;
+                        .get_label_position(LabelRef($jump_offset as u16 as u32))
+                        .map_err(|err| err.within(PathSegment::Instruction { pc: offset as u32 }))?;
                     let label_offset = label_position as i64 - offset as i64;
 
                     if let Ok(i) = i16::try_from(label_offset) {
                         let mut encoder = self.code_writer.encoder().replacing(instruction_start.offset(1));
                         encoder.write(i)?;
                     } else {
-                        unimplemented!("noak does not support changing jump offset sizes yet");
+                        // noak does not support changing jump offset sizes yet
+                        return Err(
+                            EncodeError::with_context(EncodeErrorKind::LabelTooFar, Context::Code)
+                                .within(PathSegment::Instruction { pc: offset as u32 }),
+                        );
                     }
                 }};
             }
@@ -1330,7 +1364,10 @@ impl<Ctx: EncoderContext> WriteDisassembler for InstructionWriter<Ctx> {
                     let bytes = &self.code_writer.encoder().buf()[$read_offset.get()..];
                     let mut decoder = Decoder::new(bytes, Context::Code);
                     let label_index = LabelRef(decoder.read::<u32>().unwrap());
-                    let label_position = self.code_writer.get_label_position(label_index)?;
+                    let label_position = self
+                        .code_writer
+                        .get_label_position(label_index)
+                        .map_err(|err| err.within(PathSegment::Instruction { pc: offset as u32 }))?;
                     let label_offset = label_position as i64 - offset as i64;
 
                     let mut encoder = self.code_writer.encoder().replacing($read_offset);
@@ -1426,8 +1463,8 @@ impl<Ctx: EncoderContext> WriteDisassembler for InstructionWriter<Ctx> {
 
                     // skip default, low and high
                     let offset_pair_start = offset_default.offset(4 + 4 + 4);
-                    for i in low..=high {
-                        jmp_i32!(offset_pair_start.offset(i as usize * 4));
+                    for i in 0..=(i64::from(high) - i64::from(low)) as usize {
+                        jmp_i32!(offset_pair_start.offset(i * 4));
                     }
                 }
                 _ => {}
@@ -1449,6 +1486,14 @@ impl<Ctx: EncoderContext> EncoderContext for InstructionWriter<Ctx> {
     fn insert_constant<I: Into<cpool::Item>>(&mut self, item: I) -> Result<cpool::Index<I>, EncodeError> {
         self.code_writer.insert_constant(item)
     }
+
+    fn require(&mut self, feature: Feature, context: Context) -> Result<(), EncodeError> {
+        self.code_writer.require(feature, context)
+    }
+
+    fn require_attribute(&mut self, name: cpool::Index<cpool::Utf8>) -> Result<(), EncodeError> {
+        self.code_writer.require_attribute(name)
+    }
 }
 
 impl<Ctx> fmt::Debug for InstructionWriter<Ctx> {
@@ -16,7 +16,8 @@ impl<'a, Ctx: EncoderContext> LookupSwitchWriter<'a, Ctx, LookupSwitchWriterStat
         self,
         label: LabelRef,
     ) -> Result<LookupSwitchWriter<'a, Ctx, LookupSwitchWriterState::Jumps>, EncodeError> {
-        self.context.encoder().write(label.0)?;
+        // the pair count is updated whenever a pair is written
+        self.context.encoder().write(label.0)?.write(0u32)?;
 
         Ok(LookupSwitchWriter {
             context: self.context,
@@ -31,7 +32,7 @@ impl<'a, Ctx: EncoderContext> LookupSwitchWriter<'a, Ctx, LookupSwitchWriterStat
 impl<'a, Ctx: EncoderContext> LookupSwitchWriter<'a, Ctx, LookupSwitchWriterState::Jumps> {
     /// Write a key-label pair, where the keys must be written in an increasing numerical order.
     pub fn pair(mut self, key: i32, label: LabelRef) -> Result<Self, EncodeError> {
-        if self.last_key.map_or(false, |last_key| last_key >= key) {
+        if self.last_key.is_some_and(|last_key| last_key >= key) {
             return Err(EncodeError::with_context(
                 EncodeErrorKind::InvalidKeyOrder,
                 Context::Code,
@@ -42,11 +43,7 @@ impl<'a, Ctx: EncoderContext> LookupSwitchWriter<'a, Ctx, LookupSwitchWriterStat
             .count
             .checked_add(1)
             .ok_or_else(|| EncodeError::with_context(EncodeErrorKind::TooManyItems, Context::Code))?;
-        if self.count == 1 {
-            self.context.encoder().write(self.count)?;
-        } else {
-            self.context.encoder().replacing(self.count_offset).write(self.count)?;
-        }
+        self.context.encoder().replacing(self.count_offset).write(self.count)?;
 
         self.context.encoder().write(key)?.write(label.0)?;
 
@@ -52,7 +52,8 @@ impl<'a, Ctx: EncoderContext> TableSwitchWriter<'a, Ctx, TableSwitchWriterState:
             ));
         }
 
-        self.remaining = (high - low + 1) as u32;
+        self.remaining = u32::try_from(i64::from(high) - i64::from(low) + 1)
+            .map_err(|_| EncodeError::with_context(EncodeErrorKind::TooManyItems, Context::Code))?;
 
         Ok(TableSwitchWriter {
             context: self.context,
@@ -22,7 +22,7 @@ impl<Ctx: EncoderContext> AttributeWriter<CodeWriter<Ctx, CodeWriterState::Attri
 
         let mut writer = StackMapTableWriter {
             context: self.context,
-            last_position: 0,
+            last_position: None,
             count: 0,
         };
         f(&mut writer)?;
@@ -41,25 +41,25 @@ impl<Ctx: EncoderContext> AttributeWriter<CodeWriter<Ctx, CodeWriterState::Attri
 
 pub struct StackMapTableWriter<Ctx> {
     context: CodeWriter<Ctx, CodeWriterState::Attributes>,
-    last_position: u32,
+    /// The position of the previously written frame, `None` if no frame was written yet.
+    last_position: Option<u32>,
     count: u16,
 }
 
 impl<Ctx: EncoderContext> StackMapTableWriter<Ctx> {
     pub fn same(&mut self, label: LabelRef) -> Result<(), EncodeError> {
-        let offset = self.get_label_offset(label)?;
+        let (_, offset) = self.get_label_offset(label)?;
         if offset >= 64 {
             return self.same_extended(label);
         }
 
-        self.increment_counter()?;
+        let offset = self.begin_frame(label)?;
         self.context.encoder().write(offset as u8)?;
         Ok(())
     }
 
     pub fn same_extended(&mut self, label: LabelRef) -> Result<(), EncodeError> {
-        let offset = self.get_label_offset(label)?;
-        self.increment_counter()?;
+        let offset = self.begin_frame(label)?;
         self.context.encoder().write(251u8)?.write(offset)?;
         Ok(())
     }
@@ -70,12 +70,12 @@ impl<Ctx: EncoderContext> StackMapTableWriter<Ctx> {
             Same1Writer<'ctx, Ctx, Same1WriterState::Start>,
         ) -> Result<Same1Writer<'ctx, Ctx, Same1WriterState::End>, EncodeError>,
     {
-        let offset = self.get_label_offset(label)?;
+        let (_, offset) = self.get_label_offset(label)?;
         if offset >= 64 {
             return self.same1_extended(label, f);
         }
 
-        self.increment_counter()?;
+        let offset = self.begin_frame(label)?;
         self.context.encoder().write(64 + offset as u8)?;
 
         f(Same1Writer::new(&mut self.context)?)?.finish()?;
@@ -89,8 +89,7 @@ impl<Ctx: EncoderContext> StackMapTableWriter<Ctx> {
             Same1Writer<'ctx, Ctx, Same1WriterState::Start>,
         ) -> Result<Same1Writer<'ctx, Ctx, Same1WriterState::End>, EncodeError>,
     {
-        let offset = self.get_label_offset(label)?;
-        self.increment_counter()?;
+        let offset = self.begin_frame(label)?;
         self.context.encoder().write(247u8)?.write(offset)?;
 
         f(Same1Writer::new(&mut self.context)?)?.finish()?;
@@ -98,7 +97,7 @@ impl<Ctx: EncoderContext> StackMapTableWriter<Ctx> {
         Ok(())
     }
 
-    pub fn chop(&mut self, label: LabelRef, count: u16) -> Result<(), EncodeError> {
+    pub fn chop(&mut self, label: LabelRef, count: u8) -> Result<(), EncodeError> {
         if count == 0 || count > 3 {
             return Err(EncodeError::with_context(
                 EncodeErrorKind::TooManyItems,
@@ -106,8 +105,7 @@ impl<Ctx: EncoderContext> StackMapTableWriter<Ctx> {
             ));
         }
 
-        let offset = self.get_label_offset(label)?;
-        self.increment_counter()?;
+        let offset = self.begin_frame(label)?;
         self.context.encoder().write(251 - count)?.write(offset)?;
 
         Ok(())
@@ -117,8 +115,7 @@ impl<Ctx: EncoderContext> StackMapTableWriter<Ctx> {
     where
         F: for<'ctx> FnOnce(AppendWriter<'ctx, Ctx>) -> Result<AppendWriter<'ctx, Ctx>, EncodeError>,
     {
-        let offset = self.get_label_offset(label)?;
-        self.increment_counter()?;
+        let offset = self.begin_frame(label)?;
 
         let type_offset = self.context.encoder().position();
 
@@ -142,8 +139,7 @@ impl<Ctx: EncoderContext> StackMapTableWriter<Ctx> {
             FullWriter<'ctx, Ctx, FullWriterState::Locals>,
         ) -> Result<FullWriter<'ctx, Ctx, FullWriterState::End>, EncodeError>,
     {
-        let offset = self.get_label_offset(label)?;
-        self.increment_counter()?;
+        let offset = self.begin_frame(label)?;
 
         self.context.encoder().write(255u8)?.write(offset)?;
 
@@ -152,17 +148,31 @@ impl<Ctx: EncoderContext> StackMapTableWriter<Ctx> {
         Ok(())
     }
 
-    fn get_label_offset(&self, label: LabelRef) -> Result<u16, EncodeError> {
+    /// Returns the position of the label and its offset delta to the previous frame.
+    fn get_label_offset(&self, label: LabelRef) -> Result<(u32, u16), EncodeError> {
         let position = self.context.get_label_position(label)?;
-        if position < self.last_position {
-            return Err(EncodeError::with_context(
-                EncodeErrorKind::NegativeOffset,
-                Context::AttributeContent,
-            ));
-        }
-        let offset = u16::try_from(position - self.last_position)
+        let delta = match self.last_position {
+            // every frame except the first one is located at `last_position + offset_delta + 1`
+            Some(last_position) if position <= last_position => {
+                return Err(EncodeError::with_context(
+                    EncodeErrorKind::NegativeOffset,
+                    Context::AttributeContent,
+                ));
+            }
+            Some(last_position) => position - last_position - 1,
+            None => position,
+        };
+        let delta = u16::try_from(delta)
             .map_err(|_| EncodeError::with_context(EncodeErrorKind::LabelTooFar, Context::AttributeContent))?;
-        Ok(offset)
+        Ok((position, delta))
+    }
+
+    /// Registers a new frame at the position of the label and returns its offset delta.
+    fn begin_frame(&mut self, label: LabelRef) -> Result<u16, EncodeError> {
+        let (position, delta) = self.get_label_offset(label)?;
+        self.increment_counter()?;
+        self.last_position = Some(position);
+        Ok(delta)
     }
 
     fn increment_counter(&mut self) -> Result<(), EncodeError> {
@@ -272,7 +282,7 @@ impl<'ctx, Ctx: EncoderContext> VerificationTypeWriter<'ctx, Ctx, VerificationTy
         self,
         label: LabelRef,
     ) -> Result<VerificationTypeWriter<'ctx, Ctx, VerificationTypeWriterState::End>, EncodeError> {
-        let offset = self.context.get_label_position(label)?;
+        let offset = self.context.get_label_position_u16(label)?;
         self.context.encoder().write(8u8)?.write(offset)?;
 
         Ok(VerificationTypeWriter {
@@ -422,7 +432,7 @@ pub struct FullWriter<'ctx, Ctx, State: FullWriterState::State> {
 }
 
 impl<'ctx, Ctx: EncoderContext> FullWriter<'ctx, Ctx, FullWriterState::Locals> {
-    pub fn locals<F>(mut self, f: F) -> Result<FullWriter<'ctx, Ctx, FullWriterState::End>, EncodeError>
+    pub fn locals<F>(mut self, f: F) -> Result<FullWriter<'ctx, Ctx, FullWriterState::Stack>, EncodeError>
     where
         F: FnOnce(
             &mut ManyWriter<VerificationTypeWriter<'ctx, Ctx, VerificationTypeWriterState::Start>, u16>,
@@ -69,10 +69,7 @@ impl<Ctx: EncoderContext> InnerClassWriter<Ctx, InnerClassWriterState::OuterClas
         })
     }
 
-    pub fn no_outer_class<I>(mut self) -> Result<InnerClassWriter<Ctx, InnerClassWriterState::InnerName>, EncodeError>
-    where
-        I: cpool::Insertable<cpool::Class>,
-    {
+    pub fn no_outer_class(mut self) -> Result<InnerClassWriter<Ctx, InnerClassWriterState::InnerName>, EncodeError> {
         self.context.encoder().write(0u16)?;
 
         Ok(InnerClassWriter {
@@ -99,12 +96,9 @@ impl<Ctx: EncoderContext> InnerClassWriter<Ctx, InnerClassWriterState::InnerName
         })
     }
 
-    pub fn no_inner_name<I>(
+    pub fn no_inner_name(
         mut self,
-    ) -> Result<InnerClassWriter<Ctx, InnerClassWriterState::InnerAccessFlags>, EncodeError>
-    where
-        I: cpool::Insertable<cpool::Utf8>,
-    {
+    ) -> Result<InnerClassWriter<Ctx, InnerClassWriterState::InnerAccessFlags>, EncodeError> {
         self.context.encoder().write(0u16)?;
 
         Ok(InnerClassWriter {
@@ -154,3 +148,53 @@ impl<Ctx, State: InnerClassWriterState::State> fmt::Debug for InnerClassWriter<C
 }
 
 enc_state!(pub mod InnerClassWriterState: InnerClass, OuterClass, InnerName, InnerAccessFlags, End);
+
+#[cfg(test)]
+mod tests {
+    use crate::reader::{self, attributes::InnerClasses};
+    use crate::writer::ClassWriter;
+    use crate::{AccessFlags, Version};
+
+    #[test]
+    fn anonymous_class() {
+        let bytes = ClassWriter::new()
+            .version(Version::V8)
+            .unwrap()
+            .access_flags(AccessFlags::PUBLIC | AccessFlags::SUPER)
+            .unwrap()
+            .this_class("Example$1")
+            .unwrap()
+            .super_class("java/lang/Object")
+            .unwrap()
+            .interfaces(|_| Ok(()))
+            .unwrap()
+            .fields(|_| Ok(()))
+            .unwrap()
+            .methods(|_| Ok(()))
+            .unwrap()
+            .attributes(|attributes| {
+                attributes.begin(|attribute| {
+                    attribute.inner_classes(|classes| {
+                        classes.begin(|class| {
+                            class
+                                .inner_class("Example$1")?
+                                .no_outer_class()?
+                                .no_inner_name()?
+                                .inner_access_flags(AccessFlags::empty())
+                        })?;
+                        Ok(())
+                    })
+                })?;
+                Ok(())
+            })
+            .unwrap()
+            .into_bytes()
+            .unwrap();
+
+        let class = reader::Class::new(&bytes).unwrap();
+        let inner_classes: InnerClasses<'_> = class.attributes().find_attribute(class.pool()).unwrap().unwrap();
+        let inner_class = inner_classes.classes().into_iter().next().unwrap().unwrap();
+        assert!(inner_class.outer_class().is_none());
+        assert!(inner_class.inner_name().is_none());
+    }
+}
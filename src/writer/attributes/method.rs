@@ -33,13 +33,17 @@ pub struct ExceptionWriter<Ctx, State: ExceptionWriterState::State> {
 
 impl<Ctx: EncoderContext> ExceptionWriter<Ctx, ExceptionWriterState::Start> {
     /// Writes the index to an exception able to be thrown by this method.
-    pub fn exception<I>(&mut self, name: I) -> Result<&mut Self, EncodeError>
+    pub fn exception<I>(mut self, name: I) -> Result<ExceptionWriter<Ctx, ExceptionWriterState::End>, EncodeError>
     where
         I: cpool::Insertable<cpool::Class>,
     {
         let index = name.insert(&mut self.context)?;
         self.context.encoder().write(index)?;
-        Ok(self)
+
+        Ok(ExceptionWriter {
+            context: self.context,
+            _marker: PhantomData,
+        })
     }
 }
 
@@ -69,3 +73,70 @@ impl<Ctx, State: ExceptionWriterState::State> fmt::Debug for ExceptionWriter<Ctx
 }
 
 enc_state!(pub mod ExceptionWriterState: Start, End);
+
+#[cfg(test)]
+mod tests {
+    use crate::reader::{self, attributes::Exceptions};
+    use crate::writer::ClassWriter;
+    use crate::{AccessFlags, Version};
+
+    #[test]
+    fn exceptions() {
+        let bytes = ClassWriter::new()
+            .version(Version::V8)
+            .unwrap()
+            .access_flags(AccessFlags::PUBLIC | AccessFlags::INTERFACE | AccessFlags::ABSTRACT)
+            .unwrap()
+            .this_class("Example")
+            .unwrap()
+            .super_class("java/lang/Object")
+            .unwrap()
+            .interfaces(|_| Ok(()))
+            .unwrap()
+            .fields(|_| Ok(()))
+            .unwrap()
+            .methods(|methods| {
+                methods.begin(|method| {
+                    method
+                        .access_flags(AccessFlags::PUBLIC | AccessFlags::ABSTRACT)?
+                        .name("close")?
+                        .descriptor("()V")?
+                        .attributes(|attributes| {
+                            attributes.begin(|attribute| {
+                                attribute.exceptions(|exceptions| {
+                                    exceptions.begin(|exception| exception.exception("java/io/IOException"))?;
+                                    exceptions
+                                        .begin(|exception| exception.exception("java/lang/InterruptedException"))?;
+                                    Ok(())
+                                })
+                            })?;
+                            Ok(())
+                        })
+                })?;
+                Ok(())
+            })
+            .unwrap()
+            .attributes(|_| Ok(()))
+            .unwrap()
+            .into_bytes()
+            .unwrap();
+
+        let class = reader::Class::new(&bytes).unwrap();
+        let method = class.methods().into_iter().next().unwrap().unwrap();
+        let exceptions: Exceptions<'_> = method.attributes().find_attribute(class.pool()).unwrap().unwrap();
+        let names: Vec<_> = exceptions
+            .exceptions()
+            .into_iter()
+            .map(|exception| {
+                class
+                    .pool()
+                    .retrieve(exception.unwrap())
+                    .unwrap()
+                    .name
+                    .to_str()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(names, ["java/io/IOException", "java/lang/InterruptedException"]);
+    }
+}
@@ -2,7 +2,7 @@ use std::marker::PhantomData;
 use std::{fmt, io};
 
 use crate::error::*;
-use crate::header::{AccessFlags, Version};
+use crate::header::{AccessFlags, Feature, Version};
 use crate::writer::{
     attributes::{AttributeWriter, AttributeWriterState},
     cpool::{self, ConstantPool},
@@ -40,9 +40,26 @@ pub struct ClassWriter<State: ClassWriterState::State> {
     start_encoder: VecEncoder,
     encoder: VecEncoder,
     pool: ConstantPool,
+    version: Version,
+    version_check: VersionCheck,
+    warnings: Vec<Feature>,
+    /// The access flags of the class, which are empty until they are written.
+    access_flags: AccessFlags,
     _marker: PhantomData<State>,
 }
 
+/// How a [`ClassWriter`] handles features which are not legal in the version of the class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VersionCheck {
+    /// Fails with an [`UnsupportedFeature`](EncodeErrorKind::UnsupportedFeature) error.
+    #[default]
+    Reject,
+    /// Writes the feature anyway and records it as a warning, see [`ClassWriter::warnings`].
+    Warn,
+    /// Writes the feature anyway.
+    Ignore,
+}
+
 impl Default for ClassWriter<ClassWriterState::Start> {
     fn default() -> ClassWriter<ClassWriterState::Start> {
         ClassWriter::new()
@@ -57,10 +74,44 @@ impl ClassWriter<ClassWriterState::Start> {
             start_encoder: VecEncoder::new(Vec::with_capacity(1024)),
             encoder: VecEncoder::new(Vec::with_capacity(1024)),
             pool: ConstantPool::new(),
+            version: Version::latest(),
+            version_check: VersionCheck::Reject,
+            warnings: Vec::new(),
+            access_flags: AccessFlags::empty(),
             _marker: PhantomData,
         }
     }
 
+    /// Sets how features which are not legal in the version of the class are handled.
+    ///
+    /// By default, they are rejected.
+    ///
+    /// ```
+    /// use noak::writer::{ClassWriter, VersionCheck};
+    /// use noak::{AccessFlags, Feature, Version};
+    ///
+    /// let writer = ClassWriter::new()
+    ///     .version_check(VersionCheck::Warn)
+    ///     .version(Version::V8)?
+    ///     .access_flags(AccessFlags::PUBLIC | AccessFlags::SUPER)?
+    ///     .this_class("Example")?
+    ///     .super_class("java/lang/Record")?
+    ///     .interfaces(|_| Ok(()))?
+    ///     .fields(|_| Ok(()))?
+    ///     .methods(|_| Ok(()))?
+    ///     .attributes(|attributes| {
+    ///         attributes.begin(|attribute| attribute.raw_attribute("Record", &[0, 0]))?;
+    ///         Ok(())
+    ///     })?;
+    /// assert_eq!(writer.warnings(), [Feature::Attribute("Record")]);
+    /// # Ok::<(), noak::error::EncodeError>(())
+    /// ```
+    #[must_use]
+    pub fn version_check(mut self, version_check: VersionCheck) -> ClassWriter<ClassWriterState::Start> {
+        self.version_check = version_check;
+        self
+    }
+
     pub fn version(mut self, version: Version) -> Result<ClassWriter<ClassWriterState::AccessFlags>, EncodeError> {
         self.start_encoder.write(0xCAFE_BABEu32)?;
         self.start_encoder.write(version.minor)?;
@@ -68,11 +119,16 @@ impl ClassWriter<ClassWriterState::Start> {
 
         // constant pool length
         self.start_encoder.write(1u16)?;
+        self.version = version;
 
         Ok(ClassWriter {
             start_encoder: self.start_encoder,
             encoder: self.encoder,
             pool: self.pool,
+            version: self.version,
+            version_check: self.version_check,
+            warnings: self.warnings,
+            access_flags: self.access_flags,
             _marker: PhantomData,
         })
     }
@@ -80,12 +136,22 @@ impl ClassWriter<ClassWriterState::Start> {
 
 impl ClassWriter<ClassWriterState::AccessFlags> {
     pub fn access_flags(mut self, flags: AccessFlags) -> Result<ClassWriter<ClassWriterState::ThisClass>, EncodeError> {
+        if flags.contains(AccessFlags::MODULE) {
+            self.require(Feature::Modules, Context::ClassInfo)?;
+        }
+        if flags.intersects(AccessFlags::SYNTHETIC | AccessFlags::ANNOTATION | AccessFlags::ENUM) {
+            self.require(Feature::ExtendedAccessFlags, Context::ClassInfo)?;
+        }
         self.encoder.write(flags)?;
 
         Ok(ClassWriter {
             start_encoder: self.start_encoder,
             encoder: self.encoder,
             pool: self.pool,
+            version: self.version,
+            version_check: self.version_check,
+            warnings: self.warnings,
+            access_flags: flags,
             _marker: PhantomData,
         })
     }
@@ -96,13 +162,17 @@ impl ClassWriter<ClassWriterState::ThisClass> {
     where
         I: cpool::Insertable<cpool::Class>,
     {
-        let index = name.insert(&mut self)?;
+        let index = name.insert(&mut self).map_err(step("ClassWriter::this_class"))?;
         self.encoder.write(index)?;
 
         Ok(ClassWriter {
             start_encoder: self.start_encoder,
             encoder: self.encoder,
             pool: self.pool,
+            version: self.version,
+            version_check: self.version_check,
+            warnings: self.warnings,
+            access_flags: self.access_flags,
             _marker: PhantomData,
         })
     }
@@ -113,13 +183,17 @@ impl ClassWriter<ClassWriterState::SuperClass> {
     where
         I: cpool::Insertable<cpool::Class>,
     {
-        let index = name.insert(&mut self)?;
+        let index = name.insert(&mut self).map_err(step("ClassWriter::super_class"))?;
         self.encoder.write(index)?;
 
         Ok(ClassWriter {
             start_encoder: self.start_encoder,
             encoder: self.encoder,
             pool: self.pool,
+            version: self.version,
+            version_check: self.version_check,
+            warnings: self.warnings,
+            access_flags: self.access_flags,
             _marker: PhantomData,
         })
     }
@@ -131,6 +205,10 @@ impl ClassWriter<ClassWriterState::SuperClass> {
             start_encoder: self.start_encoder,
             encoder: self.encoder,
             pool: self.pool,
+            version: self.version,
+            version_check: self.version_check,
+            warnings: self.warnings,
+            access_flags: self.access_flags,
             _marker: PhantomData,
         })
     }
@@ -142,13 +220,17 @@ impl ClassWriter<ClassWriterState::Interfaces> {
         F: FnOnce(&mut ManyWriter<InterfaceWriter<InterfaceWriterState::Start>, u16>) -> Result<(), EncodeError>,
     {
         let mut builder = ManyWriter::new(self)?;
-        f(&mut builder)?;
+        f(&mut builder).map_err(step("ClassWriter::interfaces"))?;
         self = builder.finish()?;
 
         Ok(ClassWriter {
             start_encoder: self.start_encoder,
             encoder: self.encoder,
             pool: self.pool,
+            version: self.version,
+            version_check: self.version_check,
+            warnings: self.warnings,
+            access_flags: self.access_flags,
             _marker: PhantomData,
         })
     }
@@ -160,13 +242,17 @@ impl ClassWriter<ClassWriterState::Fields> {
         F: FnOnce(&mut ManyWriter<FieldWriter<FieldWriterState::AccessFlags>, u16>) -> Result<(), EncodeError>,
     {
         let mut builder = ManyWriter::new(self)?;
-        f(&mut builder)?;
+        f(&mut builder).map_err(step("ClassWriter::fields"))?;
         self = builder.finish()?;
 
         Ok(ClassWriter {
             start_encoder: self.start_encoder,
             encoder: self.encoder,
             pool: self.pool,
+            version: self.version,
+            version_check: self.version_check,
+            warnings: self.warnings,
+            access_flags: self.access_flags,
             _marker: PhantomData,
         })
     }
@@ -178,13 +264,17 @@ impl ClassWriter<ClassWriterState::Methods> {
         F: FnOnce(&mut ManyWriter<MethodWriter<MethodWriterState::AccessFlags>, u16>) -> Result<(), EncodeError>,
     {
         let mut builder = ManyWriter::new(self)?;
-        f(&mut builder)?;
+        f(&mut builder).map_err(step("ClassWriter::methods"))?;
         self = builder.finish()?;
 
         Ok(ClassWriter {
             start_encoder: self.start_encoder,
             encoder: self.encoder,
             pool: self.pool,
+            version: self.version,
+            version_check: self.version_check,
+            warnings: self.warnings,
+            access_flags: self.access_flags,
             _marker: PhantomData,
         })
     }
@@ -201,13 +291,17 @@ impl ClassWriter<ClassWriterState::Attributes> {
         ) -> Result<(), EncodeError>,
     {
         let mut builder = ManyWriter::new(self)?;
-        f(&mut builder)?;
+        f(&mut builder).map_err(step("ClassWriter::attributes"))?;
         self = builder.finish()?;
 
         Ok(ClassWriter {
             start_encoder: self.start_encoder,
             encoder: self.encoder,
             pool: self.pool,
+            version: self.version,
+            version_check: self.version_check,
+            warnings: self.warnings,
+            access_flags: self.access_flags,
             _marker: PhantomData,
         })
     }
@@ -227,6 +321,28 @@ impl ClassWriter<ClassWriterState::End> {
     }
 }
 
+impl<State: ClassWriterState::State> ClassWriter<State> {
+    /// The features that were written even though they are not legal in the version of the class.
+    ///
+    /// Warnings are only recorded with [`VersionCheck::Warn`].
+    #[must_use]
+    pub fn warnings(&self) -> &[Feature] {
+        &self.warnings
+    }
+
+    /// Checks whether a method with these access flags is legal in the version of the class.
+    ///
+    /// Non-abstract methods have code, which interfaces may only contain since Java 8.
+    pub(crate) fn require_method(&mut self, flags: AccessFlags) -> Result<(), EncodeError> {
+        if self.access_flags.contains(AccessFlags::INTERFACE)
+            && !flags.intersects(AccessFlags::ABSTRACT | AccessFlags::STATIC)
+        {
+            self.require(Feature::DefaultMethods, Context::Methods)?;
+        }
+        Ok(())
+    }
+}
+
 impl<State: ClassWriterState::State> InternalEncoderContext for ClassWriter<State> {
     fn encoder(&mut self) -> &mut VecEncoder {
         &mut self.encoder
@@ -235,12 +351,51 @@ impl<State: ClassWriterState::State> InternalEncoderContext for ClassWriter<Stat
 
 impl<State: ClassWriterState::State> EncoderContext for ClassWriter<State> {
     fn insert_constant<I: Into<cpool::Item>>(&mut self, item: I) -> Result<cpool::Index<I>, EncodeError> {
+        let len = self.pool.len();
         let index = self.pool.insert(item, &mut self.start_encoder)?;
+        if self.pool.len() != len {
+            // only new constants are checked, so no warning is recorded twice
+            if let Some(feature) = self.pool.last().and_then(cpool::Item::feature) {
+                self.require(feature, Context::ConstantPool)?;
+            }
+        }
         self.start_encoder
             .replacing(Offset::new(4 + 2 + 2))
             .write(self.pool.len())?;
         Ok(index)
     }
+
+    fn require(&mut self, feature: Feature, context: Context) -> Result<(), EncodeError> {
+        if self.version.supports(feature) {
+            return Ok(());
+        }
+
+        match self.version_check {
+            VersionCheck::Reject => Err(EncodeError::with_context(
+                EncodeErrorKind::UnsupportedFeature {
+                    feature,
+                    version: self.version,
+                },
+                context,
+            )),
+            VersionCheck::Warn => {
+                self.warnings.push(feature);
+                Ok(())
+            }
+            VersionCheck::Ignore => Ok(()),
+        }
+    }
+
+    fn require_attribute(&mut self, name: cpool::Index<cpool::Utf8>) -> Result<(), EncodeError> {
+        let feature = match self.pool.get(name.as_item()) {
+            Some(cpool::Item::Utf8(name)) => Feature::attribute(name.content.as_bytes()),
+            _ => None,
+        };
+        match feature {
+            Some(feature) => self.require(feature, Context::Attributes),
+            None => Ok(()),
+        }
+    }
 }
 
 impl<State: ClassWriterState::State> fmt::Debug for ClassWriter<State> {
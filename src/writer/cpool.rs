@@ -1,4 +1,5 @@
 use crate::error::*;
+use crate::header::Feature;
 use crate::mutf8::MString;
 use crate::writer::encoding::*;
 use indexmap::IndexMap;
@@ -28,13 +29,22 @@ impl ConstantPool {
         item: I,
         mut encoder: E,
     ) -> Result<Index<I>, EncodeError> {
+        let item = item.into();
+        if let Some(index) = self.content.get(&item) {
+            // equal items are only stored once
+            return Ok(Index {
+                index: index.index,
+                mark: PhantomData,
+            });
+        }
+
         if self.len == u16::MAX {
             return Err(EncodeError::with_context(
                 EncodeErrorKind::TooManyItems,
                 Context::ConstantPool,
             ));
         }
-        let item = item.into();
+
         let index = NonZeroU16::new(self.len).unwrap();
         self.len += if let Item::Long(_) | Item::Double(_) = item {
             2
@@ -61,6 +71,26 @@ impl ConstantPool {
     pub(crate) fn len(&self) -> u16 {
         self.len
     }
+
+    /// The item that was inserted last.
+    pub(crate) fn last(&self) -> Option<&Item> {
+        self.content.last().map(|(item, _)| item)
+    }
+
+    pub(crate) fn get(&self, index: Index<Item>) -> Option<&Item> {
+        // the items are ordered by their index, as they are never removed
+        let (mut low, mut high) = (0, self.content.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let (item, item_index) = self.content.get_index(mid)?;
+            match item_index.index.cmp(&index.index) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some(item),
+            }
+        }
+        None
+    }
 }
 
 impl fmt::Debug for ConstantPool {
@@ -144,6 +174,19 @@ pub enum Item {
     Package(Package),
 }
 
+impl Item {
+    /// The feature which has to be supported by the version of the class to use this item.
+    pub(crate) fn feature(&self) -> Option<Feature> {
+        match self {
+            Item::MethodHandle(_) | Item::MethodType(_) => Some(Feature::MethodHandleConstants),
+            Item::InvokeDynamic(_) => Some(Feature::InvokeDynamic),
+            Item::Dynamic(_) => Some(Feature::DynamicConstants),
+            Item::Module(_) | Item::Package(_) => Some(Feature::Modules),
+            _ => None,
+        }
+    }
+}
+
 impl Encode for Item {
     fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
         match self {
@@ -929,3 +972,26 @@ macro_rules! ref_inserter {
 ref_inserter!(FieldRefInserter, FieldRef);
 ref_inserter!(MethodRefInserter, MethodRef);
 ref_inserter!(InterfaceMethodRefInserter, InterfaceMethodRef);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_items_are_stored_once() {
+        let mut pool = ConstantPool::new();
+        let mut encoder = VecEncoder::new(Vec::new());
+        let utf8 = |content: &str| Utf8 {
+            content: content.into(),
+        };
+        let first = pool.insert(utf8("Example"), &mut encoder).unwrap();
+        let long = pool.insert(Long { value: 1 }, &mut encoder).unwrap();
+        let second = pool.insert(utf8("Example"), &mut encoder).unwrap();
+        let other = pool.insert(utf8("Other"), &mut encoder).unwrap();
+
+        assert_eq!(first.as_u16(), second.as_u16());
+        assert_eq!((first.as_u16(), long.as_u16(), other.as_u16()), (1, 2, 4));
+        assert_eq!(pool.len(), 5);
+        assert_eq!(encoder.buf().len(), 10 + 9 + 8);
+    }
+}
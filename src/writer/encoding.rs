@@ -1,4 +1,5 @@
 use crate::error::*;
+use crate::header::Feature;
 use std::fmt;
 use std::marker::PhantomData;
 
@@ -177,7 +178,7 @@ pub trait InternalEncoderContext {
     fn encoder(&mut self) -> &mut VecEncoder;
 }
 
-impl<'a, Ctx: InternalEncoderContext> InternalEncoderContext for &'a mut Ctx {
+impl<Ctx: InternalEncoderContext> InternalEncoderContext for &mut Ctx {
     fn encoder(&mut self) -> &mut VecEncoder {
         (**self).encoder()
     }
@@ -185,12 +186,26 @@ impl<'a, Ctx: InternalEncoderContext> InternalEncoderContext for &'a mut Ctx {
 
 pub trait EncoderContext: InternalEncoderContext {
     fn insert_constant<I: Into<cpool::Item>>(&mut self, item: I) -> Result<cpool::Index<I>, EncodeError>;
+
+    /// Checks whether a feature may be used in the version of the class.
+    fn require(&mut self, feature: Feature, context: Context) -> Result<(), EncodeError>;
+
+    /// Checks whether an attribute with this name may be used in the version of the class.
+    fn require_attribute(&mut self, name: cpool::Index<cpool::Utf8>) -> Result<(), EncodeError>;
 }
 
-impl<'a, Ctx: EncoderContext> EncoderContext for &'a mut Ctx {
+impl<Ctx: EncoderContext> EncoderContext for &mut Ctx {
     fn insert_constant<I: Into<cpool::Item>>(&mut self, item: I) -> Result<cpool::Index<I>, EncodeError> {
         (**self).insert_constant(item)
     }
+
+    fn require(&mut self, feature: Feature, context: Context) -> Result<(), EncodeError> {
+        (**self).require(feature, context)
+    }
+
+    fn require_attribute(&mut self, name: cpool::Index<cpool::Utf8>) -> Result<(), EncodeError> {
+        (**self).require_attribute(name)
+    }
 }
 
 pub trait WriteAssembler: Sized {
@@ -263,7 +278,10 @@ where
             .ok_or_else(|| EncodeError::with_context(EncodeErrorKind::ErroredBefore, Context::None))?;
         self.count.check()?;
 
-        let mut context = f(W::new(context)?)?.finish()?;
+        let index = self.count.get();
+        let mut context = f(W::new(context)?)
+            .and_then(WriteDisassembler::finish)
+            .map_err(|err| err.within(PathSegment::Entry { index }))?;
 
         self.count.increment()?;
         context.encoder().replacing(self.count_offset).write(self.count)?;
@@ -280,6 +298,7 @@ impl<W: WriteAssembler, Count> fmt::Debug for ManyWriter<W, Count> {
 
 pub trait Counter: Copy {
     fn zero() -> Self;
+    fn get(self) -> u32;
     fn check(self) -> Result<(), EncodeError>;
     fn increment(&mut self) -> Result<(), EncodeError>;
 }
@@ -291,6 +310,10 @@ macro_rules! impl_counter {
                 0
             }
 
+            fn get(self) -> u32 {
+                u32::from(self)
+            }
+
             fn check(self) -> Result<(), EncodeError> {
                 if self == $v::MAX {
                     Err(EncodeError::with_context(
@@ -30,7 +30,7 @@ impl FieldWriter<FieldWriterState::Name> {
     where
         I: cpool::Insertable<cpool::Utf8>,
     {
-        let index = name.insert(&mut self.class_writer)?;
+        let index = name.insert(&mut self.class_writer).map_err(step("FieldWriter::name"))?;
         self.class_writer.encoder().write(index)?;
         Ok(FieldWriter {
             class_writer: self.class_writer,
@@ -44,7 +44,9 @@ impl FieldWriter<FieldWriterState::Descriptor> {
     where
         I: cpool::Insertable<cpool::Utf8>,
     {
-        let index = descriptor.insert(&mut self.class_writer)?;
+        let index = descriptor
+            .insert(&mut self.class_writer)
+            .map_err(step("FieldWriter::descriptor"))?;
         self.class_writer.encoder().write(index)?;
         Ok(FieldWriter {
             class_writer: self.class_writer,
@@ -64,7 +66,7 @@ impl FieldWriter<FieldWriterState::Attributes> {
         ) -> Result<(), EncodeError>,
     {
         let mut builder = ManyWriter::new(self.class_writer)?;
-        f(&mut builder)?;
+        f(&mut builder).map_err(step("FieldWriter::attributes"))?;
         self.class_writer = builder.finish()?;
 
         Ok(FieldWriter {
@@ -17,6 +17,7 @@ pub struct MethodWriter<State: MethodWriterState::State> {
 
 impl MethodWriter<MethodWriterState::AccessFlags> {
     pub fn access_flags(mut self, flags: AccessFlags) -> Result<MethodWriter<MethodWriterState::Name>, EncodeError> {
+        self.class_writer.require_method(flags)?;
         self.class_writer.encoder().write(flags)?;
         Ok(MethodWriter {
             class_writer: self.class_writer,
@@ -30,7 +31,9 @@ impl MethodWriter<MethodWriterState::Name> {
     where
         I: cpool::Insertable<cpool::Utf8>,
     {
-        let index = name.insert(&mut self.class_writer)?;
+        let index = name
+            .insert(&mut self.class_writer)
+            .map_err(step("MethodWriter::name"))?;
         self.class_writer.encoder().write(index)?;
         Ok(MethodWriter {
             class_writer: self.class_writer,
@@ -44,7 +47,9 @@ impl MethodWriter<MethodWriterState::Descriptor> {
     where
         I: cpool::Insertable<cpool::Utf8>,
     {
-        let index = descriptor.insert(&mut self.class_writer)?;
+        let index = descriptor
+            .insert(&mut self.class_writer)
+            .map_err(step("MethodWriter::descriptor"))?;
         self.class_writer.encoder().write(index)?;
         Ok(MethodWriter {
             class_writer: self.class_writer,
@@ -64,7 +69,7 @@ impl MethodWriter<MethodWriterState::Attributes> {
         ) -> Result<(), EncodeError>,
     {
         let mut builder = ManyWriter::new(self.class_writer)?;
-        f(&mut builder)?;
+        f(&mut builder).map_err(step("MethodWriter::attributes"))?;
         self.class_writer = builder.finish()?;
 
         Ok(MethodWriter {